@@ -0,0 +1,10 @@
+#import "/templates/page.typ": page
+
+#metadata((title: "Home")) <tola-meta>
+#show: page.with(title: "My Blog")
+
+Welcome to my blog, built with #link("https://github.com/KawaYww/tola-ssg")[tola] and Typst.
+
+== Recent posts
+
+- #link("/posts/hello-world/")[Hello, world!]
@@ -0,0 +1,14 @@
+#import "/templates/post.typ": post
+
+#metadata((
+  title: "Hello, world!",
+  date: "2025-01-01",
+  summary: "The first post of this blog.",
+)) <tola-meta>
+#show: post.with(title: "Hello, world!", date: "2025-01-01")
+
+This is the first post. Write posts as `.typ` files under `content/posts/`.
+
+Inline math like $a^2 + b^2 = c^2$ and display math both work:
+
+$ integral_0^oo e^(-x^2) dif x = sqrt(pi) / 2 $
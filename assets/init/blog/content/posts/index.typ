@@ -0,0 +1,6 @@
+#import "/templates/page.typ": page
+
+#metadata((title: "Posts")) <tola-meta>
+#show: page.with(title: "Posts")
+
+- #link("/posts/hello-world/")[Hello, world!]
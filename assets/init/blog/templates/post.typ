@@ -0,0 +1,12 @@
+// Layout of a post: `#show: post.with(title: "...", date: "YYYY-MM-DD")`
+//
+// Repeat the title and date in `<tola-meta>` so feeds and listings see them.
+
+#import "page.typ": page
+
+#let post(title: none, date: none, body) = page(title: title, {
+  if date != none {
+    html.elem("p", attrs: (class: "date"), date)
+  }
+  html.elem("article", body)
+})
@@ -0,0 +1,8 @@
+#import "/templates/doc.typ": doc
+
+#metadata((title: "Configuration")) <tola-meta>
+#show: doc.with(title: "Configuration")
+
+The site is configured in `tola.toml`, at the root of the site.
+`[base]` holds the title, description and URL; `[build]` the directories
+and the processing of the output.
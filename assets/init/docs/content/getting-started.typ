@@ -0,0 +1,18 @@
+#import "/templates/doc.typ": doc
+
+#metadata((title: "Getting started")) <tola-meta>
+#show: doc.with(title: "Getting started")
+
+Preview the site while you write:
+
+```sh
+tola serve
+```
+
+Build it for deployment into `public/`:
+
+```sh
+tola build
+```
+
+Add a page to the sidebar by listing it in `templates/doc.typ`.
@@ -0,0 +1,9 @@
+#import "/templates/doc.typ": doc
+
+#metadata((title: "Introduction")) <tola-meta>
+#show: doc.with(title: "Introduction")
+
+This documentation is built with #link("https://github.com/KawaYww/tola-ssg")[tola] and Typst.
+Every `.typ` file under `content/` becomes a page.
+
+Continue with #link("/getting-started/")[Getting started].
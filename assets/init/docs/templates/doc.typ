@@ -0,0 +1,39 @@
+// Layout of a documentation page: `#show: doc.with(title: "...")`
+//
+// The page title tola puts in `<head>` comes from
+// `#metadata((title: "...")) <tola-meta>`.
+
+// Pages listed in the sidebar, in reading order
+#let pages = (
+  ("/", "Introduction"),
+  ("/getting-started/", "Getting started"),
+  ("/configuration/", "Configuration"),
+)
+
+#let doc(title: none, body) = {
+  // Math is exported as SVG, which tola inlines or extracts
+  show math.equation.where(block: false): it => html.elem(
+    "span",
+    attrs: (class: "math", role: "math"),
+    html.frame(it),
+  )
+  show math.equation.where(block: true): it => html.elem(
+    "figure",
+    attrs: (class: "math", role: "math"),
+    html.frame(it),
+  )
+
+  html.elem("div", attrs: (class: "layout"), {
+    html.elem("nav", attrs: (class: "sidebar"), {
+      for (href, name) in pages {
+        html.elem("a", attrs: (href: href), name)
+      }
+    })
+    html.elem("main", {
+      if title != none {
+        html.elem("h1", title)
+      }
+      body
+    })
+  })
+}
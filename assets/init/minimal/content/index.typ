@@ -0,0 +1,9 @@
+#import "/templates/page.typ": page
+
+#metadata((title: "Home")) <tola-meta>
+#show: page.with(title: "Welcome!")
+
+This site is built with #link("https://github.com/KawaYww/tola-ssg")[tola] and Typst.
+Edit `content/index.typ`, then run `tola serve` to see the changes live.
+
+$ e^(i pi) + 1 = 0 $
@@ -0,0 +1,25 @@
+// Layout of every page: `#show: page.with(title: "...")`
+//
+// The page title and description tola puts in `<head>` come from
+// `#metadata((title: "...")) <tola-meta>`.
+
+#let page(title: none, body) = {
+  // Math is exported as SVG, which tola inlines or extracts
+  show math.equation.where(block: false): it => html.elem(
+    "span",
+    attrs: (class: "math", role: "math"),
+    html.frame(it),
+  )
+  show math.equation.where(block: true): it => html.elem(
+    "figure",
+    attrs: (class: "math", role: "math"),
+    html.frame(it),
+  )
+
+  html.elem("main", {
+    if title != none {
+      html.elem("h1", title)
+    }
+    body
+  })
+}
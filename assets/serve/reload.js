@@ -0,0 +1,10 @@
+// Injected by `tola serve`: reload the page when the site is rebuilt
+(() => {
+  const connect = () => {
+    const socket = new WebSocket(`ws://${location.host}/__tola_reload`);
+    socket.onmessage = () => location.reload();
+    // Reconnect after the server restarts
+    socket.onclose = () => setTimeout(connect, 1000);
+  };
+  connect();
+})();
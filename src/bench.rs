@@ -0,0 +1,232 @@
+//! Build benchmarking (`tola bench`).
+//!
+//! Builds the site (or a generated one) several times into a scratch output
+//! directory and reports per-stage timings. Stages run one after another, not
+//! in parallel as in a normal build, so each one is measured on its own.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        build::{process_asset, process_content, process_files},
+        log::format_duration,
+        report,
+        rss::build_rss,
+        site,
+    },
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Stages measured on every run, in execution order
+const STAGES: &[&str] = &["content", "assets", "rss", "total"];
+
+/// Run `runs` clean builds and print timing statistics per stage.
+///
+/// With `synthetic`, a site with that many generated pages is benchmarked
+/// instead of the configured one.
+pub fn bench_site(config: &SiteConfig, runs: usize, synthetic: Option<usize>) -> Result<()> {
+    if runs == 0 {
+        bail!("`--runs` must be at least 1");
+    }
+
+    let scratch = std::env::temp_dir().join(format!("tola-bench-{}", std::process::id()));
+    let result = bench_in(config, &scratch, runs, synthetic);
+    fs::remove_dir_all(&scratch).ok();
+    result
+}
+
+fn bench_in(
+    config: &SiteConfig,
+    scratch: &Path,
+    runs: usize,
+    synthetic: Option<usize>,
+) -> Result<()> {
+    let config = match synthetic {
+        Some(pages) => synthetic_config(config, scratch, pages)?,
+        None => scratch_config(config, scratch),
+    };
+
+    let mut samples = vec![Vec::with_capacity(runs); STAGES.len()];
+    for run in 1..=runs {
+        let timings = bench_once(&config)?;
+        log!(true; "bench"; "run {run}/{runs}: {}", format_duration(timings[STAGES.len() - 1]));
+        for (stage, timing) in samples.iter_mut().zip(timings) {
+            stage.push(timing);
+        }
+    }
+
+    log!(true; "bench"; "{:<8} {:>9} {:>9} {:>9} {:>9} {:>9}", "stage", "mean", "p50", "p90", "min", "max");
+    for (name, stage) in STAGES.iter().zip(&samples) {
+        let stats = Stats::from_samples(stage);
+        log!(true; "bench"; "{name:<8} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            format_duration(stats.mean),
+            format_duration(stats.p50),
+            format_duration(stats.p90),
+            format_duration(stats.min),
+            format_duration(stats.max)
+        );
+    }
+    Ok(())
+}
+
+/// Run one clean build, returning the duration of each stage in [`STAGES`] order
+fn bench_once(config: &SiteConfig) -> Result<Vec<Duration>> {
+    if config.build.output.exists() {
+        fs::remove_dir_all(&config.build.output)?;
+    }
+
+    site::clear();
+    let started = Instant::now();
+    let content = &config.build.content;
+
+    let stage = Instant::now();
+    process_files(
+        content,
+        config,
+        "content",
+        |path| path.starts_with(content),
+        |path, cfg| process_content(path, cfg, false, true),
+    );
+    let content_time = stage.elapsed();
+
+    let stage = Instant::now();
+    process_files(
+        &config.build.assets,
+        config,
+        "assets",
+        |_| true,
+        |path, cfg| process_asset(path, cfg, false, false),
+    );
+    let assets_time = stage.elapsed();
+
+    let stage = Instant::now();
+    let rss_result = build_rss(config);
+    let rss_time = stage.elapsed();
+
+    let total = started.elapsed();
+    report::finish(config.get_root())?;
+    rss_result?;
+
+    Ok(vec![content_time, assets_time, rss_time, total])
+}
+
+/// Clone `config` so that output (and the feeds) goes to the scratch directory
+pub(crate) fn scratch_config(config: &SiteConfig, scratch: &Path) -> SiteConfig {
+    let mut config = config.clone();
+    config.set_output(scratch.join("public"));
+    config
+}
+
+/// Generate a site with `pages` posts under the scratch directory
+fn synthetic_config(config: &SiteConfig, scratch: &Path, pages: usize) -> Result<SiteConfig> {
+    let posts = scratch.join("content/posts");
+    fs::create_dir_all(&posts).with_context(|| format!("Failed to create {}", posts.display()))?;
+    fs::create_dir_all(scratch.join("assets"))?;
+
+    for i in 0..pages {
+        fs::write(posts.join(format!("post-{i}.typ")), synthetic_page(i))?;
+    }
+
+    let mut config = config.clone();
+    config.set_root(scratch);
+    config.build.content = scratch.join("content");
+    config.build.assets = scratch.join("assets");
+    config.build.output = scratch.join("public");
+    config.build.rss.enable = false;
+    config.build.tailwind.enable = false;
+    log!(true; "bench"; "generated {pages} synthetic pages in {}", scratch.display());
+    Ok(config)
+}
+
+/// Typst source for a generated page
+fn synthetic_page(index: usize) -> String {
+    format!(
+        "= Post {index}\n\n#lorem(300)\n\n== Details\n\n#lorem(200)\n\n$ sum_(k=1)^{index} k $\n"
+    )
+}
+
+// ============================================================================
+// Statistics
+// ============================================================================
+
+/// Summary statistics of one stage's samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stats {
+    mean: Duration,
+    p50: Duration,
+    p90: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl Stats {
+    /// Compute statistics; `samples` must not be empty
+    fn from_samples(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let total: Duration = sorted.iter().sum();
+        Self {
+            mean: total / sorted.len() as u32,
+            p50: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted = ms(&[10, 20, 30, 40, 50, 60, 70, 80, 90, 100]);
+        assert_eq!(percentile(&sorted, 50), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 90), Duration::from_millis(90));
+        assert_eq!(percentile(&ms(&[7]), 90), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn test_stats_from_unsorted_samples() {
+        let stats = Stats::from_samples(&ms(&[30, 10, 20]));
+        assert_eq!(stats.mean, Duration::from_millis(20));
+        assert_eq!(stats.p50, Duration::from_millis(20));
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_scratch_config_moves_output() {
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/site/public");
+        config.build.rss.path = PathBuf::from("/site/public/feed.xml");
+
+        let config = scratch_config(&config, Path::new("/tmp/bench"));
+        assert_eq!(config.build.output, PathBuf::from("/tmp/bench/public"));
+        assert_eq!(
+            config.build.rss.path,
+            PathBuf::from("/tmp/bench/public/feed.xml")
+        );
+    }
+}
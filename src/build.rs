@@ -6,58 +6,254 @@ use crate::{
     config::SiteConfig,
     log,
     utils::{
-        build::{process_asset, process_content, process_files},
-        git,
+        api::build_api,
+        archive::build_archive,
+        authors::build_authors,
+        backlinks::build_backlinks,
+        blogroll::build_blogroll,
+        build::{collect_files, process_asset, process_content, process_files},
+        cache,
+        fingerprint::build_fingerprint,
+        git, image_queue,
+        link_check::check_links,
+        nav::build_post_nav,
+        pagination::build_pagination,
+        plugin,
+        precompress::build_precompress,
+        progress::Progress,
+        prune,
+        purge::build_purge,
+        pwa::build_pwa,
+        redirects::build_redirects,
+        report,
+        reproducible::build_reproducible,
+        rss::{build_feeds_opml, build_rss},
+        search::build_search,
+        sections::build_sections,
+        site,
+        sitemap::build_sitemap,
+        slug::{find_collisions, output_path},
+        stats,
+        taxonomies::build_taxonomies,
+        text::build_text,
+        theme, typst,
+        webmention::build_links,
     },
 };
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use gix::ThreadSafeRepository;
-use std::{ffi::OsStr, fs};
+use std::{ffi::OsStr, fs, path::Path, time::Instant};
+
+/// Build the site, then generate the RSS feed from the pages it compiled
+///
+/// Per-file errors are collected while everything is attempted, then reported
+/// together; the build fails only after the report is printed, unless
+/// `[build] keep_going` is set.
+pub fn run_build(config: &SiteConfig) -> Result<ThreadSafeRepository> {
+    let started = Instant::now();
+    stats::clear();
+    if config.build.prune {
+        prune::start();
+    }
+    let build_result = build_site(config, config.build.clear);
+    // The feed is generated from pages recorded by the build, so skip it if the build failed
+    let rss_result = match &build_result {
+        Ok(_) => build_generated(config),
+        Err(_) => Ok(()),
+    };
+
+    // Prune only after everything that writes output has run
+    let prune_result = match (&build_result, &rss_result) {
+        (Ok(_), Ok(_)) => prune_output(config),
+        _ => {
+            prune::cancel();
+            Ok(())
+        }
+    };
+
+    // Post-build plugins only see a complete output directory
+    let plugin_result = match (&build_result, &rss_result) {
+        (Ok(_), Ok(_)) => plugin::run_post_build(config),
+        _ => Ok(()),
+    };
+
+    stats::report(config, started.elapsed());
+    let report_result =
+        report::finish(config.get_root()).or_else(|err| match config.keep_going() {
+            true => {
+                log!("warn"; "{err}; the failed files keep their previous output");
+                Ok(())
+            }
+            false => Err(err),
+        });
+    rss_result?;
+    let repo = build_result?;
+    prune_result?;
+    plugin_result?;
+    report_result?;
+    Ok(repo)
+}
+
+/// Generate everything derived from the recorded pages (listings, feeds,
+/// indexes, redirects), then check the links of the finished output
+pub fn build_generated(config: &SiteConfig) -> Result<()> {
+    build_pagination(config)?;
+    build_post_nav(config)?;
+    stats::time("rss", || build_rss(config))?;
+    build_links(config)?;
+    build_backlinks(config)?;
+    build_blogroll(config)?;
+    build_text(config)?;
+    build_search(config)?;
+    build_api(config)?;
+    build_sections(config)?;
+    build_authors(config)?;
+    build_taxonomies(config)?;
+    build_archive(config)?;
+    // Listed once the section, author and taxonomy feeds are written
+    build_feeds_opml(config)?;
+    build_redirects(config)?;
+    build_pwa(config)?;
+    build_sitemap(config)?;
+    // Unused CSS is known once every page is written
+    build_purge(config)?;
+    // Pages link to the hashed assets once everything that writes them has run
+    build_fingerprint(config)?;
+    // Compressed copies are made of the final output
+    build_precompress(config)?;
+    // Modification times are pinned once nothing writes the output anymore
+    build_reproducible(config)?;
+    // Links are checked once everything that writes pages has run
+    check_links(config)
+}
 
 /// Build the entire site, processing content and assets in parallel
 ///
 /// If `force_rebuild` is true, skips timestamp checks and rebuilds all content.
-pub fn build_site(
-    config: &'static SiteConfig,
-    force_rebuild: bool,
-) -> Result<ThreadSafeRepository> {
+pub fn build_site(config: &SiteConfig, force_rebuild: bool) -> Result<ThreadSafeRepository> {
     let output = &config.build.output;
     let content = &config.build.content;
     let assets = &config.build.assets;
 
+    // Refuse to build before anything is overwritten
+    check_output_collisions(config)?;
+
     // Initialize or clear output directory with git repo
     let repo = init_output_repo(output, force_rebuild)?;
 
+    // Every page is recorded again, up to date or not
+    site::clear();
+
+    // Config or template changes invalidate the cached pages
+    cache::load_pages(config).context("Failed to load build cache")?;
+
+    // SVG count is only known while pages are processed, so its bar grows on demand
+    let _svg_progress = Progress::start("svg", 0);
+
     // Process content and assets in parallel
-    let (posts_result, assets_result) = rayon::join(
+    // Per-file failures are recorded in the build report instead of aborting
+    let (failed_posts, failed_assets) = rayon::join(
         || {
-            process_files(
-                content,
-                config,
-                |path| path.starts_with(content),
-                |path, cfg| process_content(path, cfg, false, force_rebuild),
-            )
-            .context("Failed to compile posts")
+            stats::time("content", || {
+                process_files(
+                    content,
+                    config,
+                    "content",
+                    |path| path.starts_with(content),
+                    |path, cfg| process_content(path, cfg, false, force_rebuild),
+                )
+            })
         },
         || {
-            process_files(
-                assets,
-                config,
-                |_| true,
-                |path, cfg| process_asset(path, cfg, false, false),
-            )
-            .context("Failed to copy assets")
+            stats::time("assets", || {
+                let failed = process_files(
+                    assets,
+                    config,
+                    "assets",
+                    |_| true,
+                    |path, cfg| process_asset(path, cfg, false, false),
+                );
+                // Theme assets fill in the paths the site leaves free
+                let failed_theme = match theme::assets_dir(config) {
+                    Some(dir) => process_files(
+                        &dir,
+                        config,
+                        "assets",
+                        |path| !theme::is_overridden(path, &dir, config),
+                        |path, cfg| process_asset(path, cfg, false, false),
+                    ),
+                    None => 0,
+                };
+                failed + failed_theme
+            })
         },
     );
 
-    posts_result?;
-    assets_result?;
+    // Pages whose images failed are compiled again next time
+    image_queue::wait()?;
+    cache::save_all().context("Failed to save build cache")?;
+    typst::evict_cache();
+
+    if failed_posts + failed_assets > 0 {
+        log!("warn"; "{failed_posts} post(s) and {failed_assets} asset(s) failed");
+    }
 
     log_build_result(output)?;
 
     Ok(repo)
 }
 
+/// Remove output files not produced by this build (`[build] prune`)
+fn prune_output(config: &SiteConfig) -> Result<()> {
+    // Pages skipped as up to date don't report the SVG files they share
+    if let Some(dir) = &config.build.typst.svg.dir {
+        collect_files(dir, |_| true)
+            .into_iter()
+            .for_each(prune::produced);
+    }
+    let removed = prune::finish(&config.build.output).context("Failed to prune output")?;
+    if removed > 0 {
+        log!("prune"; "removed {removed} stale file(s)");
+    }
+    Ok(())
+}
+
+/// Fail if two source files map to the same output file
+pub(crate) fn check_output_collisions(config: &SiteConfig) -> Result<()> {
+    let sources = collect_files(&config.build.content, |_| true)
+        .into_iter()
+        .chain(collect_files(&config.build.assets, |_| true))
+        .chain(theme::assets(config));
+    // Unmappable sources are reported when they are processed
+    let outputs = sources.filter_map(|source| {
+        let output = output_path(&source, config).ok()?;
+        Some((source, output))
+    });
+
+    let collisions = find_collisions(outputs);
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    let root = config.get_root();
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    let mut message = String::from("Multiple source files map to the same output file:");
+    for (output, sources) in &collisions {
+        let sources: Vec<_> = sources.iter().map(|s| relative(s)).collect();
+        message.push_str(&format!(
+            "\n  {} <- {}",
+            relative(output),
+            sources.join(", ")
+        ));
+    }
+    bail!(message)
+}
+
 /// Initialize output directory with git repository
 fn init_output_repo(output: &std::path::Path, force_rebuild: bool) -> Result<ThreadSafeRepository> {
     match (output.exists(), force_rebuild) {
@@ -0,0 +1,123 @@
+//! Site validation without publishing (`tola check`).
+//!
+//! Builds the whole site into a scratch directory instead of the output
+//! directory: every page is compiled and every generator runs, links are
+//! checked as errors, and page dates are validated. Nothing under the output
+//! directory or the build cache is touched, so it is safe to run in CI before
+//! a build or deploy. The config itself is validated when it is loaded.
+
+use crate::{
+    bench::scratch_config,
+    build::{build_generated, build_site},
+    config::{LinkCheckLevel, SiteConfig},
+    log,
+    utils::{
+        report,
+        rss::DateTimeUtc,
+        site::{self, Page},
+    },
+};
+use anyhow::{Result, anyhow};
+use std::{fs, path::Path};
+
+/// Compile and validate the site, failing if any error was reported
+pub fn check_site(config: &SiteConfig) -> Result<()> {
+    let scratch = std::env::temp_dir().join(format!("tola-check-{}", std::process::id()));
+    let result = check_in(config, &scratch);
+    fs::remove_dir_all(&scratch).ok();
+    result
+}
+
+fn check_in(config: &SiteConfig, scratch: &Path) -> Result<()> {
+    let mut config = scratch_config(config, scratch);
+    config.build.cache = scratch.join("cache");
+    config.build.link_check.enable = true;
+    config.build.link_check.level = LinkCheckLevel::Error;
+
+    let build_result = build_site(&config, true).map(drop);
+    let check_result = match &build_result {
+        Ok(()) => {
+            check_dates(&site::pages());
+            build_generated(&config)
+        }
+        Err(_) => Ok(()),
+    };
+
+    let report_result = report::finish(config.get_root());
+    build_result?;
+    check_result?;
+    report_result?;
+    log!("check"; "{} page(s) checked, no errors", site::pages().len());
+    Ok(())
+}
+
+/// Report `date` and `update` values of page metadata that are not valid dates
+fn check_dates(pages: &[Page]) {
+    for page in pages {
+        let Some(meta) = &page.meta else { continue };
+        for (field, value) in [("date", &meta.date), ("update", &meta.update)] {
+            let Some(value) = value else { continue };
+            if DateTimeUtc::parse(value)
+                .and_then(DateTimeUtc::to_fixed)
+                .is_none()
+            {
+                report::error(
+                    &page.source,
+                    &anyhow!(
+                        "invalid `{field}` `{value}`: expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+    use std::path::PathBuf;
+
+    fn page(source: &str, date: &str, update: Option<&str>) -> Page {
+        Page {
+            source: PathBuf::from(source),
+            meta: Some(PageMeta {
+                date: Some(date.into()),
+                update: update.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_dates() {
+        report::take();
+        check_dates(&[
+            page("/check/a.typ", "2024-02-29", Some("2024-03-01T10:00:00Z")),
+            page("/check/b.typ", "2023-02-30", None),
+            page("/check/c.typ", "2024-01-01", Some("yesterday")),
+        ]);
+        let mut diagnostics: Vec<_> = report::take()
+            .into_iter()
+            .filter(|d| d.path.starts_with("/check"))
+            .collect();
+        diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].path, PathBuf::from("/check/b.typ"));
+        assert!(
+            diagnostics[0]
+                .message
+                .starts_with("invalid `date` `2023-02-30`")
+        );
+        assert!(
+            diagnostics[1]
+                .message
+                .starts_with("invalid `update` `yesterday`")
+        );
+    }
+}
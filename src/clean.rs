@@ -0,0 +1,90 @@
+//! Removal of generated files (`tola clean`).
+//!
+//! Deletes the output directory (pages, SVG/AVIF images, feeds and every
+//! other artifact of `tola build` live there) and the `[build.cache]`
+//! directory, so the next build starts from scratch.
+
+use crate::{config::SiteConfig, log};
+use anyhow::{Context, Result, bail};
+use std::{fs, path::Path};
+
+/// What `tola clean` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanTarget {
+    /// Output and build cache
+    All,
+    /// Build cache only (`--cache-only`)
+    Cache,
+    /// Output only (`--output-only`)
+    Output,
+}
+
+/// Remove the output directory and/or build cache
+pub fn clean_site(config: &SiteConfig, target: CleanTarget) -> Result<()> {
+    let root = config.get_root();
+    let mut dirs = Vec::new();
+    if target != CleanTarget::Cache {
+        dirs.push(("output", config.build.output.as_path()));
+    }
+    if target != CleanTarget::Output {
+        dirs.push(("cache", config.build.cache.as_path()));
+    }
+
+    for (name, dir) in dirs {
+        remove_dir(name, dir, root)?;
+    }
+    Ok(())
+}
+
+/// Remove `dir`, refusing to touch the site root or anything containing it
+fn remove_dir(name: &str, dir: &Path, root: &Path) -> Result<()> {
+    if root.starts_with(dir) {
+        bail!(
+            "Refusing to remove the {name} directory {}: it contains the site root",
+            dir.display()
+        );
+    }
+    if !dir.exists() {
+        log!("clean"; "no {name} directory at {}", dir.display());
+        return Ok(());
+    }
+    fs::remove_dir_all(dir)
+        .with_context(|| format!("Failed to remove {name} directory {}", dir.display()))?;
+    log!("clean"; "removed {name} directory {}", dir.display());
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_site() {
+        let root = std::env::temp_dir().join("tola_clean_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("public/posts")).unwrap();
+        fs::create_dir_all(root.join(".tola-cache")).unwrap();
+
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        config.build.output = root.join("public");
+        config.build.cache = root.join(".tola-cache");
+
+        clean_site(&config, CleanTarget::Cache).unwrap();
+        assert!(root.join("public").exists());
+        assert!(!root.join(".tola-cache").exists());
+
+        clean_site(&config, CleanTarget::All).unwrap();
+        assert!(!root.join("public").exists());
+        assert!(root.exists());
+
+        config.build.output = root.clone();
+        assert!(clean_site(&config, CleanTarget::Output).is_err());
+        assert!(root.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
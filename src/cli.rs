@@ -2,6 +2,7 @@
 //!
 //! Defines all CLI arguments and subcommands using clap.
 
+use crate::utils::log::LogLevel;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -37,6 +38,22 @@ pub struct Cli {
     #[arg(short, long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true", require_equals = false)]
     pub tailwind: Option<bool>,
 
+    /// Maximum log level to print, overriding `[log] level`
+    #[arg(long, value_enum)]
+    pub log_level: Option<LogLevel>,
+
+    /// Only print warnings and errors
+    #[arg(short, long, conflicts_with_all = ["verbose", "log_level"])]
+    pub quiet: bool,
+
+    /// Print debug messages, and trace messages when repeated (`-vv`)
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "log_level")]
+    pub verbose: u8,
+
+    /// Per-module log levels, e.g. `svg=off,typst=debug`
+    #[arg(long, value_delimiter = ',')]
+    pub log_filter: Vec<String>,
+
     /// subcommands
     #[command(subcommand)]
     pub command: Commands,
@@ -49,16 +66,88 @@ pub enum Commands {
     Init {
         /// the name(path) of site directory, related to `root`
         name: Option<PathBuf>,
+
+        /// Site to scaffold: `blog`, `docs`, `minimal` or a git URL
+        #[arg(short, long, default_value = "minimal")]
+        template: String,
+
+        /// Never prompt; values not given keep their defaults
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Site title
+        #[arg(long, env = "TOLA_INIT_TITLE")]
+        title: Option<String>,
+
+        /// Site description
+        #[arg(long, env = "TOLA_INIT_DESCRIPTION")]
+        description: Option<String>,
+
+        /// Author name
+        #[arg(long, env = "TOLA_INIT_AUTHOR")]
+        author: Option<String>,
+
+        /// Author email
+        #[arg(long, env = "TOLA_INIT_EMAIL")]
+        email: Option<String>,
+
+        /// Site URL, e.g. `https://example.com`
+        #[arg(long, env = "TOLA_INIT_URL")]
+        url: Option<String>,
     },
 
     /// Deletes the output directory if there is one and rebuilds the site
-    Build {},
+    Build {
+        /// Recompile every page, even if the build cache says it is up to date
+        #[arg(long)]
+        force: bool,
+
+        /// Include draft pages
+        #[arg(long)]
+        drafts: bool,
+
+        /// Include posts dated in the future
+        #[arg(long)]
+        future: bool,
+
+        /// Report files added, removed or changed compared to the previous build
+        #[arg(long)]
+        diff: bool,
+
+        /// Print how long each stage and the slowest pages took
+        #[arg(long)]
+        stats: bool,
+
+        /// After building, export this page (source or output path) as one self-contained HTML file
+        #[arg(long, value_name = "PAGE")]
+        single_file: Option<PathBuf>,
+
+        /// Write extracted SVGs as SVG files instead of encoding them
+        #[arg(long)]
+        skip_images: bool,
+    },
+
+    /// Create a content file from an archetype, e.g. `tola new posts/my-first-post`
+    New {
+        /// Page path related to the content directory; the file name is slugified
+        path: PathBuf,
+
+        /// Archetype under `templates/archetypes/` (default: the first directory of `path`)
+        #[arg(short, long)]
+        archetype: Option<String>,
+    },
+
+    /// Install or update the `[theme]` from git
+    Theme {
+        #[command(subcommand)]
+        action: ThemeAction,
+    },
 
     /// Serve the site. Rebuild and reload on change automatically
     Serve {
-        /// Interface to bind on
-        #[arg(short, long)]
-        interface: Option<String>,
+        /// Interfaces to bind on, separated by commas
+        #[arg(short, long, value_delimiter = ',')]
+        interface: Vec<String>,
 
         /// The port you should provide
         #[arg(short, long)]
@@ -67,6 +156,22 @@ pub enum Commands {
         /// enable watch
         #[arg(short, long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true", require_equals = false)]
         watch: Option<bool>,
+
+        /// Open the site in the default browser once the server is listening
+        #[arg(long)]
+        open: bool,
+
+        /// Include draft pages
+        #[arg(long)]
+        drafts: bool,
+
+        /// Include posts dated in the future
+        #[arg(long)]
+        future: bool,
+
+        /// Write extracted SVGs as SVG files instead of encoding them
+        #[arg(long)]
+        skip_images: bool,
     },
 
     /// Deletes the output directory if there is one and rebuilds the site
@@ -74,11 +179,86 @@ pub enum Commands {
         /// enable watch
         #[arg(short, long, action = clap::ArgAction::Set, num_args = 0..=1, default_missing_value = "true", require_equals = false)]
         force: Option<bool>,
+
+        /// Create a preview deployment instead of a production one (vercel)
+        #[arg(long)]
+        preview: bool,
+
+        /// Commit message, overriding `[deploy.commit] message`
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+
+    /// Compile the site without writing the output and report every error, e.g. in CI
+    Check {
+        /// Include draft pages
+        #[arg(long)]
+        drafts: bool,
+
+        /// Include posts dated in the future
+        #[arg(long)]
+        future: bool,
+    },
+
+    /// Remove the output directory and the build cache
+    Clean {
+        /// Only remove the build cache
+        #[arg(long, conflicts_with = "output_only")]
+        cache_only: bool,
+
+        /// Only remove the output directory
+        #[arg(long)]
+        output_only: bool,
+    },
+
+    /// Compile matching posts to PDF under `pdf/` in the output directory
+    ExportPdf {
+        /// Glob matched against paths relative to the content directory (default: `**/*.typ`)
+        pattern: Option<String>,
+    },
+
+    /// Build the site repeatedly and report per-stage timings
+    Bench {
+        /// Number of measured builds
+        #[arg(short = 'n', long, default_value_t = 5)]
+        runs: usize,
+
+        /// Benchmark a generated site with this many pages instead of the current one
+        #[arg(short, long)]
+        synthetic: Option<usize>,
     },
 }
 
+/// `tola theme` subcommands
+#[derive(Subcommand, Debug, Clone)]
+pub enum ThemeAction {
+    /// Clone a theme into the themes directory and record it in tola.toml
+    Install {
+        /// Git URL of the theme (default: `[theme] url`, at `[theme] rev`)
+        url: Option<String>,
+
+        /// Branch, tag or commit to check out
+        #[arg(long)]
+        rev: Option<String>,
+    },
+
+    /// Fetch the theme and pin it to the latest commit of its default branch
+    Update,
+}
+
 #[allow(unused)]
 impl Cli {
+    /// Log level set on the command line, if any
+    pub fn level(&self) -> Option<LogLevel> {
+        match (self.log_level, self.quiet, self.verbose) {
+            (Some(level), ..) => Some(level),
+            (None, true, _) => Some(LogLevel::Warn),
+            (None, false, 0) => None,
+            (None, false, 1) => Some(LogLevel::Debug),
+            (None, false, _) => Some(LogLevel::Trace),
+        }
+    }
+
     pub fn is_init(&self) -> bool {
         matches!(self.command, Commands::Init { .. })
     }
@@ -91,4 +271,10 @@ impl Cli {
     pub fn is_deploy(&self) -> bool {
         matches!(self.command, Commands::Deploy { .. })
     }
+    pub fn is_bench(&self) -> bool {
+        matches!(self.command, Commands::Bench { .. })
+    }
+    pub fn is_theme(&self) -> bool {
+        matches!(self.command, Commands::Theme { .. })
+    }
 }
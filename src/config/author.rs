@@ -0,0 +1,66 @@
+//! `[[authors]]` section configuration.
+//!
+//! Author profiles shown on the generated author pages.
+
+use serde::{Deserialize, Serialize};
+
+/// `[[authors]]` entry in tola.toml.
+///
+/// `name` is matched against the `author` field of post metadata.
+///
+/// # Example
+/// ```toml
+/// [[authors]]
+/// name = "Alice"
+/// bio = "Writes about compilers"
+/// avatar = "/images/alice.avif"
+/// url = "https://alice.example"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorProfile {
+    /// Name as written in post metadata
+    pub name: String,
+
+    /// Short biography shown under the name
+    #[serde(default)]
+    pub bio: Option<String>,
+
+    /// Avatar image URL
+    #[serde(default)]
+    pub avatar: Option<String>,
+
+    /// Personal homepage
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SiteConfig;
+
+    #[test]
+    fn test_authors_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [[authors]]
+            name = "Alice"
+            bio = "Writes about compilers"
+
+            [[authors]]
+            name = "Bob"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.authors.len(), 2);
+        assert_eq!(config.authors[0].name, "Alice");
+        assert_eq!(
+            config.authors[0].bio.as_deref(),
+            Some("Writes about compilers")
+        );
+        assert_eq!(config.authors[1].avatar, None);
+    }
+}
@@ -0,0 +1,86 @@
+//! `[[blogroll]]` section configuration.
+//!
+//! Sites recommended by the author, rendered as a blogroll page and an OPML file.
+
+use serde::{Deserialize, Serialize};
+
+/// `[[blogroll]]` entry in tola.toml (or in the `[build.blogroll] file`).
+///
+/// # Example
+/// ```toml
+/// [[blogroll]]
+/// title = "Example Blog"
+/// url = "https://blog.example.org"
+/// feed = "https://blog.example.org/feed.xml"
+/// description = "Notes on compilers"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlogrollEntry {
+    /// Site name
+    pub title: String,
+
+    /// Site URL
+    pub url: String,
+
+    /// RSS/Atom feed URL
+    #[serde(default)]
+    pub feed: Option<String>,
+
+    /// Short description shown next to the link
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Blogroll data file: a TOML file holding only `[[blogroll]]` entries
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlogrollFile {
+    #[serde(default)]
+    pub blogroll: Vec<BlogrollEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SiteConfig;
+    use super::BlogrollFile;
+
+    #[test]
+    fn test_blogroll_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [[blogroll]]
+            title = "Example"
+            url = "https://example.org"
+            feed = "https://example.org/feed.xml"
+
+            [[blogroll]]
+            title = "No Feed"
+            url = "https://nofeed.org"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.blogroll.len(), 2);
+        assert_eq!(config.blogroll[0].title, "Example");
+        assert_eq!(
+            config.blogroll[0].feed.as_deref(),
+            Some("https://example.org/feed.xml")
+        );
+        assert_eq!(config.blogroll[1].feed, None);
+        assert_eq!(config.blogroll[1].description, None);
+    }
+
+    #[test]
+    fn test_blogroll_file() {
+        let file = r#"
+            [[blogroll]]
+            title = "Example"
+            url = "https://example.org"
+        "#;
+        let file: BlogrollFile = toml::from_str(file).unwrap();
+        assert_eq!(file.blogroll.len(), 1);
+    }
+}
@@ -5,7 +5,10 @@
 use super::defaults;
 use educe::Educe;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 // ============================================================================
 // Enums
@@ -24,6 +27,139 @@ pub enum SlugMode {
     No,
 }
 
+/// Case applied to slugified paths and anchors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugCase {
+    /// Keep the original case (default).
+    #[default]
+    Preserve,
+    /// Lowercase, so `/About` and `/about` resolve to the same page.
+    Lower,
+}
+
+/// Feed formats written by `[build.rss]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    /// RSS 2.0 only (default).
+    #[default]
+    Rss,
+    /// Atom 1.0 only.
+    Atom,
+    /// Both RSS 2.0 and Atom 1.0.
+    Both,
+}
+
+/// How often a page is expected to change, for `[build.sitemap]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeFreq {
+    Always,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    Never,
+}
+
+impl ChangeFreq {
+    /// Value of the sitemap `<changefreq>` element
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::Yearly => "yearly",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// Order of the posts in the `[build.rss]` feeds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedSort {
+    /// Newest `date` first (default).
+    #[default]
+    Date,
+    /// Most recently `update`d (or published) first.
+    Update,
+}
+
+/// Where `[build.anchors]` puts the anchor inside a heading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorPosition {
+    /// Before the heading text.
+    Before,
+    /// After the heading text (default).
+    #[default]
+    After,
+}
+
+/// Layout of the `[build.search]` index file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchFormat {
+    /// Array of documents, for filtering in the browser (default).
+    #[default]
+    Json,
+    /// Serialized index loadable with `elasticlunr.Index.load`.
+    Elasticlunr,
+}
+
+/// Page field indexed by `[build.search]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    /// `title` from the page metadata.
+    Title,
+    /// `summary` from the page metadata, as text.
+    Summary,
+    /// Visible text of the page.
+    Body,
+}
+
+/// How `[build.link_check]` reports broken links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkCheckLevel {
+    /// Report broken links as warnings (default).
+    #[default]
+    Warn,
+    /// Fail the build on broken links.
+    Error,
+}
+
+/// Host-specific redirect file written by `[build.redirects]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedirectFile {
+    /// Netlify and Cloudflare Pages `_redirects`.
+    #[serde(rename = "_redirects")]
+    Redirects,
+    /// Netlify `netlify.toml`.
+    #[serde(rename = "netlify.toml")]
+    Netlify,
+    /// Vercel `vercel.json`.
+    #[serde(rename = "vercel.json")]
+    Vercel,
+}
+
+impl RedirectFile {
+    /// Name of the file in the output directory
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::Redirects => "_redirects",
+            Self::Netlify => "netlify.toml",
+            Self::Vercel => "vercel.json",
+        }
+    }
+}
+
 /// SVG image extraction method for embedded raster images.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -41,186 +177,1871 @@ pub enum ExtractSvgType {
     Embedded,
 }
 
-// ============================================================================
-// Main BuildConfig
-// ============================================================================
+/// Compiler of the pages in `[build.typst]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypstBackend {
+    /// Typst compiled into tola, sharing fonts across pages (default).
+    #[default]
+    Library,
+    /// The `command` of `[build.typst]`, one process per page.
+    Cli,
+}
+
+// ============================================================================
+// Main BuildConfig
+// ============================================================================
+
+/// `[build]` section in tola.toml - build pipeline configuration.
+///
+/// # Example
+/// ```toml
+/// [build]
+/// content = "content"      # Source directory
+/// output = "public"        # Output directory
+/// minify = true            # Minify HTML
+///
+/// [build.typst]
+/// backend = "library"     # Or "cli" to run `command`
+/// command = ["typst"]
+///
+/// [build.typst.svg]
+/// dpi = 144.0
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildConfig {
+    /// Project root directory (usually set via CLI `--root`).
+    #[serde(default = "defaults::build::root")]
+    #[educe(Default = defaults::build::root())]
+    pub root: Option<PathBuf>,
+
+    /// URL base path prefix (e.g., "blog" → `/blog/...`).
+    #[serde(default = "defaults::build::base_path")]
+    #[educe(Default = defaults::build::base_path())]
+    pub base_path: PathBuf,
+
+    /// Content source directory (Typst files).
+    #[serde(default = "defaults::build::content")]
+    #[educe(Default = defaults::build::content())]
+    pub content: PathBuf,
+
+    /// Build output directory.
+    #[serde(default = "defaults::build::output")]
+    #[educe(Default = defaults::build::output())]
+    pub output: PathBuf,
+
+    /// Static assets directory (images, CSS, JS).
+    #[serde(default = "defaults::build::assets")]
+    #[educe(Default = defaults::build::assets())]
+    pub assets: PathBuf,
+
+    /// HTML template directory.
+    #[serde(default = "defaults::build::templates")]
+    #[educe(Default = defaults::build::templates())]
+    pub templates: PathBuf,
+
+    /// Shared Typst utilities directory.
+    #[serde(default = "defaults::build::utils")]
+    #[educe(Default = defaults::build::utils())]
+    pub utils: PathBuf,
+
+    /// Minify HTML output (removes whitespace), and CSS/JS assets as set in
+    /// `[build.minify_assets]`.
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub minify: bool,
+
+    /// Clear output directory before each build.
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub clear: bool,
+
+    /// Remove output files no longer produced by any source after each build.
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub prune: bool,
+
+    /// Build pages whose metadata has `draft: true` (CLI `--drafts`).
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub drafts: bool,
+
+    /// Build posts whose `date` is in the future (CLI `--future`).
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub future: bool,
+
+    /// Report files that fail to build without failing the build, keeping
+    /// their previous output. Defaults to on for `tola serve`, off otherwise.
+    #[serde(default)]
+    pub keep_going: Option<bool>,
+
+    /// Recompile every page, ignoring the build cache (CLI `build --force`).
+    #[serde(skip)]
+    pub force: bool,
+
+    /// Write extracted SVGs as SVG files instead of encoding them
+    /// (CLI `--skip-images`), for faster rebuilds while writing.
+    #[serde(skip)]
+    pub skip_images: bool,
+
+    /// Build cache directory (fingerprints of copied assets, etc.).
+    #[serde(default = "defaults::build::cache")]
+    #[educe(Default = defaults::build::cache())]
+    pub cache: PathBuf,
+
+    /// RSS feed generation settings.
+    #[serde(default)]
+    pub rss: RssConfig,
+
+    /// URL slugification settings.
+    #[serde(default)]
+    pub slug: SlugConfig,
+
+    /// Typst compiler configuration.
+    #[serde(default)]
+    pub typst: TypstConfig,
+
+    /// Tailwind CSS integration.
+    #[serde(default)]
+    pub tailwind: TailwindConfig,
+
+    /// Custom `<head>` elements.
+    #[serde(default)]
+    pub head: HeadConfig,
+
+    /// Worker thread pool sizes.
+    #[serde(default)]
+    pub threads: ThreadsConfig,
+
+    /// Sanitizer for untrusted HTML fragments.
+    #[serde(default)]
+    pub sanitize: SanitizeConfig,
+
+    /// Comment widget injected into post pages.
+    #[serde(default)]
+    pub comments: CommentsConfig,
+
+    /// Analytics snippet injected into every page.
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+
+    /// Web app manifest and offline service worker.
+    #[serde(default)]
+    pub pwa: PwaConfig,
+
+    /// Webmention endpoints, microformats and outbound link list.
+    #[serde(default)]
+    pub webmention: WebmentionConfig,
+
+    /// Blogroll page and OPML output.
+    #[serde(default)]
+    pub blogroll: BlogrollConfig,
+
+    /// Plain-text page variants and `llms.txt`.
+    #[serde(default)]
+    pub text: TextConfig,
+
+    /// JSON endpoints listing posts.
+    #[serde(default)]
+    pub api: ApiConfig,
+
+    /// Per-author pages and feeds.
+    #[serde(default)]
+    pub authors: AuthorsConfig,
+
+    /// Previous/next links between posts.
+    #[serde(default)]
+    pub nav: NavConfig,
+
+    /// Attributes added to external links.
+    #[serde(default)]
+    pub links: LinksConfig,
+
+    /// Tag and category listing pages.
+    #[serde(default)]
+    pub taxonomies: TaxonomiesConfig,
+
+    /// Client-side search index.
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// Splitting of post listings into pages.
+    #[serde(default)]
+    pub pagination: PaginationConfig,
+
+    /// Validation of the links in generated pages.
+    #[serde(default)]
+    pub link_check: LinkCheckConfig,
+
+    /// Redirects from old paths to their new location.
+    #[serde(default)]
+    pub redirects: RedirectsConfig,
+
+    /// XML sitemap for search engines.
+    #[serde(default)]
+    pub sitemap: SitemapConfig,
+
+    /// Summaries derived from the content of pages without one.
+    #[serde(default)]
+    pub summary: SummaryConfig,
+
+    /// Table of contents built from the headings of each page.
+    #[serde(default)]
+    pub toc: TocConfig,
+
+    /// Anchor links inside headings.
+    #[serde(default)]
+    pub anchors: AnchorsConfig,
+
+    /// Content-hashed copies of the assets.
+    #[serde(default)]
+    pub fingerprint: FingerprintConfig,
+
+    /// Minification of CSS and JS assets.
+    #[serde(default)]
+    pub minify_assets: MinifyAssetsConfig,
+
+    /// Built-in stylesheet processing.
+    #[serde(default)]
+    pub css: CssConfig,
+
+    /// Brotli and gzip copies of the text output.
+    #[serde(default)]
+    pub precompress: PrecompressConfig,
+
+    /// Timing report printed after each build.
+    #[serde(default)]
+    pub stats: StatsConfig,
+
+    /// Per-directory defaults and listings from `_section.toml` files.
+    #[serde(default)]
+    pub sections: SectionsConfig,
+
+    /// Byte-identical output for builds of the same commit.
+    #[serde(default)]
+    pub reproducible: ReproducibleConfig,
+
+    /// Word count and reading time of every page.
+    #[serde(default)]
+    pub reading_time: ReadingTimeConfig,
+
+    /// Index of the pages linking to each page.
+    #[serde(default)]
+    pub backlinks: BacklinksConfig,
+
+    /// Listings of the posts of each year and month.
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+
+    /// Rewrite rules applied to the elements of every page.
+    #[serde(default)]
+    pub html: HtmlConfig,
+
+    /// Attributes added to the `<img>` elements of pages.
+    #[serde(default)]
+    pub images: ImagesConfig,
+}
+
+// ============================================================================
+// Sub-configurations
+// ============================================================================
+
+/// `[build.rss]` section
+///
+/// # Example
+/// ```toml
+/// [build.rss]
+/// enable = true
+/// format = "both"          # "rss" (default), "atom" or "both"
+/// path = "feed.xml"
+/// atom_path = "atom.xml"
+/// full_content = true      # whole post bodies, not just summaries
+/// limit = 20               # only the 20 newest posts
+/// sort = "update"          # "date" (default) or "update"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct RssConfig {
+    /// Enable RSS feed generation
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = defaults::r#false())]
+    pub enable: bool,
+
+    /// Output path for RSS feed file
+    #[serde(default = "defaults::build::rss::path")]
+    #[educe(Default = defaults::build::rss::path())]
+    pub path: PathBuf,
+
+    /// Feed formats to generate
+    #[serde(default)]
+    pub format: FeedFormat,
+
+    /// Output path for Atom feed file
+    #[serde(default = "defaults::build::rss::atom_path")]
+    #[educe(Default = defaults::build::rss::atom_path())]
+    pub atom_path: PathBuf,
+
+    /// JSON Feed written alongside the other formats
+    #[serde(default)]
+    pub json: JsonFeedConfig,
+
+    /// OPML list of every feed of the site
+    #[serde(default)]
+    pub opml: FeedsOpmlConfig,
+
+    /// Include the rendered body of each post (`content:encoded` in RSS,
+    /// `content` in Atom, `content_html` in JSON Feed)
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub full_content: bool,
+
+    /// Most posts per feed, after sorting (all if unset)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Order of the posts
+    #[serde(default)]
+    pub sort: FeedSort,
+
+    /// Feeds of single content sections
+    #[serde(default)]
+    pub sections: SectionFeedsConfig,
+}
+
+/// `[build.rss.sections]` section
+///
+/// Writes a feed per top-level content directory, such as `posts/feed.xml`
+/// and `notes/feed.xml`, in the formats of `[build.rss] format`. The options
+/// of `[build.rss]` (full content, limit, sort) apply to them as well.
+///
+/// # Example
+/// ```toml
+/// [build.rss.sections]
+/// enable = true
+/// names = ["posts", "notes"]   # every section with posts if empty
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SectionFeedsConfig {
+    /// Generate the section feeds
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Sections to write feeds for (all if empty)
+    #[serde(default)]
+    pub names: Vec<String>,
+}
+
+/// `[build.rss.json]` section
+///
+/// Writes a [JSON Feed 1.1](https://jsonfeed.org/version/1.1) of the site's
+/// posts, in addition to the `format` feeds of `[build.rss]`.
+///
+/// # Example
+/// ```toml
+/// [build.rss.json]
+/// enable = true
+/// path = "feed.json"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct JsonFeedConfig {
+    /// Generate the JSON Feed
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Output path for the JSON Feed file
+    #[serde(default = "defaults::build::rss::json_path")]
+    #[educe(Default = defaults::build::rss::json_path())]
+    pub path: PathBuf,
+}
+
+/// `[build.rss.opml]` section
+///
+/// Lists every feed written during the build (the site feed, and the section,
+/// author and taxonomy feeds) in an OPML file, so readers can subscribe to
+/// all of them in one import.
+///
+/// # Example
+/// ```toml
+/// [build.rss.opml]
+/// enable = true
+/// path = "feeds.opml"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct FeedsOpmlConfig {
+    /// Generate the OPML file
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Output path for the OPML file
+    #[serde(default = "defaults::build::rss::opml_path")]
+    #[educe(Default = defaults::build::rss::opml_path())]
+    pub path: PathBuf,
+}
+
+/// `[build.slug]` section
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SlugConfig {
+    /// Slugify URL paths
+    #[serde(default = "defaults::build::slug::default")]
+    #[educe(Default = defaults::build::slug::default())]
+    pub path: SlugMode,
+
+    /// Slugify URL fragments (anchors)
+    #[serde(default = "defaults::build::slug::on")]
+    #[educe(Default = defaults::build::slug::on())]
+    pub fragment: SlugMode,
+
+    /// Case folding for paths and fragments (after NFC normalization)
+    #[serde(default = "defaults::build::slug::case")]
+    #[educe(Default = defaults::build::slug::case())]
+    pub case: SlugCase,
+}
+
+/// `[build.typst]` section
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct TypstConfig {
+    /// Compiler of the pages; `command` is still used to export PDFs
+    #[serde(default)]
+    pub backend: TypstBackend,
+
+    /// Typst command and arguments
+    #[serde(default = "defaults::build::typst::command")]
+    #[educe(Default = defaults::build::typst::command())]
+    pub command: Vec<String>,
+
+    /// SVG processing options
+    #[serde(default)]
+    pub svg: TypstSvgConfig,
+}
+
+/// `[build.typst.svg]` section
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct TypstSvgConfig {
+    /// Method for extracting embedded SVG images
+    #[serde(default = "defaults::build::typst::svg::extract_type")]
+    #[educe(Default = defaults::build::typst::svg::extract_type())]
+    pub extract_type: ExtractSvgType,
+
+    /// Max size for inline SVG (e.g.: "20KB", "1MB")
+    #[serde(default = "defaults::build::typst::svg::inline_max_size")]
+    #[educe(Default = defaults::build::typst::svg::inline_max_size())]
+    pub inline_max_size: String,
+
+    /// DPI for SVG rendering
+    #[serde(default = "defaults::build::typst::svg::dpi")]
+    #[educe(Default = defaults::build::typst::svg::dpi())]
+    pub dpi: f32,
+
+    /// Text alternative of SVGs that carry none (such as `"Formula"`);
+    /// empty marks them as decorative
+    #[serde(default)]
+    pub alt: String,
+
+    /// Directory (relative to output directory) shared by the SVG files of
+    /// all pages, so identical SVGs are written once per site; files are
+    /// written next to each page if unset
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// Fallback formats written alongside AVIF
+    #[serde(default)]
+    pub formats: SvgFormatsConfig,
+
+    /// Settings of the builtin AVIF encoder
+    #[serde(default)]
+    pub avif: SvgAvifConfig,
+
+    /// Rendering for dark color schemes
+    #[serde(default)]
+    pub dark: SvgDarkConfig,
+}
+
+/// How SVGs adapt to dark color schemes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SvgDarkMode {
+    /// Keep the colors typst wrote.
+    #[default]
+    None,
+    /// Black fills and strokes of SVGs kept in the page become `currentColor`.
+    CurrentColor,
+    /// Like `current-color`, and extracted SVGs also get a dark variant,
+    /// picked with `prefers-color-scheme` in a `<picture>`.
+    Paired,
+}
+
+/// `[build.typst.svg.dark]` section
+///
+/// Typst draws text and formulas in black, which disappears on dark
+/// backgrounds. SVGs kept in the page can follow the text color of the page;
+/// images (`<img>`) cannot, so `paired` also writes a copy of each extracted
+/// SVG with black replaced by `color`, shown when the reader prefers a dark
+/// color scheme.
+///
+/// # Example
+/// ```toml
+/// [build.typst.svg.dark]
+/// mode = "paired"   # "none", "current-color" or "paired"
+/// color = "#e6e6e6"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SvgDarkConfig {
+    /// How SVGs adapt to dark color schemes
+    #[serde(default)]
+    pub mode: SvgDarkMode,
+
+    /// Color replacing black in the dark variants of extracted SVGs
+    #[serde(default = "defaults::build::typst::svg::dark::color")]
+    #[educe(Default = defaults::build::typst::svg::dark::color())]
+    pub color: String,
+}
+
+/// `[build.typst.svg.avif]` section
+///
+/// Settings of the AVIF encoder used with `extract_type = "builtin"`, which
+/// renders each SVG at `dpi` before encoding it.
+///
+/// # Example
+/// ```toml
+/// [build.typst.svg.avif]
+/// quality = 80.0   # 1 to 100
+/// speed = 6        # 1 (smallest files) to 10 (fastest)
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SvgAvifConfig {
+    /// Encoding quality, from 1 to 100
+    #[serde(default = "defaults::build::typst::svg::avif::quality")]
+    #[educe(Default = defaults::build::typst::svg::avif::quality())]
+    pub quality: f32,
+
+    /// Encoding speed, from 1 (slowest, smallest) to 10 (fastest)
+    #[serde(default = "defaults::build::typst::svg::avif::speed")]
+    #[educe(Default = defaults::build::typst::svg::avif::speed())]
+    pub speed: u8,
+}
+
+/// `[build.typst.svg.formats]` section
+///
+/// Raster formats written alongside the AVIF of each extracted SVG, which the
+/// page then references through a `<picture>`, so browsers without AVIF
+/// support fall back to WebP or PNG. SVGs below `inline_max_size` are still
+/// written as SVG. WebP needs `extract_type = "magick"` or `"ffmpeg"`.
+///
+/// # Example
+/// ```toml
+/// [build.typst.svg.formats]
+/// webp = true
+/// png = true   # the <img> itself, for browsers without WebP either
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SvgFormatsConfig {
+    /// Write a WebP fallback
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub webp: bool,
+
+    /// Write a PNG fallback
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub png: bool,
+}
+
+/// `[build.tailwind]` section
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct TailwindConfig {
+    /// Enable Tailwind CSS processing
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Input CSS file path
+    #[serde(default = "defaults::build::tailwind::input")]
+    #[educe(Default = defaults::build::tailwind::input())]
+    pub input: Option<PathBuf>,
+
+    /// Tailwind command and arguments
+    #[serde(default = "defaults::build::tailwind::command")]
+    #[educe(Default = defaults::build::tailwind::command())]
+    pub command: Vec<String>,
+}
+
+/// `[build.threads]` section
+///
+/// Blocking work (typst subprocesses, file copies), CPU-bound work
+/// (minification) and image encoding (SVG/AVIF) run on separate threads.
+/// `0` means one thread per available core.
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ThreadsConfig {
+    /// Threads for CPU-bound work
+    #[serde(default = "defaults::build::threads::auto")]
+    #[educe(Default = defaults::build::threads::auto())]
+    pub cpu: usize,
+
+    /// Threads for blocking I/O and subprocess waits
+    #[serde(default = "defaults::build::threads::auto")]
+    #[educe(Default = defaults::build::threads::auto())]
+    pub io: usize,
+
+    /// Images encoded at once
+    #[serde(default = "defaults::build::threads::auto")]
+    #[educe(Default = defaults::build::threads::auto())]
+    pub images: usize,
+}
+
+/// `[build.sanitize]` section
+///
+/// When enabled, raw HTML fragments (such as `[build.head] elements`) are
+/// reduced to the allowed elements and attributes. `<script>`, `<style>` and
+/// similar elements are always removed with their content, as are `on*`
+/// event handlers and `javascript:` URLs.
+///
+/// # Example
+/// ```toml
+/// [build.sanitize]
+/// enable = true
+/// tags = ["meta", "link", "p", "a"]
+/// attributes = ["name", "content", "rel", "href"]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SanitizeConfig {
+    /// Sanitize untrusted HTML fragments
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Allowed element names; other elements are unwrapped, keeping their text
+    #[serde(default = "defaults::build::sanitize::tags")]
+    #[educe(Default = defaults::build::sanitize::tags())]
+    pub tags: Vec<String>,
+
+    /// Allowed attribute names
+    #[serde(default = "defaults::build::sanitize::attributes")]
+    #[educe(Default = defaults::build::sanitize::attributes())]
+    pub attributes: Vec<String>,
+}
+
+/// Comment system backed by GitHub.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentsProvider {
+    /// [giscus](https://giscus.app), backed by GitHub Discussions (default).
+    #[default]
+    Giscus,
+    /// [utterances](https://utteranc.es), backed by GitHub Issues.
+    Utterances,
+}
+
+/// `[build.comments]` section
+///
+/// Injects the provider's script at the end of every post (a page whose
+/// metadata has a `date`). A page can opt in or out with `comments: true/false`
+/// in its metadata.
+///
+/// # Example
+/// ```toml
+/// [build.comments]
+/// enable = true
+/// provider = "giscus"
+/// repo = "user/blog-comments"
+/// repo_id = "R_kgDO..."
+/// category = "Announcements"
+/// category_id = "DIC_kwDO..."
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct CommentsConfig {
+    /// Enable comment injection
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Comment provider
+    #[serde(default)]
+    pub provider: CommentsProvider,
+
+    /// GitHub repository holding the comments (`owner/name`)
+    #[serde(default)]
+    pub repo: String,
+
+    /// Repository ID (giscus only)
+    #[serde(default)]
+    pub repo_id: String,
+
+    /// Discussion category name (giscus only)
+    #[serde(default)]
+    pub category: String,
+
+    /// Discussion category ID (giscus only)
+    #[serde(default)]
+    pub category_id: String,
+
+    /// How a page maps to its discussion/issue (`pathname`, `url`, `title`, `og:title`)
+    #[serde(default = "defaults::build::comments::mapping")]
+    #[educe(Default = defaults::build::comments::mapping())]
+    pub mapping: String,
+
+    /// Widget theme (provider default if unset)
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+/// Analytics service.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsProvider {
+    /// [Plausible](https://plausible.io) (default).
+    #[default]
+    Plausible,
+    /// [GoatCounter](https://www.goatcounter.com).
+    GoatCounter,
+    /// [umami](https://umami.is), usually self-hosted.
+    Umami,
+    /// Any other script, loaded from `src`.
+    Custom,
+}
+
+/// `[build.analytics]` section
+///
+/// Plausible, umami and custom scripts are added to `<head>`; GoatCounter is
+/// added at the end of `<body>`. Nothing is injected during `tola serve` unless
+/// `serve = true`, so local previews don't count as visits.
+///
+/// # Example
+/// ```toml
+/// [build.analytics]
+/// enable = true
+/// provider = "umami"
+/// src = "https://stats.example.com/script.js"
+/// website_id = "94db1cb1-74f4-4a40-ad6c-962362670409"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// Enable analytics injection
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Analytics provider
+    #[serde(default)]
+    pub provider: AnalyticsProvider,
+
+    /// Script URL; overrides the provider's hosted script (required for umami and custom)
+    #[serde(default)]
+    pub src: Option<String>,
+
+    /// Site domain reported to Plausible (host of `[base.url]` if unset)
+    #[serde(default)]
+    pub domain: Option<String>,
+
+    /// Site code, as in `<code>.goatcounter.com` (GoatCounter only)
+    #[serde(default)]
+    pub code: String,
+
+    /// Website ID (umami only)
+    #[serde(default)]
+    pub website_id: String,
+
+    /// Also inject during `tola serve`
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub serve: bool,
+}
+
+/// `[build.pwa]` section
+///
+/// Generates `manifest.webmanifest` and a service worker (`sw.js`) that
+/// precaches every built page and asset, and links both from `<head>`. The
+/// service worker is not registered during `tola serve`, so it never caches
+/// pages that are being edited.
+///
+/// # Example
+/// ```toml
+/// [build.pwa]
+/// enable = true
+/// short_name = "Blog"
+/// theme_color = "#1e1e2e"
+/// icons = [{ path = "images/icon-512.png", sizes = "512x512" }]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct PwaConfig {
+    /// Generate the manifest and service worker
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// App name (`[base.title]` if unset)
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Short name shown under the app icon (`name` if unset)
+    #[serde(default)]
+    pub short_name: Option<String>,
+
+    /// Browser UI color
+    #[serde(default = "defaults::build::pwa::color")]
+    #[educe(Default = defaults::build::pwa::color())]
+    pub theme_color: String,
+
+    /// Splash screen color
+    #[serde(default = "defaults::build::pwa::color")]
+    #[educe(Default = defaults::build::pwa::color())]
+    pub background_color: String,
+
+    /// Display mode (`standalone`, `minimal-ui`, `browser`, `fullscreen`)
+    #[serde(default = "defaults::build::pwa::display")]
+    #[educe(Default = defaults::build::pwa::display())]
+    pub display: String,
+
+    /// App icons (relative to assets directory)
+    #[serde(default)]
+    pub icons: Vec<PwaIcon>,
+}
+
+/// Icon entry for `[build.pwa.icons]`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PwaIcon {
+    /// Icon path (relative to assets directory)
+    pub path: PathBuf,
+    /// Icon dimensions, e.g. `512x512`
+    pub sizes: String,
+}
+
+/// `[build.backlinks]` section
+///
+/// Writes `backlinks.json`, mapping each page URL to the pages that link to
+/// it (`url` and `title`), for "pages that link here" lists in wiki-style
+/// sites. Links are internal `<a href>`s of the built pages, relative,
+/// root-relative or under `[base] url`; a page linking to itself is left out.
+///
+/// # Example
+/// ```toml
+/// [build.backlinks]
+/// enable = true
+/// path = "backlinks.json"   # relative to the output directory
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct BacklinksConfig {
+    /// Write the backlinks index
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Output file (relative to output directory)
+    #[serde(default = "defaults::build::backlinks::path")]
+    #[educe(Default = defaults::build::backlinks::path())]
+    pub path: PathBuf,
+}
+
+/// `[build.webmention]` section
+///
+/// Advertises webmention/pingback endpoints in `<head>`, marks up posts as
+/// `h-entry` microformats, and writes `links.json` mapping each post URL to its
+/// outbound links, for a sender such as `webmention.app` to use after deploy.
+///
+/// # Example
+/// ```toml
+/// [build.webmention]
+/// enable = true
+/// endpoint = "https://webmention.io/example.com/webmention"
+/// pingback = "https://webmention.io/example.com/xmlrpc"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct WebmentionConfig {
+    /// Enable webmention support
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Webmention endpoint URL
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    /// Pingback endpoint URL
+    #[serde(default)]
+    pub pingback: Option<String>,
+
+    /// Add `h-entry` microformat markup to posts
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub h_entry: bool,
+
+    /// Outbound link list path (relative to output directory)
+    #[serde(default = "defaults::build::webmention::links")]
+    #[educe(Default = defaults::build::webmention::links())]
+    pub links: PathBuf,
+}
+
+/// `[build.blogroll]` section
+///
+/// Where the `[[blogroll]]` entries are rendered. Nothing is generated when
+/// there are no entries.
+///
+/// # Example
+/// ```toml
+/// [build.blogroll]
+/// file = "data/blogroll.toml"   # extra [[blogroll]] entries
+/// page = "friends/index.html"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct BlogrollConfig {
+    /// Data file with more `[[blogroll]]` entries (relative to site root)
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+
+    /// Page heading
+    #[serde(default = "defaults::build::blogroll::title")]
+    #[educe(Default = defaults::build::blogroll::title())]
+    pub title: String,
+
+    /// Blogroll page path (relative to output directory)
+    #[serde(default = "defaults::build::blogroll::page")]
+    #[educe(Default = defaults::build::blogroll::page())]
+    pub page: PathBuf,
+
+    /// OPML file path (relative to output directory)
+    #[serde(default = "defaults::build::blogroll::opml")]
+    #[educe(Default = defaults::build::blogroll::opml())]
+    pub opml: PathBuf,
+}
+
+/// `[build.text]` section
+///
+/// # Example
+/// ```toml
+/// [build.text]
+/// pages = true   # posts/hello/index.txt next to posts/hello/index.html
+/// llms = true    # llms.txt and llms-full.txt at the site root
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct TextConfig {
+    /// Write the visible text of each page next to its HTML
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub pages: bool,
+
+    /// Write `llms.txt` (page index) and `llms-full.txt` (all page text)
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub llms: bool,
+}
+
+/// `[build.search]` section
+///
+/// Writes an index of every page for a search box in the templates. Text is
+/// split into lowercase words, dropping `stop_words`; the `json` format keeps
+/// `title` and `summary` as written for display, with the `body` as words.
+///
+/// # Example
+/// ```toml
+/// [build.search]
+/// enable = true
+/// format = "elasticlunr"           # or "json" (default)
+/// path = "search.json"
+/// fields = ["title", "body"]
+/// stop_words = ["the", "a", "an"]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Generate the search index
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Index file layout
+    #[serde(default)]
+    pub format: SearchFormat,
+
+    /// Output path for the index file
+    #[serde(default = "defaults::build::search::path")]
+    #[educe(Default = defaults::build::search::path())]
+    pub path: PathBuf,
+
+    /// Page fields to index
+    #[serde(default = "defaults::build::search::fields")]
+    #[educe(Default = defaults::build::search::fields())]
+    pub fields: Vec<SearchField>,
+
+    /// Words left out of the index (compared in lowercase)
+    #[serde(default)]
+    pub stop_words: Vec<String>,
+}
+
+/// `[build.pagination]` section
+///
+/// Splits the author and taxonomy pages into pages of `page_size` posts: the
+/// first stays at `<dir>/index.html`, the others go to `<dir>/<path>/<n>/`.
+///
+/// A typst page declaring `paginate: true` (every post) or `paginate: "notes"`
+/// (posts under `content/notes/`) in its metadata is compiled once per page,
+/// with `sys.inputs` holding `tola-page`, `tola-pages`, `tola-page-size` and,
+/// if present, `tola-prev`/`tola-next` URLs. The template lists its slice of
+/// posts and renders its own navigation.
+///
+/// # Example
+/// ```toml
+/// [build.pagination]
+/// enable = true
+/// page_size = 10
+/// path = "page"   # tags/rust/page/2/index.html
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct PaginationConfig {
+    /// Split listings into pages
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Posts per page
+    #[serde(default = "defaults::build::pagination::page_size")]
+    #[educe(Default = defaults::build::pagination::page_size())]
+    pub page_size: usize,
+
+    /// Directory holding the pages after the first
+    #[serde(default = "defaults::build::pagination::path")]
+    #[educe(Default = defaults::build::pagination::path())]
+    pub path: PathBuf,
+}
+
+/// `[build.link_check]` section
+///
+/// Once every page is written, checks that each internal `href`/`src` in the
+/// output resolves to a file, and each `#fragment` to an element `id` in the
+/// target page. External links are only requested, with `command`, if
+/// `external` is set.
+///
+/// # Example
+/// ```toml
+/// [build.link_check]
+/// enable = true
+/// level = "error"      # or "warn" (default)
+/// external = true
+/// command = ["curl"]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct LinkCheckConfig {
+    /// Check links after the build
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Whether broken links are warnings or errors
+    #[serde(default)]
+    pub level: LinkCheckLevel,
+
+    /// Also request external links
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub external: bool,
+
+    /// Command (and arguments) used to request external links
+    #[serde(default = "defaults::build::link_check::command")]
+    #[educe(Default = defaults::build::link_check::command())]
+    pub command: Vec<String>,
+}
+
+/// `[build.api]` section
+///
+/// Writes `posts.json` listing every post (a page whose metadata has a `date`),
+/// newest first, and `sections/<name>.json` for each top-level content
+/// directory that holds posts.
+///
+/// # Example
+/// ```toml
+/// [build.api]
+/// enable = true
+/// path = "api"   # api/posts.json, api/sections/notes.json, ...
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Generate the JSON endpoints
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Endpoint directory (relative to output directory)
+    #[serde(default = "defaults::build::api::path")]
+    #[educe(Default = defaults::build::api::path())]
+    pub path: PathBuf,
+}
+
+/// `[build.sitemap]` section
+///
+/// Writes a sitemap listing every page, with `<lastmod>` from the page's
+/// `update` or `date` metadata, or the modification time of its source.
+/// `changefreq` and `priority` come from the first rule whose `pattern`
+/// matches the page's output path (relative to the output directory).
+/// Above 50,000 URLs the sitemap is split into `sitemap-1.xml`, ... and
+/// `path` becomes a sitemap index linking them.
+///
+/// # Example
+/// ```toml
+/// [build.sitemap]
+/// enable = true
+///
+/// [[build.sitemap.rules]]
+/// pattern = "posts/**"
+/// changefreq = "monthly"
+/// priority = 0.8
+///
+/// [[build.sitemap.rules]]
+/// pattern = "index.html"
+/// changefreq = "daily"
+/// priority = 1.0
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SitemapConfig {
+    /// Generate the sitemap
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Sitemap file (relative to output directory)
+    #[serde(default = "defaults::build::sitemap::path")]
+    #[educe(Default = defaults::build::sitemap::path())]
+    pub path: PathBuf,
+
+    /// `changefreq`/`priority` by path pattern, first match wins
+    #[serde(default)]
+    pub rules: Vec<SitemapRule>,
+}
+
+/// Entry of `[[build.sitemap.rules]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SitemapRule {
+    /// Glob over output paths (`*` stays within a directory, `**` crosses them)
+    pub pattern: String,
+
+    #[serde(default)]
+    pub changefreq: Option<ChangeFreq>,
+
+    /// Between 0.0 and 1.0
+    #[serde(default)]
+    pub priority: Option<f32>,
+}
+
+/// `[build.summary]` section
+///
+/// Pages whose metadata has no `summary` get the first paragraph of their
+/// content as plain text, cut at a word boundary after `length` characters.
+/// It is used for feed descriptions and the `description` meta tags.
+///
+/// # Example
+/// ```toml
+/// [build.summary]
+/// length = 200
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryConfig {
+    /// Derive summaries for pages without one
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub enable: bool,
+
+    /// Maximum length in characters
+    #[serde(default = "defaults::build::summary::length")]
+    #[educe(Default = defaults::build::summary::length())]
+    pub length: usize,
+}
+
+/// `[build.reading_time]` section
+///
+/// Counts the words of every page's content once it is compiled and derives
+/// the minutes it takes to read, rounded up. Both are written to the head as
+/// `<meta name="tola:word-count">` and `<meta name="tola:reading-time">`, to
+/// the `_tola` object of JSON feed items and to the `[build.api]` posts.
+///
+/// With `cjk` on, Chinese, Japanese and Korean characters count one by one
+/// and are read at `cjk_per_minute`; other text is split at whitespace.
+///
+/// # Example
+/// ```toml
+/// [build.reading_time]
+/// enable = true
+/// words_per_minute = 200
+/// cjk_per_minute = 500
+/// cjk = true
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ReadingTimeConfig {
+    /// Count words and estimate reading time
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Reading speed of space-separated words
+    #[serde(default = "defaults::build::reading_time::words_per_minute")]
+    #[educe(Default = defaults::build::reading_time::words_per_minute())]
+    pub words_per_minute: usize,
+
+    /// Reading speed of CJK characters
+    #[serde(default = "defaults::build::reading_time::cjk_per_minute")]
+    #[educe(Default = defaults::build::reading_time::cjk_per_minute())]
+    pub cjk_per_minute: usize,
+
+    /// Count CJK characters one by one
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub cjk: bool,
+}
+
+/// `[build.toc]` section
+///
+/// Lists the headings with an `id` (that is, with a typst label) between
+/// `min_level` and `max_level`, nested by level. A `<tola-toc>` element in a
+/// page (`#html.elem("tola-toc")`) is replaced by the list as
+/// `<nav class="toc">`; with `json`, it is also written next to each page as
+/// `index.toc.json`.
+///
+/// # Example
+/// ```toml
+/// [build.toc]
+/// enable = true
+/// min_level = 2   # typst `=` headings are <h2>
+/// max_level = 3
+/// json = true
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct TocConfig {
+    /// Build tables of contents
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Highest heading level listed (`2` for `<h2>`)
+    #[serde(default = "defaults::build::toc::min_level")]
+    #[educe(Default = defaults::build::toc::min_level())]
+    pub min_level: u8,
+
+    /// Lowest heading level listed
+    #[serde(default = "defaults::build::toc::max_level")]
+    #[educe(Default = defaults::build::toc::max_level())]
+    pub max_level: u8,
+
+    /// Also write the table of contents of each page as JSON
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub json: bool,
+}
+
+/// `[build.anchors]` section
+///
+/// Adds `<a class="anchor" href="#id">` inside every heading with an `id`
+/// (that is, with a typst label), so readers can copy a link to it.
+///
+/// # Example
+/// ```toml
+/// [build.anchors]
+/// enable = true
+/// symbol = "¶"
+/// position = "before"   # or "after" (default)
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct AnchorsConfig {
+    /// Add anchors to headings
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Text of the anchor
+    #[serde(default = "defaults::build::anchors::symbol")]
+    #[educe(Default = defaults::build::anchors::symbol())]
+    pub symbol: String,
+
+    /// Before or after the heading text
+    #[serde(default)]
+    pub position: AnchorPosition,
+}
+
+/// `[build.fingerprint]` section
+///
+/// Copies every asset to a name with a hash of its content
+/// (`styles/main.css` → `styles/main.3f2a9c1e.css`) and points the `href`
+/// and `src` attributes of all generated pages at the copies, so they can be
+/// served with far-future cache headers. The original names stay in place
+/// for links from outside the pages. `manifest` maps each asset to its copy.
+///
+/// # Example
+/// ```toml
+/// [build.fingerprint]
+/// enable = true
+/// manifest = "manifest.json"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct FingerprintConfig {
+    /// Fingerprint assets
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Manifest file (relative to output directory)
+    #[serde(default = "defaults::build::fingerprint::manifest")]
+    #[educe(Default = defaults::build::fingerprint::manifest())]
+    pub manifest: PathBuf,
+}
+
+/// `[build.minify_assets]` section
+///
+/// With `[build] minify`, `.css` and `.js` files copied from the assets and
+/// content directories are minified. A file that fails to parse is copied
+/// unchanged with a warning. The tailwind stylesheet is minified by tailwind.
+///
+/// # Example
+/// ```toml
+/// [build.minify_assets]
+/// js = false          # copy scripts as written
+/// source_maps = true  # `main.css.map` next to `main.css` under `tola serve`
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct MinifyAssetsConfig {
+    /// Minify `.css` files
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub css: bool,
+
+    /// Minify `.js` and `.mjs` files
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub js: bool,
+
+    /// Write CSS source maps while serving
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub source_maps: bool,
+}
+
+/// `[build.css]` section
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CssConfig {
+    /// Removal of unused selectors.
+    #[serde(default)]
+    pub purge: PurgeConfig,
+}
+
+/// `[build.css.purge]` section
+///
+/// Removes the rules of `stylesheets` whose selectors match nothing in the
+/// generated pages: a selector is kept if every class, id and element name
+/// in it is used by some page. Classes only added by scripts go in
+/// `safelist` (`*` matches any characters). Nothing but tola is needed, as an
+/// alternative to `[build.tailwind]`.
+///
+/// # Example
+/// ```toml
+/// [build.css.purge]
+/// enable = true
+/// stylesheets = ["assets/styles/main.css"]
+/// safelist = ["is-open", "hljs-*"]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct PurgeConfig {
+    /// Purge unused selectors
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Stylesheets in the assets directory (relative to the site root)
+    #[serde(default)]
+    pub stylesheets: Vec<PathBuf>,
+
+    /// Class and id names kept even if no page uses them
+    #[serde(default)]
+    pub safelist: Vec<String>,
+}
+
+/// `[build.precompress]` section
+///
+/// Once the build is done, every HTML, CSS, JS, SVG and XML file of at least
+/// `min_size` bytes in the output gets a `.br` and a `.gz` copy next to it, for
+/// static hosts that serve precompressed files. Copies newer than their file
+/// are kept. Nothing is compressed under `tola serve`.
+///
+/// # Example
+/// ```toml
+/// [build.precompress]
+/// enable = true
+/// gzip = false       # only `.br` copies
+/// min_size = 512     # bytes
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct PrecompressConfig {
+    /// Write compressed copies
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Write `.br` copies
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub brotli: bool,
+
+    /// Write `.gz` copies
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub gzip: bool,
+
+    /// Smallest file compressed, in bytes
+    #[serde(default = "defaults::build::precompress::min_size")]
+    #[educe(Default = defaults::build::precompress::min_size())]
+    pub min_size: u64,
+}
+
+/// `[build.stats]` section
+///
+/// Prints the time taken by each build stage and by the slowest pages once
+/// the build ends. Also enabled for one build with `tola build --stats`.
+///
+/// # Example
+/// ```toml
+/// [build.stats]
+/// enable = true
+/// slowest = 5        # pages listed
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct StatsConfig {
+    /// Print the timing report
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Number of slowest pages listed
+    #[serde(default = "defaults::build::stats::slowest")]
+    #[educe(Default = defaults::build::stats::slowest())]
+    pub slowest: usize,
+}
+
+/// `[build.reproducible]` section
+///
+/// Pins every timestamp of a build to the source date, `SOURCE_DATE_EPOCH`
+/// if set, else the commit time of the site's git `HEAD`: typst compiles with
+/// it as the current date, and output files get it as modification time.
+/// Feeds and sitemaps only carry dates from page metadata, SVGs are numbered
+/// in page order, and files are always walked in name order, so two builds of
+/// the same commit write the same bytes.
+///
+/// # Example
+/// ```toml
+/// [build.reproducible]
+/// enable = true
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ReproducibleConfig {
+    /// Pin timestamps to the source date
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+}
+
+/// `[build.sections]` section
+///
+/// Every content directory holding a `_section.toml` is a section. Its
+/// settings cascade to the pages below it, a nested section overriding its
+/// parents: taxonomy terms a page does not declare, and the `template` passed
+/// to typst as `sys.inputs.tola-template`. Each section also gets a listing of
+/// its posts at `<dir>/index.html`, unless a page is already written there,
+/// ordered by `sort_by` and split into pages of `page_size` posts.
+///
+/// # Example
+/// ```toml
+/// [build.sections]
+/// enable = true
+/// ```
+///
+/// `content/notes/_section.toml`:
+/// ```toml
+/// title = "Notes"        # listing heading (default: the directory name)
+/// template = "note.typ"  # sys.inputs.tola-template of every page below
+/// sort_by = "title"      # "date" (newest first, default) or "title"
+/// page_size = 20         # instead of [build.pagination] page_size
+///
+/// [taxonomies]
+/// categories = ["notes"]
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct SectionsConfig {
+    /// Read `_section.toml` files and write section listings
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+}
+
+/// `[build.authors]` section
+///
+/// Generates `<path>/<name>/index.html` for every author named in post
+/// metadata, listing their posts with the bio and avatar from `[[authors]]`.
+/// With `[build.rss]` enabled, each author also gets a `feed.xml` and/or
+/// `atom.xml`, depending on its `format`.
+///
+/// # Example
+/// ```toml
+/// [build.authors]
+/// enable = true
+/// path = "authors"   # authors/alice/index.html, authors/alice/feed.xml
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorsConfig {
+    /// Generate author pages
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Directory of the author pages (relative to output directory)
+    #[serde(default = "defaults::build::authors::path")]
+    #[educe(Default = defaults::build::authors::path())]
+    pub path: PathBuf,
+}
+
+/// `[build.archive]` section
+///
+/// Groups posts by the year and month of their `date`: each year gets
+/// `<path>/<year>/index.html` and, with `months`, each month
+/// `<path>/<year>/<month>/index.html`, both paginated with
+/// `[build.pagination]`; `<path>/index.html` lists every year and month.
+///
+/// # Example
+/// ```toml
+/// [build.archive]
+/// enable = true
+/// path = "archive"   # archive/2024/05/index.html
+/// months = true
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Generate archive pages
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
+
+    /// Directory of the archive pages (relative to output directory)
+    #[serde(default = "defaults::build::archive::path")]
+    #[educe(Default = defaults::build::archive::path())]
+    pub path: PathBuf,
 
-/// `[build]` section in tola.toml - build pipeline configuration.
+    /// Also generate a page per month
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub months: bool,
+}
+
+/// `[build.html]` section
+///
+/// Each `[[build.html.rewrite]]` rule matches elements by tag and, optionally,
+/// by an attribute and a glob over its value. Matching elements get their
+/// attributes renamed, removed and set, in that order, and can be wrapped in
+/// another element. Every matching rule applies, in order.
+///
+/// Rules see the elements tola writes as-is, not `<html>`, `<body>`,
+/// headings or `<svg>`.
 ///
 /// # Example
 /// ```toml
-/// [build]
-/// content = "content"      # Source directory
-/// output = "public"        # Output directory
-/// minify = true            # Minify HTML
+/// [[build.html.rewrite]]
+/// tag = "table"
+/// wrap = { tag = "div", attributes = { class = "table-wrapper" } }
 ///
-/// [build.typst]
-/// command = ["typst"]
+/// [[build.html.rewrite]]
+/// tag = "img"
+/// set = { loading = "lazy", decoding = "async" }
 ///
-/// [build.typst.svg]
-/// dpi = 144.0
+/// [[build.html.rewrite]]
+/// tag = "a"
+/// attribute = "href"
+/// value = "*.pdf"
+/// set = { download = "" }
 /// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
-#[serde(default, deny_unknown_fields)]
-pub struct BuildConfig {
-    /// Project root directory (usually set via CLI `--root`).
-    #[serde(default = "defaults::build::root")]
-    #[educe(Default = defaults::build::root())]
-    pub root: Option<PathBuf>,
-
-    /// URL base path prefix (e.g., "blog" → `/blog/...`).
-    #[serde(default = "defaults::build::base_path")]
-    #[educe(Default = defaults::build::base_path())]
-    pub base_path: PathBuf,
-
-    /// Content source directory (Typst files).
-    #[serde(default = "defaults::build::content")]
-    #[educe(Default = defaults::build::content())]
-    pub content: PathBuf,
-
-    /// Build output directory.
-    #[serde(default = "defaults::build::output")]
-    #[educe(Default = defaults::build::output())]
-    pub output: PathBuf,
-
-    /// Static assets directory (images, CSS, JS).
-    #[serde(default = "defaults::build::assets")]
-    #[educe(Default = defaults::build::assets())]
-    pub assets: PathBuf,
-
-    /// HTML template directory.
-    #[serde(default = "defaults::build::templates")]
-    #[educe(Default = defaults::build::templates())]
-    pub templates: PathBuf,
+#[serde(deny_unknown_fields)]
+pub struct HtmlConfig {
+    /// Rules applied to each element, in order
+    #[serde(default)]
+    pub rewrite: Vec<RewriteRule>,
+}
 
-    /// Shared Typst utilities directory.
-    #[serde(default = "defaults::build::utils")]
-    #[educe(Default = defaults::build::utils())]
-    pub utils: PathBuf,
+/// Entry of `[[build.html.rewrite]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteRule {
+    /// Element name, or `*` for any element
+    pub tag: String,
 
-    /// Minify HTML output (removes whitespace).
-    #[serde(default = "defaults::r#true")]
-    #[educe(Default = true)]
-    pub minify: bool,
+    /// Attribute the element must have
+    #[serde(default)]
+    pub attribute: Option<String>,
 
-    /// Clear output directory before each build.
-    #[serde(default = "defaults::r#false")]
-    #[educe(Default = false)]
-    pub clear: bool,
+    /// Glob the value of `attribute` must match
+    #[serde(default)]
+    pub value: Option<String>,
 
-    /// RSS feed generation settings.
+    /// Attributes to rename, old name to new name
     #[serde(default)]
-    pub rss: RssConfig,
+    pub rename: BTreeMap<String, String>,
 
-    /// URL slugification settings.
+    /// Attributes to remove
     #[serde(default)]
-    pub slug: SlugConfig,
+    pub remove: Vec<String>,
 
-    /// Typst compiler configuration.
+    /// Attributes to add, replacing existing values
     #[serde(default)]
-    pub typst: TypstConfig,
+    pub set: BTreeMap<String, String>,
 
-    /// Tailwind CSS integration.
+    /// Element to wrap matching elements in
     #[serde(default)]
-    pub tailwind: TailwindConfig,
+    pub wrap: Option<WrapRule>,
+}
+
+/// `wrap` of a `[[build.html.rewrite]]` rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WrapRule {
+    pub tag: String,
 
-    /// Custom `<head>` elements.
     #[serde(default)]
-    pub head: HeadConfig,
+    pub attributes: BTreeMap<String, String>,
 }
 
-// ============================================================================
-// Sub-configurations
-// ============================================================================
+/// `[build.images]` section
+///
+/// # Example
+/// ```toml
+/// [build.images.lazy]
+/// enable = true
+/// dimensions = true
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// Lazy loading of images
+    #[serde(default)]
+    pub lazy: LazyImagesConfig,
+}
 
-/// `[build.rss]` section
+/// `[build.images.lazy]` section
+///
+/// Every `<img>` gets `loading="lazy"` and `decoding="async"`, and, with
+/// `dimensions`, the `width` and `height` of its file, so the browser reserves
+/// its space before it loads. Attributes already set are kept, and images
+/// whose file cannot be read (external or missing) get no dimensions.
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
-pub struct RssConfig {
-    /// Enable RSS feed generation
+pub struct LazyImagesConfig {
+    /// Add the lazy-loading attributes
     #[serde(default = "defaults::r#false")]
-    #[educe(Default = defaults::r#false())]
+    #[educe(Default = false)]
     pub enable: bool,
 
-    /// Output path for RSS feed file
-    #[serde(default = "defaults::build::rss::path")]
-    #[educe(Default = defaults::build::rss::path())]
-    pub path: PathBuf,
+    /// Also add `width` and `height` read from the image file
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub dimensions: bool,
 }
 
-/// `[build.slug]` section
+/// `[build.taxonomies]` section
+///
+/// Every name is a metadata key holding a term or a list of terms (e.g.
+/// `tags: ("rust", "typst")`). Each term gets `<name>/<term>/index.html`
+/// listing its posts, plus feeds with `[build.rss]` enabled, and each
+/// taxonomy gets `<name>/index.html` listing its terms.
+///
+/// # Example
+/// ```toml
+/// [build.taxonomies]
+/// enable = true
+/// names = ["tags", "categories"]   # tags/rust/index.html, categories/notes/feed.xml
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
-pub struct SlugConfig {
-    /// Slugify URL paths
-    #[serde(default = "defaults::build::slug::default")]
-    #[educe(Default = defaults::build::slug::default())]
-    pub path: SlugMode,
+pub struct TaxonomiesConfig {
+    /// Generate taxonomy pages
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
 
-    /// Slugify URL fragments (anchors)
-    #[serde(default = "defaults::build::slug::on")]
-    #[educe(Default = defaults::build::slug::on())]
-    pub fragment: SlugMode,
+    /// Metadata keys to group posts by; also the output directory of each
+    #[serde(default = "defaults::build::taxonomies::names")]
+    #[educe(Default = defaults::build::taxonomies::names())]
+    pub names: Vec<String>,
 }
 
-/// `[build.typst]` section
+/// `[build.redirects]` section
+///
+/// Writes an HTML stub with a meta refresh at every old path, pointing to its
+/// new location. Old paths come from `paths` and from the `redirects` list in
+/// page metadata (which point to the page itself); both are relative to
+/// `[build] base_path`, as are new paths starting with `/`.
+///
+/// `files` additionally writes redirect rules for hosts that serve them as
+/// real HTTP redirects.
+///
+/// # Example
+/// ```toml
+/// [build.redirects]
+/// enable = true
+/// files = ["_redirects", "vercel.json"]   # or "netlify.toml"
+///
+/// [build.redirects.paths]
+/// "/old-post/" = "/posts/new-post/"
+/// "/feed.rss" = "/feed.xml"
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
-pub struct TypstConfig {
-    /// Typst command and arguments
-    #[serde(default = "defaults::build::typst::command")]
-    #[educe(Default = defaults::build::typst::command())]
-    pub command: Vec<String>,
+pub struct RedirectsConfig {
+    /// Generate redirects
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
 
-    /// SVG processing options
+    /// Host-specific redirect files to write
     #[serde(default)]
-    pub svg: TypstSvgConfig,
+    pub files: Vec<RedirectFile>,
+
+    /// Old path → new path or URL
+    #[serde(default)]
+    pub paths: BTreeMap<String, String>,
 }
 
-/// `[build.typst.svg]` section
+/// `[build.nav]` section
+///
+/// Appends a `<nav class="tola-post-nav">` to every post (a page whose
+/// metadata has a `date`), linking the previous and next post by date within
+/// the same top-level content directory.
+///
+/// # Example
+/// ```toml
+/// [build.nav]
+/// enable = true
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
-pub struct TypstSvgConfig {
-    /// Method for extracting embedded SVG images
-    #[serde(default = "defaults::build::typst::svg::extract_type")]
-    #[educe(Default = defaults::build::typst::svg::extract_type())]
-    pub extract_type: ExtractSvgType,
-
-    /// Max size for inline SVG (e.g.: "20KB", "1MB")
-    #[serde(default = "defaults::build::typst::svg::inline_max_size")]
-    #[educe(Default = defaults::build::typst::svg::inline_max_size())]
-    pub inline_max_size: String,
-
-    /// DPI for SVG rendering
-    #[serde(default = "defaults::build::typst::svg::dpi")]
-    #[educe(Default = defaults::build::typst::svg::dpi())]
-    pub dpi: f32,
+pub struct NavConfig {
+    /// Inject previous/next post links
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub enable: bool,
 }
 
-/// `[build.tailwind]` section
+/// `[build.links]` section
+///
+/// Rewrites `<a>` elements pointing to other sites (`http://` or `https://`
+/// links outside `[base.url]`): `rel="noopener noreferrer"` is added, and
+/// optionally `target="_blank"` and a class for styling an external-link icon.
+///
+/// # Example
+/// ```toml
+/// [build.links]
+/// enable = true
+/// new_tab = true
+/// class = "external"
+/// allowlist = ["github.com"]   # also matches subdomains
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
-pub struct TailwindConfig {
-    /// Enable Tailwind CSS processing
+pub struct LinksConfig {
+    /// Rewrite external links
     #[serde(default = "defaults::r#false")]
     #[educe(Default = false)]
     pub enable: bool,
 
-    /// Input CSS file path
-    #[serde(default = "defaults::build::tailwind::input")]
-    #[educe(Default = defaults::build::tailwind::input())]
-    pub input: Option<PathBuf>,
+    /// Open external links in a new tab (`target="_blank"`)
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub new_tab: bool,
 
-    /// Tailwind command and arguments
-    #[serde(default = "defaults::build::tailwind::command")]
-    #[educe(Default = defaults::build::tailwind::command())]
-    pub command: Vec<String>,
+    /// Class added to external links
+    #[serde(default)]
+    pub class: Option<String>,
+
+    /// Domains whose links are left unchanged
+    #[serde(default)]
+    pub allowlist: Vec<String>,
 }
 
 /// `[build.head]` section for custom head elements
@@ -322,6 +2143,38 @@ mod tests {
         assert_eq!(config.build.assets, PathBuf::from("assets"));
         assert!(config.build.minify);
         assert!(!config.build.clear);
+        assert!(!config.build.prune);
+        assert_eq!(config.build.cache, PathBuf::from(".tola-cache"));
+    }
+
+    #[test]
+    fn test_threads_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [build.threads]
+            cpu = 4
+            io = 16
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.build.threads.cpu, 4);
+        assert_eq!(config.build.threads.io, 16);
+    }
+
+    #[test]
+    fn test_threads_config_defaults() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.build.threads.cpu, 0);
+        assert_eq!(config.build.threads.io, 0);
     }
 
     #[test]
@@ -356,6 +2209,22 @@ mod tests {
 
         assert!(matches!(config.build.slug.path, SlugMode::On));
         assert!(matches!(config.build.slug.fragment, SlugMode::No));
+        assert_eq!(config.build.slug.case, SlugCase::Preserve);
+    }
+
+    #[test]
+    fn test_slug_case() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [build.slug]
+            case = "lower"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.build.slug.case, SlugCase::Lower);
     }
 
     #[test]
@@ -440,31 +2309,81 @@ mod tests {
             "#);
             let config: SiteConfig = toml::from_str(&config).unwrap();
 
-            assert!(
-                std::mem::discriminant(&config.build.typst.svg.extract_type)
-                    == std::mem::discriminant(&expected),
-                "Failed for type: {str_type}"
-            );
-        }
+            assert!(
+                std::mem::discriminant(&config.build.typst.svg.extract_type)
+                    == std::mem::discriminant(&expected),
+                "Failed for type: {str_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tailwind_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [build.tailwind]
+            enable = true
+            input = "assets/styles/main.css"
+            command = ["tailwindcss-v4"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert!(config.build.tailwind.enable);
+        assert_eq!(config.build.tailwind.input, Some(PathBuf::from("assets/styles/main.css")));
+        assert_eq!(config.build.tailwind.command, vec!["tailwindcss-v4".to_string()]);
+    }
+
+    #[test]
+    fn test_reading_time_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [build.reading_time]
+            enable = true
+            words_per_minute = 250
+            cjk = false
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let reading_time = &config.build.reading_time;
+        assert!(reading_time.enable);
+        assert_eq!(reading_time.words_per_minute, 250);
+        assert_eq!(reading_time.cjk_per_minute, 500);
+        assert!(!reading_time.cjk);
     }
 
     #[test]
-    fn test_tailwind_config() {
+    fn test_reproducible_config() {
         let config = r#"
             [base]
             title = "Test"
             description = "Test blog"
 
-            [build.tailwind]
+            [build.reproducible]
             enable = true
-            input = "assets/styles/main.css"
-            command = ["tailwindcss-v4"]
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.reproducible.enable);
+        assert!(!SiteConfig::default().build.reproducible.enable);
+    }
 
-        assert!(config.build.tailwind.enable);
-        assert_eq!(config.build.tailwind.input, Some(PathBuf::from("assets/styles/main.css")));
-        assert_eq!(config.build.tailwind.command, vec!["tailwindcss-v4".to_string()]);
+    #[test]
+    fn test_sections_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [build.sections]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.sections.enable);
+        assert!(!SiteConfig::default().build.sections.enable);
     }
 
     #[test]
@@ -698,6 +2617,326 @@ mod tests {
         assert!(config.build.clear);
     }
 
+    #[test]
+    fn test_sanitize_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.sanitize]
+            enable = true
+            tags = ["meta"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.sanitize.enable);
+        assert_eq!(config.build.sanitize.tags, vec!["meta"]);
+        assert!(
+            config
+                .build
+                .sanitize
+                .attributes
+                .contains(&"content".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sanitize_config_defaults() {
+        let config = SanitizeConfig::default();
+        assert!(!config.enable);
+        assert!(config.tags.contains(&"meta".to_string()));
+        assert!(!config.tags.contains(&"script".to_string()));
+    }
+
+    #[test]
+    fn test_comments_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.comments]
+            enable = true
+            provider = "utterances"
+            repo = "user/comments"
+            theme = "github-dark"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let comments = &config.build.comments;
+        assert!(comments.enable);
+        assert_eq!(comments.provider, CommentsProvider::Utterances);
+        assert_eq!(comments.repo, "user/comments");
+        assert_eq!(comments.mapping, "pathname");
+        assert_eq!(comments.theme.as_deref(), Some("github-dark"));
+    }
+
+    #[test]
+    fn test_comments_config_defaults() {
+        let config = CommentsConfig::default();
+        assert!(!config.enable);
+        assert_eq!(config.provider, CommentsProvider::Giscus);
+        assert_eq!(config.theme, None);
+    }
+
+    #[test]
+    fn test_analytics_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.analytics]
+            enable = true
+            provider = "goatcounter"
+            code = "myblog"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let analytics = &config.build.analytics;
+        assert!(analytics.enable);
+        assert_eq!(analytics.provider, AnalyticsProvider::GoatCounter);
+        assert_eq!(analytics.code, "myblog");
+        assert_eq!(analytics.src, None);
+        assert!(!analytics.serve);
+    }
+
+    #[test]
+    fn test_analytics_config_defaults() {
+        let config = AnalyticsConfig::default();
+        assert!(!config.enable);
+        assert_eq!(config.provider, AnalyticsProvider::Plausible);
+        assert!(!config.serve);
+    }
+
+    #[test]
+    fn test_pwa_config() {
+        let config = r##"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.pwa]
+            enable = true
+            theme_color = "#000000"
+            icons = [{ path = "icon.png", sizes = "192x192" }]
+        "##;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let pwa = &config.build.pwa;
+        assert!(pwa.enable);
+        assert_eq!(pwa.theme_color, "#000000");
+        assert_eq!(pwa.background_color, "#ffffff");
+        assert_eq!(pwa.display, "standalone");
+        assert_eq!(pwa.icons.len(), 1);
+        assert_eq!(pwa.icons[0].path, PathBuf::from("icon.png"));
+        assert_eq!(pwa.icons[0].sizes, "192x192");
+    }
+
+    #[test]
+    fn test_webmention_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.webmention]
+            enable = true
+            endpoint = "https://webmention.io/example.com/webmention"
+            h_entry = false
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let webmention = &config.build.webmention;
+        assert!(webmention.enable);
+        assert_eq!(
+            webmention.endpoint.as_deref(),
+            Some("https://webmention.io/example.com/webmention")
+        );
+        assert_eq!(webmention.pingback, None);
+        assert!(!webmention.h_entry);
+        assert_eq!(webmention.links, PathBuf::from("links.json"));
+    }
+
+    #[test]
+    fn test_blogroll_settings_defaults() {
+        let config = BlogrollConfig::default();
+        assert_eq!(config.file, None);
+        assert_eq!(config.title, "Blogroll");
+        assert_eq!(config.page, PathBuf::from("blogroll/index.html"));
+        assert_eq!(config.opml, PathBuf::from("blogroll.opml"));
+    }
+
+    #[test]
+    fn test_text_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.text]
+            llms = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(!config.build.text.pages);
+        assert!(config.build.text.llms);
+    }
+
+    #[test]
+    fn test_api_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.api]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.api.enable);
+        assert_eq!(config.build.api.path, PathBuf::from("api"));
+    }
+
+    #[test]
+    fn test_authors_settings_defaults() {
+        let config = AuthorsConfig::default();
+        assert!(!config.enable);
+        assert_eq!(config.path, PathBuf::from("authors"));
+    }
+
+    #[test]
+    fn test_nav_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.nav]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.nav.enable);
+        assert!(!BuildConfig::default().nav.enable);
+    }
+
+    #[test]
+    fn test_links_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.links]
+            enable = true
+            class = "external"
+            allowlist = ["github.com"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let links = &config.build.links;
+        assert!(links.enable);
+        assert!(!links.new_tab);
+        assert_eq!(links.class.as_deref(), Some("external"));
+        assert_eq!(links.allowlist, ["github.com"]);
+    }
+
+    #[test]
+    fn test_taxonomies_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.taxonomies]
+            enable = true
+            names = ["tags", "series"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.taxonomies.enable);
+        assert_eq!(config.build.taxonomies.names, ["tags", "series"]);
+        assert_eq!(TaxonomiesConfig::default().names, ["tags", "categories"]);
+    }
+
+    #[test]
+    fn test_search_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.search]
+            enable = true
+            format = "elasticlunr"
+            fields = ["title", "body"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let search = &config.build.search;
+        assert!(search.enable);
+        assert_eq!(search.format, SearchFormat::Elasticlunr);
+        assert_eq!(search.fields, [SearchField::Title, SearchField::Body]);
+        assert_eq!(search.path, PathBuf::from("search.json"));
+        assert_eq!(
+            SearchConfig::default().fields,
+            [SearchField::Title, SearchField::Summary, SearchField::Body]
+        );
+    }
+
+    #[test]
+    fn test_pagination_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.pagination]
+            enable = true
+            page_size = 5
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.pagination.enable);
+        assert_eq!(config.build.pagination.page_size, 5);
+        assert_eq!(config.build.pagination.path, PathBuf::from("page"));
+        assert_eq!(PaginationConfig::default().page_size, 10);
+    }
+
+    #[test]
+    fn test_link_check_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.link_check]
+            enable = true
+            level = "error"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let link_check = &config.build.link_check;
+        assert!(link_check.enable);
+        assert_eq!(link_check.level, LinkCheckLevel::Error);
+        assert!(!link_check.external);
+        assert_eq!(link_check.command, ["curl"]);
+        assert_eq!(LinkCheckConfig::default().level, LinkCheckLevel::Warn);
+    }
+
+    #[test]
+    fn test_redirects_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.redirects]
+            enable = true
+            files = ["_redirects", "vercel.json"]
+            [build.redirects.paths]
+            "/old/" = "/new/"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let redirects = &config.build.redirects;
+        assert!(redirects.enable);
+        assert_eq!(
+            redirects.files,
+            [RedirectFile::Redirects, RedirectFile::Vercel]
+        );
+        assert_eq!(redirects.paths["/old/"], "/new/");
+        assert_eq!(RedirectFile::Netlify.file_name(), "netlify.toml");
+    }
+
+    #[test]
+    fn test_build_prune_enabled() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build]
+            prune = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.prune);
+    }
+
     #[test]
     fn test_rss_config_defaults() {
         let config = r#"
@@ -710,6 +2949,88 @@ mod tests {
         assert_eq!(config.build.rss.path, PathBuf::from("feed.xml"));
     }
 
+    #[test]
+    fn test_rss_format() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.rss]
+            enable = true
+            format = "both"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert_eq!(config.build.rss.format, FeedFormat::Both);
+        assert_eq!(config.build.rss.atom_path, PathBuf::from("atom.xml"));
+        assert_eq!(RssConfig::default().format, FeedFormat::Rss);
+        assert!(!config.build.rss.json.enable);
+    }
+
+    #[test]
+    fn test_rss_json_feed() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.rss.json]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.rss.json.enable);
+        assert_eq!(config.build.rss.json.path, PathBuf::from("feed.json"));
+    }
+
+    #[test]
+    fn test_rss_opml() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.rss.opml]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.rss.opml.enable);
+        assert_eq!(config.build.rss.opml.path, PathBuf::from("feeds.opml"));
+    }
+
+    #[test]
+    fn test_html_rewrite() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [[build.html.rewrite]]
+            tag = "table"
+            wrap = { tag = "div", attributes = { class = "table-wrapper" } }
+            [[build.html.rewrite]]
+            tag = "a"
+            attribute = "href"
+            value = "*.pdf"
+            set = { download = "" }
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let rules = &config.build.html.rewrite;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].wrap.as_ref().unwrap().tag, "div");
+        assert_eq!(rules[1].value.as_deref(), Some("*.pdf"));
+        assert_eq!(rules[1].set["download"], "");
+    }
+
+    #[test]
+    fn test_images_lazy() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [build.images.lazy]
+            enable = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert!(config.build.images.lazy.enable);
+        assert!(config.build.images.lazy.dimensions);
+    }
+
     #[test]
     fn test_rss_unknown_field_rejection() {
         let config = r#"
@@ -865,6 +3186,25 @@ mod tests {
         assert!(both.is_async());
     }
 
+    #[test]
+    fn test_typst_backend() {
+        let config: SiteConfig = toml::from_str("").unwrap();
+        assert_eq!(config.build.typst.backend, TypstBackend::Library);
+
+        let config = r#"
+            [build.typst]
+            backend = "cli"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert_eq!(config.build.typst.backend, TypstBackend::Cli);
+
+        let config = r#"
+            [build.typst]
+            backend = "wasm"
+        "#;
+        assert!(toml::from_str::<SiteConfig>(config).is_err());
+    }
+
     #[test]
     fn test_typst_command_multiple_args() {
         let config = r#"
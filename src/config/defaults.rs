@@ -10,7 +10,6 @@ pub fn r#true() -> bool {
     true
 }
 
-#[allow(unused)]
 pub fn r#false() -> bool {
     false
 }
@@ -72,17 +71,37 @@ pub mod build {
         "utils".into()
     }
 
+    pub fn cache() -> PathBuf {
+        ".tola-cache".into()
+    }
+
     pub mod rss {
         use std::path::PathBuf;
 
         pub fn path() -> PathBuf {
             "feed.xml".into()
         }
+
+        pub fn atom_path() -> PathBuf {
+            "atom.xml".into()
+        }
+
+        pub fn json_path() -> PathBuf {
+            "feed.json".into()
+        }
+
+        pub fn opml_path() -> PathBuf {
+            "feeds.opml".into()
+        }
     }
 
     #[allow(unused)]
     pub mod slug {
-        use super::super::super::SlugMode;
+        use super::super::super::{SlugCase, SlugMode};
+
+        pub fn case() -> SlugCase {
+            SlugCase::default()
+        }
 
         pub fn default() -> SlugMode {
             SlugMode::default()
@@ -101,6 +120,232 @@ pub mod build {
         }
     }
 
+    pub mod blogroll {
+        use std::path::PathBuf;
+
+        pub fn title() -> String {
+            "Blogroll".into()
+        }
+
+        pub fn page() -> PathBuf {
+            "blogroll/index.html".into()
+        }
+
+        pub fn opml() -> PathBuf {
+            "blogroll.opml".into()
+        }
+    }
+
+    pub mod api {
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "api".into()
+        }
+    }
+
+    pub mod sitemap {
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "sitemap.xml".into()
+        }
+    }
+
+    pub mod summary {
+        pub fn length() -> usize {
+            200
+        }
+    }
+
+    pub mod reading_time {
+        pub fn words_per_minute() -> usize {
+            200
+        }
+
+        pub fn cjk_per_minute() -> usize {
+            500
+        }
+    }
+
+    pub mod fingerprint {
+        use std::path::PathBuf;
+
+        pub fn manifest() -> PathBuf {
+            "manifest.json".into()
+        }
+    }
+
+    pub mod stats {
+        pub fn slowest() -> usize {
+            10
+        }
+    }
+
+    pub mod precompress {
+        pub fn min_size() -> u64 {
+            1024
+        }
+    }
+
+    pub mod anchors {
+        pub fn symbol() -> String {
+            "#".into()
+        }
+    }
+
+    pub mod toc {
+        pub fn min_level() -> u8 {
+            2
+        }
+
+        pub fn max_level() -> u8 {
+            4
+        }
+    }
+
+    pub mod pagination {
+        use std::path::PathBuf;
+
+        pub fn page_size() -> usize {
+            10
+        }
+
+        pub fn path() -> PathBuf {
+            "page".into()
+        }
+    }
+
+    pub mod link_check {
+        pub fn command() -> Vec<String> {
+            vec!["curl".into()]
+        }
+    }
+
+    pub mod search {
+        use super::super::super::SearchField;
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "search.json".into()
+        }
+
+        pub fn fields() -> Vec<SearchField> {
+            vec![SearchField::Title, SearchField::Summary, SearchField::Body]
+        }
+    }
+
+    pub mod authors {
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "authors".into()
+        }
+    }
+
+    pub mod taxonomies {
+        pub fn names() -> Vec<String> {
+            vec!["tags".into(), "categories".into()]
+        }
+    }
+
+    pub mod archive {
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "archive".into()
+        }
+    }
+
+    pub mod backlinks {
+        use std::path::PathBuf;
+
+        pub fn path() -> PathBuf {
+            "backlinks.json".into()
+        }
+    }
+
+    pub mod webmention {
+        use std::path::PathBuf;
+
+        pub fn links() -> PathBuf {
+            "links.json".into()
+        }
+    }
+
+    pub mod pwa {
+        pub fn color() -> String {
+            "#ffffff".into()
+        }
+
+        pub fn display() -> String {
+            "standalone".into()
+        }
+    }
+
+    pub mod comments {
+        pub fn mapping() -> String {
+            "pathname".into()
+        }
+    }
+
+    pub mod sanitize {
+        pub fn tags() -> Vec<String> {
+            [
+                "meta",
+                "link",
+                "title",
+                "a",
+                "abbr",
+                "b",
+                "blockquote",
+                "br",
+                "code",
+                "del",
+                "div",
+                "em",
+                "figcaption",
+                "figure",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "hr",
+                "i",
+                "img",
+                "li",
+                "ol",
+                "p",
+                "pre",
+                "span",
+                "strong",
+                "sub",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "th",
+                "thead",
+                "tr",
+                "ul",
+            ]
+            .map(String::from)
+            .to_vec()
+        }
+
+        pub fn attributes() -> Vec<String> {
+            [
+                "name", "content", "property", "charset", "rel", "href", "hreflang", "type",
+                "media", "sizes", "src", "alt", "title", "width", "height", "id", "class", "lang",
+                "colspan", "rowspan",
+            ]
+            .map(String::from)
+            .to_vec()
+        }
+    }
+
     pub mod typst {
         use super::super::super::ExtractSvgType;
 
@@ -122,6 +367,29 @@ pub mod build {
             pub fn dpi() -> f32 {
                 96.
             }
+
+            pub mod dark {
+                pub fn color() -> String {
+                    "#ffffff".into()
+                }
+            }
+
+            pub mod avif {
+                pub fn quality() -> f32 {
+                    90.
+                }
+
+                pub fn speed() -> u8 {
+                    4
+                }
+            }
+        }
+    }
+
+    pub mod threads {
+        /// `0`: one thread per available core
+        pub fn auto() -> usize {
+            0
         }
     }
 
@@ -138,18 +406,34 @@ pub mod build {
     }
 }
 
+// ============================================================================
+// [theme] Section Defaults
+// ============================================================================
+
+pub mod theme {
+    use std::path::PathBuf;
+
+    pub fn dir() -> PathBuf {
+        "themes".into()
+    }
+}
+
 // ============================================================================
 // [serve] Section Defaults
 // ============================================================================
 
 pub mod serve {
-    pub fn interface() -> String {
-        "127.0.0.1".into()
+    pub fn interface() -> Vec<String> {
+        vec!["127.0.0.1".into()]
     }
 
     pub fn port() -> u16 {
         5277
     }
+
+    pub fn debounce() -> u64 {
+        200
+    }
 }
 
 // ============================================================================
@@ -161,6 +445,22 @@ pub mod deploy {
         "github".into()
     }
 
+    pub fn retries() -> usize {
+        3
+    }
+
+    pub mod commit {
+        pub fn message() -> String {
+            "deploy it".into()
+        }
+    }
+
+    pub mod cloudflare {
+        pub fn branch() -> String {
+            "main".into()
+        }
+    }
+
     pub mod github {
         use std::path::PathBuf;
 
@@ -33,19 +33,58 @@ pub struct DeployConfig {
     #[educe(Default = defaults::r#false())]
     pub force: bool,
 
+    /// Create a preview deployment instead of a production one (CLI `deploy --preview`).
+    #[serde(skip)]
+    pub preview: bool,
+
+    /// Commit created in the output repository before pushing.
+    #[serde(default)]
+    pub commit: DeployCommitConfig,
+
     /// GitHub Pages deployment settings.
     #[serde(default)]
     pub github: GithubDeployConfig,
 
-    /// Cloudflare Pages settings (not yet implemented).
+    /// Cloudflare Pages settings.
     #[serde(default)]
     pub cloudflare: CloudflareDeployConfig,
 
-    /// Vercel settings (not yet implemented).
+    /// Vercel settings.
     #[serde(default)]
     pub vercel: VercelDeployConfig,
 }
 
+/// `[deploy.commit]` section - commit of the output repository.
+///
+/// `{date}` (`2024-06-15`), `{datetime}` (RFC 3339, UTC) and `{count}`
+/// (number of committed files) in `message` are replaced. Without `author`
+/// and `email`, the git config identity is used.
+///
+/// # Example
+/// ```toml
+/// [deploy.commit]
+/// message = "deploy {date} ({count} files)"   # or `tola deploy --message`
+/// author = "Tola Bot"
+/// email = "bot@example.com"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct DeployCommitConfig {
+    /// Commit message template.
+    #[serde(default = "defaults::deploy::commit::message")]
+    #[educe(Default = defaults::deploy::commit::message())]
+    pub message: String,
+
+    /// Author name.
+    #[serde(default)]
+    pub author: Option<String>,
+
+    /// Author email.
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
 /// `[deploy.github]` section - GitHub Pages deployment.
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
@@ -71,26 +110,86 @@ pub struct GithubDeployConfig {
     pub token_path: Option<PathBuf>,
 }
 
-/// `[deploy.cloudflare]` section (placeholder for future implementation)
+/// `[deploy.cloudflare]` section - Cloudflare Pages direct upload.
+///
+/// # Example
+/// ```toml
+/// [deploy]
+/// provider = "cloudflare"
+///
+/// [deploy.cloudflare]
+/// account_id = "0123456789abcdef0123456789abcdef"
+/// project = "my-blog"
+/// branch = "main"                       # the production branch deploys to production
+/// token_path = "~/.cloudflare-token"    # API token with "Cloudflare Pages: Edit"
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
 pub struct CloudflareDeployConfig {
-    /// Provider identifier
-    #[serde(default = "defaults::deploy::provider")]
-    #[educe(Default = defaults::deploy::provider())]
-    pub provider: String,
+    /// Cloudflare account ID.
+    #[serde(default)]
+    pub account_id: String,
+
+    /// Pages project name (the project must already exist).
+    #[serde(default)]
+    pub project: String,
+
+    /// Branch the deployment is attributed to.
+    #[serde(default = "defaults::deploy::cloudflare::branch")]
+    #[educe(Default = defaults::deploy::cloudflare::branch())]
+    pub branch: String,
+
+    /// Path to file containing a Cloudflare API token.
+    ///
+    /// # Security
+    /// - Store outside repository (e.g., `~/.cloudflare-token`)
+    /// - Never commit tokens to version control!
+    #[serde(default)]
+    pub token_path: Option<PathBuf>,
+
+    /// Retries of a request failing with a network error, 429 or 5xx.
+    #[serde(default = "defaults::deploy::retries")]
+    #[educe(Default = defaults::deploy::retries())]
+    pub retries: usize,
 }
 
-/// `[deploy.vercel]` section (placeholder for future implementation)
+/// `[deploy.vercel]` section - Vercel deployments through the REST API.
+///
+/// # Example
+/// ```toml
+/// [deploy]
+/// provider = "vercel"
+///
+/// [deploy.vercel]
+/// project = "my-blog"
+/// token_path = "~/.vercel-token"
+/// team_id = "team_abc123"   # for projects owned by a team
+/// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
 pub struct VercelDeployConfig {
-    /// Provider identifier
-    #[serde(default = "defaults::deploy::provider")]
-    #[educe(Default = defaults::deploy::provider())]
-    pub provider: String,
+    /// Project name (created on the first deployment if missing).
+    #[serde(default)]
+    pub project: String,
+
+    /// Team owning the project, if not the token's personal account.
+    #[serde(default)]
+    pub team_id: Option<String>,
+
+    /// Path to file containing a Vercel access token.
+    ///
+    /// # Security
+    /// - Store outside repository (e.g., `~/.vercel-token`)
+    /// - Never commit tokens to version control!
+    #[serde(default)]
+    pub token_path: Option<PathBuf>,
+
+    /// Retries of a request failing with a network error, 429 or 5xx.
+    #[serde(default = "defaults::deploy::retries")]
+    #[educe(Default = defaults::deploy::retries())]
+    pub retries: usize,
 }
 
 #[cfg(test)]
@@ -216,28 +315,67 @@ mod tests {
     }
 
     #[test]
-    fn test_deploy_config_cloudflare_placeholder() {
+    fn test_deploy_commit_config() {
         let config = r#"
             [base]
             title = "Test"
             description = "Test"
-            [deploy.cloudflare]
+            [deploy.commit]
+            message = "deploy {date}"
+            author = "Bot"
+            email = "bot@example.com"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        let commit = &config.deploy.commit;
+        assert_eq!(commit.message, "deploy {date}");
+        assert_eq!(commit.author.as_deref(), Some("Bot"));
+        assert_eq!(commit.email.as_deref(), Some("bot@example.com"));
+        assert_eq!(super::DeployCommitConfig::default().message, "deploy it");
+    }
+
+    #[test]
+    fn test_deploy_config_cloudflare() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [deploy]
             provider = "cloudflare"
+            [deploy.cloudflare]
+            account_id = "abc"
+            project = "blog"
+            token_path = "~/.cloudflare-token"
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
-        assert_eq!(config.deploy.cloudflare.provider, "cloudflare");
+        let cloudflare = &config.deploy.cloudflare;
+        assert_eq!(cloudflare.account_id, "abc");
+        assert_eq!(cloudflare.project, "blog");
+        assert_eq!(cloudflare.branch, "main");
+        assert_eq!(cloudflare.retries, 3);
+        assert_eq!(
+            cloudflare.token_path,
+            Some(PathBuf::from("~/.cloudflare-token"))
+        );
     }
 
     #[test]
-    fn test_deploy_config_vercel_placeholder() {
+    fn test_deploy_config_vercel() {
         let config = r#"
             [base]
             title = "Test"
             description = "Test"
-            [deploy.vercel]
+            [deploy]
             provider = "vercel"
+            [deploy.vercel]
+            project = "blog"
+            token_path = "~/.vercel-token"
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
-        assert_eq!(config.deploy.vercel.provider, "vercel");
+        let vercel = &config.deploy.vercel;
+        assert_eq!(config.deploy.provider, "vercel");
+        assert_eq!(vercel.project, "blog");
+        assert_eq!(vercel.team_id, None);
+        assert_eq!(vercel.retries, 3);
+        assert!(!config.deploy.preview);
     }
 }
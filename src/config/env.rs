@@ -0,0 +1,219 @@
+//! Environment variable interpolation in `tola.toml`.
+//!
+//! `${NAME}` is replaced by the value of `NAME`, and `${NAME:-default}` falls
+//! back to `default` when `NAME` is unset or empty. `$${` is a literal `${`,
+//! and comments are left as they are.
+//!
+//! Values inside quoted strings are escaped for the string they land in, so
+//! quotes, backslashes and newlines in a variable stay part of the value.
+//! Outside quotes a value must be a number, boolean or date
+//! (`port = ${PORT:-5277}`). Defaults are written as they appear in the file.
+
+use super::error::ConfigError;
+
+/// Where in the TOML text a reference is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    /// Outside strings and comments, where only bare values can go
+    Bare,
+    /// `"..."`
+    Basic,
+    /// `"""..."""`
+    MultiBasic,
+    /// `'...'`
+    Literal,
+    /// `'''...'''`
+    MultiLiteral,
+    /// After `#` up to the end of the line
+    Comment,
+}
+
+/// Replace `${NAME}` references in `content`, reading variables with `lookup`
+pub(super) fn interpolate(
+    content: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let mut output = String::with_capacity(content.len());
+    let mut context = Context::Bare;
+    let mut rest = content;
+    while let Some(c) = rest.chars().next() {
+        // Bytes of `rest` copied as they are
+        let mut copied = c.len_utf8();
+        match context {
+            Context::Comment => {
+                if c == '\n' {
+                    context = Context::Bare;
+                }
+            }
+            _ if rest.starts_with("$${") => {
+                output.push_str("${");
+                rest = &rest[3..];
+                continue;
+            }
+            _ if rest.starts_with("${") => {
+                let reference = &rest[2..];
+                let Some(end) = reference.find('}') else {
+                    let line = rest.lines().next().unwrap_or_default();
+                    return Err(ConfigError::Env(format!("unclosed `${{` in `{line}`")));
+                };
+                output.push_str(&substitute(&reference[..end], context, &lookup)?);
+                rest = &reference[end + 1..];
+                continue;
+            }
+            Context::Bare => {
+                (context, copied) = match c {
+                    '#' => (Context::Comment, 1),
+                    '"' if rest.starts_with(r#"""""#) => (Context::MultiBasic, 3),
+                    '"' => (Context::Basic, 1),
+                    '\'' if rest.starts_with("'''") => (Context::MultiLiteral, 3),
+                    '\'' => (Context::Literal, 1),
+                    _ => (Context::Bare, copied),
+                };
+            }
+            Context::Basic | Context::MultiBasic if c == '\\' => {
+                // The escaped character never ends the string
+                copied += rest[1..].chars().next().map_or(0, char::len_utf8);
+            }
+            Context::Basic if matches!(c, '"' | '\n') => context = Context::Bare,
+            Context::MultiBasic if rest.starts_with(r#"""""#) => {
+                (context, copied) = (Context::Bare, 3);
+            }
+            Context::Literal if matches!(c, '\'' | '\n') => context = Context::Bare,
+            Context::MultiLiteral if rest.starts_with("'''") => {
+                (context, copied) = (Context::Bare, 3);
+            }
+            Context::Basic | Context::MultiBasic | Context::Literal | Context::MultiLiteral => {}
+        }
+        output.push_str(&rest[..copied]);
+        rest = &rest[copied..];
+    }
+    Ok(output)
+}
+
+/// TOML text for the reference `NAME` or `NAME:-default` found in `context`
+fn substitute(
+    reference: &str,
+    context: Context,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+    if !is_name(name) {
+        return Err(ConfigError::Env(format!("invalid variable name `{name}`")));
+    }
+    let value = match (lookup(name).filter(|value| !value.is_empty()), default) {
+        (Some(value), _) => value,
+        (None, Some(default)) => return Ok(default.to_owned()),
+        (None, None) => {
+            return Err(ConfigError::Env(format!(
+                "`{name}` is not set (use `${{{name}:-default}}` for a fallback)"
+            )));
+        }
+    };
+
+    let fits = match context {
+        Context::Basic | Context::MultiBasic => return Ok(escape_basic(&value)),
+        Context::Literal => !value.contains(['\'', '\n', '\r']),
+        Context::MultiLiteral => !value.contains("'''"),
+        Context::Bare => value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-' | ':' | '_')),
+        Context::Comment => true,
+    };
+    if !fits {
+        let place = match context {
+            Context::Bare => "outside quotes (quote the reference for a string)",
+            _ => "in a single-quoted string (use double quotes)",
+        };
+        return Err(ConfigError::Env(format!(
+            "the value of `{name}` cannot be used {place}"
+        )));
+    }
+    Ok(value)
+}
+
+/// `value` escaped for a double-quoted TOML string
+fn escape_basic(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "BASE_URL" => Some("https://example.com".into()),
+            "EMPTY" => Some(String::new()),
+            "QUOTED" => Some("say \"hi\"\ninjected = true".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let content = r#"url = "${BASE_URL}/blog"
+port = ${PORT:-5277}
+title = "${EMPTY:-Untitled}"
+price = "$5, $${NOT_A_VAR}"
+# token = "${TOKEN}"
+"#;
+        assert_eq!(
+            interpolate(content, lookup).unwrap(),
+            r#"url = "https://example.com/blog"
+port = 5277
+title = "Untitled"
+price = "$5, ${NOT_A_VAR}"
+# token = "${TOKEN}"
+"#
+        );
+    }
+
+    #[test]
+    fn test_interpolate_escapes_strings() {
+        let content = "a = \"${QUOTED}\" # ${TOKEN}\nb = '${BASE_URL}'\n";
+        let interpolated = interpolate(content, lookup).unwrap();
+        let table: toml::Table = toml::from_str(&interpolated).unwrap();
+        assert_eq!(table["a"].as_str(), Some("say \"hi\"\ninjected = true"));
+        assert_eq!(table["b"].as_str(), Some("https://example.com"));
+        assert!(!table.contains_key("injected"));
+    }
+
+    #[test]
+    fn test_interpolate_errors() {
+        let error = interpolate("token = \"${TOKEN}\"", lookup).unwrap_err();
+        assert!(error.to_string().contains("`TOKEN` is not set"));
+        assert!(interpolate("a = \"${BASE_URL\"", lookup).is_err());
+        assert!(interpolate("a = \"${1X}\"", lookup).is_err());
+        // Values that would change the structure of the file
+        assert!(interpolate("a = ${QUOTED}", lookup).is_err());
+        assert!(interpolate("a = ${BASE_URL}", lookup).is_err());
+        assert!(interpolate("a = '${QUOTED}'", lookup).is_err());
+    }
+}
@@ -14,6 +14,9 @@ pub enum ConfigError {
 
     #[error("Config validation error: {0}")]
     Validation(String),
+
+    #[error("Environment variable error: {0}")]
+    Env(String),
 }
 
 #[cfg(test)]
@@ -0,0 +1,90 @@
+//! `[log]` section configuration.
+//!
+//! Controls the level and format of terminal log output.
+
+use super::defaults;
+use crate::utils::log::LogLevel;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+
+/// `[log]` section in tola.toml - log output level and format.
+///
+/// # Example
+/// ```toml
+/// [log]
+/// level = "warn"     # off, error, warn, info, debug or trace; `--log-level`, `-q` and `-v` override it
+/// timestamps = true  # Prefix lines with local time (HH:MM:SS.mmm)
+/// durations = true   # Append elapsed time per page/asset: `[content] posts/foo 123ms`
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    /// Maximum level printed when the CLI sets none.
+    #[serde(default)]
+    pub level: LogLevel,
+
+    /// Prefix each log line with a wall-clock timestamp.
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub timestamps: bool,
+
+    /// Log per-task elapsed time once each page, asset or SVG is done.
+    #[serde(default = "defaults::r#false")]
+    #[educe(Default = false)]
+    pub durations: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SiteConfig;
+    use crate::utils::log::LogLevel;
+
+    #[test]
+    fn test_log_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [log]
+            level = "debug"
+            timestamps = true
+            durations = true
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.log.level, LogLevel::Debug);
+        assert!(config.log.timestamps);
+        assert!(config.log.durations);
+    }
+
+    #[test]
+    fn test_log_config_defaults() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.log.level, LogLevel::Info);
+        assert!(!config.log.timestamps);
+        assert!(!config.log.durations);
+    }
+
+    #[test]
+    fn test_log_config_unknown_field() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [log]
+            colors = false
+        "#;
+        let result: Result<SiteConfig, _> = toml::from_str(config);
+
+        assert!(result.is_err());
+    }
+}
@@ -2,13 +2,21 @@
 //!
 //! # Sections
 //!
-//! | Section     | Purpose                                      |
-//! |-------------|----------------------------------------------|
-//! | `[base]`    | Site metadata (title, author, url)           |
-//! | `[build]`   | Build paths, typst, tailwind, RSS, etc.      |
-//! | `[serve]`   | Development server (port, interface, watch)  |
-//! | `[deploy]`  | Deployment targets (GitHub, Cloudflare)      |
-//! | `[extra]`   | User-defined custom fields                   |
+//! | Section       | Purpose                                      |
+//! |---------------|----------------------------------------------|
+//! | `[base]`      | Site metadata (title, author, url)           |
+//! | `[build]`     | Build paths, typst, tailwind, RSS, etc.      |
+//! | `[serve]`     | Development server (port, interface, watch)  |
+//! | `[deploy]`    | Deployment targets (GitHub, Cloudflare)      |
+//! | `[log]`       | Log output format (timestamps, durations)    |
+//! | `[[plugins]]` | External commands hooked into the pipeline   |
+//! | `[[blogroll]]`| Recommended sites (blogroll page and OPML)   |
+//! | `[[authors]]` | Author profiles for the author pages         |
+//! | `[extra]`     | User-defined custom fields                   |
+//!
+//! A top-level `include = ["tola.local.toml"]` merges other files over the
+//! config, so a git-ignored local file can override `[serve]` or `[deploy]`.
+//! Precedence: CLI arguments > included files > `tola.toml` > defaults.
 //!
 //! # Example
 //!
@@ -32,22 +40,45 @@
 //! [extra]
 //! analytics_id = "UA-12345"
 //! ```
+//!
+//! Values can reference environment variables as `${NAME}` or, with a
+//! fallback, `${NAME:-default}`.
 
+mod author;
 mod base;
+mod blogroll;
 mod build;
 pub mod defaults;
 mod deploy;
+mod env;
 mod error;
+mod log;
+mod plugin;
 mod serve;
+mod theme;
 
 // Re-export public types used by other modules
-pub use build::{BuildConfig, ExtractSvgType, SlugMode};
+pub use author::AuthorProfile;
+pub use base::BaseConfig;
+pub use blogroll::{BlogrollEntry, BlogrollFile};
+pub use build::{
+    AnalyticsConfig, AnalyticsProvider, AnchorPosition, AnchorsConfig, ApiConfig, ArchiveConfig,
+    AuthorsConfig, BacklinksConfig, BlogrollConfig, BuildConfig, ChangeFreq, CommentsConfig,
+    CommentsProvider, CssConfig, ExtractSvgType, FeedFormat, FeedSort, FeedsOpmlConfig,
+    FingerprintConfig, HtmlConfig, ImagesConfig, JsonFeedConfig, LazyImagesConfig, LinkCheckConfig,
+    LinkCheckLevel, LinksConfig, MinifyAssetsConfig, NavConfig, PaginationConfig,
+    PrecompressConfig, PurgeConfig, PwaConfig, PwaIcon, ReadingTimeConfig, RedirectFile,
+    RedirectsConfig, ReproducibleConfig, RewriteRule, SanitizeConfig, SearchConfig, SearchField,
+    SearchFormat, SectionFeedsConfig, SectionsConfig, SitemapConfig, SitemapRule, SlugCase,
+    SlugMode, StatsConfig, SummaryConfig, SvgAvifConfig, SvgDarkMode, TaxonomiesConfig, TextConfig,
+    ThreadsConfig, TocConfig, TypstBackend, WebmentionConfig, WrapRule,
+};
 pub use deploy::DeployConfig;
 pub use error::ConfigError;
-
-// Internal imports used in this module
-use base::BaseConfig;
-use serve::ServeConfig;
+pub use log::LogConfig;
+pub use plugin::{PluginConfig, PluginStage};
+pub use serve::{ServeConfig, WatchStrategy};
+pub use theme::ThemeConfig;
 
 use crate::cli::{Cli, Commands};
 use anyhow::{Context, Result, bail};
@@ -57,6 +88,7 @@ use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 // ============================================================================
@@ -90,6 +122,51 @@ fn parse_size_string(s: &str) -> usize {
     multiplier * value
 }
 
+/// Default settings file of a theme
+const THEME_CONFIG: &str = "theme.toml";
+
+/// Read a config file as a TOML table, with environment variables interpolated
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content =
+        fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+    let content = env::interpolate(&content, |name| std::env::var(name).ok())?;
+    toml::from_str(&content)
+        .map_err(ConfigError::Toml)
+        .with_context(|| format!("in {}", path.display()))
+}
+
+/// Convert a TOML value to JSON, with dates as their TOML string
+fn toml_to_json(value: &toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(string) => string.clone().into(),
+        toml::Value::Integer(integer) => (*integer).into(),
+        toml::Value::Float(float) => (*float).into(),
+        toml::Value::Boolean(boolean) => (*boolean).into(),
+        toml::Value::Datetime(datetime) => datetime.to_string().into(),
+        toml::Value::Array(array) => array.iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| (key.clone(), toml_to_json(value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+/// Merge `overrides` into `base`: tables are merged key by key, any other
+/// value (arrays included) replaces the one in `base`
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge_tables(base, overrides)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 // ============================================================================
 // Root Configuration
 // ============================================================================
@@ -99,18 +176,27 @@ fn parse_size_string(s: &str) -> usize {
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
 pub struct SiteConfig {
-    /// CLI arguments reference
+    /// CLI arguments (unset when the config is loaded programmatically)
     #[serde(skip)]
-    pub cli: Option<&'static Cli>,
+    pub cli: Option<Cli>,
 
     /// Absolute path to the config file (set after loading)
     #[serde(skip)]
     pub config_path: PathBuf,
 
+    /// Files merged over this one, e.g. a git-ignored `tola.local.toml`
+    /// (relative to the config file; missing ones are skipped)
+    #[serde(default)]
+    pub include: Vec<PathBuf>,
+
     /// Basic site information
     #[serde(default)]
     pub base: BaseConfig,
 
+    /// Theme providing templates, assets and default settings
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
     /// Build settings
     #[serde(default)]
     pub build: BuildConfig,
@@ -123,23 +209,89 @@ pub struct SiteConfig {
     #[serde(default)]
     pub deploy: DeployConfig,
 
-    /// User-defined extra fields
+    /// Log output settings
+    #[serde(default)]
+    pub log: LogConfig,
+
+    /// External pipeline plugins
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
+
+    /// Blogroll entries
+    #[serde(default)]
+    pub blogroll: Vec<BlogrollEntry>,
+
+    /// Author profiles
+    #[serde(default)]
+    pub authors: Vec<AuthorProfile>,
+
+    /// User-defined extra fields, readable from templates
+    /// (see [`SiteConfig::typst_site_input`])
     #[serde(default)]
     pub extra: HashMap<String, toml::Value>,
 }
 
-impl SiteConfig {
-    /// Parse configuration from TOML string
-    pub fn from_str(content: &str) -> Result<Self> {
-        let config: SiteConfig = toml::from_str(content)?;
+impl FromStr for SiteConfig {
+    type Err = anyhow::Error;
+
+    /// Parse configuration from TOML string, after replacing `${VAR}`
+    /// references with environment variables
+    fn from_str(content: &str) -> Result<Self> {
+        let content = env::interpolate(content, |name| std::env::var(name).ok())?;
+        let config: SiteConfig = toml::from_str(&content)?;
         Ok(config)
     }
+}
 
-    /// Load configuration from file path
+impl SiteConfig {
+    /// Load configuration from file path, merging its `include` files over it
     pub fn from_path(path: &Path) -> Result<Self> {
         let content =
             fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
-        Self::from_str(&content)
+        let mut config = Self::from_str(&content)?;
+        if config.include.is_empty() && config.theme.name.is_none() {
+            return Ok(config);
+        }
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut table = read_table(path)?;
+        for include in config.include.iter().map(|include| dir.join(include)) {
+            if !include.exists() {
+                continue;
+            }
+            let overrides = read_table(&include)?;
+            if overrides.contains_key("include") {
+                bail!(ConfigError::Validation(format!(
+                    "[include] is only allowed in the main config, found in {}",
+                    include.display()
+                )));
+            }
+            merge_tables(&mut table, overrides);
+        }
+        let include = std::mem::take(&mut config.include);
+        config = toml::Value::Table(table.clone())
+            .try_into()
+            .map_err(ConfigError::Toml)?;
+
+        // The theme's settings apply unless the site (or an include) sets them
+        if let Some(name) = &config.theme.name {
+            let theme_file = dir.join(&config.theme.dir).join(name).join(THEME_CONFIG);
+            if theme_file.exists() {
+                let mut defaults = read_table(&theme_file)?;
+                if defaults.contains_key("include") || defaults.contains_key("theme") {
+                    bail!(ConfigError::Validation(format!(
+                        "[include] and [theme] are not allowed in {}",
+                        theme_file.display()
+                    )));
+                }
+                merge_tables(&mut defaults, table);
+                config = toml::Value::Table(defaults)
+                    .try_into()
+                    .map_err(ConfigError::Toml)?;
+            }
+        }
+        config.include = include.into_iter().map(|path| dir.join(path)).collect();
+        Ok(config)
     }
 
     /// Get the root directory path
@@ -152,9 +304,83 @@ impl SiteConfig {
         self.build.root = Some(path.to_path_buf())
     }
 
-    /// Get CLI arguments reference
-    pub fn get_cli(&self) -> &'static Cli {
-        self.cli.unwrap()
+    /// Load a config file for programmatic use (without CLI arguments).
+    ///
+    /// Relative paths are resolved against `[build.root]`, or the directory
+    /// containing the config file if unset.
+    pub fn load(config_path: &Path) -> Result<Self> {
+        let mut config = Self::from_path(config_path)?;
+        let dir = config_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("./"));
+        let root = config
+            .build
+            .root
+            .as_ref()
+            .map_or_else(|| dir.to_path_buf(), |root| dir.join(root));
+        let config_file = config_path.file_name().unwrap_or(config_path.as_os_str());
+
+        config.resolve(&root, Path::new(config_file));
+        Ok(config)
+    }
+
+    /// Read the config file again, applying the same CLI arguments, and validate it
+    pub fn reload(&self) -> Result<Self> {
+        let config = match &self.cli {
+            Some(cli) => {
+                let mut config = Self::from_path(&self.config_path)?;
+                config.update_with_cli(cli);
+                config
+            }
+            None => Self::load(&self.config_path)?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// `--input` argument exposing `[base]` and `[extra]` to typst as JSON
+    ///
+    /// Templates read it with `json(bytes(sys.inputs.at("tola-site")))`,
+    /// which gives a dictionary with `base` and `extra` keys, and `templates`
+    /// mapping each template name to its import path (see [`crate::utils::theme`]).
+    pub fn typst_site_input(&self) -> String {
+        let extra: serde_json::Map<_, _> = self
+            .extra
+            .iter()
+            .map(|(key, value)| (key.clone(), toml_to_json(value)))
+            .collect();
+        let site = serde_json::json!({
+            "base": serde_json::to_value(&self.base).unwrap_or_default(),
+            "extra": extra,
+            "templates": crate::utils::theme::templates(self),
+        });
+        format!("tola-site={site}")
+    }
+
+    /// Top-level sections (`base`, `build`, ...) that differ from `other`
+    pub fn changed_sections(&self, other: &Self) -> Vec<&'static str> {
+        let sections = |config: &Self| {
+            [
+                ("base", serde_json::to_value(&config.base)),
+                ("theme", serde_json::to_value(&config.theme)),
+                ("build", serde_json::to_value(&config.build)),
+                ("serve", serde_json::to_value(&config.serve)),
+                ("deploy", serde_json::to_value(&config.deploy)),
+                ("log", serde_json::to_value(&config.log)),
+                ("plugins", serde_json::to_value(&config.plugins)),
+                ("blogroll", serde_json::to_value(&config.blogroll)),
+                ("authors", serde_json::to_value(&config.authors)),
+                ("extra", serde_json::to_value(&config.extra)),
+            ]
+            .map(|(name, value)| (name, value.ok()))
+        };
+        sections(self)
+            .into_iter()
+            .zip(sections(other))
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((name, _), _)| name)
+            .collect()
     }
 
     /// Parse inline_max_size string to bytes.
@@ -169,6 +395,19 @@ impl SiteConfig {
         parse_size_string(&self.build.typst.svg.inline_max_size)
     }
 
+    /// Directory of the `[theme]` in use, if any
+    pub fn theme_path(&self) -> Option<PathBuf> {
+        let name = self.theme.name.as_ref()?;
+        Some(self.theme.dir.join(name))
+    }
+
+    /// Whether files that fail to build only get reported (`[build] keep_going`)
+    pub fn keep_going(&self) -> bool {
+        self.build
+            .keep_going
+            .unwrap_or_else(|| self.cli.as_ref().is_some_and(|cli| cli.is_serve()))
+    }
+
     /// Get DPI scale factor (relative to standard 96 DPI).
     ///
     /// Used for SVG rendering resolution calculation.
@@ -177,12 +416,14 @@ impl SiteConfig {
     }
 
     /// Update configuration with CLI arguments
-    pub fn update_with_cli(&mut self, cli: &'static Cli) {
-        self.cli = Some(cli);
+    pub fn update_with_cli(&mut self, cli: &Cli) {
+        self.cli = Some(cli.clone());
 
         // Determine the final root path based on command
         let root = match &cli.command {
-            Commands::Init { name: Some(name) } => {
+            Commands::Init {
+                name: Some(name), ..
+            } => {
                 let base = cli
                     .root
                     .as_ref()
@@ -197,30 +438,78 @@ impl SiteConfig {
                 .unwrap_or_else(|| self.get_root().to_owned()),
         };
 
-        self.set_root(&root);
-        self.update_path_with_root(&root);
-
+        // Apply CLI path overrides before they are resolved against root
+        Self::update_option(&mut self.build.content, cli.content.as_ref());
+        Self::update_option(&mut self.build.assets, cli.assets.as_ref());
+        Self::update_option(&mut self.build.output, cli.output.as_ref());
         Self::update_option(&mut self.build.minify, cli.minify.as_ref());
         Self::update_option(&mut self.build.tailwind.enable, cli.tailwind.as_ref());
 
-        self.build.typst.svg.inline_max_size = self.build.typst.svg.inline_max_size.to_uppercase();
+        self.resolve(&root, &cli.config);
 
         match &cli.command {
             Commands::Serve {
                 interface,
                 port,
                 watch,
+                open,
+                drafts,
+                future,
+                skip_images,
             } => {
-                Self::update_option(&mut self.serve.interface, interface.as_ref());
+                if !interface.is_empty() {
+                    self.serve.interface = interface.clone();
+                }
                 Self::update_option(&mut self.serve.port, port.as_ref());
                 Self::update_option(&mut self.serve.watch, watch.as_ref());
-                self.base.url = Some(format!(
-                    "http://{}:{}",
-                    self.serve.interface, self.serve.port
-                ));
+                self.serve.open |= open;
+                self.build.drafts |= drafts;
+                self.build.future |= future;
+                self.build.skip_images = *skip_images;
+                self.base.url = Some(self.serve.local_url());
+            }
+            Commands::Build {
+                force,
+                drafts,
+                future,
+                stats,
+                skip_images,
+                ..
+            } => {
+                self.build.force = *force;
+                self.build.skip_images = *skip_images;
+                self.build.stats.enable |= stats;
+                self.build.drafts |= drafts;
+                self.build.future |= future;
+            }
+            Commands::Init {
+                title,
+                description,
+                author,
+                email,
+                url,
+                ..
+            } => {
+                Self::update_option(&mut self.base.title, title.as_ref());
+                Self::update_option(&mut self.base.description, description.as_ref());
+                Self::update_option(&mut self.base.author, author.as_ref());
+                Self::update_option(&mut self.base.email, email.as_ref());
+                if url.is_some() {
+                    self.base.url = url.clone();
+                }
             }
-            Commands::Deploy { force } => {
+            Commands::Check { drafts, future } => {
+                self.build.drafts |= drafts;
+                self.build.future |= future;
+            }
+            Commands::Deploy {
+                force,
+                preview,
+                message,
+            } => {
                 Self::update_option(&mut self.deploy.force, force.as_ref());
+                Self::update_option(&mut self.deploy.commit.message, message.as_ref());
+                self.deploy.preview = *preview;
             }
             _ => {}
         }
@@ -233,21 +522,53 @@ impl SiteConfig {
         }
     }
 
-    /// Update all paths relative to root directory and normalize to absolute paths
-    fn update_path_with_root(&mut self, root: &Path) {
-        let cli = self.get_cli();
+    /// Resolve paths against `root` and normalize derived values
+    fn resolve(&mut self, root: &Path, config_file: &Path) {
+        self.update_path_with_root(root, config_file);
+        self.build.typst.svg.inline_max_size = self.build.typst.svg.inline_max_size.to_uppercase();
+    }
 
-        // Apply CLI overrides first
-        Self::update_option(&mut self.build.content, cli.content.as_ref());
-        Self::update_option(&mut self.build.assets, cli.assets.as_ref());
-        Self::update_option(&mut self.build.output, cli.output.as_ref());
+    /// Move the output directory, along with every output file configured inside it
+    pub fn set_output(&mut self, output: PathBuf) {
+        let old = std::mem::replace(&mut self.build.output, output);
+        for path in [
+            &mut self.build.rss.path,
+            &mut self.build.rss.atom_path,
+            &mut self.build.rss.json.path,
+            &mut self.build.rss.opml.path,
+            &mut self.build.webmention.links,
+            &mut self.build.backlinks.path,
+            &mut self.build.api.path,
+            &mut self.build.search.path,
+            &mut self.build.authors.path,
+            &mut self.build.archive.path,
+            &mut self.build.blogroll.page,
+            &mut self.build.blogroll.opml,
+            &mut self.build.sitemap.path,
+            &mut self.build.fingerprint.manifest,
+        ]
+        .into_iter()
+        .chain(self.build.typst.svg.dir.as_mut())
+        {
+            if let Ok(relative) = path.strip_prefix(&old) {
+                *path = self.build.output.join(relative);
+            }
+        }
+    }
 
+    /// Update all paths relative to root directory and normalize to absolute paths
+    fn update_path_with_root(&mut self, root: &Path, config_file: &Path) {
         // Normalize root to absolute path
         let root = Self::normalize_path(root);
         self.set_root(&root);
 
         // Normalize config path
-        self.config_path = Self::normalize_path(&root.join(&cli.config));
+        self.config_path = Self::normalize_path(&root.join(config_file));
+        self.include = self
+            .include
+            .iter()
+            .map(|path| Self::normalize_path(path))
+            .collect();
 
         // Normalize all directory paths
         self.build.content = Self::normalize_path(&root.join(&self.build.content));
@@ -255,22 +576,58 @@ impl SiteConfig {
         self.build.output = Self::normalize_path(&root.join(&self.build.output));
         self.build.templates = Self::normalize_path(&root.join(&self.build.templates));
         self.build.utils = Self::normalize_path(&root.join(&self.build.utils));
+        self.build.cache = Self::normalize_path(&root.join(&self.build.cache));
+        self.theme.dir = Self::normalize_path(&root.join(&self.theme.dir));
         self.build.rss.path = self.build.output.join(&self.build.rss.path);
+        self.build.rss.atom_path = self.build.output.join(&self.build.rss.atom_path);
+        self.build.rss.json.path = self.build.output.join(&self.build.rss.json.path);
+        self.build.rss.opml.path = self.build.output.join(&self.build.rss.opml.path);
+        self.build.webmention.links = self.build.output.join(&self.build.webmention.links);
+        self.build.backlinks.path = self.build.output.join(&self.build.backlinks.path);
+        self.build.api.path = self.build.output.join(&self.build.api.path);
+        self.build.search.path = self.build.output.join(&self.build.search.path);
+        self.build.authors.path = self.build.output.join(&self.build.authors.path);
+        self.build.archive.path = self.build.output.join(&self.build.archive.path);
+        self.build.blogroll.page = self.build.output.join(&self.build.blogroll.page);
+        self.build.blogroll.opml = self.build.output.join(&self.build.blogroll.opml);
+        self.build.sitemap.path = self.build.output.join(&self.build.sitemap.path);
+        self.build.fingerprint.manifest = self.build.output.join(&self.build.fingerprint.manifest);
+        if let Some(dir) = &self.build.typst.svg.dir {
+            self.build.typst.svg.dir = Some(self.build.output.join(dir));
+        }
+        if let Some(file) = &self.build.blogroll.file {
+            self.build.blogroll.file = Some(Self::normalize_path(&root.join(file)));
+        }
+
+        self.serve.watch_paths = std::mem::take(&mut self.serve.watch_paths)
+            .into_iter()
+            .map(|(path, strategy)| (Self::normalize_path(&root.join(path)), strategy))
+            .collect();
 
         // Normalize tailwind input path
         if let Some(input) = self.build.tailwind.input.as_ref() {
             self.build.tailwind.input = Some(Self::normalize_path(&root.join(input)));
         }
-
-        // Normalize token path (with tilde expansion)
-        if let Some(token_path) = &self.deploy.github.token_path {
-            let expanded = shellexpand::tilde(token_path.to_str().unwrap()).into_owned();
-            let path = PathBuf::from(expanded);
-            self.deploy.github.token_path = Some(if path.is_relative() {
-                Self::normalize_path(&root.join(path))
-            } else {
-                Self::normalize_path(&path)
-            });
+        self.build.css.purge.stylesheets = std::mem::take(&mut self.build.css.purge.stylesheets)
+            .into_iter()
+            .map(|path| Self::normalize_path(&root.join(path)))
+            .collect();
+
+        // Normalize token paths (with tilde expansion)
+        for token_path in [
+            &mut self.deploy.github.token_path,
+            &mut self.deploy.cloudflare.token_path,
+            &mut self.deploy.vercel.token_path,
+        ] {
+            if let Some(path) = token_path {
+                let expanded = shellexpand::tilde(&path.to_string_lossy()).into_owned();
+                let path = PathBuf::from(expanded);
+                *token_path = Some(if path.is_relative() {
+                    Self::normalize_path(&root.join(path))
+                } else {
+                    Self::normalize_path(&path)
+                });
+            }
         }
     }
 
@@ -291,8 +648,6 @@ impl SiteConfig {
     /// Validate configuration for the current command
     #[allow(unused)]
     pub fn validate(&self) -> Result<()> {
-        let cli = self.get_cli();
-
         if !self.config_path.exists() {
             bail!("Config file not found");
         }
@@ -301,7 +656,86 @@ impl SiteConfig {
             bail!("[base.url] is required for RSS generation");
         }
 
-        Self::check_command_installed("[build.typst.command]", &self.build.typst.command)?;
+        if self.build.sitemap.enable && self.base.url.is_none() {
+            bail!("[base.url] is required for sitemap generation");
+        }
+        if let Some(rule) = self
+            .build
+            .sitemap
+            .rules
+            .iter()
+            .find(|rule| rule.priority.is_some_and(|p| !(0.0..=1.0).contains(&p)))
+        {
+            bail!(ConfigError::Validation(format!(
+                "[build.sitemap.rules] priority of `{}` must be between 0.0 and 1.0",
+                rule.pattern
+            )));
+        }
+
+        if let Some(rule) = self.build.html.rewrite.iter().find(|rule| {
+            rule.tag.is_empty() || rule.wrap.as_ref().is_some_and(|wrap| wrap.tag.is_empty())
+        }) {
+            bail!(ConfigError::Validation(format!(
+                "[build.html.rewrite] rule for `{}` has an empty tag",
+                rule.tag
+            )));
+        }
+        if let Some(rule) = self
+            .build
+            .html
+            .rewrite
+            .iter()
+            .find(|rule| rule.value.is_some() && rule.attribute.is_none())
+        {
+            bail!(ConfigError::Validation(format!(
+                "[build.html.rewrite] rule for `{}` has a value but no attribute",
+                rule.tag
+            )));
+        }
+
+        // The library backend compiles in process, PDFs are always exported by the CLI
+        let exports_pdf = matches!(
+            self.cli.as_ref().map(|cli| &cli.command),
+            Some(Commands::ExportPdf { .. })
+        );
+        if self.build.typst.backend == TypstBackend::Cli || exports_pdf {
+            Self::check_command_installed("[build.typst.command]", &self.build.typst.command)?;
+        }
+
+        let svg = &self.build.typst.svg;
+        if svg.formats.webp && matches!(svg.extract_type, ExtractSvgType::Builtin) {
+            bail!(ConfigError::Validation(
+                "[build.typst.svg.formats] webp needs extract_type `magick` or `ffmpeg`".into()
+            ));
+        }
+        if !(1.0..=100.0).contains(&svg.avif.quality) {
+            bail!(ConfigError::Validation(
+                "[build.typst.svg.avif] quality must be between 1 and 100".into()
+            ));
+        }
+        if !(1..=10).contains(&svg.avif.speed) {
+            bail!(ConfigError::Validation(
+                "[build.typst.svg.avif] speed must be between 1 and 10".into()
+            ));
+        }
+
+        // `tola theme` installs missing themes
+        let installing = self.cli.as_ref().is_some_and(|cli| cli.is_theme());
+        if let Some(theme) = self.theme_path() {
+            if !theme.is_dir() && !installing {
+                bail!(ConfigError::Validation(format!(
+                    "[theme] `{}` not found",
+                    theme.display()
+                )));
+            }
+            // Typst only imports files under the site root
+            if !theme.starts_with(self.get_root()) {
+                bail!(ConfigError::Validation(format!(
+                    "[theme] `{}` must be inside the site root",
+                    theme.display()
+                )));
+            }
+        }
 
         if let Some(base_url) = &self.base.url
             && !base_url.starts_with("http")
@@ -335,6 +769,113 @@ impl SiteConfig {
             }
         }
 
+        let precompress = &self.build.precompress;
+        if precompress.enable && !precompress.brotli && !precompress.gzip {
+            bail!(ConfigError::Validation(
+                "[build.precompress] enable = true requires `brotli` or `gzip`".into()
+            ));
+        }
+
+        let purge = &self.build.css.purge;
+        if purge.enable {
+            if purge.stylesheets.is_empty() {
+                bail!(ConfigError::Validation(
+                    "[build.css.purge] enable = true requires `stylesheets`".into()
+                ));
+            }
+            if let Some(path) = purge
+                .stylesheets
+                .iter()
+                .find(|path| !path.is_file() || !path.starts_with(&self.build.assets))
+            {
+                bail!(ConfigError::Validation(format!(
+                    "[build.css.purge.stylesheets] {} is not a file in the assets directory",
+                    path.display()
+                )));
+            }
+        }
+
+        for plugin in self.plugins.iter().filter(|p| p.enable) {
+            Self::check_command_installed("[[plugins]] command", &plugin.command)?;
+        }
+
+        let comments = &self.build.comments;
+        if comments.enable {
+            let parts: Vec<_> = comments.repo.split('/').collect();
+            if parts.len() != 2 || parts.iter().any(|part| part.is_empty()) {
+                bail!(ConfigError::Validation(
+                    "[build.comments.repo] must be `owner/name`".into()
+                ));
+            }
+            if comments.provider == CommentsProvider::Giscus
+                && (comments.repo_id.is_empty() || comments.category_id.is_empty())
+            {
+                bail!(ConfigError::Validation(
+                    "[build.comments] giscus requires `repo_id` and `category_id`".into()
+                ));
+            }
+        }
+
+        let analytics = &self.build.analytics;
+        if analytics.enable {
+            let missing = match analytics.provider {
+                AnalyticsProvider::Plausible
+                    if analytics.domain.is_none() && self.base.url.is_none() =>
+                {
+                    Some("`domain` (or [base.url])")
+                }
+                AnalyticsProvider::GoatCounter if analytics.code.is_empty() => Some("`code`"),
+                AnalyticsProvider::Umami if analytics.website_id.is_empty() => Some("`website_id`"),
+                AnalyticsProvider::Umami | AnalyticsProvider::Custom if analytics.src.is_none() => {
+                    Some("`src`")
+                }
+                _ => None,
+            };
+            if let Some(field) = missing {
+                bail!(ConfigError::Validation(format!(
+                    "[build.analytics] {:?} requires {field}",
+                    analytics.provider
+                )));
+            }
+        }
+
+        if self.build.pagination.enable && self.build.pagination.page_size == 0 {
+            bail!(ConfigError::Validation(
+                "[build.pagination.page_size] must be at least 1".into()
+            ));
+        }
+
+        if self.build.summary.enable && self.build.summary.length == 0 {
+            bail!(ConfigError::Validation(
+                "[build.summary.length] must be at least 1".into()
+            ));
+        }
+
+        let toc = &self.build.toc;
+        if toc.enable
+            && !(1 <= toc.min_level && toc.min_level <= toc.max_level && toc.max_level <= 6)
+        {
+            bail!(ConfigError::Validation(
+                "[build.toc] levels must satisfy 1 <= min_level <= max_level <= 6".into()
+            ));
+        }
+
+        if self.serve.interface.is_empty() {
+            bail!(ConfigError::Validation(
+                "[serve.interface] must list at least one address".into()
+            ));
+        }
+        if let Some(interface) = self
+            .serve
+            .interface
+            .iter()
+            .find(|interface| interface.parse::<std::net::IpAddr>().is_err())
+        {
+            bail!(ConfigError::Validation(format!(
+                "[serve.interface] `{interface}` is not an IP address"
+            )));
+        }
+
         let valid_size_suffixes = ["B", "KB", "MB"];
         if !valid_size_suffixes
             .iter()
@@ -345,22 +886,60 @@ impl SiteConfig {
             ));
         }
 
-        match &cli.command {
-            Commands::Init { .. } if self.get_root().exists() => {
+        match self.cli.as_ref().map(|cli| &cli.command) {
+            Some(Commands::Init { .. }) if self.get_root().exists() => {
                 bail!("Path already exists");
             }
-            Commands::Deploy { .. } => {
-                if let Some(path) = &self.deploy.github.token_path {
-                    if !path.exists() {
-                        bail!(ConfigError::Validation(
-                            "[deploy.github.token_path] not found".into()
-                        ));
+            Some(Commands::Deploy { .. }) => {
+                let commit = &self.deploy.commit;
+                if commit.author.is_some() != commit.email.is_some() {
+                    bail!(ConfigError::Validation(
+                        "[deploy.commit] `author` and `email` must be set together".into()
+                    ));
+                }
+                Self::check_token_path(
+                    "[deploy.github.token_path]",
+                    self.deploy.github.token_path.as_deref(),
+                )?;
+                // API providers: (section, required fields, token path)
+                let api = match self.deploy.provider.as_str() {
+                    "cloudflare" => {
+                        let cloudflare = &self.deploy.cloudflare;
+                        Some((
+                            "[deploy.cloudflare]",
+                            vec![
+                                ("account_id", &cloudflare.account_id),
+                                ("project", &cloudflare.project),
+                            ],
+                            cloudflare.token_path.as_deref(),
+                        ))
+                    }
+                    "vercel" => {
+                        let vercel = &self.deploy.vercel;
+                        Some((
+                            "[deploy.vercel]",
+                            vec![("project", &vercel.project)],
+                            vercel.token_path.as_deref(),
+                        ))
                     }
-                    if !path.is_file() {
-                        bail!(ConfigError::Validation(
-                            "[deploy.github.token_path] is not a file".into()
-                        ));
+                    _ => None,
+                };
+                if let Some((section, fields, token_path)) = api {
+                    for (field, value) in fields {
+                        if value.is_empty() {
+                            bail!(ConfigError::Validation(format!(
+                                "{section} requires `{field}`"
+                            )));
+                        }
                     }
+                    if token_path.is_none() {
+                        bail!(ConfigError::Validation(format!(
+                            "{section} requires `token_path`"
+                        )));
+                    }
+                    let field = format!("{}.token_path]", section.trim_end_matches(']'));
+                    Self::check_token_path(&field, token_path)?;
+                    Self::check_command_installed("curl", &["curl".into()])?;
                 }
             }
             _ => {}
@@ -369,6 +948,19 @@ impl SiteConfig {
         Ok(())
     }
 
+    /// Check that a configured token file exists
+    fn check_token_path(field: &str, path: Option<&Path>) -> Result<()> {
+        match path {
+            Some(path) if !path.exists() => {
+                bail!(ConfigError::Validation(format!("{field} not found")))
+            }
+            Some(path) if !path.is_file() => {
+                bail!(ConfigError::Validation(format!("{field} is not a file")))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Check if a command is installed and available
     fn check_command_installed(field: &str, command: &[String]) -> Result<()> {
         if command.is_empty() {
@@ -520,6 +1112,51 @@ mod tests {
         assert_eq!(config.get_root(), Path::new("/custom/path"));
     }
 
+    #[test]
+    fn test_keep_going() {
+        use clap::Parser;
+
+        let mut config = SiteConfig::default();
+        assert!(!config.keep_going());
+        config.cli = Some(Cli::parse_from(["tola", "serve"]));
+        assert!(config.keep_going());
+        config.build.keep_going = Some(false);
+        assert!(!config.keep_going());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_path_non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let token = |name: &str| {
+            let mut bytes = b"tokens/\xff".to_vec();
+            bytes.extend_from_slice(name.as_bytes());
+            Some(PathBuf::from(OsStr::from_bytes(&bytes)))
+        };
+        let mut config = SiteConfig::default();
+        config.deploy.cloudflare.token_path = token("cloudflare");
+        config.deploy.vercel.token_path = token("vercel");
+        config.update_path_with_root(Path::new("/site"), Path::new("tola.toml"));
+        for token_path in [
+            config.deploy.cloudflare.token_path,
+            config.deploy.vercel.token_path,
+        ] {
+            assert!(token_path.unwrap().starts_with("/site/tokens"));
+        }
+    }
+
+    #[test]
+    fn test_changed_sections() {
+        let config = SiteConfig::default();
+        let mut changed = config.clone();
+        assert!(config.changed_sections(&changed).is_empty());
+
+        changed.serve.port = 8080;
+        changed.build.minify = !config.build.minify;
+        assert_eq!(config.changed_sections(&changed), ["build", "serve"]);
+    }
+
     #[test]
     fn test_extra_fields() {
         let config = r#"
@@ -682,6 +1319,99 @@ mod tests {
         assert!(config.extra.contains_key("analytics_id"));
     }
 
+    #[test]
+    fn test_typst_site_input() {
+        let config: SiteConfig = toml::from_str(
+            r#"
+            [base]
+            title = "Blog"
+            description = "Test"
+            [extra]
+            menu = [{ name = "Posts", url = "/posts/" }]
+            since = 2020-01-01
+        "#,
+        )
+        .unwrap();
+        let input = config.typst_site_input();
+        let json = input.strip_prefix("tola-site=").unwrap();
+        let site: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(site["base"]["title"], "Blog");
+        assert_eq!(site["extra"]["menu"][0]["url"], "/posts/");
+        assert_eq!(site["extra"]["since"], "2020-01-01");
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join("tola_include_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("tola.toml"),
+            r#"include = ["tola.local.toml", "missing.toml"]
+            [base]
+            title = "Blog"
+            description = "Main"
+            [serve]
+            port = 3000
+            watch = false
+            [deploy.github]
+            url = "https://github.com/alice/blog"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tola.local.toml"),
+            "[serve]\nport = 4000\n[deploy.github]\nbranch = \"preview\"\n",
+        )
+        .unwrap();
+
+        let config = SiteConfig::from_path(&dir.join("tola.toml")).unwrap();
+        assert_eq!(config.base.title, "Blog");
+        assert_eq!(config.serve.port, 4000);
+        assert!(!config.serve.watch);
+        assert_eq!(config.deploy.github.url, "https://github.com/alice/blog");
+        assert_eq!(config.deploy.github.branch, "preview");
+        assert_eq!(config.include[0], dir.join("tola.local.toml"));
+
+        fs::write(dir.join("tola.local.toml"), "include = [\"other.toml\"]").unwrap();
+        assert!(SiteConfig::from_path(&dir.join("tola.toml")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_theme_defaults() {
+        let dir = std::env::temp_dir().join("tola_theme_config_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("themes/paper")).unwrap();
+        fs::write(
+            dir.join("tola.toml"),
+            "[base]\ntitle = \"Blog\"\ndescription = \"Main\"\n[theme]\nname = \"paper\"\n[serve]\nport = 3000\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("themes/paper/theme.toml"),
+            "[base]\nlanguage = \"en\"\n[serve]\nport = 4000\n[build.head]\nstyles = [\"styles/paper.css\"]\n",
+        )
+        .unwrap();
+
+        let config = SiteConfig::from_path(&dir.join("tola.toml")).unwrap();
+        assert_eq!(config.base.title, "Blog");
+        assert_eq!(config.base.language, "en");
+        assert_eq!(config.serve.port, 3000);
+        assert_eq!(
+            config.build.head.styles,
+            [PathBuf::from("styles/paper.css")]
+        );
+
+        fs::write(
+            dir.join("themes/paper/theme.toml"),
+            "[theme]\nname = \"other\"\n",
+        )
+        .unwrap();
+        assert!(SiteConfig::from_path(&dir.join("tola.toml")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_unknown_top_level_field_rejection() {
         let config = r#"
@@ -0,0 +1,140 @@
+//! `[[plugins]]` section configuration.
+//!
+//! External commands hooked into the build pipeline.
+
+use super::defaults;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+
+/// Pipeline stage at which a plugin runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginStage {
+    /// Raw HTML compiled by typst, before tola processes links and SVGs.
+    PostCompile,
+    /// Processed HTML, right before minification.
+    PreMinify,
+    /// Once after the whole build; receives the build manifest (JSON).
+    PostBuild,
+}
+
+impl PluginStage {
+    /// Name as written in tola.toml
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PostCompile => "post-compile",
+            Self::PreMinify => "pre-minify",
+            Self::PostBuild => "post-build",
+        }
+    }
+}
+
+/// `[[plugins]]` entry in tola.toml - an external pipeline command.
+///
+/// Page stages pipe the HTML to the command's stdin and use its stdout as the
+/// new HTML. The command runs in the site root with `TOLA_STAGE`, `TOLA_ROOT`,
+/// `TOLA_SOURCE` and `TOLA_OUTPUT` set.
+///
+/// # Example
+/// ```toml
+/// [[plugins]]
+/// name = "footnotes"
+/// command = ["python3", "plugins/footnotes.py"]
+/// stage = "pre-minify"
+///
+/// [[plugins]]
+/// command = ["./plugins/notify.sh"]
+/// stage = "post-build"
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Display name used in logs (defaults to the command name).
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Command and arguments to run.
+    pub command: Vec<String>,
+
+    /// Stage at which the plugin runs.
+    #[educe(Default = PluginStage::PostBuild)]
+    pub stage: PluginStage,
+
+    /// Set to false to keep the entry but skip it.
+    #[serde(default = "defaults::r#true")]
+    #[educe(Default = true)]
+    pub enable: bool,
+}
+
+impl PluginConfig {
+    /// Name used in log prefixes
+    pub fn display_name(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.command.first().map(String::as_str))
+            .unwrap_or("plugin")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SiteConfig;
+    use super::PluginStage;
+
+    #[test]
+    fn test_plugins_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [[plugins]]
+            name = "footnotes"
+            command = ["python3", "footnotes.py"]
+            stage = "pre-minify"
+
+            [[plugins]]
+            command = ["./notify.sh"]
+            stage = "post-build"
+            enable = false
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.plugins.len(), 2);
+        assert_eq!(config.plugins[0].display_name(), "footnotes");
+        assert_eq!(config.plugins[0].stage, PluginStage::PreMinify);
+        assert!(config.plugins[0].enable);
+        assert_eq!(config.plugins[1].display_name(), "./notify.sh");
+        assert_eq!(config.plugins[1].stage, PluginStage::PostBuild);
+        assert!(!config.plugins[1].enable);
+    }
+
+    #[test]
+    fn test_plugins_default_empty() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert!(config.plugins.is_empty());
+    }
+
+    #[test]
+    fn test_plugins_invalid_stage() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [[plugins]]
+            command = ["cat"]
+            stage = "pre-compile"
+        "#;
+        let result: Result<SiteConfig, _> = toml::from_str(config);
+
+        assert!(result.is_err());
+    }
+}
@@ -4,29 +4,43 @@
 
 use super::defaults;
 use educe::Educe;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
 
 /// `[serve]` section in tola.toml - development server settings.
 ///
 /// # Example
 /// ```toml
 /// [serve]
-/// interface = "0.0.0.0"  # Listen on all interfaces
+/// interface = "0.0.0.0"  # Listen on all interfaces (or a list of addresses)
 /// port = 3000
 /// watch = true           # Auto-rebuild on file changes
+/// debounce = 200         # Milliseconds without changes before rebuilding
+/// open = true            # Open the site in the default browser
+/// log_requests = true    # Log every request with its status and latency
+///
+/// [serve.watch_paths]
+/// "data" = "full"         # Rebuild everything when data/ changes
+/// "typst" = "incremental" # Rebuild only the pages importing the changed file
 /// ```
 #[derive(Debug, Clone, Educe, Serialize, Deserialize)]
 #[educe(Default)]
 #[serde(deny_unknown_fields)]
 pub struct ServeConfig {
-    /// Network interface to bind.
+    /// Network interfaces to bind, as one address or a list.
     /// - `127.0.0.1` (default): localhost only
     /// - `0.0.0.0`: all interfaces (LAN accessible)
-    #[serde(default = "defaults::serve::interface")]
+    /// - `["127.0.0.1", "192.168.1.20"]`: localhost and one LAN address
+    #[serde(
+        default = "defaults::serve::interface",
+        deserialize_with = "one_or_many"
+    )]
     #[educe(Default = defaults::serve::interface())]
-    pub interface: String,
+    pub interface: Vec<String>,
+
+    /// HTTP port number (default: 5277). If it is taken, the next free port
+    /// is used instead.
 
-    /// HTTP port number (default: 5277).
     #[serde(default = "defaults::serve::port")]
     #[educe(Default = defaults::serve::port())]
     pub port: u16,
@@ -35,11 +49,71 @@ pub struct ServeConfig {
     #[serde(default = "defaults::r#true")]
     #[educe(Default = true)]
     pub watch: bool,
+
+    /// Milliseconds to wait for more changes before rebuilding (default: 200).
+    /// All files changed within this window are rebuilt together.
+    #[serde(default = "defaults::serve::debounce")]
+    #[educe(Default = defaults::serve::debounce())]
+    pub debounce: u64,
+
+    /// Open the site in the default browser once the server is listening.
+    #[serde(default)]
+    pub open: bool,
+
+    /// Log method, path, status and latency of every request.
+    /// Request counts are shown at `/__tola/stats` either way.
+    #[serde(default)]
+    pub log_requests: bool,
+
+    /// Extra files or directories to watch (relative to root), with what to
+    /// rebuild when they change. `content`, `assets`, `templates`, `utils`
+    /// and the config file are always watched.
+    #[serde(default)]
+    pub watch_paths: BTreeMap<PathBuf, WatchStrategy>,
+}
+
+impl ServeConfig {
+    /// URL of the site on the first interface
+    pub fn local_url(&self) -> String {
+        let host = self.interface.first().map_or("127.0.0.1", String::as_str);
+        match host.contains(':') {
+            true => format!("http://[{host}]:{}", self.port),
+            false => format!("http://{host}:{}", self.port),
+        }
+    }
+}
+
+/// Accept a single string as a list of one
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(one) => vec![one],
+        OneOrMany::Many(many) => many,
+    })
+}
+
+/// What `[serve.watch_paths]` rebuilds when a watched file changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchStrategy {
+    /// Rebuild the whole site (default).
+    #[default]
+    Full,
+    /// Rebuild the pages importing the changed file, like templates;
+    /// everything if no page imports it.
+    Incremental,
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::SiteConfig;
+    use super::WatchStrategy;
+    use std::path::PathBuf;
 
     #[test]
     fn test_serve_config() {
@@ -55,7 +129,7 @@ mod tests {
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
 
-        assert_eq!(config.serve.interface, "0.0.0.0");
+        assert_eq!(config.serve.interface, ["0.0.0.0"]);
         assert_eq!(config.serve.port, 8080);
         assert!(!config.serve.watch);
     }
@@ -69,9 +143,43 @@ mod tests {
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
 
-        assert_eq!(config.serve.interface, "127.0.0.1");
+        assert_eq!(config.serve.interface, ["127.0.0.1"]);
         assert_eq!(config.serve.port, 5277);
         assert!(config.serve.watch);
+        assert_eq!(config.serve.debounce, 200);
+    }
+
+    #[test]
+    fn test_serve_watch_paths() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [serve.watch_paths]
+            "data" = "full"
+            "typst/lib" = "incremental"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(
+            config.serve.watch_paths[&PathBuf::from("data")],
+            WatchStrategy::Full
+        );
+        assert_eq!(
+            config.serve.watch_paths[&PathBuf::from("typst/lib")],
+            WatchStrategy::Incremental
+        );
+
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [serve.watch_paths]
+            "data" = "never"
+        "#;
+        assert!(toml::from_str::<SiteConfig>(config).is_err());
     }
 
     #[test]
@@ -100,7 +208,7 @@ mod tests {
             interface = "0.0.0.0"
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
-        assert_eq!(config.serve.interface, "0.0.0.0");
+        assert_eq!(config.serve.interface, ["0.0.0.0"]);
 
         // Test IPv6 localhost
         let config = r#"
@@ -111,7 +219,18 @@ mod tests {
             interface = "::1"
         "#;
         let config: SiteConfig = toml::from_str(config).unwrap();
-        assert_eq!(config.serve.interface, "::1");
+        assert_eq!(config.serve.interface, ["::1"]);
+
+        // Test several interfaces
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test"
+            [serve]
+            interface = ["127.0.0.1", "192.168.1.20"]
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+        assert_eq!(config.serve.interface, ["127.0.0.1", "192.168.1.20"]);
     }
 
     #[test]
@@ -166,7 +285,7 @@ mod tests {
         // port is overridden
         assert_eq!(config.serve.port, 3000);
         // interface uses default
-        assert_eq!(config.serve.interface, "127.0.0.1");
+        assert_eq!(config.serve.interface, ["127.0.0.1"]);
         // watch uses default
         assert!(config.serve.watch);
     }
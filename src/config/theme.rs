@@ -0,0 +1,67 @@
+//! `[theme]` section configuration.
+//!
+//! Selects a theme providing templates, assets and default config.
+
+use super::defaults;
+use educe::Educe;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// `[theme]` section in tola.toml - theme selection.
+///
+/// A theme is a directory `<dir>/<name>/` with `templates/`, `assets/` and an
+/// optional `theme.toml`, whose settings apply unless the site sets them.
+/// The site's own `templates/` and `assets/` files override the theme's files
+/// of the same path.
+///
+/// # Example
+/// ```toml
+/// [theme]
+/// name = "paper"     # themes/paper/
+/// dir = "themes"     # relative to the site root, which must contain it
+/// url = "https://github.com/alice/paper.git"  # set by `tola theme install`
+/// rev = "3f2a9c1e..."  # commit checked out by `tola theme install`
+/// ```
+#[derive(Debug, Clone, Educe, Serialize, Deserialize)]
+#[educe(Default)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Theme directory name, none by default.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Directory holding the themes.
+    #[serde(default = "defaults::theme::dir")]
+    #[educe(Default = defaults::theme::dir())]
+    pub dir: PathBuf,
+
+    /// Git URL the theme was installed from.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Commit the theme is pinned to.
+    #[serde(default)]
+    pub rev: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SiteConfig;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_theme_config() {
+        let config = r#"
+            [base]
+            title = "Test"
+            description = "Test blog"
+
+            [theme]
+            name = "paper"
+        "#;
+        let config: SiteConfig = toml::from_str(config).unwrap();
+
+        assert_eq!(config.theme.name.as_deref(), Some("paper"));
+        assert_eq!(config.theme.dir, PathBuf::from("themes"));
+    }
+}
@@ -2,21 +2,45 @@
 //!
 //! Handles deployment to various hosting providers.
 
+mod cloudflare;
+mod vercel;
+
 use crate::{config::SiteConfig, utils::git};
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use gix::ThreadSafeRepository;
+use std::{fs, path::Path};
 
 /// Deploy the built site to configured provider
-pub fn deploy_site(repo: ThreadSafeRepository, config: &'static SiteConfig) -> Result<()> {
+pub fn deploy_site(repo: ThreadSafeRepository, config: &SiteConfig) -> Result<()> {
     match config.deploy.provider.as_str() {
         "github" => deploy_github(repo, config),
+        "cloudflare" => cloudflare::deploy(config),
+        "vercel" => vercel::deploy(config),
         _ => bail!("This platform is not supported now"),
     }
 }
 
 /// Deploy to GitHub Pages
-fn deploy_github(repo: ThreadSafeRepository, config: &'static SiteConfig) -> Result<()> {
-    git::commit_all(&repo, "deploy it")?;
+fn deploy_github(repo: ThreadSafeRepository, config: &SiteConfig) -> Result<()> {
+    let commit = &config.deploy.commit;
+    let author = commit
+        .author
+        .as_deref()
+        .zip(commit.email.as_deref())
+        .map(|(name, email)| git::Author { name, email });
+    git::commit_all(&repo, &commit.message, author)?;
     git::push(&repo, config)?;
     Ok(())
 }
+
+/// Read an API token, trimmed of surrounding whitespace
+fn read_token(path: Option<&Path>, field: &str) -> Result<String> {
+    let path = path.with_context(|| format!("{field} is required"))?;
+    let token = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {field} ({})", path.display()))?;
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("{field} ({}) is empty", path.display());
+    }
+    Ok(token.to_owned())
+}
@@ -0,0 +1,309 @@
+//! Cloudflare Pages direct upload.
+//!
+//! Follows the same steps as `wrangler pages deploy`: every file is uploaded
+//! once, keyed by a hash of its content, then a deployment is created from a
+//! manifest mapping each path to its key.
+
+use super::read_token;
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::http::{FormField, Request},
+};
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+const API: &str = "https://api.cloudflare.com/client/v4";
+
+/// Limits of a single upload request
+const BUCKET_BYTES: usize = 40 * 1024 * 1024;
+const BUCKET_FILES: usize = 2000;
+
+/// Largest file Pages serves
+const MAX_FILE_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Files configuring Pages itself, sent with the deployment instead of served
+const CONFIG_FILES: [&str; 3] = ["_headers", "_redirects", "_routes.json"];
+
+/// A file to serve
+#[derive(Debug)]
+struct Asset {
+    /// URL path, e.g. `/posts/hello/index.html`
+    path: String,
+    file: PathBuf,
+    /// Upload key derived from the content
+    hash: String,
+}
+
+/// Envelope of every API response
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ApiError>,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadToken {
+    jwt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    url: String,
+}
+
+/// Upload the output directory as a new deployment of `[deploy.cloudflare] project`
+pub fn deploy(config: &SiteConfig) -> Result<()> {
+    let cloudflare = &config.deploy.cloudflare;
+    let retries = cloudflare.retries;
+    let token = read_token(
+        cloudflare.token_path.as_deref(),
+        "[deploy.cloudflare.token_path]",
+    )?;
+    let output = &config.build.output;
+    let project_url = format!(
+        "{API}/accounts/{}/pages/projects/{}",
+        cloudflare.account_id, cloudflare.project
+    );
+
+    let assets = collect_assets(output)?;
+    log!("deploy"; "{} file(s) for cloudflare pages project `{}`", assets.len(), cloudflare.project);
+
+    // Uploads are authorized by a short-lived token of the project
+    let upload: UploadToken = call(
+        Request::get(format!("{project_url}/upload-token")).bearer(&token),
+        retries,
+    )?;
+    let jwt = upload.jwt;
+
+    let hashes: Vec<&str> = assets
+        .iter()
+        .map(|asset| asset.hash.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let missing: HashSet<String> = call(
+        Request::post(format!("{API}/pages/assets/check-missing"))
+            .bearer(&jwt)
+            .json(&json!({ "hashes": hashes }))?,
+        retries,
+    )?;
+
+    let mut seen = HashSet::new();
+    let to_upload: Vec<&Asset> = assets
+        .iter()
+        .filter(|asset| missing.contains(&asset.hash) && seen.insert(&asset.hash))
+        .collect();
+    let mut uploaded = 0;
+    for bucket in buckets(&to_upload)? {
+        let payload = bucket
+            .iter()
+            .map(|asset| upload_payload(asset))
+            .collect::<Result<Vec<_>>>()?;
+        call::<serde_json::Value>(
+            Request::post(format!("{API}/pages/assets/upload"))
+                .bearer(&jwt)
+                .json(&payload)?,
+            retries,
+        )?;
+        uploaded += bucket.len();
+        log!("deploy"; "uploaded {uploaded}/{} new file(s)", to_upload.len());
+    }
+
+    call::<serde_json::Value>(
+        Request::post(format!("{API}/pages/assets/upsert-hashes"))
+            .bearer(&jwt)
+            .json(&json!({ "hashes": hashes }))?,
+        retries,
+    )?;
+
+    let manifest: BTreeMap<&str, &str> = assets
+        .iter()
+        .map(|asset| (asset.path.as_str(), asset.hash.as_str()))
+        .collect();
+    let mut form = vec![
+        FormField::Text("manifest".into(), serde_json::to_string(&manifest)?),
+        FormField::Text("branch".into(), cloudflare.branch.clone()),
+    ];
+    for name in CONFIG_FILES {
+        let file = output.join(name);
+        if file.is_file() {
+            form.push(FormField::File(name.into(), file));
+        }
+    }
+    let deployment: Deployment = call(
+        Request::post(format!("{project_url}/deployments"))
+            .bearer(&token)
+            .form(form),
+        retries,
+    )?;
+
+    log!("deploy"; "deployed to {}", deployment.url);
+    Ok(())
+}
+
+/// Send `request` and unwrap the `result` of the API response
+fn call<T: DeserializeOwned>(request: Request, retries: usize) -> Result<T> {
+    let response = request.send_with_retry(retries)?;
+    let body: ApiResponse = response.json()?;
+    match body.success {
+        true => serde_json::from_value(body.result)
+            .with_context(|| format!("Unexpected Cloudflare API response:\n{}", response.text())),
+        false => {
+            let errors: Vec<String> = body
+                .errors
+                .iter()
+                .map(|error| format!("{} (code {})", error.message, error.code))
+                .collect();
+            bail!(
+                "Cloudflare API request failed (HTTP {}): {}",
+                response.status,
+                errors.join("; ")
+            )
+        }
+    }
+}
+
+/// Every file of the output directory except git data and Pages config files
+fn collect_assets(output: &Path) -> Result<Vec<Asset>> {
+    let mut assets = Vec::new();
+    let entries = WalkDir::new(output)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git");
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(output)?;
+        let path = format!("/{}", relative.to_string_lossy().replace('\\', "/"));
+        if CONFIG_FILES.iter().any(|name| path == format!("/{name}")) {
+            continue;
+        }
+        if entry.metadata()?.len() > MAX_FILE_BYTES {
+            bail!("{path} is larger than the 25 MiB Cloudflare Pages allows");
+        }
+        let content = fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        assets.push(Asset {
+            hash: content_hash(&content, entry.path()),
+            path,
+            file: entry.into_path(),
+        });
+    }
+    Ok(assets)
+}
+
+/// Upload key of a file, hashed from the same input wrangler uses
+/// (base64 content followed by the extension), as 32 hex digits
+fn content_hash(content: &[u8], path: &Path) -> String {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy())
+        .unwrap_or_default();
+    let mut hasher = Sha1::new();
+    hasher.update(STANDARD.encode(content));
+    hasher.update(extension.as_bytes());
+    let mut hash = format!("{:x}", hasher.finalize());
+    hash.truncate(32);
+    hash
+}
+
+/// Split uploads into requests within the size and count limits
+fn buckets<'a>(assets: &[&'a Asset]) -> Result<Vec<Vec<&'a Asset>>> {
+    let mut buckets: Vec<Vec<&Asset>> = Vec::new();
+    let mut bucket_bytes = 0;
+    for &asset in assets {
+        // Base64 grows the content by a third
+        let bytes = fs::metadata(&asset.file)?.len() as usize * 4 / 3;
+        let full = buckets.last().is_none_or(|bucket| {
+            bucket.len() >= BUCKET_FILES || bucket_bytes + bytes > BUCKET_BYTES
+        });
+        if full {
+            buckets.push(Vec::new());
+            bucket_bytes = 0;
+        }
+        buckets.last_mut().unwrap().push(asset);
+        bucket_bytes += bytes;
+    }
+    Ok(buckets)
+}
+
+fn upload_payload(asset: &Asset) -> Result<serde_json::Value> {
+    let content = fs::read(&asset.file)
+        .with_context(|| format!("Failed to read {}", asset.file.display()))?;
+    let content_type = mime_guess::from_path(&asset.file).first_or_octet_stream();
+    Ok(json!({
+        "key": asset.hash,
+        "value": STANDARD.encode(content),
+        "metadata": { "contentType": content_type.essence_str() },
+        "base64": true,
+    }))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash() {
+        let html = content_hash(b"<p>hi</p>", Path::new("a/index.html"));
+        assert_eq!(html.len(), 32);
+        assert_eq!(html, content_hash(b"<p>hi</p>", Path::new("b/index.html")));
+        assert_ne!(html, content_hash(b"<p>hi</p>", Path::new("b/index.htm")));
+    }
+
+    #[test]
+    fn test_collect_assets() {
+        let output = std::env::temp_dir().join("tola_cloudflare_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts")).unwrap();
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::write(output.join("index.html"), "home").unwrap();
+        fs::write(output.join("posts/a.html"), "a").unwrap();
+        fs::write(output.join("_redirects"), "/x /y 301").unwrap();
+        fs::write(output.join(".git/HEAD"), "ref").unwrap();
+
+        let assets = collect_assets(&output).unwrap();
+        let paths: Vec<_> = assets.iter().map(|asset| asset.path.as_str()).collect();
+        assert_eq!(paths, ["/index.html", "/posts/a.html"]);
+
+        let refs: Vec<&Asset> = assets.iter().collect();
+        assert_eq!(buckets(&refs).unwrap().len(), 1);
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_api_error() {
+        let body: ApiResponse = serde_json::from_str(
+            r#"{"success":false,"errors":[{"code":8000007,"message":"Project not found"}],"result":null}"#,
+        )
+        .unwrap();
+        assert!(!body.success);
+        assert_eq!(body.errors[0].message, "Project not found");
+        assert!(body.result.is_null());
+    }
+}
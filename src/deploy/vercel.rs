@@ -0,0 +1,250 @@
+//! Vercel deployments.
+//!
+//! Creates a deployment from the SHA-1 of every output file. Vercel answers
+//! with the digests it has never seen; those files are uploaded and the
+//! deployment is created again.
+
+use super::read_token;
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::http::{Request, Response},
+};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+const API: &str = "https://api.vercel.com";
+
+/// A file of the deployment, as listed in the request
+#[derive(Debug, Serialize)]
+struct DeploymentFile {
+    /// Path relative to the output directory, with `/` separators
+    file: String,
+    sha: String,
+    size: u64,
+    #[serde(skip)]
+    source: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    url: String,
+    #[serde(default, rename = "readyState")]
+    ready_state: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+    /// Digests of files to upload, with `missing_files`
+    #[serde(default)]
+    missing: Vec<String>,
+}
+
+/// Deploy the output directory to `[deploy.vercel] project`
+///
+/// Production unless `tola deploy --preview` was given.
+pub fn deploy(config: &SiteConfig) -> Result<()> {
+    let vercel = &config.deploy.vercel;
+    let token = read_token(vercel.token_path.as_deref(), "[deploy.vercel.token_path]")?;
+    let files = collect_files(&config.build.output)?;
+    let target = match config.deploy.preview {
+        true => "preview",
+        false => "production",
+    };
+    log!("deploy"; "{} file(s) for vercel project `{}` ({target})", files.len(), vercel.project);
+
+    let body = deployment_body(&vercel.project, &files, config.deploy.preview);
+    let create = || {
+        Request::post(api_url("/v13/deployments", vercel.team_id.as_deref()))
+            .bearer(&token)
+            .json(&body)
+    };
+
+    let mut response = create()?.send_with_retry(vercel.retries)?;
+    if let Some(missing) = missing_files(&response) {
+        upload_files(&files, &missing, &token, config)?;
+        response = create()?.send_with_retry(vercel.retries)?;
+    }
+    if !response.is_success() {
+        let error: ErrorResponse = response.json()?;
+        bail!(
+            "Vercel deployment failed (HTTP {}): {} ({})",
+            response.status,
+            error.error.message,
+            error.error.code
+        );
+    }
+
+    let deployment: Deployment = response.json()?;
+    let state = deployment.ready_state.as_deref().unwrap_or("created");
+    log!("deploy"; "deployed to https://{} ({})", deployment.url, state.to_lowercase());
+    Ok(())
+}
+
+/// Endpoint `path`, scoped to the team if there is one
+fn api_url(path: &str, team_id: Option<&str>) -> String {
+    match team_id {
+        Some(team) => format!("{API}{path}?teamId={}", urlencoding::encode(team)),
+        None => format!("{API}{path}"),
+    }
+}
+
+fn deployment_body(project: &str, files: &[DeploymentFile], preview: bool) -> serde_json::Value {
+    let mut body = json!({
+        "name": project,
+        "project": project,
+        "files": files,
+        // The files are the built site, so Vercel has nothing to build
+        "projectSettings": { "framework": null },
+    });
+    if !preview {
+        body["target"] = json!("production");
+    }
+    body
+}
+
+/// Digests Vercel asks for, if the deployment failed with `missing_files`
+fn missing_files(response: &Response) -> Option<HashSet<String>> {
+    if response.is_success() {
+        return None;
+    }
+    let error = response.json::<ErrorResponse>().ok()?.error;
+    (error.code == "missing_files").then(|| error.missing.into_iter().collect())
+}
+
+/// Upload the files whose digest is `missing`, each at most once
+fn upload_files(
+    files: &[DeploymentFile],
+    missing: &HashSet<String>,
+    token: &str,
+    config: &SiteConfig,
+) -> Result<()> {
+    let vercel = &config.deploy.vercel;
+    let mut by_sha: BTreeMap<&str, &DeploymentFile> = BTreeMap::new();
+    for file in files.iter().filter(|file| missing.contains(&file.sha)) {
+        by_sha.entry(&file.sha).or_insert(file);
+    }
+
+    let total = by_sha.len();
+    for (i, file) in by_sha.values().enumerate() {
+        let content = fs::read(&file.source)
+            .with_context(|| format!("Failed to read {}", file.source.display()))?;
+        let response = Request::post(api_url("/v2/files", vercel.team_id.as_deref()))
+            .bearer(token)
+            .header("Content-Type", "application/octet-stream")
+            .header("x-vercel-digest", &file.sha)
+            .bytes(content)
+            .send_with_retry(vercel.retries)?;
+        if !response.is_success() {
+            bail!(
+                "Failed to upload {} (HTTP {}): {}",
+                file.file,
+                response.status,
+                response.text()
+            );
+        }
+        let uploaded = i + 1;
+        if uploaded % 50 == 0 || uploaded == total {
+            log!("deploy"; "uploaded {uploaded}/{total} new file(s)");
+        }
+    }
+    Ok(())
+}
+
+/// Every file of the output directory except git data
+fn collect_files(output: &Path) -> Result<Vec<DeploymentFile>> {
+    let mut files = Vec::new();
+    let entries = WalkDir::new(output)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git");
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let content = fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        let relative = entry.path().strip_prefix(output)?;
+        files.push(DeploymentFile {
+            file: relative.to_string_lossy().replace('\\', "/"),
+            sha: format!("{:x}", Sha1::digest(&content)),
+            size: content.len() as u64,
+            source: entry.into_path(),
+        });
+    }
+    Ok(files)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_files() {
+        let output = std::env::temp_dir().join("tola_vercel_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts")).unwrap();
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::write(output.join("posts/a.html"), "a").unwrap();
+        fs::write(output.join(".git/HEAD"), "ref").unwrap();
+
+        let files = collect_files(&output).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file, "posts/a.html");
+        assert_eq!(files[0].sha, "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8");
+        assert_eq!(files[0].size, 1);
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_deployment_body() {
+        let body = deployment_body("blog", &[], false);
+        assert_eq!(body["name"], "blog");
+        assert_eq!(body["target"], "production");
+        assert!(deployment_body("blog", &[], true).get("target").is_none());
+        assert_eq!(
+            api_url("/v2/files", Some("team 1")),
+            "https://api.vercel.com/v2/files?teamId=team%201"
+        );
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let response = Response {
+            status: 400,
+            body:
+                br#"{"error":{"code":"missing_files","message":"Missing files","missing":["abc"]}}"#
+                    .to_vec(),
+        };
+        let missing = missing_files(&response).unwrap();
+        assert!(missing.contains("abc"));
+
+        let response = Response {
+            status: 403,
+            body: br#"{"error":{"code":"forbidden","message":"Not authorized"}}"#.to_vec(),
+        };
+        assert!(missing_files(&response).is_none());
+    }
+}
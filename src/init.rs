@@ -1,10 +1,23 @@
 //! Site initialization module.
 //!
-//! Creates new site structure with default configuration.
+//! Creates new site structure with default configuration, scaffolded from a
+//! built-in template or a git repository (`tola init --template`).
+//!
+//! `[base]` values come from the command line (or `TOLA_INIT_*` variables);
+//! missing ones are asked for when stdin is a terminal, unless `--yes` is given.
 
-use crate::{config::SiteConfig, utils::git};
+use crate::{
+    cli::Commands,
+    config::{BaseConfig, SiteConfig},
+    exec, log,
+    utils::{build::collect_files, git},
+};
 use anyhow::{Context, Result, bail};
-use std::{fs, path::Path};
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
 
 /// Files to write ignore patterns to
 const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
@@ -24,29 +37,260 @@ const SITE_DIRS: &[&str] = &[
     "utils",
 ];
 
-/// Create a new site with default structure
-pub fn new_site(config: &'static SiteConfig) -> Result<()> {
+/// Files of a built-in template, relative to the site root
+type TemplateFiles = &'static [(&'static str, &'static str)];
+
+/// Stylesheet shipped by every built-in template (relative to `assets/`)
+const TEMPLATE_STYLESHEET: &str = "styles/main.css";
+
+/// A bare page with a layout and starter CSS
+const MINIMAL: TemplateFiles = &[
+    (
+        "content/index.typ",
+        include_str!("../assets/init/minimal/content/index.typ"),
+    ),
+    (
+        "templates/page.typ",
+        include_str!("../assets/init/minimal/templates/page.typ"),
+    ),
+    (
+        "assets/styles/main.css",
+        include_str!("../assets/init/minimal/styles/main.css"),
+    ),
+];
+
+/// A home page, a post listing and a first post
+const BLOG: TemplateFiles = &[
+    (
+        "content/index.typ",
+        include_str!("../assets/init/blog/content/index.typ"),
+    ),
+    (
+        "content/posts/index.typ",
+        include_str!("../assets/init/blog/content/posts/index.typ"),
+    ),
+    (
+        "content/posts/hello-world.typ",
+        include_str!("../assets/init/blog/content/posts/hello-world.typ"),
+    ),
+    (
+        "templates/page.typ",
+        include_str!("../assets/init/blog/templates/page.typ"),
+    ),
+    (
+        "templates/post.typ",
+        include_str!("../assets/init/blog/templates/post.typ"),
+    ),
+    (
+        "assets/styles/main.css",
+        include_str!("../assets/init/blog/styles/main.css"),
+    ),
+];
+
+/// Documentation pages with a sidebar
+const DOCS: TemplateFiles = &[
+    (
+        "content/index.typ",
+        include_str!("../assets/init/docs/content/index.typ"),
+    ),
+    (
+        "content/getting-started.typ",
+        include_str!("../assets/init/docs/content/getting-started.typ"),
+    ),
+    (
+        "content/configuration.typ",
+        include_str!("../assets/init/docs/content/configuration.typ"),
+    ),
+    (
+        "templates/doc.typ",
+        include_str!("../assets/init/docs/templates/doc.typ"),
+    ),
+    (
+        "assets/styles/main.css",
+        include_str!("../assets/init/docs/styles/main.css"),
+    ),
+];
+
+/// Where the files of a new site come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Template<'a> {
+    Builtin(TemplateFiles),
+    /// A git repository, cloned and copied without its history
+    Git(&'a str),
+}
+
+impl<'a> Template<'a> {
+    /// Resolve `blog`, `docs`, `minimal` or a git URL
+    fn parse(name: &'a str) -> Result<Self> {
+        Ok(match name {
+            "minimal" => Self::Builtin(MINIMAL),
+            "blog" => Self::Builtin(BLOG),
+            "docs" => Self::Builtin(DOCS),
+            url if is_git_url(url) => Self::Git(url),
+            _ => {
+                bail!("Unknown template `{name}`: expected `blog`, `docs`, `minimal` or a git URL")
+            }
+        })
+    }
+}
+
+/// Whether `name` looks like a git repository URL
+fn is_git_url(name: &str) -> bool {
+    name.contains("://") || name.starts_with("git@") || name.ends_with(".git")
+}
+
+/// Create a new site from `template` (`blog`, `docs`, `minimal` or a git URL)
+///
+/// With `interactive`, `[base]` values missing from the command line are
+/// asked for if stdin is a terminal.
+pub fn new_site(config: &SiteConfig, template: &str, interactive: bool) -> Result<()> {
     let root = config.get_root();
+    let template = Template::parse(template)?;
+
+    let mut base = config.base.clone();
+    if interactive && io::stdin().is_terminal() {
+        prompt_missing(&mut base, config)?;
+    }
 
     let repo = git::create_repo(root)?;
     init_site_structure(root)?;
-    init_default_config(root)?;
+    match template {
+        Template::Builtin(files) => {
+            write_template_files(root, files)?;
+            init_default_config(root, &base)?;
+        }
+        Template::Git(url) => {
+            copy_git_template(root, url)?;
+            // A template without its own config gets the default one
+            if !root.join(CONFIG_FILE).exists() {
+                init_default_config(root, &base)?;
+            }
+        }
+    }
+    let cache = config
+        .build
+        .cache
+        .strip_prefix(root)
+        .unwrap_or(&config.build.cache);
     init_ignored_files(
         root,
-        &[config.build.output.as_path(), Path::new("/assets/images/")],
+        &[
+            config.build.output.as_path(),
+            Path::new("/assets/images/"),
+            cache,
+        ],
     )?;
-    git::commit_all(&repo, "initial commit")?;
+    git::commit_all(&repo, "initial commit", None)?;
 
     Ok(())
 }
 
-/// Write default configuration file
-fn init_default_config(root: &Path) -> Result<()> {
-    let content = toml::to_string_pretty(&SiteConfig::default())?;
+/// Write default configuration file with `base`, linking the templates' stylesheet
+fn init_default_config(root: &Path, base: &BaseConfig) -> Result<()> {
+    let mut config = SiteConfig {
+        base: base.clone(),
+        ..Default::default()
+    };
+    if root.join("assets").join(TEMPLATE_STYLESHEET).exists() {
+        config.build.head.styles = vec![TEMPLATE_STYLESHEET.into()];
+    }
+    let content = toml::to_string_pretty(&config)?;
     fs::write(root.join(CONFIG_FILE), content)?;
     Ok(())
 }
 
+/// Ask for the `[base]` values not given to `tola init`
+fn prompt_missing(base: &mut BaseConfig, config: &SiteConfig) -> Result<()> {
+    let Some(Commands::Init {
+        title,
+        description,
+        author,
+        email,
+        url,
+        ..
+    }) = config.cli.as_ref().map(|cli| &cli.command)
+    else {
+        return Ok(());
+    };
+
+    let mut input = io::stdin().lock();
+    if title.is_none() {
+        base.title = ask(&mut input, "Title", &base.title)?;
+    }
+    if description.is_none() {
+        base.description = ask(&mut input, "Description", &base.description)?;
+    }
+    if author.is_none() {
+        base.author = ask(&mut input, "Author", &base.author)?;
+    }
+    if email.is_none() {
+        base.email = ask(&mut input, "Email", &base.email)?;
+    }
+    if url.is_none() {
+        let answer = ask(&mut input, "URL", base.url.as_deref().unwrap_or_default())?;
+        base.url = Some(answer).filter(|url| !url.is_empty());
+    }
+    Ok(())
+}
+
+/// Print `label` with its default and read the answer; an empty one keeps the default
+fn ask(input: &mut impl BufRead, label: &str, default: &str) -> Result<String> {
+    let mut out = io::stdout().lock();
+    match default.is_empty() {
+        true => write!(out, "{label}: ")?,
+        false => write!(out, "{label} [{default}]: ")?,
+    }
+    out.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(match answer.trim() {
+        "" => default.to_owned(),
+        answer => answer.to_owned(),
+    })
+}
+
+/// Write the files of a built-in template
+fn write_template_files(root: &Path, files: TemplateFiles) -> Result<()> {
+    for (path, content) in files {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Clone `url` next to the site and copy its files (without `.git`) into `root`
+fn copy_git_template(root: &Path, url: &str) -> Result<()> {
+    let checkout = std::env::temp_dir().join(format!("tola-template-{}", std::process::id()));
+    let result = clone_and_copy(root, url, &checkout);
+    fs::remove_dir_all(&checkout).ok();
+    result
+}
+
+fn clone_and_copy(root: &Path, url: &str, checkout: &Path) -> Result<()> {
+    log!("init"; "cloning template {url}");
+    exec!(["git"]; "clone", "--depth", "1", url, checkout)
+        .with_context(|| format!("Failed to clone template {url}"))?;
+
+    let git_dir = checkout.join(".git");
+    let files = collect_files(checkout, |path| !path.starts_with(&git_dir));
+    for file in files {
+        let relative = file.strip_prefix(checkout)?;
+        let dest = root.join(relative);
+        if dest.exists() {
+            bail!("Template file `{}` already exists", dest.display());
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&file, &dest).with_context(|| format!("Failed to copy {}", file.display()))?;
+    }
+    Ok(())
+}
+
 /// Create site directory structure
 fn init_site_structure(root: &Path) -> Result<()> {
     for dir in SITE_DIRS {
@@ -80,3 +324,74 @@ pub fn init_ignored_files(root: &Path, paths: &[&Path]) -> Result<()> {
 
     Ok(())
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_template_parse() {
+        assert_eq!(Template::parse("blog").unwrap(), Template::Builtin(BLOG));
+        assert_eq!(
+            Template::parse("https://github.com/alice/theme").unwrap(),
+            Template::Git("https://github.com/alice/theme")
+        );
+        assert_eq!(
+            Template::parse("git@github.com:alice/theme.git").unwrap(),
+            Template::Git("git@github.com:alice/theme.git")
+        );
+        assert!(Template::parse("portfolio").is_err());
+    }
+
+    #[test]
+    fn test_ask() {
+        let mut input = "My Blog\n\n".as_bytes();
+        assert_eq!(ask(&mut input, "Title", "").unwrap(), "My Blog");
+        assert_eq!(ask(&mut input, "Author", "Alice").unwrap(), "Alice");
+    }
+
+    #[test]
+    fn test_init_builtin_template() {
+        let root = std::env::temp_dir().join("tola_init_template_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        init_site_structure(&root).unwrap();
+        write_template_files(&root, DOCS).unwrap();
+        init_default_config(&root, &BaseConfig::default()).unwrap();
+
+        assert!(root.join("content/getting-started.typ").exists());
+        let config = SiteConfig::from_path(&root.join(CONFIG_FILE)).unwrap();
+        assert_eq!(
+            config.build.head.styles,
+            vec![PathBuf::from(TEMPLATE_STYLESHEET)]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_builtin_templates_build_paths() {
+        for (name, files) in [("minimal", MINIMAL), ("blog", BLOG), ("docs", DOCS)] {
+            let root = std::env::temp_dir().join(format!("tola_init_{name}_paths_test"));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+
+            init_site_structure(&root).unwrap();
+            write_template_files(&root, files).unwrap();
+            init_default_config(&root, &BaseConfig::default()).unwrap();
+
+            let mut config = SiteConfig::from_path(&root.join(CONFIG_FILE)).unwrap();
+            config.build.content = root.join(&config.build.content);
+            config.build.assets = root.join(&config.build.assets);
+            config.build.output = root.join(&config.build.output);
+            crate::build::check_output_collisions(&config)
+                .unwrap_or_else(|e| panic!("`{name}` template: {e}"));
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+}
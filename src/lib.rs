@@ -0,0 +1,50 @@
+//! Tola - A static site generator for Typst blogs.
+//!
+//! Besides the `tola` binary, the crate can be driven programmatically:
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use tola::{SiteConfig, run_build};
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let config = SiteConfig::load(Path::new("my-blog/tola.toml"))?;
+//!     config.validate()?;
+//!     run_build(&config)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! | Function        | Purpose                                          |
+//! |-----------------|--------------------------------------------------|
+//! | [`run_build`]   | Build the site and RSS feed, then report errors  |
+//! | [`build_site`]  | Compile content and copy assets only             |
+//! | [`serve_site`]  | Run the development server (with watcher)        |
+//! | [`check_site`]  | Compile and validate without writing the output  |
+//! | [`deploy_site`] | Push or upload the output to the deploy provider |
+//! | [`clean_site`]  | Remove the output directory and build cache      |
+//!
+//! Build events can be followed by registering a [`BuildObserver`].
+
+pub mod bench;
+pub mod build;
+pub mod check;
+pub mod clean;
+pub mod cli;
+pub mod config;
+pub mod deploy;
+pub mod init;
+pub mod new;
+pub mod observer;
+pub mod pdf;
+pub mod serve;
+pub mod theme;
+pub mod utils;
+mod watch;
+
+pub use build::{build_site, run_build};
+pub use check::check_site;
+pub use clean::clean_site;
+pub use config::SiteConfig;
+pub use deploy::deploy_site;
+pub use observer::{BuildObserver, add_observer};
+pub use serve::serve_site;
@@ -1,46 +1,92 @@
 //! Tola - A static site generator for Typst blogs.
 
-mod build;
-mod cli;
-mod config;
-mod deploy;
-mod init;
-mod serve;
-mod utils;
-mod watch;
-
 use anyhow::{Result, bail};
-use build::build_site;
 use clap::Parser;
-use cli::{Cli, Commands};
-use config::SiteConfig;
-use deploy::deploy_site;
-use gix::ThreadSafeRepository;
-use init::new_site;
-use serve::serve_site;
 use std::path::Path;
-use utils::rss::build_rss;
+use tola::{
+    SiteConfig,
+    bench::bench_site,
+    check::check_site,
+    clean::{CleanTarget, clean_site},
+    cli::{Cli, Commands, ThemeAction},
+    deploy_site,
+    init::new_site,
+    new::new_page,
+    pdf::export_pdf,
+    run_build,
+    serve::pick_port,
+    serve_site,
+    theme::{install_theme, update_theme},
+    utils,
+};
 
 fn main() -> Result<()> {
-    let cli: &'static Cli = Box::leak(Box::new(Cli::parse()));
-    let config: &'static SiteConfig = Box::leak(Box::new(load_config(cli)?));
+    utils::panic::install_hook();
+    let cli = Cli::parse();
+    utils::log::init(cli.level(), &cli.log_filter)?;
+    let config = load_config(&cli)?;
+    utils::log::configure(&config.log);
 
     match cli.command {
-        Commands::Init { .. } => new_site(config),
-        Commands::Build { .. } => run_build(config).map(|_| ()),
+        Commands::Init {
+            ref template, yes, ..
+        } => new_site(&config, template, !yes),
+        Commands::Build {
+            diff,
+            ref single_file,
+            ..
+        } => {
+            let before = diff.then(|| utils::diff::snapshot(&config.build.output));
+            let result = run_build(&config);
+            if let Some(before) = before {
+                let after = utils::diff::snapshot(&config.build.output);
+                utils::diff::log_changes(&utils::diff::compare(&before, &after));
+            }
+            result?;
+            if let Some(page) = single_file {
+                utils::single_file::export(page, &config)?;
+            }
+            Ok(())
+        }
+        Commands::New {
+            ref path,
+            ref archetype,
+        } => new_page(&config, path, archetype.as_deref()),
+        Commands::Theme { ref action } => match action {
+            ThemeAction::Install { url, rev } => {
+                install_theme(&config, url.as_deref(), rev.as_deref())
+            }
+            ThemeAction::Update => update_theme(&config),
+        },
         Commands::Deploy { .. } => {
-            let repo = run_build(config)?;
-            deploy_site(repo, config)
+            let repo = run_build(&config)?;
+            deploy_site(repo, &config)
         }
         Commands::Serve { .. } => {
-            run_build(config)?;
-            tokio::runtime::Runtime::new()?.block_on(serve_site(config))
+            let mut config = config;
+            pick_port(&mut config)?;
+            run_build(&config)?;
+            tokio::runtime::Runtime::new()?.block_on(serve_site(&config))
+        }
+        Commands::Check { .. } => check_site(&config),
+        Commands::Clean {
+            cache_only,
+            output_only,
+        } => {
+            let target = match (cache_only, output_only) {
+                (true, _) => CleanTarget::Cache,
+                (_, true) => CleanTarget::Output,
+                _ => CleanTarget::All,
+            };
+            clean_site(&config, target)
         }
+        Commands::ExportPdf { ref pattern } => export_pdf(&config, pattern.as_deref()),
+        Commands::Bench { runs, synthetic } => bench_site(&config, runs, synthetic),
     }
 }
 
 /// Load and validate configuration from CLI arguments
-fn load_config(cli: &'static Cli) -> Result<SiteConfig> {
+fn load_config(cli: &Cli) -> Result<SiteConfig> {
     let root = cli.root.as_deref().unwrap_or(Path::new("./"));
     let config_path = root.join(&cli.config);
 
@@ -67,14 +113,3 @@ fn load_config(cli: &'static Cli) -> Result<SiteConfig> {
 
     Ok(config)
 }
-
-/// Run build and RSS generation in parallel
-fn run_build(config: &'static SiteConfig) -> Result<ThreadSafeRepository> {
-    let (build_result, rss_result) = rayon::join(
-        || build_site(config, config.build.clear),
-        || build_rss(config),
-    );
-
-    rss_result?;
-    build_result
-}
@@ -0,0 +1,184 @@
+//! Content file creation (`tola new`).
+//!
+//! A new page is written from an archetype: `templates/archetypes/<name>.typ`,
+//! where `<name>` is `--archetype` or the first directory of the page path
+//! (`posts` for `posts/my-first-post`), falling back to
+//! `templates/archetypes/default.typ` and then to a built-in one.
+//!
+//! Archetypes may use `{{title}}`, `{{slug}}`, `{{date}}` (today, `YYYY-MM-DD`)
+//! and `{{author}}` (`[base] author`). Values are escaped for a typst string
+//! literal, so `"{{title}}"` stays valid whatever the title contains.
+
+use crate::{config::SiteConfig, log};
+use anyhow::{Context, Result, bail};
+use std::{fs, path::Path};
+
+/// Archetype used when the site has none
+const DEFAULT_ARCHETYPE: &str = r#"#metadata((
+  title: "{{title}}",
+  date: "{{date}}",
+  author: "{{author}}",
+)) <tola-meta>
+
+= {{title}}
+
+"#;
+
+/// Create `content/<path>.typ` from its archetype
+///
+/// The file name is slugified; the title is derived from the name as typed.
+pub fn new_page(config: &SiteConfig, path: &Path, archetype: Option<&str>) -> Result<()> {
+    let name = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .context("The page path needs a file name")?;
+    let slug = slug::slugify(name);
+    if slug.is_empty() {
+        bail!("Cannot derive a file name from `{name}`");
+    }
+
+    let relative = path.with_file_name(format!("{slug}.typ"));
+    let dest = config.build.content.join(&relative);
+    if dest.exists() {
+        bail!("`{}` already exists", dest.display());
+    }
+
+    let archetype_name = archetype.or_else(|| section(&relative));
+    let template = read_archetype(&config.build.templates, archetype_name)?;
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let content = fill(
+        &template,
+        &[
+            ("title", &escape(&title(name))),
+            ("slug", &slug),
+            ("date", &date),
+            ("author", &escape(&config.base.author)),
+        ],
+    );
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&dest, content).with_context(|| format!("Failed to write {}", dest.display()))?;
+    log!("new"; "created {}", dest.display());
+    Ok(())
+}
+
+/// First directory of a page path (`posts` for `posts/a.typ`), if any
+fn section(relative: &Path) -> Option<&str> {
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?;
+    first.as_os_str().to_str()
+}
+
+/// Content of the archetype `name` (or the default one) under `templates`
+fn read_archetype(templates: &Path, name: Option<&str>) -> Result<String> {
+    let dir = templates.join("archetypes");
+    let candidates = name
+        .map(|name| format!("{name}.typ"))
+        .into_iter()
+        .chain(["default.typ".to_owned()]);
+    for file in candidates {
+        let path = dir.join(file);
+        if path.exists() {
+            return fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()));
+        }
+    }
+    Ok(DEFAULT_ARCHETYPE.to_owned())
+}
+
+/// Title from a file name as typed: `my-first-post` → `My first post`
+fn title(name: &str) -> String {
+    let words = name.replace(['-', '_'], " ");
+    let mut chars = words.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Escape `value` for a typst string literal
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Replace every `{{key}}` in `template`
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_owned(), |text, (key, value)| {
+            text.replace(&format!("{{{{{key}}}}}"), value)
+        })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title("my-first-post"), "My first post");
+        assert_eq!(title("Hello World"), "Hello World");
+    }
+
+    #[test]
+    fn test_section() {
+        assert_eq!(section(Path::new("posts/a.typ")), Some("posts"));
+        assert_eq!(section(Path::new("about.typ")), None);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Plain"), "Plain");
+        assert_eq!(escape(r#"Say "hi" \ bye"#), r#"Say \"hi\" \\ bye"#);
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(
+            fill(
+                "{{title}} ({{date}}) {{unknown}}",
+                &[("title", "Hi"), ("date", "2025-01-01")]
+            ),
+            "Hi (2025-01-01) {{unknown}}"
+        );
+    }
+
+    #[test]
+    fn test_new_page_uses_section_archetype() {
+        let root = std::env::temp_dir().join("tola_new_page_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("templates/archetypes")).unwrap();
+        fs::write(
+            root.join("templates/archetypes/posts.typ"),
+            "// {{slug}} by {{author}}\n= {{title}}\n",
+        )
+        .unwrap();
+
+        let mut config = SiteConfig::default();
+        config.build.content = root.join("content");
+        config.build.templates = root.join("templates");
+        config.base.author = "Alice".into();
+        new_page(&config, Path::new("posts/My First Post"), None).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("content/posts/my-first-post.typ")).unwrap(),
+            "// my-first-post by Alice\n= My First Post\n"
+        );
+
+        // Existing pages are never overwritten
+        assert!(new_page(&config, Path::new("posts/my-first-post"), None).is_err());
+
+        // Pages outside a section get the built-in archetype
+        new_page(&config, Path::new("about"), None).unwrap();
+        let about = fs::read_to_string(root.join("content/about.typ")).unwrap();
+        assert!(about.contains("title: \"About\""));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
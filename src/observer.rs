@@ -0,0 +1,125 @@
+//! Build event observers.
+//!
+//! Embedders (and future plugins) can register a [`BuildObserver`] to follow a
+//! build without parsing log output. All methods have empty defaults, so an
+//! observer only implements the events it cares about.
+//!
+//! ```no_run
+//! use std::{path::Path, sync::Arc};
+//! use tola::observer::{BuildObserver, add_observer};
+//!
+//! struct PrintPages;
+//!
+//! impl BuildObserver for PrintPages {
+//!     fn on_page_built(&self, source: &Path, _output: &Path) {
+//!         println!("built {}", source.display());
+//!     }
+//! }
+//!
+//! add_observer(Arc::new(PrintPages));
+//! ```
+
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+/// Outcome of a build (or a watch-mode rebuild), passed to [`BuildObserver::on_finish`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BuildSummary {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl BuildSummary {
+    /// Whether the build finished without errors
+    pub fn is_success(&self) -> bool {
+        self.errors == 0
+    }
+}
+
+/// Callbacks for build events.
+///
+/// Called from rayon worker threads, so implementations must be thread-safe.
+pub trait BuildObserver: Send + Sync {
+    /// A `.typ` page was compiled and written to `output`.
+    fn on_page_built(&self, _source: &Path, _output: &Path) {}
+
+    /// An asset (global or next to a post) was copied or generated at `output`.
+    fn on_asset_copied(&self, _source: &Path, _output: &Path) {}
+
+    /// A warning was recorded for `source`.
+    fn on_warning(&self, _source: &Path, _message: &str) {}
+
+    /// Processing `source` failed; the build keeps going.
+    fn on_error(&self, _source: &Path, _error: &anyhow::Error) {}
+
+    /// All files were attempted and the report has been printed.
+    fn on_finish(&self, _summary: &BuildSummary) {}
+}
+
+static OBSERVERS: RwLock<Vec<Arc<dyn BuildObserver>>> = RwLock::new(Vec::new());
+
+/// Register an observer for all following builds.
+pub fn add_observer(observer: Arc<dyn BuildObserver>) {
+    OBSERVERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(observer);
+}
+
+/// Remove all registered observers.
+pub fn clear_observers() {
+    OBSERVERS.write().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Dispatch an event to every registered observer.
+pub(crate) fn notify(event: impl Fn(&dyn BuildObserver)) {
+    let observers = OBSERVERS.read().unwrap_or_else(|e| e.into_inner());
+    for observer in observers.iter() {
+        event(observer.as_ref());
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountPages(AtomicUsize);
+
+    impl BuildObserver for CountPages {
+        fn on_page_built(&self, _source: &Path, _output: &Path) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_notify_reaches_observer() {
+        let counter = Arc::new(CountPages::default());
+        add_observer(counter.clone());
+
+        notify(|o| o.on_page_built(Path::new("a.typ"), Path::new("a/index.html")));
+        // Events the observer doesn't implement fall back to the no-op default
+        notify(|o| o.on_warning(Path::new("a.typ"), "unused"));
+
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_summary_success() {
+        assert!(BuildSummary::default().is_success());
+        assert!(
+            !BuildSummary {
+                errors: 1,
+                warnings: 0
+            }
+            .is_success()
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Per-post PDF export (`tola export-pdf`).
+//!
+//! Compiles matching posts with Typst's paged (PDF) target and collects the
+//! PDFs under `pdf/` in the output directory, with an index page linking them.
+//! The export is not part of `tola build`; with `[build] prune` enabled, run it
+//! again after each build.
+
+use crate::{
+    config::SiteConfig,
+    exec, log,
+    utils::{
+        build::{collect_files, process_generated_page},
+        meta::query_meta,
+        pool, report, reproducible,
+    },
+};
+use anyhow::{Context, Result, bail};
+use gix::glob::wildmatch;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use rayon::prelude::*;
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the exported PDFs, relative to the site root in the output
+const PDF_DIR: &str = "pdf";
+
+/// Pattern used when none is given: every page
+const DEFAULT_PATTERN: &str = "**/*.typ";
+
+/// An exported PDF
+#[derive(Debug)]
+struct Exported {
+    title: String,
+    /// Path relative to the PDF directory
+    file: PathBuf,
+}
+
+/// Export posts whose path (relative to the content directory) matches `pattern`
+pub fn export_pdf(config: &SiteConfig, pattern: Option<&str>) -> Result<()> {
+    let pattern = pattern.unwrap_or(DEFAULT_PATTERN);
+    let content = &config.build.content;
+    let mut sources: Vec<_> = collect_files(content, |path| {
+        path.extension().is_some_and(|ext| ext == "typ")
+            && path
+                .strip_prefix(content)
+                .is_ok_and(|relative| matches_pattern(relative, pattern))
+    });
+    sources.sort();
+    if sources.is_empty() {
+        bail!("No content files match `{pattern}`");
+    }
+
+    let pdf_dir = config
+        .build
+        .output
+        .join(&config.build.base_path)
+        .join(PDF_DIR);
+    log!("pdf"; "exporting {} file(s) to {}", sources.len(), pdf_dir.display());
+
+    let exported: Vec<_> = pool::io(config).install(|| {
+        sources
+            .par_iter()
+            .filter_map(|source| {
+                export_one(source, &pdf_dir, config)
+                    .map_err(|e| report::error(source, &e))
+                    .ok()
+            })
+            .collect()
+    });
+
+    if !exported.is_empty() {
+        let index = pdf_dir.join("index.html");
+        let html = process_generated_page(&index, &render_index(&exported)?, config)?;
+        fs::write(&index, html).with_context(|| format!("Failed to write {}", index.display()))?;
+        log!("pdf"; "{} PDF(s) written", exported.len());
+    }
+    report::finish(config.get_root())
+}
+
+/// Whether `relative` matches a glob (`*` stays within a directory, `**` crosses them)
+fn matches_pattern(relative: &Path, pattern: &str) -> bool {
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    wildmatch(
+        pattern.into(),
+        relative.as_str().into(),
+        wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+    )
+}
+
+/// Compile one source to PDF and look up its title
+fn export_one(source: &Path, pdf_dir: &Path, config: &SiteConfig) -> Result<Exported> {
+    let root = config.get_root();
+    let file = source
+        .strip_prefix(&config.build.content)?
+        .with_extension("pdf");
+    let target = pdf_dir.join(&file);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let [timestamp_flag, timestamp] = reproducible::typst_args(config);
+    exec!(&config.build.typst.command;
+        "compile", "--font-path", root, "--root", root,
+        "--input", config.typst_site_input(),
+        timestamp_flag, timestamp,
+        source, &target
+    )?;
+
+    // Pages without metadata still get listed, under their file name
+    let title = query_meta(source, config)
+        .ok()
+        .and_then(|meta| meta.title)
+        .unwrap_or_else(|| file.with_extension("").to_string_lossy().into_owned());
+    Ok(Exported { title, file })
+}
+
+/// Index page linking every exported PDF
+fn render_index(exported: &[Exported]) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+
+    writer.write_event(Event::Start(BytesStart::new("h1")))?;
+    writer.write_event(Event::Text(BytesText::new("PDF")))?;
+    writer.write_event(Event::End(BytesEnd::new("h1")))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-pdf")]),
+    ))?;
+    for entry in exported {
+        // Relative links keep working under any base path
+        let href = entry.file.to_string_lossy().replace('\\', "/");
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("a").with_attributes([("href", href.as_str())]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(&entry.title)))?;
+        writer.write_event(Event::End(BytesEnd::new("a")))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        let matches = |path: &str, pattern: &str| matches_pattern(Path::new(path), pattern);
+        assert!(matches("index.typ", DEFAULT_PATTERN));
+        assert!(matches("posts/a/hello.typ", DEFAULT_PATTERN));
+        assert!(matches("posts/hello.typ", "posts/*.typ"));
+        assert!(!matches("posts/2024/hello.typ", "posts/*.typ"));
+        assert!(matches("posts/2024/hello.typ", "posts/**"));
+    }
+
+    #[test]
+    fn test_render_index() {
+        let exported = [Exported {
+            title: "Hello & Bye".into(),
+            file: PathBuf::from("posts/hello.pdf"),
+        }];
+        let html = String::from_utf8(render_index(&exported).unwrap()).unwrap();
+        assert!(html.contains(r#"<li><a href="posts/hello.pdf">Hello &amp; Bye</a></li>"#));
+    }
+}
@@ -2,26 +2,44 @@
 //!
 //! Serves the built site and watches for file changes if enabled.
 
-use crate::{config::SiteConfig, log, watch::watch_for_changes_blocking};
+use crate::{
+    config::SiteConfig, log, utils::log::format_duration, watch::watch_for_changes_blocking,
+};
 use anyhow::{Context, Result};
 use axum::{
-    Router,
-    http::{StatusCode, Uri},
-    response::{Html, IntoResponse},
+    Json, Router,
+    body::{Body, to_bytes},
+    extract::{
+        Request,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::get,
 };
 use std::{
+    collections::VecDeque,
     fs,
+    future::IntoFuture,
     net::{IpAddr, SocketAddr},
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    process::Stdio,
     str::FromStr,
     sync::{
-        Arc,
+        Arc, LazyLock, Mutex, MutexGuard,
         atomic::{AtomicBool, Ordering},
     },
-    time::Duration,
+    task::Poll,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
 };
-use tokio::net::TcpListener;
-use tower_http::services::ServeDir;
+use tower_http::services::{ServeDir, ServeFile};
 
 /// Directory listing HTML template
 const DIRECTORY_TEMPLATE: &str = include_str!("../assets/serve/directory.html");
@@ -29,37 +47,152 @@ const DIRECTORY_TEMPLATE: &str = include_str!("../assets/serve/directory.html");
 /// Welcome page HTML template
 const WELCOME_TEMPLATE: &str = include_str!("../assets/serve/welcome.html");
 
-/// Start the development server with file watching
-pub async fn serve_site(config: &'static SiteConfig) -> Result<()> {
-    let server_ready = Arc::new(AtomicBool::new(false));
+/// Live reload client, injected into every served HTML page
+const RELOAD_SCRIPT: &str = include_str!("../assets/serve/reload.js");
 
-    // Spawn server task
-    tokio::spawn({
-        let server_ready = Arc::clone(&server_ready);
-        async move {
-            if let Err(err) = start_server(config, server_ready).await {
-                log!("serve"; "{err}");
+/// WebSocket endpoint the live reload client connects to
+const RELOAD_PATH: &str = "/__tola_reload";
+
+/// JSON endpoint with request counts and the last rebuild duration
+const STATS_PATH: &str = "/__tola/stats";
+
+/// Ports tried, counting the configured one, before giving up
+const PORT_ATTEMPTS: u16 = 20;
+
+/// Configured port and the free port used instead, if it was taken
+static PORT_FALLBACK: Mutex<Option<(u16, u16)>> = Mutex::new(None);
+
+/// Number of recent not found paths kept for the stats endpoint
+const RECENT_NOT_FOUND: usize = 20;
+
+/// Rebuild notifications, fanned out to every connected page
+static RELOAD: LazyLock<broadcast::Sender<()>> = LazyLock::new(|| broadcast::channel(16).0);
+
+/// Requests served since the server started, and the last rebuild
+static STATS: Mutex<Stats> = Mutex::new(Stats::new());
+
+#[derive(Debug)]
+struct Stats {
+    requests: u64,
+    /// Responses by status class, `1xx` to `5xx`
+    by_class: [u64; 5],
+    /// Most recent paths answered with 404, newest last
+    not_found: VecDeque<String>,
+    last_rebuild: Option<Duration>,
+}
+
+/// Tell connected browsers to reload (no-op while none are connected)
+pub fn notify_reload() {
+    let _ = RELOAD.send(());
+}
+
+/// Record how long the last rebuild took, for the stats endpoint
+pub fn record_rebuild(duration: Duration) {
+    stats().last_rebuild = Some(duration);
+}
+
+fn stats() -> MutexGuard<'static, Stats> {
+    STATS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Stats {
+    const fn new() -> Self {
+        Self {
+            requests: 0,
+            by_class: [0; 5],
+            not_found: VecDeque::new(),
+            last_rebuild: None,
+        }
+    }
+
+    fn record(&mut self, path: &str, status: StatusCode) {
+        self.requests += 1;
+        if let Some(count) = self.by_class.get_mut(status.as_u16() as usize / 100 - 1) {
+            *count += 1;
+        }
+        if status == StatusCode::NOT_FOUND {
+            if self.not_found.len() == RECENT_NOT_FOUND {
+                self.not_found.pop_front();
             }
+            self.not_found.push_back(path.to_owned());
         }
-    });
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let [info, success, redirect, client_error, server_error] = self.by_class;
+        serde_json::json!({
+            "requests": self.requests,
+            "status": {
+                "1xx": info,
+                "2xx": success,
+                "3xx": redirect,
+                "4xx": client_error,
+                "5xx": server_error,
+            },
+            "recent_not_found": self.not_found,
+            "last_rebuild_ms": self.last_rebuild.map(|d| d.as_millis() as u64),
+        })
+    }
+}
+
+/// Start the development server with file watching
+///
+/// Runs until Ctrl+C is received. The config is cloned and shared between the
+/// server task and the watcher thread; the server is restarted when the
+/// watcher reloads a config with different `[serve]` settings.
+pub async fn serve_site(config: &SiteConfig) -> Result<()> {
+    let config = Arc::new(config.clone());
+    let server_ready = Arc::new(AtomicBool::new(false));
+    let (restart_tx, mut restart_rx) = mpsc::unbounded_channel::<SiteConfig>();
+
+    let mut server = spawn_server(config.as_ref().clone(), &server_ready);
 
     // Spawn file watcher thread
     std::thread::spawn({
+        let config = Arc::clone(&config);
         let server_ready = Arc::clone(&server_ready);
         move || {
             wait_for_server(true, &server_ready);
-            if let Err(err) = watch_for_changes_blocking(config, server_ready) {
+            let restart = |config: &SiteConfig| {
+                let _ = restart_tx.send(config.clone());
+            };
+            if let Err(err) = watch_for_changes_blocking(&config, server_ready, restart) {
                 log!("watch"; "{err}");
             }
         }
     });
 
-    tokio::signal::ctrl_c().await.ok();
-    wait_for_server(false, &server_ready);
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            Some(config) = restart_rx.recv() => {
+                log!("serve"; "restarting server...");
+                server.abort();
+                let _ = (&mut server).await;
+                // The browser is already open
+                let mut config = config;
+                config.serve.open = false;
+                server = spawn_server(config, &server_ready);
+            }
+        }
+    }
 
+    // The server shuts down gracefully on the same Ctrl+C
+    wait_for_server(false, &server_ready);
     Ok(())
 }
 
+/// Run the HTTP server in a task, logging why it stopped if it failed
+fn spawn_server(config: SiteConfig, server_ready: &Arc<AtomicBool>) -> JoinHandle<()> {
+    let server_ready = Arc::clone(server_ready);
+    tokio::spawn(async move {
+        if let Err(err) = start_server(&config, Arc::clone(&server_ready)).await {
+            server_ready.store(false, Ordering::Release);
+            log!("serve"; "{err}");
+        }
+    })
+}
+
 /// Block until server reaches the expected ready state
 fn wait_for_server(ready: bool, server_ready: &Arc<AtomicBool>) {
     let state = if ready { "start" } else { "quit" };
@@ -69,65 +202,301 @@ fn wait_for_server(ready: bool, server_ready: &Arc<AtomicBool>) {
     }
 }
 
-/// Start the HTTP server on configured address
-pub async fn start_server(
-    config: &'static SiteConfig,
-    server_ready: Arc<AtomicBool>,
-) -> Result<()> {
-    let addr = SocketAddr::new(
-        IpAddr::from_str(&config.serve.interface)?,
-        config.serve.port,
-    );
-
-    let listener = TcpListener::bind(addr)
-        .await
-        .with_context(|| format!("Failed to bind to address {addr}"))?;
+/// Start the HTTP server on every configured interface
+pub async fn start_server(config: &SiteConfig, server_ready: Arc<AtomicBool>) -> Result<()> {
+    let mut listeners = Vec::new();
+    for ip in interface_addrs(&config.serve.interface)? {
+        let addr = SocketAddr::new(ip, config.serve.port);
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind to address {addr}"))?;
+        listeners.push((addr, listener));
+    }
 
     let app = create_router(config);
 
     server_ready.store(true, Ordering::Release);
-    log!("serve"; "serving site on http://{}", addr);
+    let (shutdown_tx, _) = broadcast::channel::<()>(1);
+    let mut servers: Vec<Pin<Box<dyn Future<Output = std::io::Result<()>> + Send>>> = Vec::new();
+    for (addr, listener) in listeners {
+        log!("serve"; "serving site on http://{}", addr);
+        let mut shutdown = shutdown_tx.subscribe();
+        let server = axum::serve(listener, app.clone()).with_graceful_shutdown(async move {
+            let _ = shutdown.recv().await;
+        });
+        servers.push(Box::pin(server.into_future()));
+    }
+    // One signal handler stops every listener
+    servers.push(Box::pin(async move {
+        shutdown_signal(server_ready).await;
+        let _ = shutdown_tx.send(());
+        Ok(())
+    }));
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(server_ready))
-        .await
-        .context("[serve] failed to start")?;
+    if config.serve.open
+        && let Some(ip) = interface_addrs(&config.serve.interface)?.first()
+    {
+        let addr = SocketAddr::new(*ip, config.serve.port);
+        open_browser(&browser_url(addr, &config.build.base_path));
+    }
 
+    // Polled in this task, so aborting it closes the listeners at once
+    std::future::poll_fn(|cx| {
+        let mut i = 0;
+        while i < servers.len() {
+            match servers[i].as_mut().poll(cx) {
+                Poll::Ready(result) => {
+                    drop(servers.swap_remove(i));
+                    result.context("[serve] failed to start")?;
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+        match servers.is_empty() {
+            true => Poll::Ready(Ok(())),
+            false => Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Parse `[serve] interface` into addresses
+fn interface_addrs(interfaces: &[String]) -> Result<Vec<IpAddr>> {
+    interfaces
+        .iter()
+        .map(|interface| {
+            IpAddr::from_str(interface)
+                .with_context(|| format!("Invalid [serve.interface] `{interface}`"))
+        })
+        .collect()
+}
+
+/// Move `[serve] port` to the next free one if it is taken on any interface
+///
+/// The site URL follows the port. The fallback is remembered so that
+/// reloaded configs asking for the same port get the same one
+/// (see [`apply_port_fallback`]).
+pub fn pick_port(config: &mut SiteConfig) -> Result<()> {
+    let requested = config.serve.port;
+    if requested == 0 {
+        return Ok(());
+    }
+    let addrs = interface_addrs(&config.serve.interface)?;
+    let is_free = |port: u16| {
+        addrs
+            .iter()
+            .all(|&ip| std::net::TcpListener::bind((ip, port)).is_ok())
+    };
+    let port = (0..PORT_ATTEMPTS)
+        .filter_map(|i| requested.checked_add(i))
+        .find(|&port| is_free(port))
+        .with_context(|| {
+            format!("No free port found from {requested} in {PORT_ATTEMPTS} attempts")
+        })?;
+
+    if port != requested {
+        log!("serve"; "port {requested} is in use, using {port} instead");
+    }
+    *port_fallback() = (port != requested).then_some((requested, port));
+    set_port(config, port);
     Ok(())
 }
 
+/// Use the port [`pick_port`] fell back to, if `config` asks for the same port
+pub fn apply_port_fallback(config: &mut SiteConfig) {
+    let fallback = *port_fallback();
+    if let Some((requested, port)) = fallback
+        && config.serve.port == requested
+    {
+        set_port(config, port);
+    }
+}
+
+fn set_port(config: &mut SiteConfig, port: u16) {
+    config.serve.port = port;
+    config.base.url = Some(config.serve.local_url());
+}
+
+fn port_fallback() -> MutexGuard<'static, Option<(u16, u16)>> {
+    PORT_FALLBACK.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// URL of the site served on `addr`, below `base_path`
+///
+/// Unspecified addresses (`0.0.0.0`, `::`) are replaced by localhost, since
+/// browsers cannot connect to them.
+fn browser_url(addr: SocketAddr, base_path: &Path) -> String {
+    let host = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => "127.0.0.1".to_owned(),
+        IpAddr::V6(ip) if ip.is_unspecified() => "[::1]".to_owned(),
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+    let base_path = base_path.to_string_lossy();
+    let base_path = base_path.trim_matches('/');
+    match base_path.is_empty() {
+        true => format!("http://{host}:{}/", addr.port()),
+        false => format!("http://{host}:{}/{base_path}/", addr.port()),
+    }
+}
+
+/// Open `url` in the default browser, without waiting for it
+fn open_browser(url: &str) {
+    let mut command = match std::env::consts::OS {
+        "macos" => std::process::Command::new("open"),
+        "windows" => {
+            let mut command = std::process::Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        _ => std::process::Command::new("xdg-open"),
+    };
+    let spawned = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(_) => log!("serve"; "opened {url} in the browser"),
+        Err(err) => log!("serve"; "failed to open the browser: {err}"),
+    }
+}
+
 /// Create the Axum router with static file serving
-fn create_router(config: &'static SiteConfig) -> Router {
+fn create_router(config: &SiteConfig) -> Router {
     let base_path = config.build.output.clone();
     let serve_dir = ServeDir::new(&config.build.output)
         .append_index_html_on_directories(false)
-        .not_found_service(axum::routing::get(move |uri| {
+        .fallback(axum::routing::get(move |request| {
             let base = base_path.clone();
-            async move { handle_path(uri, base).await }
+            async move { handle_path(request, base).await }
         }));
-    Router::new().fallback_service(serve_dir)
+    let log_requests = config.serve.log_requests;
+    Router::new()
+        .route(RELOAD_PATH, get(reload_socket))
+        .route(STATS_PATH, get(|| async { Json(stats().to_json()) }))
+        .fallback_service(serve_dir)
+        .layer(middleware::map_response(inject_reload_script))
+        .layer(middleware::from_fn(move |request, next| {
+            track_request(request, next, log_requests)
+        }))
+}
+
+/// Count the response for the stats endpoint, and log it with `[serve] log_requests`
+async fn track_request(request: Request, next: Next, log_requests: bool) -> Response {
+    let path = request.uri().path().to_owned();
+    if path == RELOAD_PATH || path == STATS_PATH {
+        return next.run(request).await;
+    }
+    let method = request.method().clone();
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let status = response.status();
+    stats().record(&path, status);
+    if log_requests {
+        log!("request"; "{method} {path} {} {}", status.as_u16(), format_duration(started.elapsed()));
+    }
+    response
+}
+
+// ============================================================================
+// Live Reload
+// ============================================================================
+
+/// Add the live reload client before `</body>` of HTML responses
+///
+/// Only complete pages are rewritten, including the custom `404.html`: a
+/// `206 Partial Content` body is a slice of the file, and inserting into it
+/// would corrupt the content, while `204` and `304` responses have no body.
+async fn inject_reload_script(response: Response) -> Response {
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    let has_page = !matches!(
+        response.status(),
+        StatusCode::PARTIAL_CONTENT | StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED
+    );
+    if !is_html || !has_page {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read page").into_response();
+    };
+    let html = with_reload_script(&String::from_utf8_lossy(&bytes));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(html))
+}
+
+/// Insert the client script before `</body>`, or append it if there is none
+fn with_reload_script(html: &str) -> String {
+    let script = format!("<script>{RELOAD_SCRIPT}</script>");
+    let mut html = html.to_owned();
+    let at = html.rfind("</body>").unwrap_or(html.len());
+    html.insert_str(at, &script);
+    html
+}
+
+/// Accept a WebSocket connection and send `reload` after every rebuild
+async fn reload_socket(upgrade: WebSocketUpgrade) -> Response {
+    let reloads = RELOAD.subscribe();
+    upgrade.on_upgrade(move |socket| send_reloads(socket, reloads))
+}
+
+/// Send `reload` to `socket` on every rebuild, until the page goes away
+///
+/// Client frames are read too, so pings are answered and a closed page ends
+/// the task at once rather than at the next rebuild.
+async fn send_reloads(mut socket: WebSocket, mut reloads: broadcast::Receiver<()>) {
+    loop {
+        tokio::select! {
+            reload = reloads.recv() => match reload {
+                Ok(()) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    if socket.send(Message::Text("reload".into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                // Pongs are sent by axum; other messages are not expected
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
 }
 
 /// Handle incoming requests, serving files or directory listings
-async fn handle_path(uri: Uri, base_path: PathBuf) -> impl IntoResponse {
-    let request_path = uri.path().trim_matches('/');
+async fn handle_path(request: Request, base_path: PathBuf) -> Response {
+    let request_path = request.uri().path().trim_matches('/');
     let request_path = urlencoding::decode(request_path)
         .map(|s| s.into_owned())
         .unwrap_or_default();
+    // `..` (or an absolute path) would reach files outside the output
+    let is_inside = Path::new(&request_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_inside {
+        return (StatusCode::NOT_FOUND, "404 Not Found").into_response();
+    }
     let local_path = base_path.join(&request_path);
 
-    // Try to read the file directly
-    if local_path.is_file()
-        && let Ok(content) = fs::read_to_string(&local_path)
-    {
-        return Html(content).into_response();
+    // Try to serve the file directly
+    if local_path.is_file() {
+        return serve_file(&local_path, request).await;
     }
 
     // If it's a directory, try to serve index.html or generate listing
     if local_path.is_dir() {
         let index_path = local_path.join("index.html");
-        if let Ok(content) = fs::read_to_string(&index_path) {
-            return Html(content).into_response();
+        if index_path.is_file() {
+            return serve_file(&index_path, request).await;
         }
 
         if let Ok(listing) = generate_directory_listing(&local_path, &request_path) {
@@ -135,10 +504,28 @@ async fn handle_path(uri: Uri, base_path: PathBuf) -> impl IntoResponse {
         }
     }
 
-    // Fallback to 404
+    // Fallback to the site's 404 page, as static hosts do
+    let not_found_page = base_path.join("404.html");
+    if not_found_page.is_file() {
+        let mut response = serve_file(&not_found_page, request).await;
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
     (StatusCode::NOT_FOUND, "404 Not Found").into_response()
 }
 
+/// Stream a file with the `Content-Type` of its extension, honouring `Range` requests
+async fn serve_file(path: &Path, request: Request) -> Response {
+    match ServeFile::new(path).try_call(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read {}: {err}", path.display()),
+        )
+            .into_response(),
+    }
+}
+
 /// Generate HTML directory listing for browsing
 fn generate_directory_listing(dir_path: &PathBuf, request_path: &str) -> std::io::Result<String> {
     let entries: Vec<_> = fs::read_dir(dir_path)?
@@ -193,9 +580,160 @@ fn generate_directory_listing(dir_path: &PathBuf, request_path: &str) -> std::io
 
 /// Handle graceful shutdown on Ctrl+C
 async fn shutdown_signal(server_ready: Arc<AtomicBool>) {
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to install CTRL+C signal handler");
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        // Keep serving; the process can still be killed externally
+        log!("error"; "failed to install CTRL+C signal handler: {e}");
+        std::future::pending::<()>().await;
+    }
     server_ready.store(false, Ordering::Release);
     log!("serve"; "shutting down gracefully...");
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browser_url() {
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+        assert_eq!(
+            browser_url(addr("0.0.0.0:5277"), Path::new("")),
+            "http://127.0.0.1:5277/"
+        );
+        assert_eq!(
+            browser_url(addr("192.168.1.2:80"), Path::new("/blog/")),
+            "http://192.168.1.2:80/blog/"
+        );
+        assert_eq!(
+            browser_url(addr("[::]:3000"), Path::new("docs")),
+            "http://[::1]:3000/docs/"
+        );
+    }
+
+    #[test]
+    fn test_pick_port() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let mut config = SiteConfig::default();
+        config.serve.port = port;
+        pick_port(&mut config).unwrap();
+        assert!(config.serve.port > port);
+        assert_eq!(
+            config.base.url,
+            Some(format!("http://127.0.0.1:{}", config.serve.port))
+        );
+
+        let mut reloaded = SiteConfig::default();
+        reloaded.serve.port = port;
+        apply_port_fallback(&mut reloaded);
+        assert_eq!(reloaded.serve.port, config.serve.port);
+
+        config.serve.interface = vec!["localhost".into()];
+        assert!(pick_port(&mut config).is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::new();
+        for i in 0..=RECENT_NOT_FOUND {
+            stats.record(&format!("/missing-{i}.png"), StatusCode::NOT_FOUND);
+        }
+        stats.record("/", StatusCode::OK);
+        stats.last_rebuild = Some(Duration::from_millis(42));
+
+        let json = stats.to_json();
+        assert_eq!(json["requests"], 22);
+        assert_eq!(json["status"]["2xx"], 1);
+        assert_eq!(json["status"]["4xx"], 21);
+        assert_eq!(json["recent_not_found"][0], "/missing-1.png");
+        assert_eq!(json["last_rebuild_ms"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_handle_path() {
+        let output = std::env::temp_dir().join("tola_serve_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts")).unwrap();
+        fs::write(output.join("posts/index.html"), "<p>posts</p>").unwrap();
+        fs::write(output.join("clip.mp4"), [0u8, 1, 2, 3, 4, 5, 6, 7]).unwrap();
+
+        let request = |uri: &str, range: Option<&str>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some(range) = range {
+                request = request.header(header::RANGE, range);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = handle_path(request("/clip.mp4", Some("bytes=2-4")), output.clone()).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "video/mp4");
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-4/8");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], [2, 3, 4]);
+
+        let response = handle_path(request("/posts/", None), output.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html")
+        );
+
+        let response = handle_path(request("/missing.png", None), output.clone()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Nothing outside the output is served
+        fs::write(output.with_file_name("tola_serve_test_secret"), "secret").unwrap();
+        for uri in [
+            "/../tola_serve_test_secret",
+            "/posts/%2E%2E/%2E%2E/tola_serve_test_secret",
+        ] {
+            let response = handle_path(request(uri, None), output.clone()).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+        fs::remove_file(output.with_file_name("tola_serve_test_secret")).unwrap();
+
+        fs::write(output.join("404.html"), "<p>lost</p>").unwrap();
+        let response = handle_path(request("/missing/", None), output.clone()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"<p>lost</p>");
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_with_reload_script() {
+        let html = with_reload_script("<body><p>x</p></body>");
+        assert!(html.starts_with("<body><p>x</p><script>"));
+        assert!(html.ends_with("</script></body>"));
+        assert!(with_reload_script("<p>x").starts_with("<p>x<script>"));
+    }
+
+    #[tokio::test]
+    async fn test_inject_reload_script() {
+        let response = |status: StatusCode| {
+            let mut response = Html("<body></body>").into_response();
+            *response.status_mut() = status;
+            response
+        };
+        let body = |response: Response| async {
+            to_bytes(response.into_body(), usize::MAX).await.unwrap()
+        };
+
+        let injected = inject_reload_script(response(StatusCode::OK)).await;
+        assert!(body(injected).await.starts_with(b"<body><script>"));
+        // The custom not found page reloads too
+        let not_found = inject_reload_script(response(StatusCode::NOT_FOUND)).await;
+        assert!(body(not_found).await.starts_with(b"<body><script>"));
+
+        // A range of the page is served as is
+        let partial = inject_reload_script(response(StatusCode::PARTIAL_CONTENT)).await;
+        assert_eq!(&body(partial).await[..], b"<body></body>");
+    }
+}
@@ -0,0 +1,260 @@
+//! Theme installation (`tola theme install`, `tola theme update`).
+//!
+//! Themes are cloned into `[theme] dir` with git, checked out at a revision,
+//! and recorded in tola.toml as `[theme] name`, `url` and `rev`, so another
+//! checkout of the site installs the same revision with `tola theme install`.
+
+use crate::{config::SiteConfig, exec, log, utils::git};
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, Table, value};
+
+/// Clone the theme at `url` (or the one recorded in `[theme]`) into the themes directory
+///
+/// Checks out `rev`, or the recorded revision when installing the recorded
+/// theme, or else the default branch.
+pub fn install_theme(config: &SiteConfig, url: Option<&str>, rev: Option<&str>) -> Result<()> {
+    let (name, url, rev) = match url {
+        Some(url) => {
+            let name = name_from_url(url)
+                .with_context(|| format!("Cannot derive a theme name from `{url}`"))?;
+            (name, url, rev)
+        }
+        None => {
+            let theme = &config.theme;
+            let (Some(name), Some(url)) = (&theme.name, &theme.url) else {
+                bail!("No theme to install: pass a git URL or set [theme] name and url");
+            };
+            (name.clone(), url.as_str(), rev.or(theme.rev.as_deref()))
+        }
+    };
+
+    let dest = config.theme.dir.join(&name);
+    if dest.exists() {
+        bail!(
+            "Theme `{}` already exists. Use `tola theme update` instead.",
+            dest.display()
+        );
+    }
+
+    log!("theme"; "cloning {url} into {}", dest.display());
+    exec!(["git"]; "clone", url, &dest).with_context(|| format!("Failed to clone {url}"))?;
+    let installed = checkout(&dest, rev).and_then(|_| verify(&dest));
+    if let Err(err) = installed {
+        fs::remove_dir_all(&dest).ok();
+        return Err(err);
+    }
+
+    let rev = head_rev(&dest)?;
+    record(&config.config_path, &name, url, &rev)?;
+    log!("theme"; "installed `{name}` at {rev}");
+    Ok(())
+}
+
+/// Move the theme in use to the latest revision of its default branch
+pub fn update_theme(config: &SiteConfig) -> Result<()> {
+    let (Some(name), Some(dir)) = (&config.theme.name, config.theme_path()) else {
+        bail!("No theme to update: [theme] name is not set");
+    };
+    if !dir.exists() {
+        bail!(
+            "Theme `{}` is not installed. Run `tola theme install` first.",
+            dir.display()
+        );
+    }
+
+    let previous = head_rev(&dir)?;
+    exec!(dir.as_path(); ["git"]; "fetch", "origin").context("Failed to fetch the theme")?;
+    checkout(&dir, Some("origin/HEAD"))?;
+    verify(&dir)?;
+
+    let rev = head_rev(&dir)?;
+    let url = match &config.theme.url {
+        Some(url) => url.clone(),
+        None => remote_url(&dir)?,
+    };
+    record(&config.config_path, name, &url, &rev)?;
+    if rev == previous {
+        log!("theme"; "`{name}` is up to date at {rev}");
+    } else {
+        log!("theme"; "updated `{name}` from {previous} to {rev}");
+    }
+    Ok(())
+}
+
+/// Detach the checkout in `dir` at `rev` (no-op without one)
+fn checkout(dir: &Path, rev: Option<&str>) -> Result<()> {
+    if let Some(rev) = rev {
+        exec!(dir; ["git"]; "checkout", "--detach", rev)
+            .with_context(|| format!("Failed to check out `{rev}`"))?;
+    }
+    Ok(())
+}
+
+/// Commit checked out in `dir`
+fn head_rev(dir: &Path) -> Result<String> {
+    let repo = git::open_repo(dir)?.to_thread_local();
+    let id = repo
+        .head_id()
+        .with_context(|| format!("No commit checked out in {}", dir.display()))?;
+    Ok(id.to_string())
+}
+
+/// URL of the `origin` remote of `dir`
+fn remote_url(dir: &Path) -> Result<String> {
+    let output = exec!(dir; ["git"]; "remote", "get-url", "origin")?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Fail unless `dir` has `templates/` and the assets its `theme.toml` links to
+fn verify(dir: &Path) -> Result<()> {
+    if !dir.join("templates").is_dir() {
+        bail!("`{}` is not a theme: it has no templates/", dir.display());
+    }
+
+    let theme_file = dir.join("theme.toml");
+    if !theme_file.exists() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(&theme_file)
+        .with_context(|| format!("Failed to read {}", theme_file.display()))?;
+    let defaults: SiteConfig = toml::from_str(&content)
+        .with_context(|| format!("Invalid theme settings in {}", theme_file.display()))?;
+    let head = &defaults.build.head;
+    let linked = head
+        .styles
+        .iter()
+        .map(|path| path.as_path())
+        .chain(head.scripts.iter().map(|script| script.path()))
+        .chain(head.icon.as_deref());
+
+    let assets = dir.join("assets");
+    let missing: Vec<_> = linked
+        .filter(|path| !assets.join(within_assets(path)).exists())
+        .map(|path| path.display().to_string())
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "Theme `{}` links to missing assets: {}",
+            dir.display(),
+            missing.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// `[build.head]` asset path relative to `assets/`, as links resolve it:
+/// `./assets/styles/main.css` → `styles/main.css`
+fn within_assets(path: &Path) -> PathBuf {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    path.strip_prefix("assets/").unwrap_or(path).to_path_buf()
+}
+
+/// Set `[theme] name`, `url` and `rev` in the config file, keeping its formatting
+fn record(config_path: &Path, name: &str, url: &str, rev: &str) -> Result<()> {
+    let content = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    let mut doc: DocumentMut = content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+    if !doc.contains_key("theme") {
+        // Without a position the new table would be written before everything else
+        let mut theme = Table::new();
+        theme.set_position(last_position(doc.as_table()).map_or(0, |last| last + 1));
+        doc.insert("theme", Item::Table(theme));
+    }
+    doc["theme"]["name"] = value(name);
+    doc["theme"]["url"] = value(url);
+    doc["theme"]["rev"] = value(rev);
+    fs::write(config_path, doc.to_string())
+        .with_context(|| format!("Failed to write {}", config_path.display()))
+}
+
+/// Highest position of `table` and the tables inside it, as written in the file
+fn last_position(table: &Table) -> Option<usize> {
+    let nested = table.iter().filter_map(|(_, item)| match item {
+        Item::Table(table) => last_position(table),
+        Item::ArrayOfTables(tables) => tables.iter().filter_map(last_position).max(),
+        _ => None,
+    });
+    table.position().into_iter().chain(nested).max()
+}
+
+/// `https://github.com/alice/paper.git` → `paper`
+fn name_from_url(url: &str) -> Option<String> {
+    let last = url.trim_end_matches('/').rsplit(['/', ':']).next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_from_url() {
+        assert_eq!(
+            name_from_url("https://github.com/alice/paper.git").as_deref(),
+            Some("paper")
+        );
+        assert_eq!(
+            name_from_url("git@github.com:alice/paper").as_deref(),
+            Some("paper")
+        );
+        assert_eq!(
+            name_from_url("https://example.com/themes/ink/").as_deref(),
+            Some("ink")
+        );
+        assert_eq!(name_from_url(""), None);
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = std::env::temp_dir().join("tola_theme_record_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tola.toml");
+        fs::write(
+            &path,
+            "# My site\n[base]\ntitle = \"Blog\"\ndescription = \"\"\n",
+        )
+        .unwrap();
+
+        record(&path, "paper", "https://example.com/paper.git", "abc123").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# My site\n[base]\n"));
+        assert!(content.ends_with(
+            "[theme]\nname = \"paper\"\nurl = \"https://example.com/paper.git\"\nrev = \"abc123\"\n"
+        ));
+        let config: SiteConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.theme.name.as_deref(), Some("paper"));
+        assert_eq!(config.theme.rev.as_deref(), Some("abc123"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = std::env::temp_dir().join("tola_theme_verify_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("assets/styles")).unwrap();
+        assert!(verify(&dir).is_err());
+
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        fs::write(
+            dir.join("theme.toml"),
+            "[build.head]\nstyles = [\"styles/paper.css\"]\n",
+        )
+        .unwrap();
+        assert!(verify(&dir).is_err());
+        fs::write(dir.join("assets/styles/paper.css"), "").unwrap();
+        assert!(verify(&dir).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
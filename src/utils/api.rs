@@ -0,0 +1,128 @@
+//! JSON endpoints for client-side widgets and external consumers.
+//!
+//! Writes `posts.json` and one file per content section from the post metadata
+//! recorded in the site model, so a site can be queried without scraping HTML.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        prune,
+        rss::DateTimeUtc,
+        site::{self, Page, section},
+    },
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::{cmp::Reverse, collections::BTreeMap, path::Path};
+
+/// A post as listed by the endpoints
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct ApiPost<'a> {
+    title: Option<&'a str>,
+    url: &'a str,
+    date: &'a str,
+    update: Option<&'a str>,
+    author: Option<&'a str>,
+    summary: Option<&'a str>,
+    /// Top-level content directory, if the post is inside one
+    section: Option<String>,
+    /// With `[build.reading_time]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    word_count: Option<usize>,
+    /// Minutes, with `[build.reading_time]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reading_time: Option<usize>,
+}
+
+/// Write the endpoints if `[build.api]` is enabled
+pub fn build_api(config: &SiteConfig) -> Result<()> {
+    if !config.build.api.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let posts = api_posts(&pages, &config.build.content);
+    let dir = &config.build.api.path;
+    write_json(&dir.join("posts.json"), &posts)?;
+
+    let mut sections: BTreeMap<&str, Vec<&ApiPost>> = BTreeMap::new();
+    for post in &posts {
+        if let Some(section) = &post.section {
+            sections.entry(section).or_default().push(post);
+        }
+    }
+    for (section, posts) in &sections {
+        write_json(&dir.join("sections").join(format!("{section}.json")), posts)?;
+    }
+
+    log!("api"; "{} post(s) in {} section(s)", posts.len(), sections.len());
+    Ok(())
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    prune::write_produced(path, json.as_bytes())
+}
+
+/// Pages with a `date`, newest first
+fn api_posts<'a>(pages: &'a [Page], content: &Path) -> Vec<ApiPost<'a>> {
+    let mut posts: Vec<_> = pages
+        .iter()
+        .filter_map(|page| {
+            let meta = page.meta.as_ref()?;
+            Some(ApiPost {
+                title: meta.title.as_deref(),
+                url: &page.url,
+                date: meta.date.as_deref()?,
+                update: meta.update.as_deref(),
+                author: meta.author.as_deref(),
+                summary: meta.summary.as_deref(),
+                section: section(&page.source, content),
+                word_count: meta.word_count,
+                reading_time: meta.reading_time,
+            })
+        })
+        .collect();
+    posts.sort_by_cached_key(|post| (Reverse(DateTimeUtc::parse(post.date)), post.url));
+    posts
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+    use std::path::PathBuf;
+
+    fn page(source: &str, date: Option<&str>) -> Page {
+        Page {
+            source: PathBuf::from("/site/content").join(source),
+            url: format!("https://example.com/{source}"),
+            meta: Some(PageMeta {
+                title: Some(source.into()),
+                date: date.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_api_posts_newest_first() {
+        let pages = [
+            page("posts/a.typ", Some("2024-01-01")),
+            page("about.typ", None),
+            page("notes/b.typ", Some("2024-03-01")),
+            page("c.typ", Some("2024-02-01")),
+        ];
+        let posts = api_posts(&pages, Path::new("/site/content"));
+        let titles: Vec<_> = posts.iter().map(|post| post.title.unwrap()).collect();
+        assert_eq!(titles, ["notes/b.typ", "c.typ", "posts/a.typ"]);
+        assert_eq!(posts[0].section.as_deref(), Some("notes"));
+        assert_eq!(posts[1].section, None);
+    }
+}
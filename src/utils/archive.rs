@@ -0,0 +1,249 @@
+//! Archive pages by year and month.
+//!
+//! Groups the posts recorded in the site model by the year and month of their
+//! `date`, and renders a page per year and per month plus an index of all
+//! years, so readers can browse the site chronologically.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        build::process_generated_page,
+        pagination::{Pager, paginate, write_nav},
+        prune,
+        site::{self, Page},
+    },
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::{cmp::Reverse, collections::BTreeMap, io::Cursor};
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Posts of each month of each year, newest first within a month
+type Archive = BTreeMap<u16, BTreeMap<u8, Vec<Page>>>;
+
+/// Generate the archive pages if `[build.archive]` is enabled
+pub fn build_archive(config: &SiteConfig) -> Result<()> {
+    let archive_config = &config.build.archive;
+    if !archive_config.enable {
+        return Ok(());
+    }
+
+    let archive = group_by_month(site::pages());
+    let dir = &archive_config.path;
+    for (year, months) in archive.iter().rev() {
+        let year_dir = dir.join(year.to_string());
+        // Months are kept in ascending order, so the newest posts come last
+        let posts: Vec<Page> = months.values().rev().flatten().cloned().collect();
+        for (pager, posts) in paginate(&posts, &year_dir.join("index.html"), config) {
+            let html = render_posts_page(&year.to_string(), posts, &pager)?;
+            prune::write_produced(
+                &pager.output,
+                &process_generated_page(&pager.output, &html, config)?,
+            )?;
+        }
+
+        if !archive_config.months {
+            continue;
+        }
+        for (month, posts) in months {
+            let month_dir = year_dir.join(format!("{month:02}"));
+            let title = format!("{year}-{month:02}");
+            for (pager, posts) in paginate(posts, &month_dir.join("index.html"), config) {
+                let html = render_posts_page(&title, posts, &pager)?;
+                prune::write_produced(
+                    &pager.output,
+                    &process_generated_page(&pager.output, &html, config)?,
+                )?;
+            }
+        }
+    }
+
+    let index = dir.join("index.html");
+    let html = render_index_page(&archive, archive_config.months)?;
+    prune::write_produced(&index, &process_generated_page(&index, &html, config)?)?;
+
+    log!("archive"; "{} year(s) archived", archive.len());
+    Ok(())
+}
+
+/// Posts with a parseable `date`, by year and month
+fn group_by_month(pages: Vec<Page>) -> Archive {
+    let mut archive = Archive::new();
+    for page in pages {
+        let Some(date) = page.date() else {
+            continue;
+        };
+        archive
+            .entry(date.year)
+            .or_default()
+            .entry(date.month)
+            .or_default()
+            .push(page);
+    }
+    for posts in archive.values_mut().flat_map(|months| months.values_mut()) {
+        posts.sort_by_key(|post| Reverse(post.date()));
+    }
+    archive
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Page listing the posts of a year or month, before head injection and minification
+fn render_posts_page(title: &str, posts: &[Page], pager: &Pager) -> Result<Vec<u8>> {
+    let mut writer = start_page()?;
+    write_text_element(&mut writer, "h1", title)?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-archive-posts")]),
+    ))?;
+    for post in posts {
+        let title = post
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.title.as_deref())
+            .unwrap_or(&post.url);
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        write_link(&mut writer, &post.url, title)?;
+        let date = post.date_text();
+        writer.write_event(Event::Text(BytesText::new(" ")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("time").with_attributes([("datetime", date)]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(date)))?;
+        writer.write_event(Event::End(BytesEnd::new("time")))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+    write_nav(&mut writer, pager)?;
+
+    end_page(writer)
+}
+
+/// Page listing every year, newest first, with its months if they have pages
+fn render_index_page(archive: &Archive, months: bool) -> Result<Vec<u8>> {
+    let mut writer = start_page()?;
+    write_text_element(&mut writer, "h1", "Archive")?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-archive-years")]),
+    ))?;
+    for (year, year_months) in archive.iter().rev() {
+        let count: usize = year_months.values().map(Vec::len).sum();
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        write_link(&mut writer, &format!("{year}/"), &year.to_string())?;
+        writer.write_event(Event::Text(BytesText::new(&format!(" ({count})"))))?;
+        if months {
+            writer.write_event(Event::Start(BytesStart::new("ul")))?;
+            for (month, posts) in year_months.iter().rev() {
+                writer.write_event(Event::Start(BytesStart::new("li")))?;
+                write_link(
+                    &mut writer,
+                    &format!("{year}/{month:02}/"),
+                    &format!("{year}-{month:02}"),
+                )?;
+                let count = format!(" ({})", posts.len());
+                writer.write_event(Event::Text(BytesText::new(&count)))?;
+                writer.write_event(Event::End(BytesEnd::new("li")))?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("ul")))?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+
+    end_page(writer)
+}
+
+fn start_page() -> Result<XmlWriter> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    Ok(writer)
+}
+
+fn end_page(mut writer: XmlWriter) -> Result<Vec<u8>> {
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+fn write_link(writer: &mut XmlWriter, href: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(
+        BytesStart::new("a").with_attributes([("href", href)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
+    Ok(())
+}
+
+fn write_text_element(writer: &mut XmlWriter, tag: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn post(url: &str, date: Option<&str>) -> Page {
+        Page {
+            url: url.into(),
+            meta: Some(PageMeta {
+                title: Some(url.into()),
+                date: date.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_by_month() {
+        let archive = group_by_month(vec![
+            post("/a/", Some("2024-05-01")),
+            post("/b/", Some("2024-05-20T08:00:00Z")),
+            post("/c/", Some("2023-12-31")),
+            post("/about/", None),
+            post("/bad/", Some("someday")),
+        ]);
+        assert_eq!(archive.keys().copied().collect::<Vec<_>>(), [2023, 2024]);
+        let may: Vec<_> = archive[&2024][&5].iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(may, ["/b/", "/a/"]);
+        assert_eq!(archive[&2023][&12].len(), 1);
+    }
+
+    #[test]
+    fn test_render_index_page() {
+        let archive = group_by_month(vec![
+            post("/a/", Some("2024-05-01")),
+            post("/b/", Some("2023-01-02")),
+        ]);
+        let html = String::from_utf8(render_index_page(&archive, true).unwrap()).unwrap();
+        let (newer, older) = (html.find("2024/").unwrap(), html.find("2023/").unwrap());
+        assert!(newer < older);
+        assert!(html.contains(r#"<a href="2024/05/">2024-05</a> (1)"#));
+
+        let html = String::from_utf8(render_index_page(&archive, false).unwrap()).unwrap();
+        assert!(!html.contains("2024/05/"));
+    }
+}
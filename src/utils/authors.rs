@@ -0,0 +1,228 @@
+//! Author pages.
+//!
+//! Groups the posts recorded in the site model by their `author` metadata and
+//! renders one page per author, with the bio and avatar of the matching
+//! `[[authors]]` profile, plus a per-author RSS feed.
+
+use crate::{
+    config::{AuthorProfile, SiteConfig},
+    log,
+    utils::{
+        build::process_generated_page,
+        pagination::{Pager, paginate, write_nav},
+        prune,
+        rss::RssFeed,
+        site::{self, Page},
+        slug::slugify_path,
+    },
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::{cmp::Reverse, collections::BTreeMap, io::Cursor};
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Generate a page (and feed) for every author with posts
+pub fn build_authors(config: &SiteConfig) -> Result<()> {
+    if !config.build.authors.enable {
+        return Ok(());
+    }
+
+    let posts_by_author = group_by_author(site::pages());
+    for (name, posts) in &posts_by_author {
+        let dir = config
+            .build
+            .authors
+            .path
+            .join(slugify_path(name.replace('/', "-"), config));
+        let profile = config.authors.iter().find(|profile| profile.name == *name);
+
+        for (pager, posts) in paginate(posts, &dir.join("index.html"), config) {
+            let html = render_page(name, profile, posts, &pager)?;
+            let html = process_generated_page(&pager.output, &html, config)?;
+            prune::write_produced(&pager.output, &html)?;
+        }
+
+        if config.build.rss.enable {
+            let title = format!("{} — {name}", config.base.title);
+            let feed = RssFeed::from_pages(title, posts.clone(), config);
+            let (rss, atom) = (dir.join("feed.xml"), dir.join("atom.xml"));
+            for (path, xml) in feed.render(&rss, &atom, config)? {
+                prune::write_produced(&path, xml.as_bytes())?;
+            }
+        }
+    }
+
+    log!("authors"; "{} author page(s) written", posts_by_author.len());
+    Ok(())
+}
+
+/// Posts (pages with a `date`) keyed by author, newest first
+fn group_by_author(pages: Vec<Page>) -> BTreeMap<String, Vec<Page>> {
+    let mut authors: BTreeMap<String, Vec<Page>> = BTreeMap::new();
+    for page in pages {
+        let Some(meta) = &page.meta else { continue };
+        let Some(author) = meta.author.as_ref().filter(|_| meta.date.is_some()) else {
+            continue;
+        };
+        authors.entry(author.clone()).or_default().push(page);
+    }
+    for posts in authors.values_mut() {
+        posts.sort_by_key(|post| Reverse(post.date()));
+    }
+    authors
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Author page, before the usual head injection and minification
+fn render_page(
+    name: &str,
+    profile: Option<&AuthorProfile>,
+    posts: &[Page],
+    pager: &Pager,
+) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("header").with_attributes([("class", "tola-author")]),
+    ))?;
+    if let Some(avatar) = profile.and_then(|profile| profile.avatar.as_deref()) {
+        writer.write_event(Event::Empty(BytesStart::new("img").with_attributes([
+            ("src", avatar),
+            ("alt", name),
+            ("class", "tola-author-avatar"),
+        ])))?;
+    }
+    match profile.and_then(|profile| profile.url.as_deref()) {
+        Some(url) => {
+            writer.write_event(Event::Start(BytesStart::new("h1")))?;
+            write_link(&mut writer, url, name)?;
+            writer.write_event(Event::End(BytesEnd::new("h1")))?;
+        }
+        None => write_text_element(&mut writer, "h1", name)?,
+    }
+    if let Some(bio) = profile.and_then(|profile| profile.bio.as_deref()) {
+        write_text_element(&mut writer, "p", bio)?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("header")))?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-author-posts")]),
+    ))?;
+    for post in posts {
+        let meta = post.meta.as_ref();
+        let title = meta
+            .and_then(|meta| meta.title.as_deref())
+            .unwrap_or(&post.url);
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        write_link(&mut writer, &post.url, title)?;
+        let date = post.date_text();
+        writer.write_event(Event::Text(BytesText::new(" ")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("time").with_attributes([("datetime", date)]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(date)))?;
+        writer.write_event(Event::End(BytesEnd::new("time")))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+    write_nav(&mut writer, pager)?;
+
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+fn write_link(writer: &mut XmlWriter, href: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(
+        BytesStart::new("a").with_attributes([("href", href)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
+    Ok(())
+}
+
+fn write_text_element(writer: &mut XmlWriter, tag: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+    use std::path::Path;
+
+    fn post(url: &str, author: Option<&str>, date: Option<&str>) -> Page {
+        Page {
+            url: url.into(),
+            meta: Some(PageMeta {
+                title: Some(url.into()),
+                author: author.map(Into::into),
+                date: date.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_by_author() {
+        let pages = vec![
+            post("/a/", Some("Alice"), Some("2024-01-01")),
+            post("/b/", Some("Bob"), Some("2024-02-01")),
+            post("/c/", Some("Alice"), Some("2024-03-01")),
+            post("/about/", Some("Alice"), None),
+            post("/d/", None, Some("2024-04-01")),
+        ];
+        let authors = group_by_author(pages);
+        assert_eq!(authors.len(), 2);
+        let urls: Vec<_> = authors["Alice"].iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(urls, ["/c/", "/a/"]);
+    }
+
+    #[test]
+    fn test_render_page() {
+        let profile = AuthorProfile {
+            name: "Alice".into(),
+            bio: Some("Compilers & more".into()),
+            avatar: Some("/alice.png".into()),
+            url: None,
+        };
+        let posts = [post("/a/", Some("Alice"), Some("2024-01-01"))];
+        let config = SiteConfig::default();
+        let (pager, _) = paginate(&posts, Path::new("/authors/alice/index.html"), &config)
+            .pop()
+            .unwrap();
+        let html = render_page("Alice", Some(&profile), &posts, &pager).unwrap();
+        let html = String::from_utf8(html).unwrap();
+
+        assert!(html.contains(
+            r#"<img src="/alice.png" alt="Alice" class="tola-author-avatar"/><h1>Alice</h1>"#
+        ));
+        assert!(html.contains("<p>Compilers &amp; more</p>"));
+        assert!(html.contains(
+            r#"<li><a href="/a/">/a/</a> <time datetime="2024-01-01">2024-01-01</time></li>"#
+        ));
+    }
+}
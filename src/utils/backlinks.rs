@@ -0,0 +1,205 @@
+//! Backlinks index (`[build.backlinks]`).
+//!
+//! Every page records the internal pages it links to while it is processed;
+//! once all pages are in the site model, `backlinks.json` maps each page URL
+//! to the pages linking to it, for "pages that link here" lists.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        prune,
+        site::{self, Page, anchor_hrefs},
+    },
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::{collections::BTreeMap, path::Path};
+
+/// A page linking to another, as listed in `backlinks.json`
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct Backlink<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+}
+
+/// Write the backlinks index if `[build.backlinks]` is enabled
+pub fn build_backlinks(config: &SiteConfig) -> Result<()> {
+    if !config.build.backlinks.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let backlinks = backlinks(&pages, config);
+    let json = serde_json::to_string_pretty(&backlinks)?;
+    prune::write_produced(&config.build.backlinks.path, json.as_bytes())?;
+
+    log!("backlinks"; "{} page(s) linked from other pages", backlinks.len());
+    Ok(())
+}
+
+/// Site paths of the internal pages `html`, written to `output`, links to
+pub fn internal_links(html: &[u8], output: &Path, config: &SiteConfig) -> Vec<String> {
+    let base_url = config.base.url.as_deref().unwrap_or_default();
+    let dir = site_path(output, config);
+    let mut links = Vec::new();
+    for href in anchor_hrefs(html) {
+        if let Some(path) = resolve(&href, &dir, base_url)
+            && !links.contains(&path)
+        {
+            links.push(path);
+        }
+    }
+    links
+}
+
+/// Page URL → pages linking to it, each once and never to themselves
+fn backlinks<'a>(pages: &'a [Page], config: &SiteConfig) -> BTreeMap<&'a str, Vec<Backlink<'a>>> {
+    let by_path: BTreeMap<String, &Page> = pages
+        .iter()
+        .map(|page| (site_path(&page.output, config), page))
+        .collect();
+
+    let mut backlinks: BTreeMap<&str, Vec<Backlink>> = BTreeMap::new();
+    for source in pages {
+        for link in &source.internal_links {
+            let Some(target) = by_path
+                .get(link)
+                .filter(|target| target.source != source.source)
+            else {
+                continue;
+            };
+            backlinks.entry(&target.url).or_default().push(Backlink {
+                url: &source.url,
+                title: source.meta.as_ref().and_then(|meta| meta.title.as_deref()),
+            });
+        }
+    }
+    backlinks
+}
+
+/// Path of an output page below the output directory, without `index.html`:
+/// `public/posts/hello/index.html` → `/posts/hello/`
+fn site_path(output: &Path, config: &SiteConfig) -> String {
+    let relative = output.strip_prefix(&config.build.output).unwrap_or(output);
+    let path: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    let path = format!("/{}", path.join("/"));
+    path.strip_suffix("index.html").unwrap_or(&path).to_owned()
+}
+
+/// Site path an `href` found in the page at directory `dir` points to, if internal
+///
+/// Queries and fragments are dropped, and a path without extension gets the
+/// trailing slash of the directory it names.
+fn resolve(href: &str, dir: &str, base_url: &str) -> Option<String> {
+    let base_url = base_url.trim_end_matches('/');
+    let href = match href.strip_prefix(base_url) {
+        Some(own) if !base_url.is_empty() && (own.is_empty() || own.starts_with('/')) => own,
+        _ => href,
+    };
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    // Other schemes (`https:`, `mailto:`) and protocol-relative links leave the site
+    let scheme = href
+        .split('/')
+        .next()
+        .is_some_and(|first| first.contains(':'));
+    if href.is_empty() || scheme || href.starts_with("//") {
+        return None;
+    }
+
+    let joined = match href.starts_with('/') {
+        true => href.to_owned(),
+        false => format!("{dir}{href}"),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let last = segments.last().copied().unwrap_or_default();
+    let mut path = format!("/{}", segments.join("/"));
+    if last == "index.html" {
+        path.truncate(path.len() - last.len());
+    } else if !last.is_empty() && !last.contains('.') {
+        path.push('/');
+    }
+    Some(path)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://example.com";
+        let dir = "/posts/hello/";
+        assert_eq!(
+            resolve("../other/", dir, base).as_deref(),
+            Some("/posts/other/")
+        );
+        assert_eq!(
+            resolve("/about#team", dir, base).as_deref(),
+            Some("/about/")
+        );
+        assert_eq!(
+            resolve("https://example.com/notes/index.html", dir, base).as_deref(),
+            Some("/notes/")
+        );
+        assert_eq!(resolve("/", dir, base).as_deref(), Some("/"));
+        assert_eq!(resolve("#intro", dir, base), None);
+        assert_eq!(resolve("https://other.org/", dir, base), None);
+        assert_eq!(resolve("mailto:a@example.com", dir, base), None);
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/site/public");
+        let page = |slug: &str, links: &[&str]| Page {
+            source: PathBuf::from(format!("/site/content/{slug}.typ")),
+            output: PathBuf::from(format!("/site/public/{slug}/index.html")),
+            url: format!("https://example.com/{slug}/index.html"),
+            meta: Some(PageMeta {
+                title: Some(slug.to_uppercase()),
+                ..Default::default()
+            }),
+            internal_links: links.iter().map(|&link| link.into()).collect(),
+            ..Default::default()
+        };
+        let pages = [
+            page("a", &["/b/", "/a/", "/missing/"]),
+            page("b", &["/a/"]),
+            page("c", &["/a/"]),
+        ];
+
+        let backlinks = backlinks(&pages, &config);
+        assert_eq!(backlinks.len(), 2);
+        let to_a: Vec<_> = backlinks["https://example.com/a/index.html"]
+            .iter()
+            .map(|link| link.title.unwrap())
+            .collect();
+        assert_eq!(to_a, ["B", "C"]);
+        assert_eq!(
+            backlinks["https://example.com/b/index.html"],
+            [Backlink {
+                url: "https://example.com/a/index.html",
+                title: Some("A")
+            }]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Blogroll generation.
+//!
+//! Renders the `[[blogroll]]` entries as a page styled like the rest of the site,
+//! and as an OPML 2.0 file that feed readers can import.
+
+use crate::{
+    config::{BlogrollEntry, BlogrollFile, SiteConfig},
+    log,
+    utils::{build::process_generated_page, opml, prune},
+};
+use anyhow::{Context, Result};
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::{fs, io::Cursor, path::PathBuf};
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Generate the blogroll page and OPML file if there are any entries
+pub fn build_blogroll(config: &SiteConfig) -> Result<()> {
+    let entries = load_entries(config)?;
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let blogroll = &config.build.blogroll;
+
+    let html = process_generated_page(&blogroll.page, &render_page(&entries, config)?, config)?;
+    prune::write_produced(&blogroll.page, &html)?;
+    prune::write_produced(&blogroll.opml, &render_opml(&entries, config)?)?;
+
+    log!("blogroll"; "{} site(s) written", entries.len());
+    Ok(())
+}
+
+/// Files written by [`build_blogroll`]
+pub fn outputs(config: &SiteConfig) -> Vec<PathBuf> {
+    match load_entries(config) {
+        Ok(entries) if !entries.is_empty() => {
+            let blogroll = &config.build.blogroll;
+            vec![blogroll.page.clone(), blogroll.opml.clone()]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Entries from tola.toml followed by those from `[build.blogroll] file`
+fn load_entries(config: &SiteConfig) -> Result<Vec<BlogrollEntry>> {
+    let mut entries = config.blogroll.clone();
+    if let Some(path) = &config.build.blogroll.file {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read blogroll file {}", path.display()))?;
+        let file: BlogrollFile = toml::from_str(&content)
+            .with_context(|| format!("Invalid blogroll file {}", path.display()))?;
+        entries.extend(file.blogroll);
+    }
+    Ok(entries)
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Blogroll page, before the usual head injection and minification
+fn render_page(entries: &[BlogrollEntry], config: &SiteConfig) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+
+    write_text_element(&mut writer, "h1", &config.build.blogroll.title)?;
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-blogroll")]),
+    ))?;
+    for entry in entries {
+        write_entry(&mut writer, entry)?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+/// `<li><a href=url>title</a> (<a href=feed>feed</a>) — description</li>`
+fn write_entry(writer: &mut XmlWriter, entry: &BlogrollEntry) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new("li")))?;
+    write_link(writer, &entry.url, &entry.title)?;
+    if let Some(feed) = &entry.feed {
+        writer.write_event(Event::Text(BytesText::new(" (")))?;
+        write_link(writer, feed, "feed")?;
+        writer.write_event(Event::Text(BytesText::new(")")))?;
+    }
+    if let Some(description) = &entry.description {
+        writer.write_event(Event::Text(BytesText::new(&format!(" — {description}"))))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("li")))?;
+    Ok(())
+}
+
+fn write_link(writer: &mut XmlWriter, href: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(
+        BytesStart::new("a").with_attributes([("href", href)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
+    Ok(())
+}
+
+fn write_text_element(writer: &mut XmlWriter, tag: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+/// OPML 2.0 document with one outline per entry
+///
+/// Entries with a feed become `type="rss"` outlines; others are plain links.
+fn render_opml(entries: &[BlogrollEntry], config: &SiteConfig) -> Result<Vec<u8>> {
+    let title = match config.base.title.as_str() {
+        "" => config.build.blogroll.title.clone(),
+        site => format!("{site} — {}", config.build.blogroll.title),
+    };
+    let outlines = entries.iter().map(|entry| {
+        let mut outline = match &entry.feed {
+            Some(feed) => opml::feed_outline(&entry.title, feed, &entry.url),
+            None => BytesStart::new("outline").with_attributes([
+                ("text", entry.title.as_str()),
+                ("title", entry.title.as_str()),
+                ("type", "link"),
+                ("url", entry.url.as_str()),
+            ]),
+        };
+        if let Some(description) = &entry.description {
+            outline.push_attribute(("description", description.as_str()));
+        }
+        outline
+    });
+    opml::render(&title, outlines)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<BlogrollEntry> {
+        vec![
+            BlogrollEntry {
+                title: "A & B".into(),
+                url: "https://ab.example".into(),
+                feed: Some("https://ab.example/feed.xml".into()),
+                description: Some("Notes".into()),
+            },
+            BlogrollEntry {
+                title: "C".into(),
+                url: "https://c.example".into(),
+                feed: None,
+                description: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_page() {
+        let html =
+            String::from_utf8(render_page(&entries(), &SiteConfig::default()).unwrap()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html><html><head>"));
+        assert!(html.contains("<h1>Blogroll</h1>"));
+        assert!(html.contains(concat!(
+            r#"<li><a href="https://ab.example">A &amp; B</a> "#,
+            r#"(<a href="https://ab.example/feed.xml">feed</a>) — Notes</li>"#
+        )));
+        assert!(html.contains(r#"<li><a href="https://c.example">C</a></li>"#));
+    }
+
+    #[test]
+    fn test_render_opml() {
+        let mut config = SiteConfig::default();
+        config.base.title = "My Blog".into();
+        let opml = String::from_utf8(render_opml(&entries(), &config).unwrap()).unwrap();
+
+        assert!(opml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(opml.contains("<title>My Blog — Blogroll</title>"));
+        assert!(opml.contains(concat!(
+            r#"<outline text="A &amp; B" title="A &amp; B" type="rss" "#,
+            r#"xmlUrl="https://ab.example/feed.xml" htmlUrl="https://ab.example" description="Notes"/>"#
+        )));
+        assert!(
+            opml.contains(r#"<outline text="C" title="C" type="link" url="https://c.example"/>"#)
+        );
+    }
+
+    #[test]
+    fn test_load_entries_from_file() {
+        let dir = std::env::temp_dir().join("tola_blogroll_test");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("blogroll.toml");
+        fs::write(
+            &file,
+            "[[blogroll]]\ntitle = \"D\"\nurl = \"https://d.example\"\n",
+        )
+        .unwrap();
+
+        let mut config = SiteConfig {
+            blogroll: entries(),
+            ..Default::default()
+        };
+        config.build.blogroll.file = Some(file);
+        let loaded = load_entries(&config).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[2].title, "D");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -2,23 +2,51 @@
 //!
 //! Handles compilation of Typst files to HTML and asset copying/optimization.
 
-use crate::utils::svg::{HtmlContext, Svg, compress_svgs_parallel, extract_svg_element};
+use crate::utils::cache;
+use crate::utils::exec::{self, collect_warnings};
+use crate::utils::images;
+use crate::utils::inject;
+use crate::utils::log::Task;
+use crate::utils::meta::{self, PageMeta};
+use crate::utils::minify;
+use crate::utils::panic;
+use crate::utils::plugin;
+use crate::utils::pool;
+use crate::utils::progress::{self, Progress};
+use crate::utils::prune;
+use crate::utils::reading_time;
+use crate::utils::report;
+use crate::utils::reproducible;
+use crate::utils::rewrite;
+use crate::utils::sections;
+use crate::utils::site;
+use crate::utils::stats;
+use crate::utils::svg::{HtmlContext, Svg, extract_svg_element, queue_svgs, write_embedded_svg};
+use crate::utils::theme;
+use crate::utils::toc;
+use crate::utils::typst;
 use crate::utils::watch::wait_until_stable;
 use crate::utils::xml::{
     create_xml_reader, write_element_with_processed_links, write_head_content,
-    write_heading_with_slugified_id, write_html_with_lang,
+    write_heading_anchor, write_heading_with_slugified_id, write_html_with_lang,
 };
-use crate::{config::SiteConfig, exec, log, utils::slug::content_paths};
-use anyhow::{Result, anyhow};
+use crate::{
+    config::{AnchorPosition, PluginStage, SiteConfig, TypstBackend},
+    debug, exec, observer,
+    utils::slug::content_paths,
+};
+use anyhow::{Context, Result};
 use quick_xml::{
     Reader, Writer,
     events::{BytesEnd, BytesStart, Event},
 };
 use rayon::prelude::*;
 use std::{
+    ffi::OsString,
     fs,
-    io::Cursor,
+    io::{Cursor, Write},
     path::{Path, PathBuf},
+    time::Instant,
 };
 use walkdir::WalkDir;
 
@@ -34,33 +62,53 @@ pub fn collect_files<P>(dir: &Path, should_collect: P) -> Vec<PathBuf>
 where
     P: Fn(&Path) -> bool + Send + Sync,
 {
+    // Name order keeps everything derived from the walk the same across builds
     WalkDir::new(dir)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .filter(|e| {
-            let name = e.file_name().to_str().unwrap_or_default();
-            !IGNORED_FILES.contains(&name) && should_collect(e.path())
+            !IGNORED_FILES.iter().any(|name| e.file_name() == *name) && should_collect(e.path())
         })
         .map(|e| e.into_path())
         .collect()
 }
 
 /// Process files in parallel with the given processor function
+///
+/// Progress is reported on a bar keyed by `stage` (e.g. `content`, `assets`).
+/// A failing (or panicking) file does not stop the others: its error is recorded
+/// in the build report, and the number of failed files is returned.
 pub fn process_files<P, F>(
     dir: &Path,
-    config: &'static SiteConfig,
+    config: &SiteConfig,
+    stage: &'static str,
     should_process: P,
     processor: F,
-) -> Result<()>
+) -> usize
 where
     P: Fn(&Path) -> bool + Send + Sync,
-    F: Fn(&Path, &'static SiteConfig) -> Result<()> + Sync,
+    F: Fn(&Path, &SiteConfig) -> Result<()> + Sync,
 {
     let files = collect_files(dir, should_process);
-    files
-        .par_iter()
-        .try_for_each(|path| processor(path, config))
+    debug!("build"; "processing {} files in {}", files.len(), dir.display());
+
+    let _progress = Progress::start(stage, files.len());
+    pool::io(config).install(|| {
+        files
+            .par_iter()
+            .filter(|path| {
+                let started = Instant::now();
+                let result = panic::catch(|| processor(path, config));
+                if stage == "content" && path.extension().is_some_and(|ext| ext == "typ") {
+                    stats::page(path, started.elapsed());
+                }
+                progress::advance(stage, 1);
+                result.map_err(|e| report::error(path, &e)).is_err()
+            })
+            .count()
+    })
 }
 
 // ============================================================================
@@ -69,25 +117,32 @@ where
 
 pub fn process_content(
     content_path: &Path,
-    config: &'static SiteConfig,
+    config: &SiteConfig,
     should_log_newline: bool,
     force_rebuild: bool,
 ) -> Result<()> {
-    let root = config.get_root();
     let content = &config.build.content;
     let output = &config.build.output.join(&config.build.base_path);
 
     let is_relative_asset = content_path.extension().is_some_and(|ext| ext != "typ");
 
+    // Section settings are read by the pages below them, never copied
+    if is_relative_asset && config.build.sections.enable && sections::is_section_file(content_path)
+    {
+        return Ok(());
+    }
+
     if is_relative_asset {
-        let relative_asset_path = content_path
-            .strip_prefix(content)?
-            .to_str()
-            .ok_or(anyhow!("Invalid path"))?;
+        let relative_asset_path = content_path.strip_prefix(content)?;
 
-        log!(should_log_newline; "content"; "{}", relative_asset_path);
+        let _task = Task::start(
+            "content",
+            relative_asset_path.to_string_lossy(),
+            should_log_newline,
+        );
 
         let output = output.join(relative_asset_path);
+        prune::produced(&output);
 
         // Ensure parent directory exists
         if let Some(parent) = output.parent() {
@@ -102,49 +157,233 @@ pub fn process_content(
             return Ok(());
         }
 
-        fs::copy(content_path, output)?;
+        match minify::minifies(content_path, config) {
+            true => {
+                let content = fs::read(content_path)?;
+                fs::write(
+                    &output,
+                    minify::minify_asset(content_path, content, &output, config),
+                )?;
+            }
+            false => {
+                fs::copy(content_path, &output)?;
+            }
+        }
+        observer::notify(|o| o.on_asset_copied(content_path, &output));
         return Ok(());
     }
 
     // Process .typ file: get output paths, compile, and post-process
     let paths = content_paths(content_path, config)?;
-    log!(should_log_newline; "content"; "{}", paths.relative);
+    let _task = Task::start("content", paths.relative.as_str(), should_log_newline);
+
+    let section = sections::page_section(content_path, config)?;
+    let page_meta = |json: Option<&str>| {
+        let mut meta = site::page_meta(content_path, json, config);
+        if let Some(meta) = &mut meta {
+            sections::apply(meta, &section, config);
+        }
+        meta
+    };
+    // Drafts and future posts are neither written nor recorded, so their old
+    // output is pruned and they never reach feeds or listings
+    let excluded = |meta: Option<&PageMeta>| {
+        let excluded = site::is_excluded(meta, config);
+        if excluded {
+            debug!("build"; "skipping draft or future post {}", paths.relative);
+            site::remove_page(content_path);
+        }
+        excluded
+    };
+
+    // Skip if the source is unchanged since the page was last compiled,
+    // reusing the metadata of that compile
+    let page_cache = cache::pages().filter(|_| !config.build.force);
+    if !force_rebuild
+        && paths.html.exists()
+        && let Some(page_cache) = &page_cache
+        && page_cache.is_current(paths.relative.as_str(), content_path)?
+        && let Some(json) = meta::cached_meta(content_path, config)
+    {
+        let mut meta = page_meta(json.as_deref());
+        if excluded(meta.as_ref()) {
+            return Ok(());
+        }
+        prune::produced(&paths.html);
+        let html = fs::read(&paths.html)?;
+        if let Some(meta) = &mut meta {
+            site::fill_summary(meta, &html, config);
+            reading_time::fill_reading_time(meta, &html, config);
+        }
+        site::record_page(content_path, &paths.html, &html, meta, config);
+        prune::produced_page(&paths.html);
+        return Ok(());
+    }
+
+    let inputs = sections::page_inputs(&section);
+    let html_content = compile_typst(content_path, &inputs, config)
+        // A page that fails to compile keeps its last output
+        .inspect_err(|_| prune::produced(&paths.html))?;
+    let json = meta::embedded_meta(&html_content);
+    let mut meta = page_meta(json.as_deref());
+    if excluded(meta.as_ref()) {
+        return Ok(());
+    }
+    prune::produced(&paths.html);
 
     // Create output directory for the post
     if let Some(parent) = paths.html.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    // Skip if up-to-date
-    if !force_rebuild && paths.html.exists() {
-        let src_time = content_path.metadata()?.modified()?;
-        let dst_time = paths
-            .html
-            .metadata()?
-            .modified()
-            .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-        if src_time <= dst_time {
-            return Ok(());
-        }
+    let html_content = finish_page(
+        content_path,
+        &paths.html,
+        html_content,
+        meta.as_ref(),
+        config,
+    )?;
+
+    fs::write(&paths.html, &html_content)?;
+    if let Some(page_cache) = &page_cache {
+        page_cache.record(paths.relative.as_str(), content_path)?;
+        meta::record_meta(content_path, json.as_deref(), config)?;
+    }
+    if let Some(meta) = &mut meta {
+        site::fill_summary(meta, &html_content, config);
+        reading_time::fill_reading_time(meta, &html_content, config);
+    }
+    site::record_page(content_path, &paths.html, &html_content, meta, config);
+    prune::produced_page(&paths.html);
+    observer::notify(|o| o.on_page_built(content_path, &paths.html));
+    Ok(())
+}
+
+/// Compile a typst page to its final HTML, passing the site data and `inputs`
+/// as `sys.inputs`
+pub fn compile_page(
+    content_path: &Path,
+    html_path: &Path,
+    inputs: &[(&str, String)],
+    meta: Option<&PageMeta>,
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let html_content = compile_typst(content_path, inputs, config)?;
+    finish_page(content_path, html_path, html_content, meta, config)
+}
+
+/// The HTML typst compiles a page to, with its metadata embedded
+/// (see [`meta::compile_source`])
+fn compile_typst(
+    content_path: &Path,
+    inputs: &[(&str, String)],
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    match config.build.typst.backend {
+        TypstBackend::Library => typst::compile_to_html(content_path, inputs, config),
+        TypstBackend::Cli => compile_typst_cli(content_path, inputs, config),
     }
+}
 
-    let output = exec!(&config.build.typst.command;
-        "compile", "--features", "html", "--format", "html",
-        "--font-path", root, "--root", root,
-        content_path, "-"
+/// [`compile_typst`] with the `[build.typst]` command, one process per page
+fn compile_typst_cli(
+    content_path: &Path,
+    inputs: &[(&str, String)],
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let root = config.get_root();
+    // Human diagnostics carry the file, line and column, a source snippet and hints
+    let mut args: Vec<OsString> = [
+        "compile",
+        "--features",
+        "html",
+        "--format",
+        "html",
+        "--diagnostic-format",
+        "human",
+    ]
+    .into_iter()
+    .map(OsString::from)
+    .collect();
+    args.extend(["--font-path".into(), root.into()]);
+    args.extend(["--root".into(), root.into()]);
+    args.extend(["--input".into(), config.typst_site_input().into()]);
+    args.extend(reproducible::typst_args(config).map(OsString::from));
+    for (key, value) in inputs {
+        args.extend(["--input".into(), format!("{key}={value}").into()]);
+    }
+    // The page is included by a source read from stdin
+    args.extend(["-".into(), "-".into()]);
+    // Without a source date the timestamp arguments are empty
+    let args = exec::filter_args(&args);
+    let source = meta::compile_source(content_path, config)?;
+    let output = exec::exec_with_input(
+        None,
+        &exec::to_cmd_vec(&config.build.typst.command),
+        &args,
+        source.as_bytes(),
     )?;
 
-    let html_content = output.stdout;
-    let html_content = process_html(&paths.html, &html_content, config)?;
+    for warning in collect_warnings(&output.stderr) {
+        report::warning(content_path, warning);
+    }
+    Ok(output.stdout)
+}
 
-    let html_content = if config.build.minify {
-        minify_html::minify(html_content.as_slice(), &minify_html::Cfg::new())
-    } else {
-        html_content
-    };
+/// Turn the HTML typst compiled a page to into its final HTML
+fn finish_page(
+    content_path: &Path,
+    html_path: &Path,
+    html_content: Vec<u8>,
+    meta: Option<&PageMeta>,
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let html_content = plugin::transform_page(
+        PluginStage::PostCompile,
+        html_content,
+        content_path,
+        html_path,
+        config,
+    )?;
+    // The head is written before the body, so the summary and reading time are derived up front
+    let missing =
+        |meta: &&PageMeta| meta.summary.is_none() || reading_time::is_missing(meta, config);
+    let summarized = meta.filter(missing).map(|meta| {
+        let mut meta = meta.clone();
+        site::fill_summary(&mut meta, &html_content, config);
+        reading_time::fill_reading_time(&mut meta, &html_content, config);
+        meta
+    });
+    let meta = summarized.as_ref().or(meta);
+    let html_content = process_html(html_path, Some(content_path), &html_content, meta, config)?;
+    let html_content = plugin::transform_page(
+        PluginStage::PreMinify,
+        html_content,
+        content_path,
+        html_path,
+        config,
+    )?;
 
-    fs::write(&paths.html, html_content)?;
-    Ok(())
+    Ok(minify(html_content, config))
+}
+
+/// Post-process a page generated by tola itself rather than compiled from typst
+pub fn process_generated_page(
+    html_path: &Path,
+    html: &[u8],
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let html = process_html(html_path, None, html, None, config)?;
+    Ok(minify(html, config))
+}
+
+/// Minify HTML if `[build] minify` is enabled
+fn minify(html: Vec<u8>, config: &SiteConfig) -> Vec<u8> {
+    if config.build.minify {
+        pool::cpu(config).install(|| minify_html::minify(html.as_slice(), &minify_html::Cfg::new()))
+    } else {
+        html
+    }
 }
 
 // ============================================================================
@@ -153,26 +392,24 @@ pub fn process_content(
 
 pub fn process_asset(
     asset_path: &Path,
-    config: &'static SiteConfig,
+    config: &SiteConfig,
     should_wait_until_stable: bool,
     should_log_newline: bool,
 ) -> Result<()> {
-    let assets = &config.build.assets;
     let output = &config.build.output.join(&config.build.base_path);
 
     let asset_extension = asset_path
         .extension()
-        .unwrap_or_default()
-        .to_str()
+        .and_then(|ext| ext.to_str())
         .unwrap_or_default();
-    let relative_asset_path = asset_path
-        .strip_prefix(assets)?
-        .to_str()
-        .ok_or(anyhow!("Invalid path"))?;
+    let relative_asset_path = theme::asset_relative(asset_path, config)?;
+    // Cache keys are text; a lossy key only risks an unneeded copy
+    let cache_key = relative_asset_path.to_string_lossy();
 
-    log!(should_log_newline; "assets"; "{}", relative_asset_path);
+    let _task = Task::start("assets", cache_key.as_ref(), should_log_newline);
 
     let output_path = output.join(relative_asset_path);
+    prune::produced(&output_path);
 
     if let Some(parent) = output_path.parent() {
         fs::create_dir_all(parent)?;
@@ -182,24 +419,50 @@ pub fn process_asset(
         wait_until_stable(asset_path, 5)?;
     }
 
+    let cache = cache::assets(&config.build.cache);
     match asset_extension {
         "css" if config.build.tailwind.enable => {
-            let input = config.build.tailwind.input.as_ref().unwrap();
+            let input = config
+                .build
+                .tailwind
+                .input
+                .as_ref()
+                .context("[build.tailwind.input] is not set")?;
             // Config paths are already absolute, just canonicalize the runtime path
-            let asset_path = asset_path.canonicalize().unwrap();
+            let asset_path = asset_path
+                .canonicalize()
+                .with_context(|| format!("Failed to resolve {}", asset_path.display()))?;
             if *input == asset_path {
                 exec!(config.get_root(); &config.build.tailwind.command;
                     "-i", input, "-o", &output_path, if config.build.minify { "--minify" } else { "" }
                 )?;
-            } else {
-                fs::copy(asset_path, &output_path)?;
+            } else if !cache.copy(&cache_key, &asset_path, &output_path)? {
+                return Ok(());
             }
         }
+        // Minified under their own key, since the output differs from the source
+        _ if minify::minifies(asset_path, config) => {
+            let mode = match minify::writes_source_maps(config) {
+                true => "min+map",
+                false => "min",
+            };
+            let key = format!("{mode}:{cache_key}");
+            let minified = cache.transform(&key, asset_path, &output_path, |content| {
+                minify::minify_asset(asset_path, content, &output_path, config)
+            })?;
+            if !minified {
+                return Ok(());
+            }
+        }
+        // Unchanged assets already in the output are skipped via the build cache
         _ => {
-            fs::copy(asset_path, &output_path)?;
+            if !cache.copy(&cache_key, asset_path, &output_path)? {
+                return Ok(());
+            }
         }
     }
 
+    observer::notify(|o| o.on_asset_copied(asset_path, &output_path));
     Ok(())
 }
 
@@ -207,8 +470,18 @@ pub fn process_asset(
 // HTML Processing
 // ============================================================================
 
-fn process_html(html_path: &Path, content: &[u8], config: &'static SiteConfig) -> Result<Vec<u8>> {
-    let mut ctx = HtmlContext::new(config, html_path);
+fn process_html(
+    html_path: &Path,
+    source: Option<&Path>,
+    content: &[u8],
+    meta: Option<&PageMeta>,
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let mut ctx = HtmlContext::new(config, html_path, meta);
+    ctx.source = source;
+    if config.build.toc.enable {
+        ctx.toc = toc::collect(content, config);
+    }
     let mut writer = Writer::new(Cursor::new(Vec::with_capacity(content.len())));
     let mut reader = create_xml_reader(content);
     let mut svgs = Vec::new();
@@ -219,7 +492,10 @@ fn process_html(html_path: &Path, content: &[u8], config: &'static SiteConfig) -
                 handle_start_element(&elem, &mut reader, &mut writer, &mut ctx, &mut svgs)?;
             }
             Ok(Event::End(elem)) => {
-                handle_end_element(&elem, &mut writer, config)?;
+                handle_end_element(&elem, &mut writer, &mut ctx)?;
+            }
+            Ok(Event::Empty(elem)) => {
+                handle_empty_element(&elem, &mut writer, &mut ctx)?;
             }
             Ok(Event::Eof) => break,
             Ok(event) => writer.write_event(event)?,
@@ -231,9 +507,13 @@ fn process_html(html_path: &Path, content: &[u8], config: &'static SiteConfig) -
         }
     }
 
-    // Compress SVGs in parallel
+    if config.build.toc.json && source.is_some() {
+        toc::write_json(&ctx.toc, html_path)?;
+    }
+
+    // Encoded on the image queue, which the build waits for
     if ctx.extract_svg && !svgs.is_empty() {
-        compress_svgs_parallel(&svgs, html_path, config)?;
+        queue_svgs(svgs, html_path, config)?;
     }
 
     Ok(writer.into_inner().into_inner())
@@ -248,27 +528,140 @@ fn handle_start_element(
 ) -> Result<()> {
     match elem.name().as_ref() {
         b"html" => write_html_with_lang(elem, writer, ctx.config)?,
+        b"body" => inject::write_body_start(elem, writer, ctx)?,
         b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
-            write_heading_with_slugified_id(elem, writer, ctx.config)?;
+            let id = write_heading_with_slugified_id(elem, writer, ctx.config)?;
+            let anchors = &ctx.config.build.anchors;
+            match id.filter(|_| anchors.enable) {
+                Some(id) if anchors.position == AnchorPosition::Before => {
+                    write_heading_anchor(writer, &id, ctx.config)?;
+                }
+                id => ctx.anchor = id,
+            }
+        }
+        toc::MARKER if ctx.config.build.toc.enable => {
+            writer
+                .get_mut()
+                .write_all(toc::render(&ctx.toc).as_bytes())?;
+            reader.read_to_end(elem.name())?;
         }
         b"svg" if ctx.extract_svg => {
             if let Some(svg) = extract_svg_element(reader, writer, elem, ctx)? {
                 svgs.push(svg);
             }
         }
-        _ => write_element_with_processed_links(elem, writer, ctx.config)?,
+        b"svg" => write_embedded_svg(elem, reader, writer, ctx.config)?,
+        // The page's metadata, read before processing; void, so it has no end tag
+        b"meta" if meta::is_meta_element(elem) => {}
+        name => {
+            let mut new_elem = ctx
+                .wrapped
+                .open(elem, writer, &ctx.config.build.html.rewrite)?;
+            if name == b"img" {
+                new_elem = images::with_lazy_attributes(new_elem, ctx.source, ctx.config);
+            }
+            write_element_with_processed_links(&new_elem, writer, ctx.config, ctx.source)?;
+            if rewrite::is_void(name) {
+                ctx.wrapped.close(name, writer)?;
+            }
+        }
     }
     Ok(())
 }
 
+/// Write a self-closed element, such as `<img/>`, with its rewrites applied
+fn handle_empty_element(
+    elem: &BytesStart<'_>,
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    ctx: &mut HtmlContext<'_>,
+) -> Result<()> {
+    if meta::is_meta_element(elem) {
+        return Ok(());
+    }
+    let name = elem.name();
+    let mut new_elem = ctx
+        .wrapped
+        .open(elem, writer, &ctx.config.build.html.rewrite)?;
+    if name.as_ref() == b"img" {
+        new_elem = images::with_lazy_attributes(new_elem, ctx.source, ctx.config);
+    }
+    writer.write_event(Event::Empty(new_elem.into_owned()))?;
+    ctx.wrapped.close(name.as_ref(), writer)
+}
+
 fn handle_end_element(
     elem: &BytesEnd<'_>,
     writer: &mut Writer<Cursor<Vec<u8>>>,
-    config: &'static SiteConfig,
+    ctx: &mut HtmlContext<'_>,
 ) -> Result<()> {
     match elem.name().as_ref() {
-        b"head" => write_head_content(writer, config)?,
-        _ => writer.write_event(Event::End(elem.to_owned()))?,
+        b"head" => {
+            write_head_content(writer, ctx.config, ctx.meta)?;
+            ctx.wrapped.close(b"head", writer)?;
+        }
+        b"h1" | b"h2" | b"h3" | b"h4" | b"h5" | b"h6" => {
+            if let Some(id) = ctx.anchor.take() {
+                write_heading_anchor(writer, &id, ctx.config)?;
+            }
+            writer.write_event(Event::End(elem.to_owned()))?;
+        }
+        b"body" => {
+            inject::write_body_end(writer, ctx)?;
+            writer.write_event(Event::End(elem.to_owned()))?;
+        }
+        name => {
+            writer.write_event(Event::End(elem.to_owned()))?;
+            ctx.wrapped.close(name, writer)?;
+        }
     }
     Ok(())
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RewriteRule;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_process_html_rewrite() {
+        let mut config = SiteConfig::default();
+        config.build.html.rewrite = toml::from_str::<BTreeMap<String, Vec<RewriteRule>>>(
+            r#"
+            [[rewrite]]
+            tag = "table"
+            wrap = { tag = "div", attributes = { class = "table-wrapper" } }
+            [[rewrite]]
+            tag = "img"
+            set = { loading = "lazy" }
+            "#,
+        )
+        .unwrap()
+        .remove("rewrite")
+        .unwrap();
+
+        let html = br#"<div><table><tr><td><table></table></td></tr></table><img src="https://example.com/a.png"><br/></div>"#;
+        let html = process_html(Path::new("index.html"), None, html, None, &config).unwrap();
+        assert_eq!(
+            String::from_utf8(html).unwrap(),
+            concat!(
+                r#"<div><div class="table-wrapper"><table><tr><td>"#,
+                r#"<div class="table-wrapper"><table></table></div>"#,
+                r#"</td></tr></table></div>"#,
+                r#"<img src="https://example.com/a.png" loading="lazy"><br/></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_process_html_drops_metadata() {
+        let config = SiteConfig::default();
+        let html = br#"<div><meta name="tola-meta" content="{&quot;title&quot;:&quot;A&quot;}"><p>A</p></div>"#;
+        let html = process_html(Path::new("index.html"), None, html, None, &config).unwrap();
+        assert_eq!(String::from_utf8(html).unwrap(), "<div><p>A</p></div>");
+    }
+}
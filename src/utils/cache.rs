@@ -0,0 +1,550 @@
+//! Persistent build cache.
+//!
+//! Stores file fingerprints (size, mtime, content hash) in `[build.cache]`
+//! (`.tola-cache/` by default), so unchanged files can be skipped across runs.
+//! Compiled pages, and the metadata queried from their sources, are only
+//! reused while the config and shared Typst files (templates, utils) they
+//! were built with are unchanged.
+
+use crate::{
+    config::SiteConfig,
+    utils::{build::collect_files, reproducible, sections, theme},
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::UNIX_EPOCH,
+};
+
+/// Cache file for copied assets, inside the cache directory
+const ASSETS_FILE: &str = "assets.json";
+
+/// Cache file for compiled pages, inside the cache directory
+const PAGES_FILE: &str = "manifest.json";
+
+// ============================================================================
+// Fingerprints
+// ============================================================================
+
+/// Identity of a file's content at a point in time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch
+    pub mtime: u128,
+    /// Hex-encoded SHA-1 of the content
+    pub hash: String,
+}
+
+/// Read size and mtime of `path` (cheap, no content read)
+fn stat(path: &Path) -> io::Result<(u64, u128)> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    Ok((meta.len(), mtime))
+}
+
+/// Hash file content with SHA-1
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash of everything besides its own source that a compiled page depends on
+///
+/// Covers the tola version, the config, and the templates and utils directories
+/// (and the theme's templates).
+pub fn config_hash(config: &SiteConfig) -> Result<String> {
+    let mut hasher = Sha1::new();
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(serde_json::to_vec(config)?);
+    // Typst sees the source date as `datetime.today()`
+    if let Some(date) = reproducible::source_date(config) {
+        hasher.update(date.to_le_bytes());
+    }
+    let theme_templates = theme::templates_dir(config);
+    let dirs = [&config.build.templates, &config.build.utils]
+        .into_iter()
+        .chain(theme_templates.as_ref());
+    let mut files: Vec<_> = dirs.flat_map(|dir| collect_files(dir, |_| true)).collect();
+    // Section settings reach the pages below them through their inputs
+    if config.build.sections.enable {
+        files.extend(collect_files(
+            &config.build.content,
+            sections::is_section_file,
+        ));
+    }
+    files.sort();
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update(hash_file(&file)?);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Fingerprint of `source` if its content is still the one of `cached`
+///
+/// A matching size+mtime is trusted without reading the file, otherwise the
+/// content hash decides; the returned fingerprint has the current mtime.
+fn current_fingerprint(cached: &Fingerprint, source: &Path) -> Result<Option<Fingerprint>> {
+    let (size, mtime) =
+        stat(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    if cached.size != size {
+        return Ok(None);
+    }
+    if cached.mtime == mtime {
+        return Ok(Some(cached.clone()));
+    }
+    let hash = hash_file(source)?;
+    Ok((cached.hash == hash).then_some(Fingerprint { size, mtime, hash }))
+}
+
+// ============================================================================
+// FileCache
+// ============================================================================
+
+/// Thread-safe map of cache keys (relative paths) to fingerprints, backed by a JSON file
+#[derive(Debug)]
+pub struct FileCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Fingerprint>>,
+    dirty: AtomicBool,
+}
+
+impl FileCache {
+    /// Load from `path`; a missing or unreadable cache starts empty.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, Fingerprint>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Copy `source` to `output` unless the cached fingerprint shows `output` is current.
+    ///
+    /// A matching size+mtime skips the copy without reading the file. If only the
+    /// mtime changed (e.g. after a checkout), the content hash decides.
+    /// Returns `true` if the file was copied.
+    pub fn copy(&self, key: &str, source: &Path, output: &Path) -> Result<bool> {
+        let (size, mtime) =
+            stat(source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let output_ok = stat(output).is_ok_and(|(out_size, _)| out_size == size);
+        let cached = self.entries().get(key).cloned();
+
+        if output_ok
+            && let Some(cached) = &cached
+            && cached.size == size
+        {
+            if cached.mtime == mtime {
+                return Ok(false);
+            }
+            let hash = hash_file(source)?;
+            if cached.hash == hash {
+                self.insert(key, Fingerprint { size, mtime, hash });
+                return Ok(false);
+            }
+        }
+
+        fs::copy(source, output).with_context(|| {
+            format!(
+                "Failed to copy {} to {}",
+                source.display(),
+                output.display()
+            )
+        })?;
+        let hash = hash_file(source)?;
+        self.insert(key, Fingerprint { size, mtime, hash });
+        Ok(true)
+    }
+
+    /// Write `transform` of `source` to `output` unless the cached fingerprint
+    /// shows `output` is current.
+    ///
+    /// Unlike [`copy`](Self::copy), `output` may differ from `source` in size.
+    /// Returns `true` if the file was written.
+    pub fn transform(
+        &self,
+        key: &str,
+        source: &Path,
+        output: &Path,
+        transform: impl FnOnce(Vec<u8>) -> Vec<u8>,
+    ) -> Result<bool> {
+        let cached = self.entries().get(key).cloned();
+        if output.exists()
+            && let Some(cached) = cached
+            && let Some(current) = current_fingerprint(&cached, source)?
+        {
+            if current != cached {
+                self.insert(key, current);
+            }
+            return Ok(false);
+        }
+
+        let (size, mtime) =
+            stat(source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let content =
+            fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let hash = format!("{:x}", Sha1::digest(&content));
+        fs::write(output, transform(content))
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        self.insert(key, Fingerprint { size, mtime, hash });
+        Ok(true)
+    }
+
+    /// Whether `hash` is the content hash last recorded under `key`
+    pub fn has_hash(&self, key: &str, hash: &str) -> bool {
+        self.entries()
+            .get(key)
+            .is_some_and(|cached| cached.hash == hash)
+    }
+
+    /// Record the content hash of `size` bytes written under `key`
+    ///
+    /// For outputs whose freshness only depends on their content, whatever
+    /// the modification time.
+    pub fn record_hash(&self, key: &str, size: u64, hash: String) {
+        self.insert(
+            key,
+            Fingerprint {
+                size,
+                mtime: 0,
+                hash,
+            },
+        );
+    }
+
+    fn insert(&self, key: &str, fingerprint: Fingerprint) {
+        self.entries().insert(key.to_owned(), fingerprint);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Write the cache back to disk if anything changed.
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec(&*self.entries())?;
+        fs::write(&self.path, data)
+            .with_context(|| format!("Failed to write cache {}", self.path.display()))
+    }
+}
+
+// ============================================================================
+// PageCache
+// ============================================================================
+
+/// On-disk form of [`PageCache`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// [`config_hash`] the pages were compiled with
+    config: String,
+    pages: HashMap<String, Fingerprint>,
+    /// Metadata JSON of each page, by source
+    #[serde(default)]
+    meta: HashMap<String, CachedMeta>,
+}
+
+/// Page metadata as read from a source with the given fingerprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedMeta {
+    source: Fingerprint,
+    json: String,
+}
+
+/// Fingerprints of the sources of compiled pages, backed by a JSON file
+#[derive(Debug)]
+pub struct PageCache {
+    path: PathBuf,
+    manifest: Mutex<Manifest>,
+    dirty: AtomicBool,
+}
+
+impl PageCache {
+    /// Load from `path`; entries recorded under another config are dropped.
+    pub fn load(path: PathBuf, config: String) -> Self {
+        let manifest: Manifest = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        let stale = manifest.config != config;
+        let manifest = if stale {
+            Manifest {
+                config,
+                ..Default::default()
+            }
+        } else {
+            manifest
+        };
+        Self {
+            path,
+            manifest: Mutex::new(manifest),
+            dirty: AtomicBool::new(stale),
+        }
+    }
+
+    fn manifest(&self) -> std::sync::MutexGuard<'_, Manifest> {
+        self.manifest.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `source` has the content it had when last recorded under `key`.
+    ///
+    /// Like [`FileCache::copy`], a matching size+mtime is trusted without reading
+    /// the file, otherwise the content hash decides.
+    pub fn is_current(&self, key: &str, source: &Path) -> Result<bool> {
+        let Some(cached) = self.manifest().pages.get(key).cloned() else {
+            return Ok(false);
+        };
+        match current_fingerprint(&cached, source)? {
+            Some(fingerprint) if fingerprint == cached => Ok(true),
+            Some(fingerprint) => {
+                self.insert(key, fingerprint);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Metadata JSON last read from `source`, if its content is unchanged
+    pub fn meta(&self, key: &str, source: &Path) -> Option<String> {
+        let cached = self.manifest().meta.get(key).cloned()?;
+        current_fingerprint(&cached.source, source).ok()??;
+        Some(cached.json)
+    }
+
+    /// Remember the metadata JSON read from the current content of `source`
+    pub fn record_meta(&self, key: &str, source: &Path, json: &str) -> Result<()> {
+        let (size, mtime) =
+            stat(source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let hash = hash_file(source)?;
+        let cached = CachedMeta {
+            source: Fingerprint { size, mtime, hash },
+            json: json.to_owned(),
+        };
+        self.manifest().meta.insert(key.to_owned(), cached);
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Remember the current content of `source` after compiling it.
+    pub fn record(&self, key: &str, source: &Path) -> Result<()> {
+        let (size, mtime) =
+            stat(source).with_context(|| format!("Failed to read {}", source.display()))?;
+        let hash = hash_file(source)?;
+        self.insert(key, Fingerprint { size, mtime, hash });
+        Ok(())
+    }
+
+    fn insert(&self, key: &str, fingerprint: Fingerprint) {
+        self.manifest().pages.insert(key.to_owned(), fingerprint);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Write the cache back to disk if anything changed.
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec(&*self.manifest())?;
+        fs::write(&self.path, data)
+            .with_context(|| format!("Failed to write cache {}", self.path.display()))
+    }
+}
+
+// ============================================================================
+// Asset Cache
+// ============================================================================
+
+static ASSETS: Mutex<Option<Arc<FileCache>>> = Mutex::new(None);
+
+/// Asset copy cache for the site whose cache directory is `cache_dir`.
+///
+/// Loaded once and shared by all builds of the same site in this process.
+pub fn assets(cache_dir: &Path) -> Arc<FileCache> {
+    let path = cache_dir.join(ASSETS_FILE);
+    let mut slot = ASSETS.lock().unwrap_or_else(|e| e.into_inner());
+    match slot.as_ref() {
+        Some(cache) if cache.path == path => Arc::clone(cache),
+        _ => {
+            let cache = Arc::new(FileCache::load(path));
+            *slot = Some(Arc::clone(&cache));
+            cache
+        }
+    }
+}
+
+// ============================================================================
+// Page Cache
+// ============================================================================
+
+static PAGES: Mutex<Option<Arc<PageCache>>> = Mutex::new(None);
+
+/// (Re)load the page cache at the start of a build, under the current config.
+pub fn load_pages(config: &SiteConfig) -> Result<Arc<PageCache>> {
+    let path = config.build.cache.join(PAGES_FILE);
+    let cache = Arc::new(PageCache::load(path, config_hash(config)?));
+    *PAGES.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::clone(&cache));
+    Ok(cache)
+}
+
+/// Page cache of the current build, if one has been loaded.
+pub fn pages() -> Option<Arc<PageCache>> {
+    PAGES.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Persist all loaded caches.
+pub fn save_all() -> Result<()> {
+    let cache = ASSETS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(cache) = cache {
+        cache.save()?;
+    }
+    match pages() {
+        Some(cache) => cache.save(),
+        None => Ok(()),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tola_cache_test_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let dir = scratch("hash");
+        fs::write(dir.join("a.txt"), "hello").unwrap();
+        assert_eq!(
+            hash_file(&dir.join("a.txt")).unwrap(),
+            "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_skips_unchanged() {
+        let dir = scratch("copy");
+        let (src, dst) = (dir.join("src.bin"), dir.join("dst.bin"));
+        fs::write(&src, "font data").unwrap();
+        let cache = FileCache::load(dir.join("cache.json"));
+
+        assert!(cache.copy("src.bin", &src, &dst).unwrap());
+        assert!(!cache.copy("src.bin", &src, &dst).unwrap());
+
+        // Missing output forces a copy even with a cache hit
+        fs::remove_file(&dst).unwrap();
+        assert!(cache.copy("src.bin", &src, &dst).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let dir = scratch("save");
+        let (src, dst) = (dir.join("src.bin"), dir.join("dst.bin"));
+        fs::write(&src, "image").unwrap();
+
+        let cache = FileCache::load(dir.join("cache/assets.json"));
+        cache.copy("src.bin", &src, &dst).unwrap();
+        cache.save().unwrap();
+
+        let reloaded = FileCache::load(dir.join("cache/assets.json"));
+        assert!(!reloaded.copy("src.bin", &src, &dst).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_page_cache() {
+        let dir = scratch("pages");
+        let (src, manifest) = (dir.join("post.typ"), dir.join("manifest.json"));
+        fs::write(&src, "= Hello").unwrap();
+
+        let cache = PageCache::load(manifest.clone(), "config-a".into());
+        assert!(!cache.is_current("post.typ", &src).unwrap());
+        cache.record("post.typ", &src).unwrap();
+        assert!(cache.is_current("post.typ", &src).unwrap());
+        cache.save().unwrap();
+
+        // Same content survives a reload, a changed one does not
+        let cache = PageCache::load(manifest.clone(), "config-a".into());
+        assert!(cache.is_current("post.typ", &src).unwrap());
+        fs::write(&src, "= Hello, world").unwrap();
+        assert!(!cache.is_current("post.typ", &src).unwrap());
+
+        // A different config invalidates every page
+        fs::write(&src, "= Hello").unwrap();
+        let cache = PageCache::load(manifest, "config-b".into());
+        assert!(!cache.is_current("post.typ", &src).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_page_cache_meta() {
+        let dir = scratch("meta");
+        let (src, manifest) = (dir.join("post.typ"), dir.join("manifest.json"));
+        fs::write(&src, "= Hello").unwrap();
+
+        let cache = PageCache::load(manifest.clone(), "config-a".into());
+        assert_eq!(cache.meta("post.typ", &src), None);
+        cache
+            .record_meta("post.typ", &src, r#"{"title":"Hello"}"#)
+            .unwrap();
+        cache.save().unwrap();
+
+        let cache = PageCache::load(manifest.clone(), "config-a".into());
+        assert_eq!(
+            cache.meta("post.typ", &src).as_deref(),
+            Some(r#"{"title":"Hello"}"#)
+        );
+        fs::write(&src, "= Bye").unwrap();
+        assert_eq!(cache.meta("post.typ", &src), None);
+
+        let cache = PageCache::load(manifest, "config-b".into());
+        fs::write(&src, "= Hello").unwrap();
+        assert_eq!(cache.meta("post.typ", &src), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Typst import graph of the content.
+//!
+//! Compiles don't record the files a page reads, so its dependencies are read
+//! from the sources instead: every `import`/`include` of a string path is
+//! followed, relative to the importing file or, for paths starting with `/`,
+//! to the site root (typst's `--root`). Packages (`@preview/...`) are not
+//! tracked.
+
+use crate::{config::SiteConfig, utils::build::collect_files};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Content pages that import any of `changed`, directly or through other files
+pub fn dependents(changed: &[PathBuf], config: &SiteConfig) -> Vec<PathBuf> {
+    let changed: HashSet<&Path> = changed.iter().map(PathBuf::as_path).collect();
+    let mut pages = collect_files(&config.build.content, |path| {
+        path.extension().is_some_and(|ext| ext == "typ")
+    });
+    pages.retain(|page| {
+        dependencies(page, config.get_root())
+            .iter()
+            .any(|dependency| changed.contains(dependency.as_path()))
+    });
+    pages.sort();
+    pages
+}
+
+/// Local files `source` imports or includes, transitively
+pub fn dependencies(source: &Path, root: &Path) -> HashSet<PathBuf> {
+    let mut found = HashSet::new();
+    let mut queue = vec![source.to_path_buf()];
+    while let Some(file) = queue.pop() {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for import in imports(&text) {
+            let Some(path) = resolve(&file, import, root) else {
+                continue;
+            };
+            if path != source && path.is_file() && found.insert(path.clone()) {
+                queue.push(path);
+            }
+        }
+    }
+    found
+}
+
+/// File an import path refers to, if it is local and stays inside `root`
+fn resolve(file: &Path, import: &str, root: &Path) -> Option<PathBuf> {
+    if import.starts_with('@') {
+        return None;
+    }
+    let joined = match import.strip_prefix('/') {
+        Some(relative) => root.join(relative),
+        None => file.parent()?.join(import),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized.starts_with(root).then_some(normalized)
+}
+
+/// String paths of the `import` and `include` expressions in typst source
+///
+/// Comments are skipped; expressions computing the path are not followed.
+fn imports(source: &str) -> Vec<&str> {
+    let mut imports = Vec::new();
+    let bytes = source.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &source[i..];
+        if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("/*") {
+            i += rest.find("*/").map_or(rest.len(), |end| end + 2);
+            continue;
+        }
+
+        let at_word_start = i == 0 || !is_ident(bytes[i - 1]);
+        let keyword = ["import", "include"]
+            .into_iter()
+            .find(|keyword| rest.starts_with(keyword));
+        if let Some(keyword) = keyword.filter(|_| at_word_start) {
+            let after = &rest[keyword.len()..];
+            let path = after
+                .strip_prefix(|c: char| c.is_whitespace())
+                .map(str::trim_start)
+                .and_then(|after| after.strip_prefix('"'))
+                .and_then(|after| after.find('"').map(|end| &after[..end]));
+            if let Some(path) = path {
+                imports.push(path);
+            }
+            i += keyword.len();
+            continue;
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    imports
+}
+
+fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-'
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imports() {
+        let source = r#"#import "/templates/post.typ": post
+#import "@preview/cetz:0.3.0"
+// #import "commented.typ"
+/* #include "block.typ" */
+#let reimport = 1
+#{ import "../utils/math.typ": * }
+Größe #include "chapter.typ"
+"#;
+        assert_eq!(
+            imports(source),
+            [
+                "/templates/post.typ",
+                "@preview/cetz:0.3.0",
+                "../utils/math.typ",
+                "chapter.typ"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependents() {
+        let root = std::env::temp_dir().join("tola_deps_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("content/posts")).unwrap();
+        fs::create_dir_all(root.join("templates")).unwrap();
+        fs::write(root.join("templates/base.typ"), "#let base = 1").unwrap();
+        fs::write(
+            root.join("templates/post.typ"),
+            r#"#import "base.typ": base"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("content/posts/a.typ"),
+            r#"#import "/templates/post.typ": *"#,
+        )
+        .unwrap();
+        fs::write(root.join("content/posts/b.typ"), "= B").unwrap();
+
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        config.build.content = root.join("content");
+
+        let base = root.join("templates/base.typ");
+        assert_eq!(
+            dependencies(&root.join("content/posts/a.typ"), &root),
+            HashSet::from([root.join("templates/post.typ"), base.clone()])
+        );
+        assert_eq!(
+            dependents(&[base], &config),
+            [root.join("content/posts/a.typ")]
+        );
+        assert!(dependents(&[root.join("templates/other.typ")], &config).is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
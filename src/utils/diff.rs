@@ -0,0 +1,155 @@
+//! Output diff (`tola build --diff`).
+//!
+//! Snapshots the output directory before and after a build and reports added,
+//! removed and changed files with their size changes.
+
+use crate::{
+    log,
+    utils::{build::collect_files, cache::hash_file, log::format_size},
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Size and content hash of one output file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileState {
+    size: u64,
+    hash: String,
+}
+
+/// Output files keyed by path relative to the output directory
+#[derive(Debug, Default)]
+pub struct Snapshot(BTreeMap<PathBuf, FileState>);
+
+/// Difference of one file between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added { size: u64 },
+    Removed { size: u64 },
+    Changed { old: u64, new: u64 },
+}
+
+/// Snapshot all files under `output`, excluding the `.git` directory
+pub fn snapshot(output: &Path) -> Snapshot {
+    let git_dir = output.join(".git");
+    let files = collect_files(output, |path| !path.starts_with(&git_dir));
+    Snapshot(
+        files
+            .into_iter()
+            .filter_map(|path| {
+                let size = fs::metadata(&path).ok()?.len();
+                let hash = hash_file(&path).ok()?;
+                let relative = path.strip_prefix(output).ok()?.to_path_buf();
+                Some((relative, FileState { size, hash }))
+            })
+            .collect(),
+    )
+}
+
+/// Changes from `before` to `after`, sorted by path
+pub fn compare(before: &Snapshot, after: &Snapshot) -> Vec<(PathBuf, Change)> {
+    let mut changes: Vec<_> = after
+        .0
+        .iter()
+        .filter_map(|(path, new)| {
+            let change = match before.0.get(path) {
+                None => Change::Added { size: new.size },
+                Some(old) if old != new => Change::Changed {
+                    old: old.size,
+                    new: new.size,
+                },
+                Some(_) => return None,
+            };
+            Some((path.clone(), change))
+        })
+        .chain(
+            before
+                .0
+                .iter()
+                .filter(|(path, _)| !after.0.contains_key(*path))
+                .map(|(path, old)| (path.clone(), Change::Removed { size: old.size })),
+        )
+        .collect();
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+/// Log the changes and a summary line
+pub fn log_changes(changes: &[(PathBuf, Change)]) {
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for (path, change) in changes {
+        let path = path.display();
+        match *change {
+            Change::Added { size } => {
+                added += 1;
+                log!("diff"; "+ {path} ({})", format_size(size));
+            }
+            Change::Removed { size } => {
+                removed += 1;
+                log!("diff"; "- {path} ({})", format_size(size));
+            }
+            Change::Changed { old, new } => {
+                changed += 1;
+                log!("diff"; "~ {path} ({})", format_delta(old, new));
+            }
+        }
+    }
+    log!("diff"; "{added} added, {removed} removed, {changed} changed");
+}
+
+/// Signed size change, e.g. `+1.5KB` or `-12B`
+fn format_delta(old: u64, new: u64) -> String {
+    if new >= old {
+        format!("+{}", format_size(new - old))
+    } else {
+        format!("-{}", format_size(old - new))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(size: u64, hash: &str) -> FileState {
+        FileState {
+            size,
+            hash: hash.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = Snapshot(BTreeMap::from([
+            ("index.html".into(), state(100, "a")),
+            ("old/index.html".into(), state(50, "b")),
+            ("same.css".into(), state(10, "c")),
+        ]));
+        let after = Snapshot(BTreeMap::from([
+            ("index.html".into(), state(120, "d")),
+            ("new/index.html".into(), state(70, "e")),
+            ("same.css".into(), state(10, "c")),
+        ]));
+
+        assert_eq!(
+            compare(&before, &after),
+            vec![
+                ("index.html".into(), Change::Changed { old: 100, new: 120 }),
+                ("new/index.html".into(), Change::Added { size: 70 }),
+                ("old/index.html".into(), Change::Removed { size: 50 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(100, 120), "+20B");
+        assert_eq!(format_delta(2048, 512), "-1.5KB");
+    }
+}
@@ -3,12 +3,13 @@
 //! Provides macros and functions for running shell commands with proper
 //! output handling and error reporting.
 
-use crate::log;
+use crate::{debug, log};
 use anyhow::{Context, Result};
 use std::{
-    ffi::OsString,
+    ffi::{OsStr, OsString},
+    io::Write,
     path::Path,
-    process::{Child, ChildStdin, Command, Output, Stdio},
+    process::{Command, Output, Stdio},
 };
 
 // ============================================================================
@@ -43,24 +44,20 @@ macro_rules! exec {
     }};
 }
 
-/// Run an external command and return a handle to its stdin.
+/// Run an external command with `input` on stdin and capture its output.
 ///
-/// The child process is spawned with stdin piped, stdout/stderr nulled.
-/// Caller is responsible for writing to stdin and dropping it to signal EOF.
+/// # Examples
+/// ```ignore
+/// exec_with_stdin!(["magick"]; "-", output; input = svg)?;
+/// ```
 #[macro_export]
 macro_rules! exec_with_stdin {
-    ($cmd:expr; $($arg:expr),* $(,)?) => {{
-        $crate::utils::exec::spawn_with_stdin(
+    ($cmd:expr; $($arg:expr),*; input = $input:expr) => {{
+        $crate::utils::exec::exec_with_input(
             None,
             &$crate::utils::exec::to_cmd_vec($cmd),
             &$crate::utils::exec::filter_args(&[$($crate::utils::exec::to_os($arg)),*]),
-        )
-    }};
-    ($root:expr; $cmd:expr; $($arg:expr),* $(,)?) => {{
-        $crate::utils::exec::spawn_with_stdin(
-            Some($root),
-            &$crate::utils::exec::to_cmd_vec($cmd),
-            &$crate::utils::exec::filter_args(&[$($crate::utils::exec::to_os($arg)),*]),
+            $input,
         )
     }};
 }
@@ -132,27 +129,66 @@ pub fn exec(root: Option<&Path>, cmd: &[OsString], args: &[OsString]) -> Result<
     Ok(output)
 }
 
-/// Spawn a command and return a handle to write to its stdin.
+/// Run a command with `input` on stdin and capture its output.
+///
+/// `envs` are added to the child's environment. Only stderr is logged, since
+/// stdout carries the result.
+///
+/// # Errors
+/// Returns error if command fails to execute or returns non-zero exit code.
+pub fn pipe(
+    root: Option<&Path>,
+    cmd: &[OsString],
+    envs: &[(&str, &OsStr)],
+    input: &[u8],
+) -> Result<Vec<u8>> {
+    let (name, mut command) = prepare(root, cmd, &[])?;
+    command.envs(envs.iter().copied());
+    let output = run_with_input(&name, &mut command, input)?;
+
+    check_status(&name, &output)?;
+    STDERR_FILTER.log(&name, &String::from_utf8_lossy(&output.stderr));
+    Ok(output.stdout)
+}
+
+/// Execute a command with `input` on stdin and capture its output, like [`exec`].
 ///
 /// # Errors
-/// Returns error if command fails to spawn.
-pub fn spawn_with_stdin(
+/// Returns error if command fails to execute or returns non-zero exit code.
+pub fn exec_with_input(
     root: Option<&Path>,
     cmd: &[OsString],
     args: &[OsString],
-) -> Result<ChildStdin> {
+    input: &[u8],
+) -> Result<Output> {
     let (name, mut command) = prepare(root, cmd, args)?;
+    let output = run_with_input(&name, &mut command, input)?;
 
+    log_output(&name, &output)?;
+    Ok(output)
+}
+
+/// Run `command` with `input` on stdin, capturing stdout and stderr.
+fn run_with_input(name: &str, command: &mut Command, input: &[u8]) -> Result<Output> {
     command
         .stdin(Stdio::piped())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
 
-    let mut child: Child = command
+    let mut child = command
         .spawn()
         .with_context(|| format!("Failed to spawn `{name}`"))?;
 
-    child.stdin.take().context("Failed to acquire stdin")
+    // Write stdin from a separate thread so a large output can't deadlock on full pipes
+    let mut stdin = child.stdin.take().context("Failed to acquire stdin")?;
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output();
+        // A child that exits without reading all input closes the pipe; only its status matters
+        writer.join().ok();
+        output
+    })
+    .with_context(|| format!("Failed to run `{name}`"))
 }
 
 /// Prepare a Command from components.
@@ -165,6 +201,7 @@ fn prepare(root: Option<&Path>, cmd: &[OsString], args: &[OsString]) -> Result<(
 
     let mut command = Command::new(&cmd[0]);
     command.args(&cmd[1..]).args(args);
+    debug!(&name; "running {command:?}");
 
     if let Some(dir) = root {
         command.current_dir(dir);
@@ -220,8 +257,38 @@ const STDERR_FILTER: FilterRule = FilterRule::new(&[
     "≈ tailwindcss",
 ]);
 
+/// Extract `warning:` diagnostics from command stderr, skipping known noise.
+///
+/// Each warning keeps the lines printed under it (typst's source span,
+/// snippet and hints), up to the next diagnostic or blank line.
+pub fn collect_warnings(stderr: &[u8]) -> Vec<String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let mut warnings = Vec::new();
+    let mut current: Option<String> = None;
+    for line in stderr.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("warning:") || trimmed.starts_with("error:") {
+            warnings.extend(current.take());
+        }
+        match trimmed.strip_prefix("warning:") {
+            Some(_) if STDERR_FILTER.should_skip(trimmed) => {}
+            Some(message) => current = Some(message.trim().to_owned()),
+            None => {
+                if let Some(warning) = &mut current {
+                    warning.push('\n');
+                    warning.push_str(line.trim_end());
+                }
+            }
+        }
+    }
+    warnings.extend(current);
+    warnings
+}
+
 /// Log command output, filtering known noise.
 fn log_output(name: &str, output: &Output) -> Result<()> {
+    check_status(name, output)?;
+
     let stdout = std::str::from_utf8(&output.stdout)
         .context("Invalid UTF-8 in stdout")?
         .trim();
@@ -229,21 +296,31 @@ fn log_output(name: &str, output: &Output) -> Result<()> {
         .context("Invalid UTF-8 in stderr")?
         .trim();
 
+    STDOUT_FILTER.log(name, stdout);
+    STDERR_FILTER.log(name, stderr);
+
+    Ok(())
+}
+
+/// Fail with the (filtered) stderr if the command exited unsuccessfully.
+fn check_status(name: &str, output: &Output) -> Result<()> {
     if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
         // Strip warning prefix from error output
         let error_msg = STDERR_FILTER
             .skip_prefixes
             .iter()
             .fold(stderr, |s, p| s.trim_start_matches(p).trim_start());
+        // Keep stderr in the error so it shows up in the build report
         if !error_msg.is_empty() {
-            eprintln!("{error_msg}");
+            anyhow::bail!(
+                "Command `{name}` failed with {}\n{error_msg}",
+                output.status
+            );
         }
         anyhow::bail!("Command `{name}` failed with {}", output.status);
     }
-
-    STDOUT_FILTER.log(name, stdout);
-    STDERR_FILTER.log(name, stderr);
-
     Ok(())
 }
 
@@ -302,4 +379,37 @@ mod tests {
         let (name, _) = result.unwrap();
         assert_eq!(name, "echo");
     }
+
+    #[test]
+    fn test_collect_warnings() {
+        let stderr = "warning: html export is under active development\n  = hint: some elements are ignored\n\nwarning: unknown font family: foo\n  ┌─ content/posts/a.typ:3:17\n  │\n3 │ #set text(font: \"foo\")\n  │                 ^^^^^\n\nwarning: label not found\n  = hint: check the label name\n";
+        assert_eq!(
+            collect_warnings(stderr.as_bytes()),
+            vec![
+                "unknown font family: foo\n  ┌─ content/posts/a.typ:3:17\n  │\n3 │ #set text(font: \"foo\")\n  │                 ^^^^^",
+                "label not found\n  = hint: check the label name",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe_roundtrip() {
+        let cmd = to_cmd_vec(["sh", "-c", "printf \"$TOLA_STAGE:\"; cat"]);
+        let output = pipe(
+            None,
+            &cmd,
+            &[("TOLA_STAGE", OsStr::new("pre-minify"))],
+            b"<p>hi</p>",
+        );
+        assert_eq!(output.unwrap(), b"pre-minify:<p>hi</p>");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe_failure_includes_stderr() {
+        let cmd = to_cmd_vec(["sh", "-c", "echo broken >&2; exit 3"]);
+        let err = pipe(None, &cmd, &[], b"").unwrap_err();
+        assert!(err.to_string().contains("broken"));
+    }
 }
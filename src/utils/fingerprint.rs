@@ -0,0 +1,234 @@
+//! Asset fingerprinting (`[build.fingerprint]`).
+//!
+//! Runs after everything that writes pages: each asset in the output gets a
+//! copy named after a hash of its content, and `href`/`src` attributes of the
+//! generated HTML pointing at an asset are rewritten to the copy. Hashing the
+//! output rather than the source covers the generated tailwind stylesheet.
+//!
+//! Pages skipped by the build cache still link to the copies of a previous
+//! build, so the previous manifest is read to rewrite those links as well.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{build::collect_files, cache::hash_file, prune, slug::output_path},
+};
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use walkdir::WalkDir;
+
+/// Hex digits of the content hash kept in file names
+const HASH_LEN: usize = 8;
+
+/// Copy the assets to hashed names, write the manifest and rewrite the pages
+pub fn build_fingerprint(config: &SiteConfig) -> Result<()> {
+    let fingerprint = &config.build.fingerprint;
+    if !fingerprint.enable {
+        return Ok(());
+    }
+
+    let previous = read_manifest(&fingerprint.manifest);
+    let manifest = copy_assets(config)?;
+    let json = serde_json::to_string_pretty(&manifest)?;
+    prune::write_produced(&fingerprint.manifest, json.as_bytes())?;
+
+    let urls = asset_urls(&manifest, &previous, config);
+    let rewritten = rewrite_pages(&config.build.output, &urls)?;
+    log!("fingerprint"; "{} asset(s), {rewritten} page(s) rewritten", manifest.len());
+    Ok(())
+}
+
+/// Copy every built asset next to itself under its hashed name
+///
+/// Returns the manifest: asset path → hashed path, relative to the output
+/// directory (below `[build] base_path`).
+fn copy_assets(config: &SiteConfig) -> Result<BTreeMap<String, String>> {
+    let base = config.build.output.join(&config.build.base_path);
+    let mut manifest = BTreeMap::new();
+    for source in collect_files(&config.build.assets, |_| true) {
+        let Ok(output) = output_path(&source, config) else {
+            continue;
+        };
+        if !output.is_file() {
+            continue;
+        }
+        let hash =
+            hash_file(&output).with_context(|| format!("Failed to hash {}", output.display()))?;
+        let hashed = hashed_path(&output, &hash[..HASH_LEN]);
+        if !hashed.exists() {
+            fs::copy(&output, &hashed)
+                .with_context(|| format!("Failed to write {}", hashed.display()))?;
+        }
+        prune::produced(&hashed);
+
+        let relative = |path: &Path| {
+            let path = path.strip_prefix(&base).unwrap_or(path);
+            path.to_string_lossy().replace('\\', "/")
+        };
+        manifest.insert(relative(&output), relative(&hashed));
+    }
+    Ok(manifest)
+}
+
+/// `styles/main.css` → `styles/main.<hash>.css`
+fn hashed_path(path: &Path, hash: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}.{hash}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{hash}"),
+    };
+    path.with_file_name(name)
+}
+
+fn read_manifest(path: &Path) -> BTreeMap<String, String> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// URL of each asset, and of its previous copy, → URL of its current copy
+fn asset_urls(
+    manifest: &BTreeMap<String, String>,
+    previous: &BTreeMap<String, String>,
+    config: &SiteConfig,
+) -> HashMap<String, String> {
+    let url = |relative: &str| {
+        let path = config.build.base_path.join(relative);
+        format!("/{}", path.to_string_lossy().replace('\\', "/"))
+    };
+    let mut urls = HashMap::new();
+    for (asset, hashed) in manifest {
+        if let Some(old) = previous.get(asset).filter(|old| *old != hashed) {
+            urls.insert(url(old), url(hashed));
+        }
+        urls.insert(url(asset), url(hashed));
+    }
+    urls
+}
+
+/// Point the asset links of every HTML file in `output` at the copies;
+/// returns the number of files changed
+fn rewrite_pages(output: &Path, urls: &HashMap<String, String>) -> Result<usize> {
+    let mut rewritten = 0;
+    let entries = WalkDir::new(output)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "html"));
+    for entry in entries {
+        let path = entry.path();
+        let html = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if let Some(new_html) = rewrite_links(&html, urls) {
+            fs::write(path, new_html)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            rewritten += 1;
+        }
+    }
+    Ok(rewritten)
+}
+
+/// `html` with the `href`/`src` values found in `urls` replaced, if any was
+///
+/// Values may be unquoted, as minified pages leave them, and keep their
+/// query and fragment.
+fn rewrite_links(html: &str, urls: &HashMap<String, String>) -> Option<String> {
+    static RE_LINK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"(?-u:\b)(href|src)=("[^"]*"|'[^']*'|[^ \t\r\n"'=<>`]+)"#).unwrap()
+    });
+
+    let mut changed = false;
+    let new_html = RE_LINK.replace_all(html, |caps: &Captures| {
+        let value = &caps[2];
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\''))
+            .map(String::from)
+            .unwrap_or_default();
+        let inner = &value[quote.len()..value.len() - quote.len()];
+        let end = inner.find(['?', '#']).unwrap_or(inner.len());
+        match urls.get(&inner[..end]) {
+            Some(url) => {
+                changed = true;
+                format!("{}={quote}{url}{}{quote}", &caps[1], &inner[end..])
+            }
+            None => caps[0].to_owned(),
+        }
+    });
+    changed.then(|| new_html.into_owned())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_links() {
+        let urls = HashMap::from([
+            (
+                "/styles/main.css".to_owned(),
+                "/styles/main.0a1b2c3d.css".to_owned(),
+            ),
+            ("/app.js".to_owned(), "/app.99887766.js".to_owned()),
+        ]);
+        let html = r#"<link rel=stylesheet href=/styles/main.css><script src="/app.js?v=2"></script><a href='/about/'>About</a>"#;
+        assert_eq!(
+            rewrite_links(html, &urls).unwrap(),
+            r#"<link rel=stylesheet href=/styles/main.0a1b2c3d.css><script src="/app.99887766.js?v=2"></script><a href='/about/'>About</a>"#
+        );
+        assert_eq!(rewrite_links(r#"<a href="/about/">"#, &urls), None);
+    }
+
+    #[test]
+    fn test_build_fingerprint() {
+        let root = std::env::temp_dir().join("tola_fingerprint_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("assets/styles")).unwrap();
+        fs::create_dir_all(root.join("public/styles")).unwrap();
+        fs::write(root.join("assets/styles/main.css"), "body{}").unwrap();
+        fs::write(root.join("public/styles/main.css"), "body{}").unwrap();
+        fs::write(
+            root.join("public/index.html"),
+            r#"<link href="/styles/main.css">"#,
+        )
+        .unwrap();
+
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        config.build.assets = root.join("assets");
+        config.build.output = root.join("public");
+        config.build.fingerprint.enable = true;
+        config.build.fingerprint.manifest = root.join("public/manifest.json");
+        build_fingerprint(&config).unwrap();
+
+        let manifest = read_manifest(&config.build.fingerprint.manifest);
+        let hashed = &manifest["styles/main.css"];
+        assert!(hashed.starts_with("styles/main.") && hashed.ends_with(".css"));
+        assert_eq!(hashed.len(), "styles/main..css".len() + HASH_LEN);
+        assert!(root.join("public").join(hashed).is_file());
+        assert!(root.join("public/styles/main.css").is_file());
+        let index = fs::read_to_string(root.join("public/index.html")).unwrap();
+        assert_eq!(index, format!(r#"<link href="/{hashed}">"#));
+
+        // A changed asset moves the links of pages written for the old copy
+        fs::write(root.join("public/styles/main.css"), "body{color:red}").unwrap();
+        build_fingerprint(&config).unwrap();
+        let new_hashed = &read_manifest(&config.build.fingerprint.manifest)["styles/main.css"];
+        assert_ne!(new_hashed, hashed);
+        let index = fs::read_to_string(root.join("public/index.html")).unwrap();
+        assert_eq!(index, format!(r#"<link href="/{new_hashed}">"#));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
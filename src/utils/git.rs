@@ -35,8 +35,29 @@ pub fn open_repo(root: &Path) -> Result<ThreadSafeRepository> {
     Ok(repo.into_sync())
 }
 
+/// Commit time of `HEAD` in the repository containing `path`, in seconds since the Unix epoch
+pub fn head_commit_time(path: &Path) -> Result<i64> {
+    let repo = gix::discover(path)?;
+    let time = repo.head_commit()?.time()?;
+    Ok(time.seconds)
+}
+
+/// Author (and committer) of a commit, instead of the git config identity
+#[derive(Debug, Clone, Copy)]
+pub struct Author<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
 /// Commit all changes in the repository
-pub fn commit_all(repo: &ThreadSafeRepository, message: &str) -> Result<()> {
+///
+/// `{date}`, `{datetime}` and `{count}` (number of committed files) in
+/// `message` are replaced.
+pub fn commit_all(
+    repo: &ThreadSafeRepository,
+    message: &str,
+    author: Option<Author>,
+) -> Result<()> {
     if message.trim().is_empty() {
         bail!("Commit message cannot be empty");
     }
@@ -49,6 +70,7 @@ pub fn commit_all(repo: &ThreadSafeRepository, message: &str) -> Result<()> {
     let mut index = State::new(repo_local.object_hash());
     let tree = TreeBuilder::new(repo, &gitignore_patterns).build_from_dir(root, &mut index)?;
     index.sort_entries();
+    let message = expand_message(message, index.entries().len(), chrono::Utc::now());
 
     // Write index file
     let mut index_file = gix::index::File::from_state(index, repo_local.index_path());
@@ -57,14 +79,37 @@ pub fn commit_all(repo: &ThreadSafeRepository, message: &str) -> Result<()> {
     // Create commit
     let tree_id = repo_local.write_object(&tree)?;
     let parent_ids = get_parent_commit_ids(repo)?;
-    let commit_id = repo_local.commit("HEAD", message, tree_id, parent_ids)?;
+    let commit_id = match author {
+        Some(author) => {
+            let signature = gix::actor::Signature {
+                name: author.name.into(),
+                email: author.email.into(),
+                time: gix::date::Time::now_local_or_utc(),
+            };
+            let mut time = gix::date::parse::TimeBuf::default();
+            let signature = signature.to_ref(&mut time);
+            repo_local.commit_as(signature, signature, "HEAD", message, tree_id, parent_ids)?
+        }
+        None => repo_local.commit("HEAD", message, tree_id, parent_ids)?,
+    };
 
     log!("commit"; "created commit `{commit_id}` in repo `{}`", root.display());
     Ok(())
 }
 
+/// Replace the placeholders of a commit message
+fn expand_message(message: &str, count: usize, now: chrono::DateTime<chrono::Utc>) -> String {
+    message
+        .replace(
+            "{datetime}",
+            &now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        )
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{count}", &count.to_string())
+}
+
 /// Push commits to remote repository
-pub fn push(repo: &ThreadSafeRepository, config: &'static SiteConfig) -> Result<()> {
+pub fn push(repo: &ThreadSafeRepository, config: &SiteConfig) -> Result<()> {
     let github = &config.deploy.github;
     log!("git"; "pushing to `{}`", github.url);
 
@@ -338,3 +383,27 @@ fn get_parent_commit_ids(repo: &ThreadSafeRepository) -> Result<Vec<gix::ObjectI
 
     Ok(parent_ids)
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_expand_message() {
+        let now = chrono::Utc.with_ymd_and_hms(2024, 6, 15, 8, 30, 0).unwrap();
+        assert_eq!(
+            expand_message("deploy {date}: {count} files", 42, now),
+            "deploy 2024-06-15: 42 files"
+        );
+        assert_eq!(
+            expand_message("at {datetime}", 0, now),
+            "at 2024-06-15T08:30:00Z"
+        );
+        assert_eq!(expand_message("deploy it", 1, now), "deploy it");
+    }
+}
@@ -0,0 +1,280 @@
+//! HTTP requests to hosting provider APIs.
+//!
+//! tola has no TLS stack of its own, so requests go through `curl`. Each
+//! request is passed to curl as a config on stdin, which keeps API tokens out
+//! of the process list; request bodies are staged in a temporary file.
+
+use crate::{
+    log,
+    utils::exec::{pipe, to_cmd_vec},
+};
+use anyhow::{Context, Result, bail};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Command (and arguments) sending the requests; reads its config from stdin
+pub const CURL: [&str; 3] = ["curl", "--config", "-"];
+
+/// Separates the response body from the status code in curl's output
+const STATUS_MARKER: &str = "\n%tola-status:";
+
+/// Number of staged request bodies, for unique file names
+static BODIES: AtomicUsize = AtomicUsize::new(0);
+
+/// An HTTP request, built with chained calls
+#[derive(Debug, Clone)]
+pub struct Request {
+    method: &'static str,
+    url: String,
+    headers: Vec<String>,
+    body: Option<Body>,
+}
+
+#[derive(Debug, Clone)]
+enum Body {
+    Bytes(Vec<u8>),
+    Form(Vec<FormField>),
+}
+
+/// A field of a `multipart/form-data` body
+#[derive(Debug, Clone)]
+pub enum FormField {
+    Text(String, String),
+    File(String, PathBuf),
+}
+
+/// Status and body of a response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: &'static str, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new("POST", url)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push(format!("{name}: {value}"));
+        self
+    }
+
+    pub fn bearer(self, token: &str) -> Self {
+        self.header("Authorization", &format!("Bearer {token}"))
+    }
+
+    pub fn json(self, value: &impl Serialize) -> Result<Self> {
+        let body = serde_json::to_vec(value)?;
+        Ok(self.header("Content-Type", "application/json").bytes(body))
+    }
+
+    /// Raw body; set `Content-Type` with [`Request::header`]
+    pub fn bytes(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(Body::Bytes(body));
+        self
+    }
+
+    pub fn form(mut self, fields: Vec<FormField>) -> Self {
+        self.body = Some(Body::Form(fields));
+        self
+    }
+
+    /// Send the request once
+    ///
+    /// Fails only if no response was received; HTTP error statuses are returned.
+    pub fn send(&self) -> Result<Response> {
+        let staged = match &self.body {
+            Some(Body::Bytes(bytes)) => {
+                let n = BODIES.fetch_add(1, Ordering::Relaxed);
+                let path =
+                    std::env::temp_dir().join(format!("tola-http-{}-{n}", std::process::id()));
+                fs::write(&path, bytes)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Some(path)
+            }
+            _ => None,
+        };
+
+        let config = self.curl_config(staged.as_ref());
+        let output = pipe(None, &to_cmd_vec(CURL), &[], config.as_bytes());
+        if let Some(path) = &staged {
+            let _ = fs::remove_file(path);
+        }
+        let output = output.with_context(|| format!("{} {} failed", self.method, self.url))?;
+        parse_output(output)
+    }
+
+    /// Send the request, retrying up to `retries` times with growing delays
+    /// while it fails without a response, is rate limited or hits a server error
+    pub fn send_with_retry(&self, retries: usize) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let result = self.send();
+            let reason = match &result {
+                Ok(response) if response.is_transient() => format!("HTTP {}", response.status),
+                Err(err) => format!("{err:#}"),
+                Ok(_) => return result,
+            };
+            if attempt == retries {
+                return result;
+            }
+            attempt += 1;
+            let delay = Duration::from_secs(1 << attempt.min(5));
+            log!("http"; "{} {}: {reason}, retry {attempt}/{retries} in {}s", self.method, self.url, delay.as_secs());
+            thread::sleep(delay);
+        }
+    }
+
+    /// curl config sending this request, with the body read from `staged`
+    fn curl_config(&self, staged: Option<&PathBuf>) -> String {
+        let mut config = String::from("silent\nshow-error\nlocation\n");
+        let mut option = |name: &str, value: &str| {
+            let _ = writeln!(config, "{name} = {}", quote(value));
+        };
+        option("request", self.method);
+        option("url", &self.url);
+        for header in &self.headers {
+            option("header", header);
+        }
+        if let Some(path) = staged {
+            option("data-binary", &format!("@{}", path.display()));
+        }
+        if let Some(Body::Form(fields)) = &self.body {
+            for field in fields {
+                match field {
+                    FormField::Text(name, value) => {
+                        option("form-string", &format!("{name}={value}"))
+                    }
+                    FormField::File(name, path) => {
+                        option("form", &format!("{name}=@{}", path.display()))
+                    }
+                }
+            }
+        }
+        option("write-out", &format!("{STATUS_MARKER}%{{http_code}}"));
+        config
+    }
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Rate limited or a server error: worth retrying
+    pub fn is_transient(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).with_context(|| {
+            format!(
+                "Unexpected response (HTTP {}):\n{}",
+                self.status,
+                self.text()
+            )
+        })
+    }
+}
+
+/// Quote a value for a curl config file
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Split curl's output into the body and the status written after it
+fn parse_output(mut output: Vec<u8>) -> Result<Response> {
+    let marker = STATUS_MARKER.as_bytes();
+    let Some(at) = output
+        .windows(marker.len())
+        .rposition(|window| window == marker)
+    else {
+        bail!("No status in curl output");
+    };
+    let status = std::str::from_utf8(&output[at + marker.len()..])?
+        .trim()
+        .parse()
+        .context("Invalid status in curl output")?;
+    output.truncate(at);
+    Ok(Response {
+        status,
+        body: output,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curl_config() {
+        let request = Request::post("https://api.example.com/x?a=1")
+            .bearer("se\"cret")
+            .form(vec![
+                FormField::Text("manifest".into(), r#"{"/":"abc"}"#.into()),
+                FormField::File("_redirects".into(), PathBuf::from("/out/_redirects")),
+            ]);
+        let config = request.curl_config(None);
+        assert!(config.starts_with("silent\nshow-error\nlocation\nrequest = \"POST\"\n"));
+        assert!(config.contains("url = \"https://api.example.com/x?a=1\"\n"));
+        assert!(config.contains("header = \"Authorization: Bearer se\\\"cret\"\n"));
+        assert!(config.contains(r#"form-string = "manifest={\"/\":\"abc\"}""#));
+        assert!(config.contains("form = \"_redirects=@/out/_redirects\"\n"));
+        assert!(config.ends_with("write-out = \"\\n%tola-status:%{http_code}\"\n"));
+
+        let config = Request::get("https://x").curl_config(Some(&PathBuf::from("/tmp/b")));
+        assert!(config.contains("data-binary = \"@/tmp/b\"\n"));
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let response = parse_output(b"{\"ok\":true}\n%tola-status:201".to_vec()).unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, b"{\"ok\":true}");
+        assert!(response.is_success());
+        assert!(!response.is_transient());
+        assert!(parse_output(b"no status".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Image encoding queue.
+//!
+//! Pages hand the images extracted from them to a fixed set of encoder threads
+//! (`[build.threads] images`) through a bounded queue, instead of encoding
+//! them on the page's own thread. A page only blocks while the queue is full,
+//! so SVG-heavy sites keep at most that many encodings running at once.
+//! [`wait`] returns once every queued image is written.
+
+use crate::{config::SiteConfig, utils::pool};
+use anyhow::{Result, anyhow};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, OnceLock,
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread,
+};
+
+/// Queued jobs per encoder thread before pages block
+const QUEUE_DEPTH: usize = 4;
+
+type Job = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Jobs not finished yet and the errors of the finished ones
+#[derive(Default)]
+struct State {
+    pending: usize,
+    errors: Vec<anyhow::Error>,
+}
+
+static QUEUE: OnceLock<SyncSender<Job>> = OnceLock::new();
+static STATE: Mutex<State> = Mutex::new(State {
+    pending: 0,
+    errors: Vec::new(),
+});
+static FINISHED: Condvar = Condvar::new();
+
+fn state_lock() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Queue an encoding job, blocking while the queue is full
+pub fn submit(config: &SiteConfig, job: impl FnOnce() -> Result<()> + Send + 'static) {
+    state_lock().pending += 1;
+    // Workers never exit, so the channel stays open
+    let _ = queue(config).send(Box::new(job));
+}
+
+/// Wait until every queued job has finished, returning the first error
+pub fn wait() -> Result<()> {
+    let mut state = state_lock();
+    while state.pending > 0 {
+        state = FINISHED.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    let mut errors = std::mem::take(&mut state.errors).into_iter();
+    match errors.next() {
+        Some(first) => Err(first.context(format!("{} image(s) failed", errors.len() + 1))),
+        None => Ok(()),
+    }
+}
+
+/// Sender of the queue, starting the encoder threads on first use
+fn queue(config: &SiteConfig) -> &'static SyncSender<Job> {
+    QUEUE.get_or_init(|| {
+        let workers = pool::resolve_size(config.build.threads.images);
+        let (sender, receiver) = sync_channel::<Job>(workers * QUEUE_DEPTH);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("tola-images-{i}"))
+                .spawn(move || work(&receiver))
+                .expect("failed to spawn image encoder threads");
+        }
+        sender
+    })
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = receiver.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let Ok(job) = job else { return };
+        let result = catch_unwind(AssertUnwindSafe(job))
+            .unwrap_or_else(|_| Err(anyhow!("image encoder panicked")));
+
+        let mut state = state_lock();
+        if let Err(err) = result {
+            state.errors.push(err);
+        }
+        state.pending -= 1;
+        if state.pending == 0 {
+            FINISHED.notify_all();
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_submit_and_wait() {
+        let config = SiteConfig::default();
+        let done = Arc::new(AtomicUsize::new(0));
+        for _ in 0..20 {
+            let done = Arc::clone(&done);
+            submit(&config, move || {
+                done.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        }
+        submit(&config, || Err(anyhow!("broken")));
+        let err = wait().unwrap_err();
+        assert_eq!(done.load(Ordering::SeqCst), 20);
+        assert!(format!("{err:#}").contains("broken"));
+
+        // Errors are reported once
+        wait().unwrap();
+    }
+}
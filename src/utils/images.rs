@@ -0,0 +1,138 @@
+//! Image attributes (`[build.images]`).
+//!
+//! Adds `loading="lazy"`, `decoding="async"` and the dimensions of the image
+//! file to `<img>` elements, so pages do not shift as their images load.
+
+use crate::config::SiteConfig;
+use crate::utils::theme;
+use crate::utils::xml::{is_asset_link, is_external_link};
+use quick_xml::events::BytesStart;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+/// `elem` with the `[build.images.lazy]` attributes it lacks
+///
+/// `src` must not be processed yet: it is resolved the way typst wrote it,
+/// against the assets or the directory of `source`.
+pub fn with_lazy_attributes<'e>(
+    elem: Cow<'e, BytesStart<'e>>,
+    source: Option<&Path>,
+    config: &SiteConfig,
+) -> Cow<'e, BytesStart<'e>> {
+    let lazy = &config.build.images.lazy;
+    if !lazy.enable {
+        return elem;
+    }
+
+    let has = |name: &str| elem.try_get_attribute(name).ok().flatten().is_some();
+    let mut added: Vec<(&str, String)> = Vec::new();
+    for (name, value) in [("loading", "lazy"), ("decoding", "async")] {
+        if !has(name) {
+            added.push((name, value.to_owned()));
+        }
+    }
+    if lazy.dimensions && !has("width") && !has("height") {
+        let size = elem
+            .try_get_attribute("src")
+            .ok()
+            .flatten()
+            .and_then(|src| src.unescape_value().ok().map(Cow::into_owned))
+            .and_then(|src| image_file(&src, source, config))
+            .and_then(|path| imagesize::size(path).ok());
+        if let Some(size) = size {
+            added.push(("width", size.width.to_string()));
+            added.push(("height", size.height.to_string()));
+        }
+    }
+    if added.is_empty() {
+        return elem;
+    }
+
+    let mut elem = elem.into_owned();
+    for (name, value) in &added {
+        elem.push_attribute((*name, value.as_str()));
+    }
+    Cow::Owned(elem)
+}
+
+/// File an unprocessed `src` points to, if it is local
+///
+/// | `src` (in `content/posts/a.typ`) | File |
+/// |----------------------------------|------|
+/// | `/images/b.png` | `assets/images/b.png`, or the theme's |
+/// | `/posts/b.png` | `content/posts/b.png` |
+/// | `b.png` | `content/posts/b.png` |
+fn image_file(src: &str, source: Option<&Path>, config: &SiteConfig) -> Option<PathBuf> {
+    if is_external_link(src) || src.starts_with("//") {
+        return None;
+    }
+    let path = src.split(['?', '#']).next().unwrap_or_default();
+    let path = urlencoding::decode(path).ok()?;
+
+    let file = match path.strip_prefix('/') {
+        Some(relative) if is_asset_link(&path, config) => {
+            let file = config.build.assets.join(relative);
+            match theme::assets_dir(config) {
+                Some(theme_assets) if !file.exists() => theme_assets.join(relative),
+                _ => file,
+            }
+        }
+        Some(relative) => config.build.content.join(relative),
+        None => source?.parent()?.join(path.as_ref()),
+    };
+    file.is_file().then_some(file)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resvg::tiny_skia::Pixmap;
+    use std::fs;
+
+    fn attribute(elem: &BytesStart<'_>, name: &str) -> Option<String> {
+        let value = elem.try_get_attribute(name).unwrap()?.value;
+        Some(String::from_utf8(value.into_owned()).unwrap())
+    }
+
+    #[test]
+    fn test_with_lazy_attributes() {
+        let dir = std::env::temp_dir().join("tola_images_lazy_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("posts")).unwrap();
+        let png = Pixmap::new(3, 2).unwrap().encode_png().unwrap();
+        fs::write(dir.join("posts/a.png"), png).unwrap();
+
+        let mut config = SiteConfig::default();
+        config.build.content = dir.clone();
+        config.build.images.lazy.enable = true;
+        let source = dir.join("posts/page.typ");
+
+        let elem = BytesStart::from_content(r#"img src="a.png" loading="eager""#, 3);
+        let elem = with_lazy_attributes(Cow::Borrowed(&elem), Some(&source), &config);
+        assert_eq!(attribute(&elem, "loading").as_deref(), Some("eager"));
+        assert_eq!(attribute(&elem, "decoding").as_deref(), Some("async"));
+        assert_eq!(attribute(&elem, "width").as_deref(), Some("3"));
+        assert_eq!(attribute(&elem, "height").as_deref(), Some("2"));
+
+        // Missing and external images only get the loading attributes
+        for src in ["missing.png", "https://example.com/a.png"] {
+            let elem = BytesStart::from_content(format!(r#"img src="{src}""#), 3);
+            let elem = with_lazy_attributes(Cow::Borrowed(&elem), Some(&source), &config);
+            assert_eq!(attribute(&elem, "loading").as_deref(), Some("lazy"));
+            assert_eq!(attribute(&elem, "width"), None);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_disabled() {
+        let config = SiteConfig::default();
+        let elem = BytesStart::from_content(r#"img src="a.png""#, 3);
+        let elem = with_lazy_attributes(Cow::Borrowed(&elem), None, &config);
+        assert!(matches!(elem, Cow::Borrowed(_)));
+    }
+}
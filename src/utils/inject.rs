@@ -0,0 +1,486 @@
+//! Snippets injected into pages from configuration.
+//!
+//! | Snippet    | Config               | Position                    |
+//! |------------|----------------------|-----------------------------|
+//! | Analytics  | `[build.analytics]`  | end of `<head>` or `<body>` |
+//! | Comments   | `[build.comments]`   | end of `<body>`             |
+//! | PWA        | `[build.pwa]`        | end of `<head>`             |
+//! | Webmention | `[build.webmention]` | `<head>`, `<body>` of posts |
+
+use crate::{
+    config::{
+        AnalyticsConfig, AnalyticsProvider, CommentsConfig, CommentsProvider, SiteConfig,
+        WebmentionConfig,
+    },
+    utils::{meta::PageMeta, pwa, site, svg::HtmlContext, xml::write_element_with_processed_links},
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::io::Cursor;
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Write snippets that go right before `</head>`.
+pub fn write_head_end(writer: &mut XmlWriter, config: &SiteConfig) -> Result<()> {
+    if config.build.pwa.enable {
+        write_pwa(writer, config)?;
+    }
+    if config.build.webmention.enable {
+        write_webmention_links(writer, &config.build.webmention)?;
+    }
+    if wants_analytics(config) && config.build.analytics.provider != AnalyticsProvider::GoatCounter
+    {
+        write_analytics(writer, config)?;
+    }
+    Ok(())
+}
+
+/// Write the `<body>` start tag, marking posts as `h-entry`.
+pub fn write_body_start(
+    elem: &BytesStart<'_>,
+    writer: &mut XmlWriter,
+    ctx: &HtmlContext<'_>,
+) -> Result<()> {
+    if wants_h_entry(ctx.config, ctx.meta) {
+        writer.write_event(Event::Start(with_class(elem, "h-entry")?))?;
+        Ok(())
+    } else {
+        write_element_with_processed_links(elem, writer, ctx.config, ctx.source)
+    }
+}
+
+/// Write snippets that go right before `</body>`.
+pub fn write_body_end(writer: &mut XmlWriter, ctx: &HtmlContext<'_>) -> Result<()> {
+    let (config, meta) = (ctx.config, ctx.meta);
+    if let Some(meta) = meta.filter(|_| wants_h_entry(config, meta)) {
+        write_h_entry_properties(writer, &site::page_url(ctx.html_path, config), meta)?;
+    }
+    let comments = &config.build.comments;
+    if comments.enable && wants_comments(meta) {
+        write_comments(writer, comments)?;
+    }
+    if wants_analytics(config) && config.build.analytics.provider == AnalyticsProvider::GoatCounter
+    {
+        write_analytics(writer, config)?;
+    }
+    Ok(())
+}
+
+/// Write `<script ...> </script>` with the given attributes
+fn write_script(writer: &mut XmlWriter, attributes: &[(&str, &str)]) -> Result<()> {
+    let mut script = BytesStart::new("script");
+    for &attribute in attributes {
+        script.push_attribute(attribute);
+    }
+    writer.write_event(Event::Start(script))?;
+    // Space ensures proper HTML parsing of script tags
+    writer.write_event(Event::Text(BytesText::new(" ")))?;
+    writer.write_event(Event::End(BytesEnd::new("script")))?;
+    Ok(())
+}
+
+/// Posts are pages whose metadata has a date
+fn is_post(meta: Option<&PageMeta>) -> bool {
+    meta.is_some_and(|meta| meta.date.is_some())
+}
+
+/// Whether pages are built for `tola serve`
+fn is_serving(config: &SiteConfig) -> bool {
+    config.cli.as_ref().is_some_and(|cli| cli.is_serve())
+}
+
+// ============================================================================
+// PWA
+// ============================================================================
+
+/// Write the manifest link, theme color and service worker registration
+fn write_pwa(writer: &mut XmlWriter, config: &SiteConfig) -> Result<()> {
+    let mut link = BytesStart::new("link");
+    link.push_attribute(("rel", "manifest"));
+    link.push_attribute(("href", pwa::site_url(pwa::MANIFEST, config).as_str()));
+    writer.write_event(Event::Empty(link))?;
+
+    let mut meta = BytesStart::new("meta");
+    meta.push_attribute(("name", "theme-color"));
+    meta.push_attribute(("content", config.build.pwa.theme_color.as_str()));
+    writer.write_event(Event::Empty(meta))?;
+
+    // A service worker would keep serving stale pages while editing
+    if !is_serving(config) {
+        let worker = pwa::site_url(pwa::SERVICE_WORKER, config);
+        let script = format!(
+            r#"if ("serviceWorker" in navigator) navigator.serviceWorker.register("{worker}");"#
+        );
+        writer.write_event(Event::Start(BytesStart::new("script")))?;
+        writer.write_event(Event::Text(BytesText::from_escaped(script)))?;
+        writer.write_event(Event::End(BytesEnd::new("script")))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Webmention
+// ============================================================================
+
+fn wants_h_entry(config: &SiteConfig, meta: Option<&PageMeta>) -> bool {
+    let webmention = &config.build.webmention;
+    webmention.enable && webmention.h_entry && is_post(meta)
+}
+
+/// Write `<link rel="webmention">` and `<link rel="pingback">`
+fn write_webmention_links(writer: &mut XmlWriter, webmention: &WebmentionConfig) -> Result<()> {
+    let endpoints = [
+        ("webmention", &webmention.endpoint),
+        ("pingback", &webmention.pingback),
+    ];
+    for (rel, href) in endpoints {
+        if let Some(href) = href {
+            let mut link = BytesStart::new("link");
+            link.push_attribute(("rel", rel));
+            link.push_attribute(("href", href.as_str()));
+            writer.write_event(Event::Empty(link))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy of a start tag with `class` appended to its classes
+fn with_class(elem: &BytesStart<'_>, class: &str) -> Result<BytesStart<'static>> {
+    let mut new_elem = BytesStart::new(String::from_utf8_lossy(elem.name().as_ref()).into_owned());
+    let mut classes = None;
+    for attr in elem.attributes() {
+        let attr = attr?;
+        if attr.key.as_ref() == b"class" {
+            classes = Some(format!("{} {class}", attr.unescape_value()?));
+        } else {
+            new_elem.push_attribute(attr);
+        }
+    }
+    let classes = classes.unwrap_or_else(|| class.to_owned());
+    new_elem.push_attribute(("class", classes.as_str()));
+    Ok(new_elem)
+}
+
+/// Write hidden `h-entry` properties: URL, name, published date and author
+fn write_h_entry_properties(writer: &mut XmlWriter, url: &str, meta: &PageMeta) -> Result<()> {
+    let mut block = BytesStart::new("div");
+    block.push_attribute(("class", "tola-h-entry"));
+    block.push_attribute(("hidden", ""));
+    writer.write_event(Event::Start(block))?;
+
+    let properties = [
+        ("a", "u-url", "href", Some(url)),
+        ("data", "p-name", "value", meta.title.as_deref()),
+        ("time", "dt-published", "datetime", meta.date.as_deref()),
+        ("time", "dt-updated", "datetime", meta.update.as_deref()),
+    ];
+    for (tag, class, key, value) in properties {
+        if let Some(value) = value {
+            let mut elem = BytesStart::new(tag);
+            elem.push_attribute(("class", class));
+            elem.push_attribute((key, value));
+            writer.write_event(Event::Start(elem))?;
+            writer.write_event(Event::End(BytesEnd::new(tag)))?;
+        }
+    }
+    if let Some(author) = &meta.author {
+        let mut elem = BytesStart::new("span");
+        elem.push_attribute(("class", "p-author h-card"));
+        writer.write_event(Event::Start(elem))?;
+        writer.write_event(Event::Text(BytesText::new(author)))?;
+        writer.write_event(Event::End(BytesEnd::new("span")))?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("div")))?;
+    Ok(())
+}
+
+// ============================================================================
+// Analytics
+// ============================================================================
+
+/// Analytics is skipped during `tola serve` unless `[build.analytics] serve` is set
+fn wants_analytics(config: &SiteConfig) -> bool {
+    let analytics = &config.build.analytics;
+    analytics.enable && (analytics.serve || !is_serving(config))
+}
+
+/// Script attributes for the configured provider
+fn analytics_attributes(config: &SiteConfig) -> Vec<(&'static str, String)> {
+    let analytics = &config.build.analytics;
+    let src = |default: &str| analytics.src.clone().unwrap_or_else(|| default.into());
+    match analytics.provider {
+        AnalyticsProvider::Plausible => vec![
+            ("defer", String::new()),
+            ("data-domain", plausible_domain(analytics, config)),
+            ("src", src("https://plausible.io/js/script.js")),
+        ],
+        AnalyticsProvider::GoatCounter => vec![
+            (
+                "data-goatcounter",
+                format!("https://{}.goatcounter.com/count", analytics.code),
+            ),
+            ("async", String::new()),
+            ("src", src("https://gc.zgo.at/count.js")),
+        ],
+        AnalyticsProvider::Umami => vec![
+            ("defer", String::new()),
+            ("src", src("")),
+            ("data-website-id", analytics.website_id.clone()),
+        ],
+        AnalyticsProvider::Custom => vec![("defer", String::new()), ("src", src(""))],
+    }
+}
+
+/// Write the analytics `<script>`
+fn write_analytics(writer: &mut XmlWriter, config: &SiteConfig) -> Result<()> {
+    let attributes = analytics_attributes(config);
+    let attributes: Vec<_> = attributes.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    write_script(writer, &attributes)
+}
+
+/// `[build.analytics] domain`, or the host of `[base.url]`
+fn plausible_domain(analytics: &AnalyticsConfig, config: &SiteConfig) -> String {
+    if let Some(domain) = &analytics.domain {
+        return domain.clone();
+    }
+    let url = config.base.url.as_deref().unwrap_or_default();
+    let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+    host.split('/').next().unwrap_or_default().to_owned()
+}
+
+// ============================================================================
+// Comments
+// ============================================================================
+
+/// Posts get comments unless their metadata says otherwise
+fn wants_comments(meta: Option<&PageMeta>) -> bool {
+    meta.is_some_and(|m| m.comments.unwrap_or(is_post(meta)))
+}
+
+/// Script attributes for the configured provider
+fn comments_attributes(comments: &CommentsConfig) -> Vec<(&'static str, &str)> {
+    match comments.provider {
+        CommentsProvider::Giscus => vec![
+            ("src", "https://giscus.app/client.js"),
+            ("data-repo", &comments.repo),
+            ("data-repo-id", &comments.repo_id),
+            ("data-category", &comments.category),
+            ("data-category-id", &comments.category_id),
+            ("data-mapping", &comments.mapping),
+            ("data-reactions-enabled", "1"),
+            ("data-input-position", "bottom"),
+            (
+                "data-theme",
+                comments
+                    .theme
+                    .as_deref()
+                    .unwrap_or("preferred_color_scheme"),
+            ),
+            ("crossorigin", "anonymous"),
+        ],
+        CommentsProvider::Utterances => vec![
+            ("src", "https://utteranc.es/client.js"),
+            ("repo", &comments.repo),
+            ("issue-term", &comments.mapping),
+            (
+                "theme",
+                comments
+                    .theme
+                    .as_deref()
+                    .unwrap_or("preferred-color-scheme"),
+            ),
+            ("crossorigin", "anonymous"),
+        ],
+    }
+}
+
+/// Write `<section class="tola-comments"><script ...></script></section>`
+fn write_comments(writer: &mut XmlWriter, comments: &CommentsConfig) -> Result<()> {
+    let mut section = BytesStart::new("section");
+    section.push_attribute(("class", "tola-comments"));
+    writer.write_event(Event::Start(section))?;
+
+    let mut attributes = comments_attributes(comments);
+    attributes.push(("async", ""));
+    write_script(writer, &attributes)?;
+
+    writer.write_event(Event::End(BytesEnd::new("section")))?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn body_end(config: &SiteConfig, meta: Option<&PageMeta>) -> String {
+        let ctx = HtmlContext::new(config, Path::new("/public/index.html"), meta);
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_body_end(&mut writer, &ctx).unwrap();
+        String::from_utf8(writer.into_inner().into_inner()).unwrap()
+    }
+
+    fn post() -> PageMeta {
+        PageMeta {
+            date: Some("2024-01-01".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_wants_comments() {
+        assert!(wants_comments(Some(&post())));
+        assert!(!wants_comments(Some(&PageMeta::default())));
+        assert!(!wants_comments(None));
+
+        let opted_out = PageMeta {
+            comments: Some(false),
+            ..post()
+        };
+        assert!(!wants_comments(Some(&opted_out)));
+        let opted_in = PageMeta {
+            comments: Some(true),
+            ..Default::default()
+        };
+        assert!(wants_comments(Some(&opted_in)));
+    }
+
+    #[test]
+    fn test_write_utterances() {
+        let mut config = SiteConfig::default();
+        config.build.comments.enable = true;
+        config.build.comments.provider = CommentsProvider::Utterances;
+        config.build.comments.repo = "user/comments".into();
+
+        let html = body_end(&config, Some(&post()));
+        assert!(html.starts_with(r#"<section class="tola-comments"><script src="https://utteranc.es/client.js" repo="user/comments" issue-term="pathname""#));
+        assert!(html.ends_with("</script></section>"));
+    }
+
+    fn head_end(config: &SiteConfig) -> String {
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_head_end(&mut writer, config).unwrap();
+        String::from_utf8(writer.into_inner().into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_write_plausible() {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://blog.example.com/sub/".into());
+        config.build.analytics.enable = true;
+
+        assert_eq!(
+            head_end(&config),
+            r#"<script defer="" data-domain="blog.example.com" src="https://plausible.io/js/script.js"> </script>"#
+        );
+        assert_eq!(body_end(&config, None), "");
+    }
+
+    #[test]
+    fn test_write_goatcounter_at_body_end() {
+        let mut config = SiteConfig::default();
+        config.build.analytics.enable = true;
+        config.build.analytics.provider = AnalyticsProvider::GoatCounter;
+        config.build.analytics.code = "myblog".into();
+
+        assert_eq!(head_end(&config), "");
+        assert_eq!(
+            body_end(&config, None),
+            r#"<script data-goatcounter="https://myblog.goatcounter.com/count" async="" src="https://gc.zgo.at/count.js"> </script>"#
+        );
+    }
+
+    #[test]
+    fn test_analytics_skipped_during_serve() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        let mut config = SiteConfig::default();
+        config.build.analytics.enable = true;
+        config.build.analytics.provider = AnalyticsProvider::Custom;
+        config.build.analytics.src = Some("https://stats.example.com/s.js".into());
+        config.cli = Some(Cli::parse_from(["tola", "serve"]));
+        assert_eq!(head_end(&config), "");
+
+        config.build.analytics.serve = true;
+        assert_eq!(
+            head_end(&config),
+            r#"<script defer="" src="https://stats.example.com/s.js"> </script>"#
+        );
+    }
+
+    #[test]
+    fn test_write_pwa() {
+        let mut config = SiteConfig::default();
+        config.build.pwa.enable = true;
+
+        let html = head_end(&config);
+        assert!(html.starts_with(
+            r##"<link rel="manifest" href="/manifest.webmanifest"/><meta name="theme-color" content="#ffffff"/>"##
+        ));
+        assert!(html.contains(r#"navigator.serviceWorker.register("/sw.js")"#));
+    }
+
+    #[test]
+    fn test_write_webmention_links() {
+        let mut config = SiteConfig::default();
+        config.build.webmention.enable = true;
+        config.build.webmention.endpoint = Some("https://wm.example/webmention".into());
+
+        assert_eq!(
+            head_end(&config),
+            r#"<link rel="webmention" href="https://wm.example/webmention"/>"#
+        );
+    }
+
+    #[test]
+    fn test_write_body_start_h_entry() {
+        let mut config = SiteConfig::default();
+        config.build.webmention.enable = true;
+        let elem = BytesStart::from_content(r#"body class="dark""#, 4);
+
+        let write = |meta: Option<&PageMeta>| {
+            let ctx = HtmlContext::new(&config, Path::new("/public/index.html"), meta);
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            write_body_start(&elem, &mut writer, &ctx).unwrap();
+            String::from_utf8(writer.into_inner().into_inner()).unwrap()
+        };
+        assert_eq!(write(Some(&post())), r#"<body class="dark h-entry">"#);
+        assert_eq!(write(None), r#"<body class="dark">"#);
+    }
+
+    #[test]
+    fn test_write_h_entry_properties() {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com".into());
+        config.build.output = "/public".into();
+        config.build.webmention.enable = true;
+        let meta = PageMeta {
+            title: Some("Hello".into()),
+            author: Some("Alice".into()),
+            ..post()
+        };
+
+        assert_eq!(
+            body_end(&config, Some(&meta)),
+            concat!(
+                r#"<div class="tola-h-entry" hidden=""><a class="u-url" href="https://example.com/index.html"></a>"#,
+                r#"<data class="p-name" value="Hello"></data><time class="dt-published" datetime="2024-01-01"></time>"#,
+                r#"<span class="p-author h-card">Alice</span></div>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_disabled_writes_nothing() {
+        assert_eq!(body_end(&SiteConfig::default(), Some(&post())), "");
+    }
+}
@@ -0,0 +1,387 @@
+//! Link validation of the generated site.
+//!
+//! Scans every HTML file in the output directory once everything is written,
+//! and checks that each internal `href`/`src` resolves to an output file and
+//! each `#fragment` to an element `id` in the target page. External links are
+//! only requested if `[build.link_check] external` is set.
+
+use crate::{
+    config::{LinkCheckLevel, SiteConfig},
+    exec, log,
+    utils::{
+        build::collect_files,
+        report,
+        site::{self, tag_attribute},
+    },
+};
+use anyhow::anyhow;
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Seconds to wait for an external link
+const EXTERNAL_TIMEOUT: &str = "10";
+
+/// Links and element ids of one output page
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Document {
+    /// `href`/`src` values, with entities decoded
+    links: Vec<String>,
+    /// `id`s (and `<a name>`s) usable as fragments
+    ids: HashSet<String>,
+}
+
+/// A link as classified by [`classify`]
+#[derive(Debug, PartialEq, Eq)]
+enum Link<'a> {
+    /// Path (root-relative or relative to the page) and fragment within the site
+    Internal { path: &'a str, fragment: &'a str },
+    /// `http(s)` URL of another site
+    External(String),
+    /// Other schemes (`mailto:`, `data:`, ...) and empty links
+    Skip,
+}
+
+/// Check the links of every output page if `[build.link_check]` is enabled
+///
+/// Broken links are recorded in the build report against the page holding
+/// them (its source, if it was compiled), as warnings or errors depending on
+/// `level`.
+pub fn check_links(config: &SiteConfig) -> anyhow::Result<()> {
+    let link_check = &config.build.link_check;
+    if !link_check.enable {
+        return Ok(());
+    }
+
+    let output = &config.build.output;
+    let git_dir = output.join(".git");
+    let files = collect_files(output, |path| {
+        !path.starts_with(&git_dir) && path.extension().is_some_and(|ext| ext == "html")
+    });
+    let documents: BTreeMap<PathBuf, Document> = files
+        .into_par_iter()
+        .filter_map(|path| {
+            let html = fs::read(&path).ok()?;
+            Some((path, Document::parse(&html)))
+        })
+        .collect();
+
+    let base_url = config.base.url.as_deref().unwrap_or_default();
+    let mut broken: Vec<(&Path, &str, String)> = Vec::new();
+    let mut external: BTreeMap<String, Vec<(&Path, &str)>> = BTreeMap::new();
+    let mut checked = 0;
+    for (page, document) in &documents {
+        for link in &document.links {
+            checked += 1;
+            match classify(link, base_url) {
+                Link::Skip => {}
+                Link::External(url) => {
+                    if link_check.external {
+                        external.entry(url).or_default().push((page, link));
+                    }
+                }
+                Link::Internal { path, fragment } => {
+                    if let Err(reason) = check_internal(page, path, fragment, &documents, config) {
+                        broken.push((page, link, reason));
+                    }
+                }
+            }
+        }
+    }
+
+    let failures: Vec<(&String, String)> = external
+        .par_iter()
+        .filter_map(|(url, _)| {
+            // A ranged GET, since some servers refuse HEAD requests
+            exec!(&link_check.command;
+                "-sSfL", "-o", "/dev/null", "-r", "0-0", "--max-time", EXTERNAL_TIMEOUT, url)
+            .err()
+            .map(|err| (url, last_line(&format!("{err:#}"))))
+        })
+        .collect();
+    for (url, reason) in failures {
+        for &(page, link) in &external[url] {
+            broken.push((page, link, reason.clone()));
+        }
+    }
+
+    // Compiled pages are reported against their source file
+    let sources: HashMap<PathBuf, PathBuf> = site::pages()
+        .into_iter()
+        .map(|page| (page.output, page.source))
+        .collect();
+    let pages: HashSet<&Path> = broken.iter().map(|(page, ..)| *page).collect();
+    for (page, link, reason) in &broken {
+        let page = sources.get(*page).map_or(*page, PathBuf::as_path);
+        let message = format!("broken link `{link}`: {reason}");
+        match link_check.level {
+            LinkCheckLevel::Warn => report::warning(page, message),
+            LinkCheckLevel::Error => report::error(page, &anyhow!(message)),
+        }
+    }
+
+    log!("links"; "{checked} link(s) checked, {} broken in {} page(s)", broken.len(), pages.len());
+    Ok(())
+}
+
+fn last_line(message: &str) -> String {
+    message.lines().last().unwrap_or(message).to_owned()
+}
+
+impl Document {
+    /// Collect links and ids from the start tags of `html`
+    ///
+    /// Scripts and styles are skipped, since `<` in them does not start a tag.
+    fn parse(html: &[u8]) -> Self {
+        let html = String::from_utf8_lossy(html);
+        let mut document = Self::default();
+        let mut rest = html.as_ref();
+
+        while let Some(start) = rest.find('<') {
+            let Some(end) = rest[start..].find('>') else {
+                break;
+            };
+            let tag = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+
+            let name_len = tag
+                .find(|c: char| c.is_whitespace() || c == '/')
+                .unwrap_or(tag.len());
+            let name = tag[..name_len].to_ascii_lowercase();
+            if name.is_empty() || name.starts_with('!') {
+                continue;
+            }
+            if name == "script" || name == "style" {
+                let close = format!("</{name}");
+                let skip = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                rest = &rest[skip..];
+            }
+
+            for attribute in ["href", "src"] {
+                if let Some(value) = tag_attribute(tag, attribute) {
+                    document.links.push(decode_entities(value));
+                }
+            }
+            let anchor_name = (name == "a").then(|| tag_attribute(tag, "name")).flatten();
+            for id in tag_attribute(tag, "id").into_iter().chain(anchor_name) {
+                document.ids.insert(decode_entities(id));
+            }
+        }
+        document
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    quick_xml::escape::unescape(value)
+        .map(|value| value.into_owned())
+        .unwrap_or_else(|_| value.replace("&amp;", "&"))
+}
+
+/// Tell internal links from external ones, treating `base_url` as internal
+fn classify<'a>(link: &'a str, base_url: &str) -> Link<'a> {
+    let link = link.trim();
+    let base_url = base_url.trim_end_matches('/');
+    let link =
+        match link.strip_prefix(base_url) {
+            Some(rest)
+                if !base_url.is_empty()
+                    && (rest.is_empty() || rest.starts_with(['/', '?', '#'])) =>
+            {
+                if rest.is_empty() { "/" } else { rest }
+            }
+            _ => link,
+        };
+
+    if link.is_empty() || link == "#" {
+        return Link::Skip;
+    }
+    if let Some(rest) = link.strip_prefix("//") {
+        return Link::External(format!("https://{rest}"));
+    }
+    let scheme_end = link.find(':');
+    let path_start = link.find(['/', '?', '#']).unwrap_or(link.len());
+    if let Some(end) = scheme_end.filter(|&end| end < path_start) {
+        let scheme = link[..end].to_ascii_lowercase();
+        return match scheme.as_str() {
+            "http" | "https" => Link::External(link.to_owned()),
+            _ => Link::Skip,
+        };
+    }
+
+    let (link, fragment) = link.split_once('#').unwrap_or((link, ""));
+    let path = link.split_once('?').map_or(link, |(path, _)| path);
+    Link::Internal { path, fragment }
+}
+
+/// Check that `path` (as linked from `page`) is an output file holding `fragment`
+fn check_internal(
+    page: &Path,
+    path: &str,
+    fragment: &str,
+    documents: &BTreeMap<PathBuf, Document>,
+    config: &SiteConfig,
+) -> Result<(), String> {
+    let target = match path {
+        "" => Some(page.to_owned()),
+        _ => resolve(page, path, config),
+    };
+    let Some(target) = target else {
+        return Err("no such file in the output".into());
+    };
+    if fragment.is_empty() {
+        return Ok(());
+    }
+
+    // Only HTML pages have ids to check against
+    let Some(document) = documents.get(&target) else {
+        return Ok(());
+    };
+    let fragment =
+        urlencoding::decode(fragment).map_or_else(|_| fragment.into(), |f| f.into_owned());
+    match document.ids.contains(&fragment) {
+        true => Ok(()),
+        false => Err(format!("no element with id `{fragment}`")),
+    }
+}
+
+/// Output file served for `path`, if any
+///
+/// Root-relative paths are tried both with and without `[build] base_path`;
+/// directories resolve to their `index.html`.
+fn resolve(page: &Path, path: &str, config: &SiteConfig) -> Option<PathBuf> {
+    let output = &config.build.output;
+    let decoded = urlencoding::decode(path).ok()?;
+    let candidates = match decoded.strip_prefix('/') {
+        Some(relative) => {
+            let mut candidates = vec![output.join(relative)];
+            if let Ok(stripped) = Path::new(relative).strip_prefix(&config.build.base_path)
+                && !config.build.base_path.as_os_str().is_empty()
+            {
+                candidates.push(output.join(stripped));
+            }
+            candidates
+        }
+        None => vec![page.parent()?.join(decoded.as_ref())],
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|candidate| normalize(&candidate))
+        .filter(|candidate| candidate.starts_with(output))
+        .find_map(|candidate| match candidate.is_dir() {
+            true => Some(candidate.join("index.html")).filter(|index| index.is_file()),
+            false => candidate.is_file().then_some(candidate),
+        })
+}
+
+/// Resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_document() {
+        let html = br#"<html><head><style>a<b{}</style><script>if (a<b) {}</script></head>
+            <body><h2 id="intro">Intro</h2><a name=top></a>
+            <a href="/posts/?a=1&amp;b=2#intro">x</a><img src=logo.png><div data-id="no"></div></body></html>"#;
+        let document = Document::parse(html);
+        assert_eq!(document.links, ["/posts/?a=1&b=2#intro", "logo.png"]);
+        assert_eq!(document.ids, HashSet::from(["intro".into(), "top".into()]));
+    }
+
+    #[test]
+    fn test_classify() {
+        let base = "https://example.com/";
+        assert_eq!(
+            classify("/a/#b", base),
+            Link::Internal {
+                path: "/a/",
+                fragment: "b"
+            }
+        );
+        assert_eq!(
+            classify("https://example.com/a?x=1", base),
+            Link::Internal {
+                path: "/a",
+                fragment: ""
+            }
+        );
+        assert_eq!(
+            classify("#top", base),
+            Link::Internal {
+                path: "",
+                fragment: "top"
+            }
+        );
+        assert_eq!(
+            classify("https://example.community/", base),
+            Link::External("https://example.community/".into())
+        );
+        assert_eq!(
+            classify("//cdn.example.org/x.js", base),
+            Link::External("https://cdn.example.org/x.js".into())
+        );
+        assert_eq!(classify("mailto:me@example.com", base), Link::Skip);
+        assert_eq!(classify("#", base), Link::Skip);
+    }
+
+    #[test]
+    fn test_check_internal() {
+        let output = std::env::temp_dir().join("tola_link_check_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts/hello")).unwrap();
+        fs::write(output.join("posts/hello/index.html"), "").unwrap();
+        fs::write(output.join("style.css"), "").unwrap();
+        let page = output.join("index.html");
+        let hello = output.join("posts/hello/index.html");
+
+        let mut config = SiteConfig::default();
+        config.build.output = output.clone();
+        config.build.base_path = PathBuf::from("blog");
+        let documents = BTreeMap::from([
+            (page.clone(), Document::parse(br#"<h1 id="top"></h1>"#)),
+            (
+                hello.clone(),
+                Document::parse(r#"<h2 id="café"></h2>"#.as_bytes()),
+            ),
+        ]);
+        let check = |path, fragment| check_internal(&page, path, fragment, &documents, &config);
+
+        assert_eq!(check("/posts/hello/", ""), Ok(()));
+        assert_eq!(check("/blog/posts/hello", "caf%C3%A9"), Ok(()));
+        assert_eq!(check("posts/hello/index.html", ""), Ok(()));
+        assert_eq!(check("./style.css", "ignored"), Ok(()));
+        assert_eq!(check("", "top"), Ok(()));
+        assert!(check("/posts/missing/", "").is_err());
+        assert!(check("../outside.html", "").is_err());
+        assert_eq!(
+            check("/posts/hello/", "nope"),
+            Err("no element with id `nope`".into())
+        );
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
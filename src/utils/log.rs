@@ -1,73 +1,328 @@
 //! Logging utilities with colored output.
 //!
-//! Provides a log macro and functions for formatted terminal output.
+//! Provides leveled log macros with per-module filters, and functions for
+//! formatted terminal output (optionally with timestamps and task durations).
 
+use crate::{config::LogConfig, utils::progress};
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
 use colored::{ColoredString, Colorize};
 use crossterm::{
     execute,
     terminal::{Clear, ClearType, size},
 };
+use serde::{Deserialize, Serialize};
 use std::{
-    io::{Write, stdout},
-    sync::OnceLock,
+    collections::HashMap,
+    io::{IsTerminal, Write, stderr, stdout},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 /// Cached terminal width (only fetched once)
 static TERMINAL_WIDTH: OnceLock<u16> = OnceLock::new();
 
-fn get_terminal_width() -> u16 {
+pub fn get_terminal_width() -> u16 {
     *TERMINAL_WIDTH.get_or_init(|| size().map(|(w, _)| w).unwrap_or(120))
 }
 
+/// Whether stdout is not a terminal (CI, pipes), cached on first use
+static PLAIN_OUTPUT: OnceLock<bool> = OnceLock::new();
+
+/// Check if output should be plain lines without colors, clears or `\r` overwrites
+pub fn is_plain() -> bool {
+    *PLAIN_OUTPUT.get_or_init(|| !stdout().is_terminal())
+}
+
 /// Modules that use carriage return instead of newline (for progress display)
 const INLINE_MODULES: &[&str] = &["content", "assets", "svg"];
 
+// ============================================================================
+// Levels and Filters
+// ============================================================================
+
+/// Log verbosity, ordered from quietest to noisiest.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Disable output entirely.
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+/// Global level from the CLI plus per-module overrides.
+#[derive(Debug, Default)]
+struct LogFilter {
+    level: Option<LogLevel>,
+    modules: HashMap<String, LogLevel>,
+}
+
+static FILTER: OnceLock<LogFilter> = OnceLock::new();
+
+/// Level from `[log] level`, used when the CLI sets none
+static CONFIG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Initialize the global log filter.
+///
+/// Without a `level`, the one set by [`configure`] applies.
+/// `module_filters` entries have the form `module=level` (e.g. `svg=off`, `typst=debug`).
+pub fn init(level: Option<LogLevel>, module_filters: &[String]) -> Result<()> {
+    let modules = module_filters
+        .iter()
+        .map(|spec| parse_module_filter(spec))
+        .collect::<Result<_>>()?;
+
+    if is_plain() {
+        colored::control::set_override(false);
+    }
+
+    FILTER
+        .set(LogFilter { level, modules })
+        .map_err(|_| anyhow!("Logger already initialized"))
+}
+
+/// Parse a `module=level` filter spec.
+fn parse_module_filter(spec: &str) -> Result<(String, LogLevel)> {
+    let (module, level) = spec
+        .split_once('=')
+        .with_context(|| format!("Invalid log filter `{spec}`, expected `module=level`"))?;
+    let level = LogLevel::from_str(level.trim(), true)
+        .map_err(|_| anyhow!("Invalid log level `{level}` in filter `{spec}`"))?;
+    Ok((module.trim().to_ascii_lowercase(), level))
+}
+
+/// Check whether a message at `level` from `module` should be printed.
+#[inline]
+pub fn enabled(level: LogLevel, module: &str) -> bool {
+    let filter = FILTER.get_or_init(LogFilter::default);
+    let max = filter
+        .modules
+        .get(&module.to_ascii_lowercase())
+        .copied()
+        .or(filter.level)
+        .unwrap_or_else(config_level);
+    level != LogLevel::Off && level <= max
+}
+
+/// Level set by `[log] level`
+fn config_level() -> LogLevel {
+    LogLevel::value_variants()[CONFIG_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+/// Level implied by the module name for plain `log!` calls.
+#[inline]
+pub fn default_level(module: &str) -> LogLevel {
+    match module.to_ascii_lowercase().as_str() {
+        "error" => LogLevel::Error,
+        "warn" => LogLevel::Warn,
+        _ => LogLevel::Info,
+    }
+}
+
+// ============================================================================
+// Format
+// ============================================================================
+
+/// Prefix lines with wall-clock time (`[log] timestamps`)
+static TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+
+/// Log elapsed time per task (`[log] durations`)
+static DURATIONS: AtomicBool = AtomicBool::new(false);
+
+/// Apply the `[log]` section of the site config.
+pub fn configure(config: &LogConfig) {
+    TIMESTAMPS.store(config.timestamps, Ordering::Relaxed);
+    DURATIONS.store(config.durations, Ordering::Relaxed);
+    CONFIG_LEVEL.store(config.level as u8, Ordering::Relaxed);
+}
+
+/// Format a duration compactly (e.g. `123ms`, `1.42s`)
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+/// Format a byte count compactly (e.g. `512B`, `1.5KB`, `2.3MB`)
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    match bytes {
+        b if b < KB => format!("{b}B"),
+        b if b < MB => format!("{:.1}KB", b as f64 / KB as f64),
+        b => format!("{:.1}MB", b as f64 / MB as f64),
+    }
+}
+
+/// Suffix with the time elapsed since `started`, or empty if durations are disabled.
+pub fn duration_suffix(started: Instant) -> String {
+    if DURATIONS.load(Ordering::Relaxed) {
+        format!(" {}", format_duration(started.elapsed()))
+    } else {
+        String::new()
+    }
+}
+
+/// A timed unit of work (a page or an asset).
+///
+/// Logs `name` when started, or `name 123ms` when finished if durations are enabled.
+pub struct Task {
+    module: &'static str,
+    name: String,
+    newline: bool,
+    started: Instant,
+}
+
+impl Task {
+    pub fn start(module: &'static str, name: impl Into<String>, newline: bool) -> Self {
+        let name = name.into();
+        if !DURATIONS.load(Ordering::Relaxed) {
+            crate::log!(newline; module; "{name}");
+        }
+        Self {
+            module,
+            name,
+            newline,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if DURATIONS.load(Ordering::Relaxed) {
+            let suffix = duration_suffix(self.started);
+            crate::log!(self.newline; self.module; "{}{suffix}", self.name);
+        }
+    }
+}
+
+/// Current local time for log line prefixes, if timestamps are enabled
+fn timestamp() -> Option<String> {
+    TIMESTAMPS
+        .load(Ordering::Relaxed)
+        .then(|| chrono::Local::now().format("%H:%M:%S%.3f").to_string())
+}
+
+// ============================================================================
+// Macros
+// ============================================================================
+
 #[macro_export]
 macro_rules! log {
     ($newline:expr; $module:expr; $($arg:tt)*) => {{
-        $crate::utils::log::log($module, &format!($($arg)*), $newline)
+        let level = $crate::utils::log::default_level($module);
+        if $crate::utils::log::enabled(level, $module) {
+            $crate::utils::log::log($module, &format!($($arg)*), $newline)
+        }
+    }};
+    ($module:expr; $($arg:tt)*) => {{
+        let level = $crate::utils::log::default_level($module);
+        if $crate::utils::log::enabled(level, $module) {
+            $crate::utils::log::log($module, &format!($($arg)*), false)
+        }
+    }};
+}
+
+/// Log at debug level (hidden unless enabled via `--log-level` or a module filter).
+#[macro_export]
+macro_rules! debug {
+    ($module:expr; $($arg:tt)*) => {{
+        if $crate::utils::log::enabled($crate::utils::log::LogLevel::Debug, $module) {
+            $crate::utils::log::log($module, &format!($($arg)*), true)
+        }
     }};
+}
+
+/// Log at trace level (hidden unless enabled via `--log-level` or a module filter).
+#[macro_export]
+macro_rules! trace {
     ($module:expr; $($arg:tt)*) => {{
-        $crate::utils::log::log($module, &format!($($arg)*), false)
+        if $crate::utils::log::enabled($crate::utils::log::LogLevel::Trace, $module) {
+            $crate::utils::log::log($module, &format!($($arg)*), true)
+        }
     }};
 }
 
+// ============================================================================
+// Output
+// ============================================================================
+
+/// Write a log line; errors go to stderr, everything else to stdout.
 #[inline]
 pub fn log(module: &str, message: &str, force_newline: bool) {
+    let timestamp = timestamp();
     let module_lower = module.to_ascii_lowercase();
+    let is_error = module_lower == "error";
+
+    if is_plain() {
+        let ts = timestamp.map(|t| t + " ").unwrap_or_default();
+        match is_error {
+            true => writeln!(stderr().lock(), "{ts}[{module}] {message}").ok(),
+            false => writeln!(stdout().lock(), "{ts}[{module}] {message}").ok(),
+        };
+        return;
+    }
+
     let use_newline = force_newline || !INLINE_MODULES.contains(&module_lower.as_str());
 
+    // Inline messages of a stage with an active progress bar become its status text
+    if !use_newline && progress::set_message(&module_lower, message) {
+        return;
+    }
+
     let prefix = colorize_prefix(module, &module_lower);
     let width = get_terminal_width() as usize;
+    let ts = timestamp.map(|t| format!("{t} ")).unwrap_or_default();
 
-    let mut stdout = stdout().lock();
-    execute!(stdout, Clear(ClearType::UntilNewLine)).ok();
-
-    // Write prefix and message, truncating if needed
-    let prefix_len = module.len() + 3; // "[module] "
-    let max_msg_len = width.saturating_sub(prefix_len + 1);
+    progress::suspend(|stdout| {
+        execute!(stdout, Clear(ClearType::UntilNewLine)).ok();
 
-    if message.len() <= max_msg_len {
-        if use_newline {
-            writeln!(stdout, "{prefix} {message}").ok();
-        } else {
-            write!(stdout, "{prefix} {message}\r").ok();
+        // Errors are written in full, below the cleared progress bars
+        if is_error {
+            stdout.flush().ok();
+            writeln!(stderr().lock(), "{}{prefix} {message}", ts.dimmed()).ok();
+            return;
         }
-    } else {
-        // Truncate message (byte-safe for ASCII, char-safe for Unicode)
-        let truncated = truncate_str(message, max_msg_len);
-        if use_newline {
-            writeln!(stdout, "{prefix} {truncated}").ok();
+
+        write!(stdout, "{}", ts.dimmed()).ok();
+
+        // Write prefix and message, truncating if needed
+        let prefix_len = ts.len() + module.len() + 3; // "[module] "
+        let max_msg_len = width.saturating_sub(prefix_len + 1);
+
+        if message.len() <= max_msg_len {
+            if use_newline {
+                writeln!(stdout, "{prefix} {message}").ok();
+            } else {
+                write!(stdout, "{prefix} {message}\r").ok();
+            }
         } else {
-            write!(stdout, "{prefix} {truncated}\r").ok();
+            // Truncate message (byte-safe for ASCII, char-safe for Unicode)
+            let truncated = truncate_str(message, max_msg_len);
+            if use_newline {
+                writeln!(stdout, "{prefix} {truncated}").ok();
+            } else {
+                write!(stdout, "{prefix} {truncated}\r").ok();
+            }
         }
-    }
-
-    stdout.flush().ok();
+    });
 }
 
 #[inline]
-fn colorize_prefix(module: &str, module_lower: &str) -> ColoredString {
+pub fn colorize_prefix(module: &str, module_lower: &str) -> ColoredString {
     let prefix = format!("[{module}]");
     match module_lower {
         "serve" => prefix.bright_blue().bold(),
@@ -79,7 +334,7 @@ fn colorize_prefix(module: &str, module_lower: &str) -> ColoredString {
 
 /// Truncate string to max bytes, ensuring valid UTF-8 boundary
 #[inline]
-fn truncate_str(s: &str, max_len: usize) -> &str {
+pub fn truncate_str(s: &str, max_len: usize) -> &str {
     if s.len() <= max_len {
         return s;
     }
@@ -90,3 +345,72 @@ fn truncate_str(s: &str, max_len: usize) -> &str {
     }
     &s[..end]
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_filter() {
+        assert_eq!(
+            parse_module_filter("svg=off").unwrap(),
+            ("svg".to_string(), LogLevel::Off)
+        );
+        assert_eq!(
+            parse_module_filter(" Typst = DEBUG ").unwrap(),
+            ("typst".to_string(), LogLevel::Debug)
+        );
+    }
+
+    #[test]
+    fn test_parse_module_filter_invalid() {
+        assert!(parse_module_filter("svg").is_err());
+        assert!(parse_module_filter("svg=loud").is_err());
+    }
+
+    #[test]
+    fn test_level_ordering() {
+        assert!(LogLevel::Error < LogLevel::Warn);
+        assert!(LogLevel::Info < LogLevel::Debug);
+        assert!(LogLevel::Debug < LogLevel::Trace);
+    }
+
+    #[test]
+    fn test_config_level() {
+        for level in LogLevel::value_variants() {
+            CONFIG_LEVEL.store(*level as u8, Ordering::Relaxed);
+            assert_eq!(config_level(), *level);
+        }
+        CONFIG_LEVEL.store(LogLevel::Info as u8, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_default_level() {
+        assert_eq!(default_level("error"), LogLevel::Error);
+        assert_eq!(default_level("WARN"), LogLevel::Warn);
+        assert_eq!(default_level("content"), LogLevel::Info);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(123)), "123ms");
+        assert_eq!(format_duration(Duration::from_millis(1420)), "1.42s");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512B");
+        assert_eq!(format_size(1536), "1.5KB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0MB");
+    }
+
+    #[test]
+    fn test_truncate_str_unicode_boundary() {
+        assert_eq!(truncate_str("你好", 4), "你");
+        assert_eq!(truncate_str("hello", 10), "hello");
+    }
+}
@@ -0,0 +1,669 @@
+//! Page metadata.
+//!
+//! Reads the `<tola-meta>` label of a Typst page and converts it into
+//! [`PageMeta`], shared by all generators through the site model. Pages are
+//! compiled with their metadata written into the HTML, so reading it needs no
+//! second typst process.
+
+use crate::{
+    config::SiteConfig,
+    exec,
+    utils::{cache, reproducible, xml::create_xml_reader},
+};
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Tag name for querying typst metadata
+pub const META_TAG_NAME: &str = "<tola-meta>";
+
+// ============================================================================
+// Page Metadata
+// ============================================================================
+
+/// Metadata declared by a page via `#metadata(..) <tola-meta>`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageMeta {
+    /// Also the page's `<title>`, instead of `[base] title`
+    pub title: Option<String>,
+    /// `<meta name="description">` of the page, instead of `[base] description`
+    pub description: Option<String>,
+    /// Summary rendered to HTML, with relative links made absolute
+    pub summary: Option<String>,
+    pub date: Option<String>,
+    pub update: Option<String>,
+    pub author: Option<String>,
+    /// Per-page comments toggle (`comments: true/false`)
+    pub comments: Option<bool>,
+    /// Terms of each `[build.taxonomies]` name the page declares
+    pub taxonomies: BTreeMap<String, Vec<String>>,
+    /// Posts the page lists, for `[build.pagination]`
+    pub paginate: Option<Paginate>,
+    /// Draft pages are only built with `--drafts`
+    pub draft: bool,
+    /// Old paths redirecting to the page, for `[build.redirects]`
+    pub redirects: Vec<String>,
+    /// Raw HTML added to the page's `<head>` after `[build.head] elements`
+    /// (`head_extra`: a string or a list of them)
+    pub head_extra: Vec<String>,
+    /// Words in the page content, for `[build.reading_time]`
+    pub word_count: Option<usize>,
+    /// Minutes to read the page content, for `[build.reading_time]`
+    pub reading_time: Option<usize>,
+}
+
+/// Posts listed by a paginated page (`paginate: true` or `paginate: "posts"`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Paginate {
+    /// Every post of the site
+    All,
+    /// Posts in one top-level content directory
+    Section(String),
+}
+
+// ============================================================================
+// Typst Element Parsing
+// ============================================================================
+
+/// Represents parsed Typst content elements
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "func", rename_all = "lowercase")]
+enum TypstElement {
+    Space,
+    Linebreak,
+    Text {
+        text: String,
+    },
+    /// Struck-through `text`, or `body` as typst writes it
+    Strike {
+        #[serde(default)]
+        text: String,
+        #[serde(default)]
+        body: Option<Box<TypstElement>>,
+    },
+    Link {
+        dest: String,
+        body: Box<TypstElement>,
+    },
+    Strong {
+        body: Box<TypstElement>,
+    },
+    Emph {
+        body: Box<TypstElement>,
+    },
+    Highlight {
+        body: Box<TypstElement>,
+    },
+    /// Inline or block code
+    Raw {
+        text: String,
+        #[serde(default)]
+        block: bool,
+        #[serde(default)]
+        lang: Option<String>,
+    },
+    /// A quote typst turns into an opening or closing one by context
+    Smartquote {
+        #[serde(default = "default_true")]
+        double: bool,
+    },
+    /// Content with set rules applied, such as `#text(red)[..]`
+    Styled {
+        child: Box<TypstElement>,
+    },
+    Sequence {
+        children: Vec<TypstElement>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Whether a quote after `html` opens: at the start, after whitespace or an
+/// opening bracket or line break, looking through opening tags such as `<em>`
+fn opens_quote(html: &str) -> bool {
+    let mut before = html;
+    while let Some(rest) = before.strip_suffix('>') {
+        match rest.rfind('<') {
+            // `<br/>`
+            Some(_) if rest.ends_with('/') => return true,
+            Some(start) if !rest[start..].starts_with("</") => before = &rest[..start],
+            _ => return false,
+        }
+    }
+    before
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace() || "([{".contains(c))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl TypstElement {
+    /// Convert Typst element to HTML string
+    fn to_html(&self, base_url: &str) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html, base_url);
+        html
+    }
+
+    /// Append the HTML of the element to `html`, which holds everything
+    /// before it (smart quotes open or close depending on it)
+    fn write_html(&self, html: &mut String, base_url: &str) {
+        let wrap = |html: &mut String, tag: &str, body: &TypstElement| {
+            html.push_str(&format!("<{tag}>"));
+            body.write_html(html, base_url);
+            html.push_str(&format!("</{tag}>"));
+        };
+        match self {
+            Self::Space => html.push(' '),
+            Self::Linebreak => html.push_str("<br/>"),
+            Self::Text { text } => html.push_str(&html_escape(text)),
+            Self::Strike {
+                body: Some(body), ..
+            } => wrap(html, "s", body),
+            Self::Strike { text, body: None } => {
+                html.push_str(&format!("<s>{}</s>", html_escape(text)))
+            }
+            Self::Link { dest, body } => {
+                let href = normalize_link(dest, base_url);
+                html.push_str(&format!("<a href=\"{href}\">"));
+                body.write_html(html, base_url);
+                html.push_str("</a>");
+            }
+            Self::Strong { body } => wrap(html, "strong", body),
+            Self::Emph { body } => wrap(html, "em", body),
+            Self::Highlight { body } => wrap(html, "mark", body),
+            Self::Raw { text, block, lang } => {
+                let code = match lang {
+                    Some(lang) => format!(
+                        "<code class=\"language-{}\">{}</code>",
+                        html_escape(lang),
+                        html_escape(text)
+                    ),
+                    None => format!("<code>{}</code>", html_escape(text)),
+                };
+                match block {
+                    true => html.push_str(&format!("<pre>{code}</pre>")),
+                    false => html.push_str(&code),
+                }
+            }
+            Self::Smartquote { double } => {
+                let opening = opens_quote(html);
+                html.push(match (double, opening) {
+                    (true, true) => '“',
+                    (true, false) => '”',
+                    (false, true) => '‘',
+                    (false, false) => '’',
+                });
+            }
+            Self::Styled { child } => child.write_html(html, base_url),
+            Self::Sequence { children } => {
+                for child in children {
+                    child.write_html(html, base_url);
+                }
+            }
+            Self::Unknown => {}
+        }
+    }
+}
+
+/// Escape HTML special characters
+#[inline]
+fn html_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Normalize relative links to absolute URLs
+#[inline]
+fn normalize_link(dest: &str, base_url: &str) -> String {
+    if dest.starts_with(['.', '/']) {
+        let path = dest.trim_start_matches(['.', '/']);
+        format!("{}/{}", base_url.trim_end_matches('/'), path)
+    } else {
+        dest.to_string()
+    }
+}
+
+// ============================================================================
+// Metadata Extraction
+// ============================================================================
+
+/// `name` of the `<meta>` element a page's `<tola-meta>` is compiled into
+const META_ELEMENT_NAME: &str = "tola-meta";
+
+/// Cached JSON of a page without metadata
+const NO_META: &str = "null";
+
+/// Typst source compiling the page `path` with its `<tola-meta>` written into
+/// the HTML as `<meta name="tola-meta" content="{json}">`, so the compile that
+/// renders a page also yields its metadata (see [`embedded_meta`]).
+///
+/// A page that writes its own `<html>` element must place `<tola-meta>` inside it.
+pub fn compile_source(path: &Path, config: &SiteConfig) -> Result<String> {
+    let root = config.get_root();
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path
+            .canonicalize()?
+            .strip_prefix(root.canonicalize()?)?
+            .to_path_buf(),
+    };
+    let mut include = String::new();
+    for component in relative.components() {
+        if let Component::Normal(name) = component {
+            include.push('/');
+            include.push_str(&name.to_string_lossy());
+        }
+    }
+    let include = include.replace('\\', "\\\\").replace('"', "\\\"");
+    Ok(format!(
+        r#"#show {META_TAG_NAME}: it => html.elem("meta", attrs: (name: "{META_ELEMENT_NAME}", content: json.encode(it.value, pretty: false)))
+#include "{include}"
+"#
+    ))
+}
+
+/// Metadata JSON of a page compiled from [`compile_source`]; `None` if the
+/// page declares none
+pub fn embedded_meta(html: &[u8]) -> Option<String> {
+    let mut reader = create_xml_reader(html);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(elem) | Event::Empty(elem) if is_meta_element(&elem) => {
+                let content = elem.try_get_attribute("content").ok()??;
+                return content.unescape_value().ok().map(Cow::into_owned);
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Whether `elem` is the element [`compile_source`] writes the metadata to
+pub fn is_meta_element(elem: &BytesStart<'_>) -> bool {
+    elem.name().as_ref() == b"meta"
+        && elem
+            .try_get_attribute("name")
+            .ok()
+            .flatten()
+            .is_some_and(|name| name.value.as_ref() == META_ELEMENT_NAME.as_bytes())
+}
+
+/// Metadata JSON recorded when `path` was last compiled or queried, if its
+/// content is unchanged; `Some(None)` for a page without metadata
+pub fn cached_meta(path: &Path, config: &SiteConfig) -> Option<Option<String>> {
+    let cache = cache::pages().filter(|_| !config.build.force)?;
+    let json = cache.meta(&cache_key(path, config), path)?;
+    Some(Some(json).filter(|json| json != NO_META))
+}
+
+/// Remember the metadata JSON of the current content of `path`
+pub fn record_meta(path: &Path, json: Option<&str>, config: &SiteConfig) -> Result<()> {
+    match cache::pages() {
+        Some(cache) => cache.record_meta(&cache_key(path, config), path, json.unwrap_or(NO_META)),
+        None => Ok(()),
+    }
+}
+
+fn cache_key(path: &Path, config: &SiteConfig) -> String {
+    path.strip_prefix(config.get_root())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Query metadata from a Typst page with `typst query`
+///
+/// Built pages get their metadata from their compile instead; this is for
+/// sources exported otherwise, such as PDFs. The result is cached like the
+/// metadata of compiled pages.
+pub fn query_meta(path: &Path, config: &SiteConfig) -> Result<PageMeta> {
+    if let Some(json) = cached_meta(path, config) {
+        let json = json
+            .with_context(|| format!("No {META_TAG_NAME} metadata in page: {}", path.display()))?;
+        return parse_meta(&json, config);
+    }
+
+    let root = config.get_root();
+    let [timestamp_flag, timestamp] = reproducible::typst_args(config);
+    let output = exec!(
+        &config.build.typst.command;
+        "query", "--features", "html", "--format", "json",
+        "--font-path", root, "--root", root,
+        "--input", config.typst_site_input(),
+        timestamp_flag, timestamp,
+        path,
+        META_TAG_NAME, "--field", "value", "--one"
+    )
+    .with_context(|| {
+        format!(
+            "Failed to query metadata for page: {}\nEnsure tag name \"{}\" is correct",
+            path.display(),
+            META_TAG_NAME
+        )
+    })?;
+
+    let json_str = std::str::from_utf8(&output.stdout)?;
+    let meta = parse_meta(json_str, config)?;
+    if !config.build.force {
+        record_meta(path, Some(json_str), config)?;
+    }
+    Ok(meta)
+}
+
+/// Parse page metadata from JSON string
+pub fn parse_meta(json_str: &str, config: &SiteConfig) -> Result<PageMeta> {
+    let json: serde_json::Value = serde_json::from_str(json_str)
+        .with_context(|| format!("Failed to parse page metadata JSON:\n{json_str}"))?;
+
+    let get_string = |key: &str| json.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    // Parse summary from Typst element
+    let base_url = config.base.url.as_deref().unwrap_or_default();
+    let summary = get_string("summary")
+        .and_then(|s| parse_typst_element(&s).ok())
+        .map(|elem| elem.to_html(base_url));
+
+    // Taxonomy terms and redirects are a single string or a list of them
+    let get_strings = |key: &str| -> Option<Vec<String>> {
+        match json.get(key)? {
+            serde_json::Value::String(term) => Some(vec![term.clone()]),
+            serde_json::Value::Array(terms) => Some(
+                terms
+                    .iter()
+                    .filter_map(|term| term.as_str().map(String::from))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    };
+    let mut taxonomies = BTreeMap::new();
+    for name in &config.build.taxonomies.names {
+        if let Some(terms) = get_strings(name) {
+            taxonomies.insert(name.clone(), terms);
+        }
+    }
+
+    Ok(PageMeta {
+        title: get_string("title"),
+        description: get_string("description"),
+        summary,
+        date: get_string("date"),
+        update: get_string("update"),
+        author: get_string("author"),
+        comments: json.get("comments").and_then(|v| v.as_bool()),
+        taxonomies,
+        paginate: match json.get("paginate") {
+            Some(serde_json::Value::Bool(true)) => Some(Paginate::All),
+            Some(serde_json::Value::String(section)) => Some(Paginate::Section(section.clone())),
+            _ => None,
+        },
+        draft: json
+            .get("draft")
+            .and_then(|v| v.as_bool())
+            .unwrap_or_default(),
+        redirects: get_strings("redirects").unwrap_or_default(),
+        head_extra: get_strings("head_extra").unwrap_or_default(),
+        word_count: None,
+        reading_time: None,
+    })
+}
+
+/// Parse Typst element from JSON string
+fn parse_typst_element(content: &str) -> Result<TypstElement> {
+    serde_json::from_str(content).map_err(Into::into)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meta() {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com".into());
+        let json = r#"{
+            "title": "Hello",
+            "date": "2024-06-15",
+            "author": "Alice",
+            "comments": false,
+            "description": "A greeting",
+            "head_extra": "<meta name=\"robots\" content=\"noindex\">",
+            "summary": "{ \"func\": \"link\", \"dest\": \"/about\", \"body\": { \"func\": \"text\", \"text\": \"about\" } }"
+        }"#;
+
+        let meta = parse_meta(json, &config).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Hello"));
+        assert_eq!(meta.date.as_deref(), Some("2024-06-15"));
+        assert_eq!(meta.author.as_deref(), Some("Alice"));
+        assert_eq!(meta.update, None);
+        assert_eq!(meta.comments, Some(false));
+        assert_eq!(meta.paginate, None);
+        assert!(!meta.draft);
+        assert_eq!(meta.description.as_deref(), Some("A greeting"));
+        assert_eq!(
+            meta.head_extra,
+            [r#"<meta name="robots" content="noindex">"#]
+        );
+        assert_eq!(
+            meta.summary.as_deref(),
+            Some("<a href=\"https://example.com/about\">about</a>")
+        );
+    }
+
+    #[test]
+    fn test_compile_source() {
+        let mut config = SiteConfig::default();
+        config.set_root(Path::new("/site"));
+        let source = compile_source(Path::new("/site/content/a \"b\".typ"), &config).unwrap();
+        assert!(source.starts_with("#show <tola-meta>: it => html.elem(\"meta\""));
+        assert!(source.ends_with("#include \"/content/a \\\"b\\\".typ\"\n"));
+    }
+
+    #[test]
+    fn test_embedded_meta() {
+        // As typst writes it
+        let html = br#"<!DOCTYPE html>
+<html>
+  <body>
+    <meta name="tola-meta" content="{&quot;title&quot;:&quot;Hi \&quot;there\&quot; <x> &amp; y&quot;}">
+    <p>Hello</p>
+  </body>
+</html>"#;
+        let json = embedded_meta(html).unwrap();
+        assert_eq!(json, r#"{"title":"Hi \"there\" <x> & y"}"#);
+        let meta = parse_meta(&json, &SiteConfig::default()).unwrap();
+        assert_eq!(meta.title.as_deref(), Some(r#"Hi "there" <x> & y"#));
+
+        let html = br#"<html><head><meta name="viewport" content="x"></head></html>"#;
+        assert_eq!(embedded_meta(html), None);
+    }
+
+    #[test]
+    fn test_parse_meta_taxonomies() {
+        let config = SiteConfig::default();
+        let json = r#"{ "tags": ["rust", "typst"], "categories": "notes", "series": "x", "paginate": "notes", "redirects": "/old/" }"#;
+
+        let meta = parse_meta(json, &config).unwrap();
+        assert_eq!(meta.redirects, ["/old/"]);
+        assert_eq!(meta.paginate, Some(Paginate::Section("notes".into())));
+        assert_eq!(meta.taxonomies.len(), 2);
+        assert_eq!(meta.taxonomies["tags"], ["rust", "typst"]);
+        assert_eq!(meta.taxonomies["categories"], ["notes"]);
+    }
+
+    #[test]
+    fn test_parse_element_from_typst_sequence() {
+        let json_str = r#"
+        {
+            "func": "sequence",
+            "children": [
+                { "func": "space" },
+                { "func": "text", "text": "小鹤双拼是一个简洁, 流畅, 自由的双拼输入法方案" },
+                { "func": "space" },
+                { "func": "linebreak" },
+                { "func": "space" },
+                { "func": "link", "dest": "https://example.com", "body": { "func": "text", "text": "小鹤双拼" } },
+                { "func": "text", "text": "适合想提高打字速度, 但又不想投入巨量精力进行记忆, 追求高性价比的同学" },
+                { "func": "space" },
+                { "func": "unknown_func" }
+            ]
+        }
+        "#;
+
+        let result = parse_typst_element(json_str).unwrap();
+        assert_eq!(
+            result,
+            TypstElement::Sequence {
+                children: vec![
+                    TypstElement::Space,
+                    TypstElement::Text {
+                        text: "小鹤双拼是一个简洁, 流畅, 自由的双拼输入法方案".to_string()
+                    },
+                    TypstElement::Space,
+                    TypstElement::Linebreak,
+                    TypstElement::Space,
+                    TypstElement::Link {
+                        dest: "https://example.com".to_string(),
+                        body: Box::new(TypstElement::Text {
+                            text: "小鹤双拼".to_string()
+                        }),
+                    },
+                    TypstElement::Text {
+                        text:
+                            "适合想提高打字速度, 但又不想投入巨量精力进行记忆, 追求高性价比的同学"
+                                .to_string()
+                    },
+                    TypstElement::Space,
+                    TypstElement::Unknown,
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_typst_element_text() {
+        let json = r#"{ "func": "text", "text": "Hello World" }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+        assert!(matches!(elem, TypstElement::Text { text } if text == "Hello World"));
+    }
+
+    #[test]
+    fn test_typst_element_space() {
+        let json = r#"{ "func": "space" }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+        assert!(matches!(elem, TypstElement::Space));
+    }
+
+    #[test]
+    fn test_typst_element_linebreak() {
+        let json = r#"{ "func": "linebreak" }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+        assert!(matches!(elem, TypstElement::Linebreak));
+    }
+
+    #[test]
+    fn test_typst_element_strike() {
+        let json = r#"{ "func": "strike", "text": "strikethrough" }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+        assert!(matches!(elem, TypstElement::Strike { text, .. } if text == "strikethrough"));
+    }
+
+    #[test]
+    fn test_typst_element_styling() {
+        let json = r#"{
+            "func": "sequence",
+            "children": [
+                { "func": "smartquote", "double": true },
+                { "func": "strong", "body": { "func": "emph", "body": { "func": "text", "text": "Bold" } } },
+                { "func": "smartquote", "double": true },
+                { "func": "space" },
+                { "func": "raw", "text": "a < b", "block": false, "lang": "rust" },
+                { "func": "space" },
+                { "func": "highlight", "body": { "func": "text", "text": "marked" } },
+                { "func": "space" },
+                { "func": "styled", "child": { "func": "strike", "body": { "func": "text", "text": "old" } }, "styles": "..." },
+                { "func": "text", "text": " it" },
+                { "func": "smartquote", "double": false },
+                { "func": "text", "text": "s" }
+            ]
+        }"#;
+        let elem = parse_typst_element(json).unwrap();
+        assert_eq!(
+            elem.to_html(""),
+            "“<strong><em>Bold</em></strong>” <code class=\"language-rust\">a &lt; b</code> \
+             <mark>marked</mark> <s>old</s> it’s"
+        );
+
+        let block = r#"{ "func": "raw", "text": "fn main() {}", "block": true }"#;
+        let elem = parse_typst_element(block).unwrap();
+        assert_eq!(elem.to_html(""), "<pre><code>fn main() {}</code></pre>");
+    }
+
+    #[test]
+    fn test_typst_element_link() {
+        let json = r#"{ "func": "link", "dest": "https://example.com", "body": { "func": "text", "text": "link text" } }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+
+        if let TypstElement::Link { dest, body } = elem {
+            assert_eq!(dest, "https://example.com");
+            assert!(matches!(*body, TypstElement::Text { text } if text == "link text"));
+        } else {
+            panic!("Expected Link element");
+        }
+    }
+
+    #[test]
+    fn test_typst_element_unknown_ignored() {
+        let json = r#"{ "func": "custom_unknown_func" }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+        assert!(matches!(elem, TypstElement::Unknown));
+    }
+
+    #[test]
+    fn test_typst_element_sequence() {
+        let json = r#"{
+            "func": "sequence",
+            "children": [
+                { "func": "text", "text": "Hello" },
+                { "func": "space" },
+                { "func": "text", "text": "World" }
+            ]
+        }"#;
+        let elem: TypstElement = serde_json::from_str(json).unwrap();
+
+        if let TypstElement::Sequence { children } = elem {
+            assert_eq!(children.len(), 3);
+            assert!(matches!(&children[0], TypstElement::Text { text } if text == "Hello"));
+            assert!(matches!(&children[1], TypstElement::Space));
+            assert!(matches!(&children[2], TypstElement::Text { text } if text == "World"));
+        } else {
+            panic!("Expected Sequence element");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Minification of CSS and JS assets (`[build.minify_assets]`).
+//!
+//! CSS goes through lightningcss, which can also write a source map while
+//! serving; JS through minify-js. A file that fails to parse is written as is
+//! with a warning, so a syntax the minifiers do not know never breaks a build.
+
+use crate::{
+    config::SiteConfig,
+    utils::{prune, report},
+};
+use anyhow::{Result, anyhow};
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use minify_js::{Session, TopLevelMode};
+use parcel_sourcemap::SourceMap;
+use std::{fs, path::Path};
+
+/// Whether `path` is an asset `[build.minify_assets]` minifies
+pub fn minifies(path: &Path, config: &SiteConfig) -> bool {
+    let assets = &config.build.minify_assets;
+    config.build.minify
+        && match path.extension().and_then(|ext| ext.to_str()) {
+            Some("css") => assets.css,
+            Some("js" | "mjs") => assets.js,
+            _ => false,
+        }
+}
+
+/// Whether CSS source maps are written, which changes the minified output
+pub fn writes_source_maps(config: &SiteConfig) -> bool {
+    config.build.minify_assets.source_maps && config.cli.as_ref().is_some_and(|cli| cli.is_serve())
+}
+
+/// Minified `content` of the asset `source`, to be written to `output`
+///
+/// The CSS source map, if any, is written next to `output`.
+pub fn minify_asset(
+    source: &Path,
+    content: Vec<u8>,
+    output: &Path,
+    config: &SiteConfig,
+) -> Vec<u8> {
+    let result = match source.extension().and_then(|ext| ext.to_str()) {
+        Some("css") => {
+            let source_map = writes_source_maps(config).then(|| output.with_extension("css.map"));
+            minify_css(&content, source, source_map.as_deref())
+        }
+        Some("mjs") => minify_js(&content, TopLevelMode::Module),
+        _ => minify_js(&content, TopLevelMode::Global),
+    };
+    match result {
+        Ok(minified) => minified,
+        Err(err) => {
+            report::warning(source, format!("not minified: {err}"));
+            content
+        }
+    }
+}
+
+/// Minify a stylesheet, writing its source map to `source_map` if given
+fn minify_css(content: &[u8], source: &Path, source_map: Option<&Path>) -> Result<Vec<u8>> {
+    let code = std::str::from_utf8(content)?;
+    let filename = source
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let options = ParserOptions {
+        filename: filename.clone(),
+        ..Default::default()
+    };
+    let mut sheet = StyleSheet::parse(code, options).map_err(|err| anyhow!("{err}"))?;
+    sheet
+        .minify(MinifyOptions::default())
+        .map_err(|err| anyhow!("{err}"))?;
+
+    let mut map = SourceMap::new("/");
+    map.add_source(&filename);
+    let printer = PrinterOptions {
+        minify: true,
+        source_map: source_map.is_some().then_some(&mut map),
+        ..Default::default()
+    };
+    let mut css = sheet.to_css(printer).map_err(|err| anyhow!("{err}"))?.code;
+
+    if let Some(path) = source_map {
+        map.set_source_content(0, code)
+            .map_err(|err| anyhow!("{err}"))?;
+        let json = map.to_json(None).map_err(|err| anyhow!("{err}"))?;
+        fs::write(path, json)?;
+        prune::produced(path);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        css.push_str(&format!("\n/*# sourceMappingURL={name} */"));
+    }
+    Ok(css.into_bytes())
+}
+
+fn minify_js(content: &[u8], mode: TopLevelMode) -> Result<Vec<u8>> {
+    let session = Session::new();
+    let mut output = Vec::new();
+    minify_js::minify(&session, mode, content, &mut output).map_err(|err| anyhow!("{err:?}"))?;
+    Ok(output)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_css() {
+        let css = b"body {\n  color: #ff0000;\n  margin: 0px;\n}\n";
+        let minified = minify_css(css, Path::new("main.css"), None).unwrap();
+        assert_eq!(minified, b"body{color:red;margin:0}");
+        assert!(minify_css(b"\xff", Path::new("main.css"), None).is_err());
+
+        let dir = std::env::temp_dir().join("tola_minify_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let map = dir.join("main.css.map");
+        let minified = minify_css(css, Path::new("main.css"), Some(&map)).unwrap();
+        let minified = String::from_utf8(minified).unwrap();
+        assert!(minified.ends_with("/*# sourceMappingURL=main.css.map */"));
+        let json = fs::read_to_string(&map).unwrap();
+        assert!(json.contains("main.css"));
+        assert!(json.contains("#ff0000"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_minify_asset() {
+        let mut config = SiteConfig::default();
+        let js = b"function add(first, second) {\n  return first + second;\n}\nadd(1, 2);\n";
+        let minified = minify_asset(
+            Path::new("app.js"),
+            js.to_vec(),
+            Path::new("app.js"),
+            &config,
+        );
+        assert!(minified.len() < js.len());
+
+        assert!(minifies(Path::new("a/app.mjs"), &config));
+        assert!(!minifies(Path::new("a/logo.svg"), &config));
+        config.build.minify_assets.js = false;
+        assert!(!minifies(Path::new("a/app.js"), &config));
+        assert!(minifies(Path::new("a/main.css"), &config));
+        config.build.minify = false;
+        assert!(!minifies(Path::new("a/main.css"), &config));
+    }
+}
@@ -1,11 +1,56 @@
 //! Utility modules for the static site generator.
 
+pub mod api;
+pub mod archive;
+pub mod authors;
+pub mod backlinks;
+pub mod blogroll;
 pub mod build;
+pub mod cache;
+pub mod deps;
+pub mod diff;
 pub mod exec;
+pub mod fingerprint;
 pub mod git;
+pub mod http;
+pub mod image_queue;
+pub mod images;
+pub mod inject;
+pub mod link_check;
 pub mod log;
+pub mod meta;
+pub mod minify;
+pub mod nav;
+pub mod opml;
+pub mod pagination;
+pub mod panic;
+pub mod plugin;
+pub mod pool;
+pub mod precompress;
+pub mod progress;
+pub mod prune;
+pub mod purge;
+pub mod pwa;
+pub mod reading_time;
+pub mod redirects;
+pub mod report;
+pub mod reproducible;
+pub mod rewrite;
 pub mod rss;
+pub mod sanitize;
+pub mod search;
+pub mod sections;
+pub mod single_file;
+pub mod site;
+pub mod sitemap;
 pub mod slug;
+pub mod stats;
 pub mod svg;
+pub mod taxonomies;
+pub mod text;
+pub mod theme;
+pub mod toc;
+pub mod typst;
 pub mod watch;
+pub mod webmention;
 pub mod xml;
@@ -0,0 +1,189 @@
+//! Previous/next post navigation.
+//!
+//! Links every post to its chronological neighbours in the same section. The
+//! links depend on all other posts, so they are added to the written pages once
+//! the content pass has recorded every page in the site model.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        rss::DateTimeUtc,
+        site::{self, Page, section},
+    },
+};
+use anyhow::{Context, Result};
+use quick_xml::escape::escape;
+use std::{collections::BTreeMap, fs};
+
+/// Start of the injected element; also used to find it in pages built before
+const NAV_START: &str = r#"<nav class="tola-post-nav">"#;
+const NAV_END: &str = "</nav>";
+
+/// Neighbours of a post: the older and the newer one
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Neighbours<'a> {
+    prev: Option<&'a Page>,
+    next: Option<&'a Page>,
+}
+
+/// Add (or refresh) the navigation of every post if `[build.nav]` is enabled
+pub fn build_post_nav(config: &SiteConfig) -> Result<()> {
+    if !config.build.nav.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let neighbours = neighbours(&pages, config);
+    let mut updated = 0;
+    for (page, neighbours) in &neighbours {
+        let html = fs::read_to_string(&page.output)
+            .with_context(|| format!("Failed to read {}", page.output.display()))?;
+        let new_html = with_nav(&html, &render_nav(neighbours));
+        if new_html != html {
+            fs::write(&page.output, new_html)
+                .with_context(|| format!("Failed to write {}", page.output.display()))?;
+            updated += 1;
+        }
+    }
+
+    log!("nav"; "navigation updated in {updated} of {} post(s)", neighbours.len());
+    Ok(())
+}
+
+/// Posts (pages with a valid `date`) with their neighbours in the same section
+fn neighbours<'a>(pages: &'a [Page], config: &SiteConfig) -> Vec<(&'a Page, Neighbours<'a>)> {
+    let mut sections: BTreeMap<Option<String>, Vec<(DateTimeUtc, &Page)>> = BTreeMap::new();
+    for page in pages {
+        if let Some(date) = page.date() {
+            let section = section(&page.source, &config.build.content);
+            sections.entry(section).or_default().push((date, page));
+        }
+    }
+
+    let mut result = Vec::new();
+    for posts in sections.values_mut() {
+        posts.sort_by(|(a_date, a), (b_date, b)| a_date.cmp(b_date).then(a.url.cmp(&b.url)));
+        for (i, (_, page)) in posts.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|i| posts[i].1);
+            let next = posts.get(i + 1).map(|(_, page)| *page);
+            result.push((*page, Neighbours { prev, next }));
+        }
+    }
+    result
+}
+
+/// `<nav>` with `rel="prev"`/`rel="next"` links; empty if the post has no neighbours
+fn render_nav(neighbours: &Neighbours) -> String {
+    if neighbours.prev.is_none() && neighbours.next.is_none() {
+        return String::new();
+    }
+    let link = |page: &Page, rel: &str, label: &str| {
+        let title = page
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.title.as_deref())
+            .unwrap_or(&page.url);
+        format!(
+            r#"<a class="tola-{rel}" rel="{rel}" href="{}">{label}{}</a>"#,
+            escape(page.url.as_str()),
+            escape(title)
+        )
+    };
+
+    let mut nav = String::from(NAV_START);
+    if let Some(prev) = neighbours.prev {
+        nav.push_str(&link(prev, "prev", "← "));
+    }
+    if let Some(next) = neighbours.next {
+        nav.push_str(&link(next, "next", "→ "));
+    }
+    nav.push_str(NAV_END);
+    nav
+}
+
+/// Replace the navigation of a page, placing it right before `</body>`
+///
+/// Minified pages may omit `</body>`, so the navigation is then appended.
+fn with_nav(html: &str, nav: &str) -> String {
+    let mut html = html.to_owned();
+    if let Some(start) = html.find(NAV_START)
+        && let Some(len) = html[start..].find(NAV_END)
+    {
+        html.replace_range(start..start + len + NAV_END.len(), "");
+    }
+    let at = html.rfind("</body>").unwrap_or(html.len());
+    html.insert_str(at, nav);
+    html
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+    use std::path::PathBuf;
+
+    fn post(source: &str, date: Option<&str>) -> Page {
+        Page {
+            source: PathBuf::from("/site/content").join(source),
+            url: format!("/{source}"),
+            meta: Some(PageMeta {
+                title: Some(source.into()),
+                date: date.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_neighbours_per_section() {
+        let mut config = SiteConfig::default();
+        config.build.content = PathBuf::from("/site/content");
+        let pages = [
+            post("posts/b.typ", Some("2024-02-01")),
+            post("posts/a.typ", Some("2024-01-01")),
+            post("notes/x.typ", Some("2024-01-15")),
+            post("posts/c.typ", Some("2024-03-01")),
+            post("about.typ", None),
+        ];
+        let neighbours = neighbours(&pages, &config);
+        let find = |url: &str| &neighbours.iter().find(|(p, _)| p.url == url).unwrap().1;
+
+        assert_eq!(neighbours.len(), 4);
+        assert_eq!(find("/notes/x.typ"), &Neighbours::default());
+        assert_eq!(find("/posts/b.typ").prev.unwrap().url, "/posts/a.typ");
+        assert_eq!(find("/posts/b.typ").next.unwrap().url, "/posts/c.typ");
+        assert!(find("/posts/c.typ").next.is_none());
+    }
+
+    #[test]
+    fn test_render_nav() {
+        let older = post("a&b.typ", Some("2024-01-01"));
+        let nav = render_nav(&Neighbours {
+            prev: Some(&older),
+            next: None,
+        });
+        assert_eq!(
+            nav,
+            r#"<nav class="tola-post-nav"><a class="tola-prev" rel="prev" href="/a&amp;b.typ">← a&amp;b.typ</a></nav>"#
+        );
+        assert_eq!(render_nav(&Neighbours::default()), "");
+    }
+
+    #[test]
+    fn test_with_nav_replaces_previous() {
+        let nav = r#"<nav class="tola-post-nav"><a>new</a></nav>"#;
+        let html = "<body><p>x</p></body>";
+        let once = with_nav(html, nav);
+        assert_eq!(once, format!("<body><p>x</p>{nav}</body>"));
+        assert_eq!(with_nav(&once, nav), once);
+        assert_eq!(with_nav(&once, ""), html);
+        // Minified pages without `</body>`
+        assert_eq!(with_nav("<p>x", nav), format!("<p>x{nav}"));
+    }
+}
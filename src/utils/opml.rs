@@ -0,0 +1,49 @@
+//! OPML 2.0 documents.
+//!
+//! Shared by the blogroll (other sites' feeds) and the list of the site's own
+//! feeds, so readers can subscribe to all of them in one import.
+
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+};
+use std::io::Cursor;
+
+/// OPML 2.0 document titled `title`, with one `<outline>` per item of `outlines`
+pub fn render<'a>(
+    title: &str,
+    outlines: impl IntoIterator<Item = BytesStart<'a>>,
+) -> Result<Vec<u8>> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("opml").with_attributes([("version", "2.0")]),
+    ))?;
+
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("title")))?;
+    writer.write_event(Event::Text(BytesText::new(title)))?;
+    writer.write_event(Event::End(BytesEnd::new("title")))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    for outline in outlines {
+        writer.write_event(Event::Empty(outline))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+
+    writer.write_event(Event::End(BytesEnd::new("opml")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+/// `<outline type="rss">` subscribing to the feed at `xml_url`
+pub fn feed_outline<'a>(title: &'a str, xml_url: &'a str, html_url: &'a str) -> BytesStart<'a> {
+    BytesStart::new("outline").with_attributes([
+        ("text", title),
+        ("title", title),
+        ("type", "rss"),
+        ("xmlUrl", xml_url),
+        ("htmlUrl", html_url),
+    ])
+}
@@ -0,0 +1,280 @@
+//! Pagination of post listings.
+//!
+//! Splits the listings generated by tola (author and taxonomy pages) into pages
+//! of `[build.pagination] page_size` posts, and recompiles typst pages that
+//! declare `paginate` once per page, passing the page through `sys.inputs`.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        build::compile_page,
+        meta::Paginate,
+        prune, sections,
+        site::{self, Page, page_url, section},
+    },
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+/// One page of a listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pager {
+    /// Page number, starting at 1
+    pub number: usize,
+    /// Number of pages
+    pub total: usize,
+    /// Output HTML file
+    pub output: PathBuf,
+    /// URL of the previous page
+    pub prev: Option<String>,
+    /// URL of the next page
+    pub next: Option<String>,
+}
+
+/// Split `items` into pages, the first of which is written to `index`
+///
+/// Without `[build.pagination]` everything is listed on the first page.
+pub fn paginate<'a, T>(items: &'a [T], index: &Path, config: &SiteConfig) -> Vec<(Pager, &'a [T])> {
+    paginate_by(items, index, None, config)
+}
+
+/// [`paginate`] with `page_size` posts per page instead of `[build.pagination] page_size`
+pub fn paginate_by<'a, T>(
+    items: &'a [T],
+    index: &Path,
+    page_size: Option<usize>,
+    config: &SiteConfig,
+) -> Vec<(Pager, &'a [T])> {
+    let pagination = &config.build.pagination;
+    let page_size = match pagination.enable {
+        true => page_size.unwrap_or(pagination.page_size).max(1),
+        false => items.len().max(1),
+    };
+    let chunks: Vec<&[T]> = match items.is_empty() {
+        true => vec![items],
+        false => items.chunks(page_size).collect(),
+    };
+
+    let total = chunks.len();
+    let outputs: Vec<PathBuf> = (1..=total)
+        .map(|number| match (number, index.parent()) {
+            (2.., Some(dir)) => dir
+                .join(&pagination.path)
+                .join(number.to_string())
+                .join("index.html"),
+            _ => index.to_owned(),
+        })
+        .collect();
+    let url = |i: usize| page_url(&outputs[i], config);
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let pager = Pager {
+                number: i + 1,
+                total,
+                output: outputs[i].clone(),
+                prev: i.checked_sub(1).map(url),
+                next: (i + 1 < total).then(|| url(i + 1)),
+            };
+            (pager, chunk)
+        })
+        .collect()
+}
+
+/// Write `<nav class="tola-pagination">` with links to the neighbouring pages
+///
+/// Nothing is written for a listing that fits on one page.
+pub fn write_nav(writer: &mut Writer<Cursor<Vec<u8>>>, pager: &Pager) -> Result<()> {
+    if pager.total <= 1 {
+        return Ok(());
+    }
+    let link = |writer: &mut Writer<Cursor<Vec<u8>>>, rel: &str, href: &str, text: &str| {
+        let class = format!("tola-{rel}");
+        writer.write_event(Event::Start(BytesStart::new("a").with_attributes([
+            ("class", class.as_str()),
+            ("rel", rel),
+            ("href", href),
+        ])))?;
+        writer.write_event(Event::Text(BytesText::new(text)))?;
+        writer.write_event(Event::End(BytesEnd::new("a")))
+    };
+
+    writer.write_event(Event::Start(
+        BytesStart::new("nav").with_attributes([("class", "tola-pagination")]),
+    ))?;
+    if let Some(prev) = &pager.prev {
+        link(writer, "prev", prev, "←")?;
+    }
+    writer.write_event(Event::Start(BytesStart::new("span")))?;
+    let position = format!("{} / {}", pager.number, pager.total);
+    writer.write_event(Event::Text(BytesText::new(&position)))?;
+    writer.write_event(Event::End(BytesEnd::new("span")))?;
+    if let Some(next) = &pager.next {
+        link(writer, "next", next, "→")?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("nav")))?;
+    Ok(())
+}
+
+// ============================================================================
+// Typst Pages
+// ============================================================================
+
+/// Compile every page of the typst pages that declare `paginate`
+pub fn build_pagination(config: &SiteConfig) -> Result<()> {
+    if !config.build.pagination.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let mut written = 0;
+    for page in &pages {
+        let Some(paginate) = page.meta.as_ref().and_then(|meta| meta.paginate.as_ref()) else {
+            continue;
+        };
+        let posts = listed_posts(&pages, paginate, config);
+        let pagers = paginate_posts(&posts, &page.output, config);
+        // A single page needs no inputs, so the regular build already wrote it
+        if pagers.len() <= 1 {
+            continue;
+        }
+
+        let section = sections::page_section(&page.source, config)?;
+        for pager in &pagers {
+            let mut inputs = page_inputs(pager, config);
+            inputs.extend(sections::page_inputs(&section));
+            let html = compile_page(
+                &page.source,
+                &pager.output,
+                &inputs,
+                page.meta.as_ref(),
+                config,
+            )?;
+            prune::write_produced(&pager.output, &html)?;
+            written += 1;
+        }
+    }
+
+    log!("pagination"; "{written} page(s) written");
+    Ok(())
+}
+
+fn paginate_posts(posts: &[&Page], index: &Path, config: &SiteConfig) -> Vec<Pager> {
+    paginate(posts, index, config)
+        .into_iter()
+        .map(|(pager, _)| pager)
+        .collect()
+}
+
+/// Posts (pages with a `date`) listed by a paginated page
+fn listed_posts<'a>(pages: &'a [Page], paginate: &Paginate, config: &SiteConfig) -> Vec<&'a Page> {
+    pages
+        .iter()
+        .filter(|page| page.meta.as_ref().is_some_and(|meta| meta.date.is_some()))
+        .filter(|page| match paginate {
+            Paginate::All => true,
+            Paginate::Section(name) => {
+                section(&page.source, &config.build.content).as_ref() == Some(name)
+            }
+        })
+        .collect()
+}
+
+/// `sys.inputs` of one page
+fn page_inputs(pager: &Pager, config: &SiteConfig) -> Vec<(&'static str, String)> {
+    let mut inputs = vec![
+        ("tola-page", pager.number.to_string()),
+        ("tola-pages", pager.total.to_string()),
+        (
+            "tola-page-size",
+            config.build.pagination.page_size.to_string(),
+        ),
+    ];
+    inputs.extend(pager.prev.clone().map(|url| ("tola-prev", url)));
+    inputs.extend(pager.next.clone().map(|url| ("tola-next", url)));
+    inputs
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(page_size: usize) -> SiteConfig {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com".into());
+        config.build.output = PathBuf::from("/site/public");
+        config.build.pagination.enable = true;
+        config.build.pagination.page_size = page_size;
+        config
+    }
+
+    #[test]
+    fn test_paginate() {
+        let config = config(2);
+        let index = Path::new("/site/public/tags/rust/index.html");
+        let pages = paginate(&[1, 2, 3, 4, 5], index, &config);
+
+        assert_eq!(pages.len(), 3);
+        let (first, items) = &pages[0];
+        assert_eq!(items, &[1, 2]);
+        assert_eq!(first.output, index);
+        assert_eq!(first.prev, None);
+        assert_eq!(
+            first.next.as_deref(),
+            Some("https://example.com/tags/rust/page/2/index.html")
+        );
+        let (last, items) = &pages[2];
+        assert_eq!(items, &[5]);
+        assert_eq!(
+            last.output,
+            PathBuf::from("/site/public/tags/rust/page/3/index.html")
+        );
+        assert_eq!(last.next, None);
+    }
+
+    #[test]
+    fn test_paginate_disabled_or_empty() {
+        let mut config = config(2);
+        let index = Path::new("/site/public/index.html");
+        assert_eq!(paginate::<u8>(&[], index, &config).len(), 1);
+        config.build.pagination.enable = false;
+        assert_eq!(paginate(&[1, 2, 3], index, &config).len(), 1);
+    }
+
+    #[test]
+    fn test_write_nav() {
+        let config = config(1);
+        let pages = paginate(&[1, 2, 3], Path::new("/site/public/a/index.html"), &config);
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_nav(&mut writer, &pages[1].0).unwrap();
+        let html = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+        assert_eq!(
+            html,
+            r#"<nav class="tola-pagination"><a class="tola-prev" rel="prev" href="https://example.com/a/index.html">←</a><span>2 / 3</span><a class="tola-next" rel="next" href="https://example.com/a/page/3/index.html">→</a></nav>"#
+        );
+    }
+
+    #[test]
+    fn test_page_inputs() {
+        let config = config(1);
+        let pages = paginate(&[1, 2], Path::new("/site/public/a/index.html"), &config);
+        let inputs = page_inputs(&pages[0].0, &config);
+        assert_eq!(inputs[0], ("tola-page", "1".to_string()));
+        assert_eq!(inputs[1], ("tola-pages", "2".to_string()));
+        assert_eq!(inputs.last().unwrap().0, "tola-next");
+    }
+}
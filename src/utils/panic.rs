@@ -0,0 +1,91 @@
+//! Panic handling.
+//!
+//! Panics inside per-file processing are turned into regular errors for that
+//! file, so one malformed document cannot take down the whole build. Panics
+//! elsewhere are reported as a readable message instead of a raw backtrace.
+//!
+//! Catching needs unwinding, so the release profile must not set `panic = "abort"`.
+
+use crate::{debug, log};
+use anyhow::{Result, anyhow};
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Number of active [`catch`] scopes (across all threads)
+static CATCHING: AtomicUsize = AtomicUsize::new(0);
+
+/// Install the global panic hook.
+///
+/// Set `RUST_BACKTRACE=1` to get the default hook output with a backtrace.
+pub fn install_hook() {
+    if std::env::var_os("RUST_BACKTRACE").is_some_and(|v| v != "0") {
+        return;
+    }
+
+    panic::set_hook(Box::new(|info| {
+        let message = payload_message(info.payload());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+
+        // Caught panics become per-file errors; don't print them twice
+        if CATCHING.load(Ordering::Relaxed) > 0 {
+            debug!("panic"; "{message} at {location}");
+            return;
+        }
+
+        log!(true; "error"; "internal error: {message} ({location})");
+        log!(true; "error"; "this is a bug, please report it (run with RUST_BACKTRACE=1 for details)");
+    }));
+}
+
+/// Run `f`, converting a panic into an error.
+pub fn catch<R>(f: impl FnOnce() -> Result<R>) -> Result<R> {
+    CATCHING.fetch_add(1, Ordering::Relaxed);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.fetch_sub(1, Ordering::Relaxed);
+
+    result.unwrap_or_else(|payload| Err(anyhow!("panicked: {}", payload_message(&*payload))))
+}
+
+/// Extract the message from a panic payload
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_message() {
+        let payload: Box<dyn Any + Send> = Box::new("boom");
+        assert_eq!(payload_message(&*payload), "boom");
+
+        let payload: Box<dyn Any + Send> = Box::new(String::from("bad svg"));
+        assert_eq!(payload_message(&*payload), "bad svg");
+
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(payload_message(&*payload), "unknown panic");
+    }
+
+    #[test]
+    fn test_catch() {
+        assert_eq!(catch(|| Ok(1)).unwrap(), 1);
+
+        let err = catch::<()>(|| panic!("malformed document")).unwrap_err();
+        assert_eq!(err.to_string(), "panicked: malformed document");
+    }
+}
@@ -0,0 +1,159 @@
+//! External plugin execution.
+//!
+//! Runs `[[plugins]]` commands at their pipeline stage: page stages transform
+//! HTML through stdin/stdout, `post-build` receives the build manifest.
+
+use crate::{
+    config::{PluginConfig, PluginStage, SiteConfig},
+    debug,
+    utils::{build::collect_files, exec},
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{ffi::OsStr, path::Path};
+
+/// Build manifest passed to `post-build` plugins on stdin
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    root: &'a Path,
+    output: &'a Path,
+    /// Generated files, relative to `output`
+    files: Vec<String>,
+}
+
+/// Enabled plugins for `stage`, in config order
+fn plugins_at(config: &SiteConfig, stage: PluginStage) -> impl Iterator<Item = &PluginConfig> {
+    config
+        .plugins
+        .iter()
+        .filter(move |p| p.enable && p.stage == stage)
+}
+
+/// Run a single plugin with `input` on stdin
+fn run(
+    plugin: &PluginConfig,
+    config: &SiteConfig,
+    source: &Path,
+    output: &Path,
+    input: &[u8],
+) -> Result<Vec<u8>> {
+    let name = plugin.display_name();
+    debug!("plugin"; "running `{name}` ({})", plugin.stage.as_str());
+
+    let envs = [
+        ("TOLA_STAGE", OsStr::new(plugin.stage.as_str())),
+        ("TOLA_ROOT", config.get_root().as_os_str()),
+        ("TOLA_SOURCE", source.as_os_str()),
+        ("TOLA_OUTPUT", output.as_os_str()),
+    ];
+    let command = exec::to_cmd_vec(&plugin.command);
+    exec::pipe(Some(config.get_root()), &command, &envs, input)
+        .with_context(|| format!("Plugin `{name}` failed at {}", plugin.stage.as_str()))
+}
+
+/// Pass page HTML through all plugins of a page stage.
+///
+/// Each plugin receives the previous one's output.
+pub fn transform_page(
+    stage: PluginStage,
+    html: Vec<u8>,
+    source: &Path,
+    output: &Path,
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    plugins_at(config, stage).try_fold(html, |html, plugin| {
+        run(plugin, config, source, output, &html)
+    })
+}
+
+/// Run `post-build` plugins with the build manifest as JSON on stdin.
+pub fn run_post_build(config: &SiteConfig) -> Result<()> {
+    let mut plugins = plugins_at(config, PluginStage::PostBuild).peekable();
+    if plugins.peek().is_none() {
+        return Ok(());
+    }
+
+    let output = &config.build.output;
+    let manifest = Manifest {
+        root: config.get_root(),
+        output,
+        files: manifest_files(output),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)?;
+
+    for plugin in plugins {
+        // Output of post-build plugins is not used, only their exit status
+        run(plugin, config, config.get_root(), output, &manifest)?;
+    }
+    Ok(())
+}
+
+/// Generated files relative to `output`, sorted, excluding the `.git` directory
+fn manifest_files(output: &Path) -> Vec<String> {
+    let git_dir = output.join(".git");
+    let mut files: Vec<_> = collect_files(output, |path| !path.starts_with(&git_dir))
+        .iter()
+        .filter_map(|path| path.strip_prefix(output).ok())
+        .map(|path| path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    files.sort();
+    files
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_plugins_at_filters_stage_and_enable() {
+        let config = SiteConfig::from_str(
+            r#"
+            [base]
+            title = "Test"
+            description = "Test"
+
+            [[plugins]]
+            name = "a"
+            command = ["cat"]
+            stage = "pre-minify"
+
+            [[plugins]]
+            name = "b"
+            command = ["cat"]
+            stage = "pre-minify"
+            enable = false
+
+            [[plugins]]
+            name = "c"
+            command = ["cat"]
+            stage = "post-build"
+        "#,
+        )
+        .unwrap();
+
+        let names: Vec<_> = plugins_at(&config, PluginStage::PreMinify)
+            .map(PluginConfig::display_name)
+            .collect();
+        assert_eq!(names, vec!["a"]);
+    }
+
+    #[test]
+    fn test_manifest_files() {
+        let dir = std::env::temp_dir().join("tola_plugin_manifest_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("posts")).unwrap();
+        std::fs::write(dir.join(".git/HEAD"), "").unwrap();
+        std::fs::write(dir.join("index.html"), "").unwrap();
+        std::fs::write(dir.join("posts/a.html"), "").unwrap();
+
+        assert_eq!(manifest_files(&dir), vec!["index.html", "posts/a.html"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
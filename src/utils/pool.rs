@@ -0,0 +1,64 @@
+//! Worker thread pools.
+//!
+//! Two rayon pools keep blocking work from starving CPU-bound work:
+//!
+//! | Pool    | Work                                                    |
+//! |---------|---------------------------------------------------------|
+//! | [`io`]  | Typst/tailwind subprocess waits, file copies, RSS query |
+//! | [`cpu`] | HTML minification                                       |
+//!
+//! Pools are created on first use with the sizes from `[build.threads]`.
+//! Images are encoded on the threads of the
+//! [`image_queue`](crate::utils::image_queue) instead.
+
+use crate::config::{SiteConfig, ThreadsConfig};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::OnceLock;
+
+static CPU: OnceLock<ThreadPool> = OnceLock::new();
+static IO: OnceLock<ThreadPool> = OnceLock::new();
+
+/// Resolve a configured size (`0` = available cores)
+pub fn resolve_size(configured: usize) -> usize {
+    match configured {
+        0 => std::thread::available_parallelism().map_or(4, |n| n.get()),
+        n => n,
+    }
+}
+
+fn build_pool(name: &'static str, size: usize) -> ThreadPool {
+    ThreadPoolBuilder::new()
+        .num_threads(resolve_size(size))
+        .thread_name(move |i| format!("tola-{name}-{i}"))
+        .build()
+        .expect("failed to spawn worker threads")
+}
+
+fn threads(config: &SiteConfig) -> &ThreadsConfig {
+    &config.build.threads
+}
+
+/// Pool for CPU-bound work.
+pub fn cpu(config: &SiteConfig) -> &'static ThreadPool {
+    CPU.get_or_init(|| build_pool("cpu", threads(config).cpu))
+}
+
+/// Pool for blocking I/O and subprocess waits.
+pub fn io(config: &SiteConfig) -> &'static ThreadPool {
+    IO.get_or_init(|| build_pool("io", threads(config).io))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_size() {
+        assert_eq!(resolve_size(3), 3);
+        assert!(resolve_size(0) >= 1);
+    }
+}
@@ -0,0 +1,183 @@
+//! Precompressed output (`[build.precompress]`).
+//!
+//! Runs last, once no other step changes the output: text files get `.br` and
+//! `.gz` copies next to them, compressed in parallel at the highest levels.
+//! The content hash of each compressed file is kept in the build cache, and a
+//! copy of a file whose content has not changed is left as is, so unchanged
+//! files are not compressed again on every build.
+
+use crate::{
+    config::{PrecompressConfig, SiteConfig},
+    log,
+    utils::{build::collect_files, cache::FileCache, prune},
+};
+use anyhow::{Context, Result};
+use flate2::{Compression, write::GzEncoder};
+use rayon::prelude::*;
+use sha1::{Digest, Sha1};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Extensions of the files compressed
+const EXTENSIONS: &[&str] = &["html", "css", "js", "mjs", "svg", "xml"];
+
+/// Cache file for the hashes of compressed files, inside the cache directory
+const CACHE_FILE: &str = "precompress.json";
+
+/// Compresses the content of a file
+type Encoder = fn(&[u8]) -> Result<Vec<u8>>;
+
+/// Compress the output if `[build.precompress]` is enabled
+pub fn build_precompress(config: &SiteConfig) -> Result<()> {
+    let precompress = &config.build.precompress;
+    if !precompress.enable || config.cli.as_ref().is_some_and(|cli| cli.is_serve()) {
+        return Ok(());
+    }
+
+    let git_dir = config.build.output.join(".git");
+    let files = collect_files(&config.build.output, |path| {
+        !path.starts_with(&git_dir) && compresses(path, precompress.min_size)
+    });
+    let cache = FileCache::load(config.build.cache.join(CACHE_FILE));
+    let copies = files
+        .par_iter()
+        .map(|file| compress_file(file, &config.build.output, precompress, &cache))
+        .collect::<Result<Vec<_>>>()?;
+    cache.save()?;
+
+    log!("precompress"; "{} compressed cop(ies) of {} file(s)",
+        copies.iter().sum::<usize>(), files.len());
+    Ok(())
+}
+
+/// Whether `path` is a text file of at least `min_size` bytes
+fn compresses(path: &Path, min_size: u64) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext))
+        && fs::metadata(path).is_ok_and(|meta| meta.len() >= min_size)
+}
+
+/// Write the enabled copies of `path`; returns how many it has
+///
+/// A copy that would not be smaller than the file is not written. Copies are
+/// kept while `cache` has the hash of the content they were made from.
+fn compress_file(
+    path: &Path,
+    output: &Path,
+    config: &PrecompressConfig,
+    cache: &FileCache,
+) -> Result<usize> {
+    let encoders: [(bool, &str, Encoder); 2] =
+        [(config.brotli, "br", brotli), (config.gzip, "gz", gzip)];
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let hash = format!("{:x}", Sha1::digest(&content));
+    let mut copies = 0;
+    for (_, ext, encode) in encoders.into_iter().filter(|(enabled, ..)| *enabled) {
+        let copy = sibling(path, ext);
+        let key = copy.strip_prefix(output).unwrap_or(&copy).to_string_lossy();
+        if !(copy.exists() && cache.has_hash(&key, &hash)) {
+            let compressed = encode(&content)?;
+            if compressed.len() >= content.len() {
+                // A copy of an earlier content would be stale
+                let _ = fs::remove_file(&copy);
+                continue;
+            }
+            fs::write(&copy, compressed)
+                .with_context(|| format!("Failed to write {}", copy.display()))?;
+            cache.record_hash(&key, content.len() as u64, hash.clone());
+        }
+        prune::produced(&copy);
+        copies += 1;
+    }
+    Ok(copies)
+}
+
+/// `index.html` → `index.html.br`
+fn sibling(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    name.into()
+}
+
+fn brotli(content: &[u8]) -> Result<Vec<u8>> {
+    let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    writer.write_all(content)?;
+    Ok(writer.into_inner())
+}
+
+fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_build_precompress() {
+        let output = std::env::temp_dir().join("tola_precompress_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts")).unwrap();
+        let html = "<p>Hello, precompressed world!</p>\n".repeat(100);
+        fs::write(output.join("posts/index.html"), &html).unwrap();
+        fs::write(output.join("small.css"), "body{}").unwrap();
+        fs::write(output.join("photo.png"), html.as_bytes()).unwrap();
+
+        let mut config = SiteConfig::default();
+        config.build.output = output.clone();
+        config.build.cache = std::env::temp_dir().join("tola_precompress_test_cache");
+        let _ = fs::remove_dir_all(&config.build.cache);
+        config.build.precompress.enable = true;
+        build_precompress(&config).unwrap();
+
+        let mut decoded = String::new();
+        let br = fs::read(output.join("posts/index.html.br")).unwrap();
+        brotli::Decompressor::new(br.as_slice(), 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, html);
+        decoded.clear();
+        let gz = fs::read(output.join("posts/index.html.gz")).unwrap();
+        GzDecoder::new(gz.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, html);
+        assert!(!output.join("small.css.br").exists());
+        assert!(!output.join("photo.png.gz").exists());
+
+        // Copies of unchanged content are kept, even when the file is rewritten
+        fs::write(output.join("posts/index.html.gz"), "kept").unwrap();
+        fs::write(output.join("posts/index.html"), &html).unwrap();
+        build_precompress(&config).unwrap();
+        assert_eq!(
+            fs::read_to_string(output.join("posts/index.html.gz")).unwrap(),
+            "kept"
+        );
+
+        // Changed content is compressed again, however soon it was written
+        let changed = html.replace("Hello", "Bye");
+        fs::write(output.join("posts/index.html"), &changed).unwrap();
+        build_precompress(&config).unwrap();
+        decoded.clear();
+        let gz = fs::read(output.join("posts/index.html.gz")).unwrap();
+        GzDecoder::new(gz.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, changed);
+        fs::remove_dir_all(&output).unwrap();
+        fs::remove_dir_all(&config.build.cache).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+//! Terminal progress bars for long build stages.
+//!
+//! Bars are keyed by log module (`content`, `assets`, `svg`), so inline
+//! `log!` messages of an active stage become the bar's status text instead of
+//! overwriting each other with carriage returns. When stdout is not a
+//! terminal, bars are never drawn and only their final summary is logged.
+
+use crate::log;
+use crate::utils::log::{colorize_prefix, get_terminal_width, is_plain, truncate_str};
+use crossterm::{
+    cursor::MoveUp,
+    queue,
+    terminal::{Clear, ClearType},
+};
+use std::{
+    io::{StdoutLock, Write, stdout},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Minimum interval between redraws
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Width of the bar itself (in characters)
+const BAR_WIDTH: usize = 24;
+
+/// State of a single progress bar
+struct Bar {
+    module: &'static str,
+    total: usize,
+    done: usize,
+    message: String,
+    started: Instant,
+}
+
+impl Bar {
+    /// Render this bar as a single terminal line (without newline)
+    fn render(&self, width: usize) -> String {
+        let ratio = if self.total == 0 {
+            0.0
+        } else {
+            (self.done as f64 / self.total as f64).min(1.0)
+        };
+        let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+
+        let line = format!(
+            "{bar} {}/{} ({:>3.0}%) ETA {} {}",
+            self.done,
+            self.total,
+            ratio * 100.0,
+            format_eta(self.eta()),
+            self.message
+        );
+        let prefix = colorize_prefix(self.module, self.module);
+        let max_len = width.saturating_sub(self.module.len() + 4);
+        format!("{prefix} {}", truncate_str(&line, max_len))
+    }
+
+    /// Estimate remaining time from the average rate so far
+    fn eta(&self) -> Option<Duration> {
+        if self.done == 0 || self.done >= self.total {
+            return None;
+        }
+        let per_item = self.started.elapsed().as_secs_f64() / self.done as f64;
+        Some(Duration::from_secs_f64(
+            per_item * (self.total - self.done) as f64,
+        ))
+    }
+}
+
+/// All active bars and how many lines they currently occupy on screen
+struct Board {
+    bars: Vec<Bar>,
+    drawn: usize,
+    last_draw: Option<Instant>,
+}
+
+static BOARD: Mutex<Board> = Mutex::new(Board {
+    bars: Vec::new(),
+    drawn: 0,
+    last_draw: None,
+});
+
+impl Board {
+    fn find(&mut self, module: &str) -> Option<&mut Bar> {
+        self.bars.iter_mut().find(|b| b.module == module)
+    }
+
+    /// Erase the lines occupied by bars
+    fn clear(&mut self, out: &mut StdoutLock<'_>) {
+        if self.drawn > 0 {
+            queue!(
+                out,
+                MoveUp(self.drawn as u16),
+                Clear(ClearType::FromCursorDown)
+            )
+            .ok();
+            self.drawn = 0;
+        }
+    }
+
+    /// Draw all visible bars below the cursor
+    fn draw(&mut self, out: &mut StdoutLock<'_>) {
+        let width = get_terminal_width() as usize;
+        for bar in self.bars.iter().filter(|b| b.total > 0) {
+            writeln!(out, "{}", bar.render(width)).ok();
+            self.drawn += 1;
+        }
+        self.last_draw = Some(Instant::now());
+    }
+
+    /// Redraw all bars, throttled unless `force` is set
+    fn redraw(&mut self, force: bool) {
+        if is_plain() {
+            return;
+        }
+        let due = self
+            .last_draw
+            .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+        if !force && !due {
+            return;
+        }
+        let mut out = stdout().lock();
+        self.clear(&mut out);
+        self.draw(&mut out);
+        out.flush().ok();
+    }
+}
+
+fn board() -> std::sync::MutexGuard<'static, Board> {
+    BOARD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ============================================================================
+// Public API
+// ============================================================================
+
+/// RAII guard for a progress bar; the bar is removed when dropped.
+pub struct Progress {
+    module: &'static str,
+}
+
+impl Progress {
+    /// Start a progress bar for `module` with `total` expected items.
+    ///
+    /// Bars with a total of zero stay hidden until [`grow`] raises it.
+    pub fn start(module: &'static str, total: usize) -> Self {
+        let mut board = board();
+        board.bars.push(Bar {
+            module,
+            total,
+            done: 0,
+            message: String::new(),
+            started: Instant::now(),
+        });
+        board.redraw(true);
+        Self { module }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        let finished = {
+            let mut board = board();
+            let Some(pos) = board.bars.iter().position(|b| b.module == self.module) else {
+                return;
+            };
+            let bar = board.bars.remove(pos);
+            board.redraw(true);
+            bar
+        };
+
+        if finished.total > 0 {
+            log!(true; finished.module; "{}/{} done in {:.2?}", finished.done, finished.total, finished.started.elapsed());
+        }
+    }
+}
+
+/// Advance the bar of `module` by `n` items (no-op if no bar is active).
+pub fn advance(module: &str, n: usize) {
+    let mut board = board();
+    let Some(bar) = board.find(module) else {
+        return;
+    };
+    bar.done += n;
+    let complete = bar.done >= bar.total;
+    board.redraw(complete);
+}
+
+/// Increase the expected total of `module` by `n` items (no-op if no bar is active).
+pub fn grow(module: &str, n: usize) {
+    let mut board = board();
+    if let Some(bar) = board.find(module) {
+        bar.total += n;
+        board.redraw(false);
+    }
+}
+
+/// Show `message` as the status text of `module`'s bar.
+///
+/// Returns `false` if no bar is active for `module`.
+pub fn set_message(module: &str, message: &str) -> bool {
+    let mut board = board();
+    let Some(bar) = board.find(module) else {
+        return false;
+    };
+    bar.message.clear();
+    bar.message.push_str(message);
+    board.redraw(false);
+    true
+}
+
+/// Temporarily remove bars from the screen while `f` writes regular output.
+pub fn suspend<R>(f: impl FnOnce(&mut StdoutLock<'_>) -> R) -> R {
+    let mut board = board();
+    let mut out = stdout().lock();
+    board.clear(&mut out);
+    let result = f(&mut out);
+    board.draw(&mut out);
+    out.flush().ok();
+    result
+}
+
+/// Format an ETA compactly (e.g. `42s`, `3m05s`)
+fn format_eta(eta: Option<Duration>) -> String {
+    match eta.map(|d| d.as_secs()) {
+        None => "--".to_owned(),
+        Some(secs) if secs < 60 => format!("{secs}s"),
+        Some(secs) => format!("{}m{:02}s", secs / 60, secs % 60),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(total: usize, done: usize) -> Bar {
+        Bar {
+            module: "content",
+            total,
+            done,
+            message: "posts/hello".into(),
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(None), "--");
+        assert_eq!(format_eta(Some(Duration::from_secs(42))), "42s");
+        assert_eq!(format_eta(Some(Duration::from_secs(185))), "3m05s");
+    }
+
+    #[test]
+    fn test_bar_eta_unknown_without_progress() {
+        assert!(bar(10, 0).eta().is_none());
+        assert!(bar(10, 10).eta().is_none());
+    }
+
+    #[test]
+    fn test_bar_render_counts() {
+        let line = bar(4, 1).render(200);
+        assert!(line.contains("1/4"));
+        assert!(line.contains("25%"));
+        assert!(line.contains("posts/hello"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Orphan output cleanup (`[build] prune`).
+//!
+//! While a build runs, every output file it writes (or finds up to date) is
+//! recorded. Afterwards, files in the output directory that no source produced
+//! are removed, so renamed or deleted sources don't leave stale pages behind.
+//! While serving, the watcher removes the output of deleted sources directly.
+
+use crate::utils::build::collect_files;
+use anyhow::{Context, Result};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// Produced files; `None` while no pruning build is running
+static PRODUCED: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+fn produced_lock() -> MutexGuard<'static, Option<HashSet<PathBuf>>> {
+    PRODUCED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start recording produced files
+pub fn start() {
+    *produced_lock() = Some(HashSet::new());
+}
+
+/// Record an output file as produced by this build (no-op unless started)
+pub fn produced(path: impl Into<PathBuf>) {
+    if let Some(produced) = produced_lock().as_mut() {
+        produced.insert(path.into());
+    }
+}
+
+/// Write a generated output file, creating its directory, and record it as produced
+pub fn write_produced(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    produced(path);
+    Ok(())
+}
+
+/// Record a page and the resources generated next to it (`svg-<hash>.svg`, `svg-<hash>.avif`)
+pub fn produced_page(html: &Path) {
+    if produced_lock().is_none() {
+        return;
+    }
+    produced(html);
+    let Some(dir) = html.parent() else { return };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| is_page_resource(&e.file_name().to_string_lossy()))
+        .for_each(|e| produced(e.path()));
+}
+
+/// Whether `name` is a resource extracted from a page
+fn is_page_resource(name: &str) -> bool {
+    name.strip_prefix("svg-")
+        .and_then(|rest| rest.split_once('.'))
+        .is_some_and(|(hash, ext)| {
+            !hash.is_empty()
+                && hash.bytes().all(|b| b.is_ascii_hexdigit())
+                && matches!(ext, "svg" | "avif" | "webp" | "png")
+        })
+}
+
+/// Stop recording without removing anything (e.g. after a failed build)
+pub fn cancel() {
+    produced_lock().take();
+}
+
+/// Stop recording and remove every file under `output` that was not produced.
+///
+/// The `.git` directory is kept. Returns the number of removed files.
+pub fn finish(output: &Path) -> Result<usize> {
+    let Some(produced) = produced_lock().take() else {
+        return Ok(0);
+    };
+
+    let git_dir = output.join(".git");
+    let stale = collect_files(output, |path| {
+        !path.starts_with(&git_dir) && !produced.contains(path)
+    });
+    for file in &stale {
+        fs::remove_file(file).with_context(|| format!("Failed to remove {}", file.display()))?;
+        remove_empty_parents(file, output);
+    }
+    Ok(stale.len())
+}
+
+/// Remove a page's HTML and the resources generated next to it, then the
+/// directories left empty
+pub fn remove_page_output(html: &Path, output: &Path) -> Result<()> {
+    if let Some(dir) = html.parent()
+        && let Ok(entries) = fs::read_dir(dir)
+    {
+        let resources = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| is_page_resource(&e.file_name().to_string_lossy()));
+        for resource in resources {
+            remove_output(&resource.path(), output)?;
+        }
+    }
+    remove_output(html, output)
+}
+
+/// Remove an output file or directory below `output` if it exists, then the
+/// directories left empty
+pub fn remove_output(path: &Path, output: &Path) -> Result<()> {
+    if path == output || !path.starts_with(output) {
+        return Ok(());
+    }
+    let result = match path.is_dir() {
+        true => fs::remove_dir_all(path),
+        false => fs::remove_file(path),
+    };
+    match result {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => {
+            remove_empty_parents(path, output);
+            Ok(())
+        }
+    }
+}
+
+/// Remove directories left empty by a removed file, up to (excluding) `output`
+fn remove_empty_parents(file: &Path, output: &Path) {
+    let mut dir = file.parent();
+    while let Some(current) = dir
+        && current != output
+        && current.starts_with(output)
+    {
+        // Fails (and stops) as soon as a directory is not empty
+        if fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_page_resource() {
+        assert!(is_page_resource("svg-0.svg"));
+        assert!(is_page_resource("svg-12.avif"));
+        assert!(is_page_resource("svg-9f3a0c1d2e4b5a68.avif"));
+        assert!(is_page_resource("svg-9f3a0c1d2e4b5a68.webp"));
+        assert!(!is_page_resource("svg-.svg"));
+        assert!(!is_page_resource("svg-z.svg"));
+        assert!(!is_page_resource("logo.svg"));
+    }
+
+    #[test]
+    fn test_remove_page_output() {
+        let output = std::env::temp_dir().join("tola_prune_page_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts/hello")).unwrap();
+        fs::write(output.join("posts/hello/index.html"), "").unwrap();
+        fs::write(output.join("posts/hello/svg-0.avif"), "").unwrap();
+        fs::write(output.join("posts/index.html"), "").unwrap();
+
+        remove_page_output(&output.join("posts/hello/index.html"), &output).unwrap();
+        assert!(!output.join("posts/hello").exists());
+        assert!(output.join("posts/index.html").exists());
+
+        // Already gone, or outside the output directory
+        remove_page_output(&output.join("posts/hello/index.html"), &output).unwrap();
+        remove_output(&output, &output).unwrap();
+        assert!(output.exists());
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_remove_empty_parents() {
+        let output = std::env::temp_dir().join("tola_prune_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("old/post")).unwrap();
+        fs::write(output.join("keep.html"), "").unwrap();
+
+        let file = output.join("old/post/index.html");
+        remove_empty_parents(&file, &output);
+        assert!(!output.join("old").exists());
+        assert!(output.join("keep.html").exists());
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+//! Removal of unused CSS (`[build.css.purge]`).
+//!
+//! Runs after everything that writes pages. The class, id and element names
+//! used anywhere in the output are collected, then every rule of the chosen
+//! stylesheets whose selectors all need a name no page uses is dropped.
+//! Stylesheets are read from the assets directory each time, so a rule
+//! removed by one build comes back once a page uses it.
+//!
+//! Matching is per site, not per element: `.card .title` is kept if some page
+//! has a `card` and some page a `title`. Selectors inside `:not()`, `:is()`,
+//! `:has()` and the like are not looked into, so they never cause removals.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{prune, slug::output_path},
+};
+use anyhow::{Context, Result, anyhow};
+use gix::glob::wildmatch;
+use lightningcss::{
+    rules::{CssRule, CssRuleList},
+    selector::{Component, Selector},
+    stylesheet::{ParserOptions, PrinterOptions, StyleSheet},
+};
+use regex::Regex;
+use std::{collections::HashSet, fs, path::Path, sync::LazyLock};
+use walkdir::WalkDir;
+
+/// Names used by the generated pages
+#[derive(Debug, Default)]
+struct UsedNames {
+    tags: HashSet<String>,
+    classes: HashSet<String>,
+    ids: HashSet<String>,
+}
+
+/// Purge the configured stylesheets if `[build.css.purge]` is enabled
+pub fn build_purge(config: &SiteConfig) -> Result<()> {
+    let purge = &config.build.css.purge;
+    if !purge.enable {
+        return Ok(());
+    }
+
+    let used = used_names(&config.build.output)?;
+    for stylesheet in &purge.stylesheets {
+        let output = output_path(stylesheet, config)?;
+        let css = fs::read_to_string(stylesheet)
+            .with_context(|| format!("Failed to read {}", stylesheet.display()))?;
+        let (purged, removed) = purge_css(&css, &used, &purge.safelist, config.build.minify)
+            .with_context(|| format!("Failed to purge {}", stylesheet.display()))?;
+        prune::write_produced(&output, purged.as_bytes())?;
+
+        let relative = stylesheet
+            .strip_prefix(&config.build.assets)
+            .unwrap_or(stylesheet);
+        log!("purge"; "{}: {removed} unused rule(s), {} -> {} bytes",
+            relative.display(), css.len(), purged.len());
+    }
+    Ok(())
+}
+
+/// Tag, class and id names of every HTML file in `output`
+fn used_names(output: &Path) -> Result<UsedNames> {
+    static RE_TAG: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"<([A-Za-z][A-Za-z0-9-]*)").unwrap());
+    static RE_ATTR: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"(?-u:\b)(class|id)=(?:"([^"]*)"|'([^']*)'|([^ \t\r\n"'=<>`]+))"#).unwrap()
+    });
+
+    let mut used = UsedNames::default();
+    let pages = WalkDir::new(output)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "html"));
+    for page in pages {
+        let html = fs::read_to_string(page.path())
+            .with_context(|| format!("Failed to read {}", page.path().display()))?;
+        for caps in RE_TAG.captures_iter(&html) {
+            used.tags.insert(caps[1].to_ascii_lowercase());
+        }
+        for caps in RE_ATTR.captures_iter(&html) {
+            let value = caps.get(2).or(caps.get(3)).or(caps.get(4));
+            let names = value.map_or("", |value| value.as_str()).split_whitespace();
+            match &caps[1] {
+                "class" => used.classes.extend(names.map(String::from)),
+                _ => used.ids.extend(names.map(String::from)),
+            }
+        }
+    }
+    Ok(used)
+}
+
+/// `css` without the rules no page uses, and the number of rules removed
+fn purge_css(
+    css: &str,
+    used: &UsedNames,
+    safelist: &[String],
+    minify: bool,
+) -> Result<(String, usize)> {
+    let mut sheet =
+        StyleSheet::parse(css, ParserOptions::default()).map_err(|err| anyhow!("{err}"))?;
+    let is_used = |selector: &Selector| {
+        let kept = |names: &HashSet<String>, name: &str| {
+            names.contains(name)
+                || safelist.iter().any(|pattern| {
+                    wildmatch(
+                        pattern.as_str().into(),
+                        name.into(),
+                        wildmatch::Mode::empty(),
+                    )
+                })
+        };
+        selector
+            .iter_raw_match_order()
+            .all(|component| match component {
+                Component::Class(name) => kept(&used.classes, &name.0),
+                Component::ID(name) => kept(&used.ids, &name.0),
+                Component::LocalName(local) => used.tags.contains(local.lower_name.0.as_ref()),
+                _ => true,
+            })
+    };
+    let removed = purge_rules(&mut sheet.rules, &is_used);
+    let printer = PrinterOptions {
+        minify,
+        ..Default::default()
+    };
+    let purged = sheet.to_css(printer).map_err(|err| anyhow!("{err}"))?;
+    Ok((purged.code, removed))
+}
+
+/// Drop the style rules without a used selector, and the unused selectors
+/// of the others; grouping rules left empty are dropped too
+fn purge_rules(rules: &mut CssRuleList, is_used: &impl Fn(&Selector) -> bool) -> usize {
+    let mut removed = 0;
+    rules.0.retain_mut(|rule| {
+        let nested = match rule {
+            CssRule::Style(style) => {
+                style.selectors.0.retain(|selector| is_used(selector));
+                if style.selectors.0.is_empty() {
+                    removed += 1;
+                    return false;
+                }
+                removed += purge_rules(&mut style.rules, is_used);
+                return true;
+            }
+            CssRule::Media(media) => &mut media.rules,
+            CssRule::Supports(supports) => &mut supports.rules,
+            CssRule::LayerBlock(layer) => &mut layer.rules,
+            CssRule::Container(container) => &mut container.rules,
+            _ => return true,
+        };
+        removed += purge_rules(nested, is_used);
+        !nested.0.is_empty()
+    });
+    removed
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn used() -> UsedNames {
+        UsedNames {
+            tags: ["html", "body", "p", "a"].map(String::from).into(),
+            classes: ["card", "title"].map(String::from).into(),
+            ids: ["main"].map(String::from).into(),
+        }
+    }
+
+    #[test]
+    fn test_purge_css() {
+        let css = "p, table { margin: 0 }
+.card .title { color: red }
+.card:hover, .unused { color: blue }
+#main, #sidebar { width: 1px }
+a:not(.external)::after { content: '' }
+@media (min-width: 600px) { .unused { display: none } }
+@media print { .card { display: none } }
+.hljs-keyword, .is-open { color: green }
+@keyframes spin { from { opacity: 0 } }
+";
+        let safelist = ["hljs-*".to_owned()];
+        let (purged, removed) = purge_css(css, &used(), &safelist, true).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(
+            purged,
+            "p{margin:0}.card .title{color:red}.card:hover{color:#00f}#main{width:1px}\
+             a:not(.external):after{content:\"\"}@media print{.card{display:none}}\
+             .hljs-keyword{color:green}@keyframes spin{0%{opacity:0}}"
+        );
+    }
+
+    #[test]
+    fn test_used_names() {
+        let output = std::env::temp_dir().join("tola_purge_test");
+        let _ = fs::remove_dir_all(&output);
+        fs::create_dir_all(output.join("posts")).unwrap();
+        fs::write(
+            output.join("posts/index.html"),
+            r#"<DIV class="card  title" id=main><p class=lead>"#,
+        )
+        .unwrap();
+        fs::write(output.join("style.css"), ".ignored{}").unwrap();
+
+        let used = used_names(&output).unwrap();
+        let set = |names: &[&str]| -> HashSet<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        };
+        assert_eq!(used.tags, set(&["div", "p"]));
+        assert_eq!(used.classes, set(&["card", "title", "lead"]));
+        assert_eq!(used.ids, set(&["main"]));
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+//! Progressive web app support.
+//!
+//! Writes `manifest.webmanifest` and a service worker that precaches every
+//! page and asset of the build, so the site can be installed and read offline.
+
+use crate::{
+    config::{FeedFormat, SiteConfig},
+    log,
+    utils::{
+        blogroll,
+        build::collect_files,
+        prune, site,
+        slug::output_path,
+        xml::{compute_asset_href, get_icon_mime_type},
+    },
+};
+use anyhow::Result;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Manifest file name, relative to the site root
+pub const MANIFEST: &str = "manifest.webmanifest";
+
+/// Service worker file name, relative to the site root
+pub const SERVICE_WORKER: &str = "sw.js";
+
+/// Generate the manifest and service worker if `[build.pwa]` is enabled
+pub fn build_pwa(config: &SiteConfig) -> Result<()> {
+    if !config.build.pwa.enable {
+        return Ok(());
+    }
+    log!("pwa"; "generating manifest and service worker");
+
+    let root = config.build.output.join(&config.build.base_path);
+    let manifest_path = root.join(MANIFEST);
+    let worker_path = root.join(SERVICE_WORKER);
+
+    let manifest = serde_json::to_string_pretty(&manifest(config)?)?;
+    prune::write_produced(&manifest_path, manifest.as_bytes())?;
+
+    let mut urls = precache_urls(config);
+    urls.push(site_url(MANIFEST, config));
+    prune::write_produced(&worker_path, service_worker(&urls).as_bytes())?;
+
+    log!("pwa"; "service worker precaches {} file(s)", urls.len());
+    Ok(())
+}
+
+/// URL of a file at the site root, e.g. `/blog/sw.js`
+pub fn site_url(name: &str, config: &SiteConfig) -> String {
+    let path = PathBuf::from("/").join(&config.build.base_path).join(name);
+    path.to_string_lossy().into_owned()
+}
+
+/// Contents of `manifest.webmanifest`
+fn manifest(config: &SiteConfig) -> Result<serde_json::Value> {
+    let pwa = &config.build.pwa;
+    let name = pwa.name.as_deref().unwrap_or(&config.base.title);
+    let icons = pwa
+        .icons
+        .iter()
+        .map(|icon| {
+            Ok(json!({
+                "src": compute_asset_href(&icon.path, &config.build.base_path)?,
+                "sizes": icon.sizes,
+                "type": get_icon_mime_type(&icon.path),
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(json!({
+        "name": name,
+        "short_name": pwa.short_name.as_deref().unwrap_or(name),
+        "description": config.base.description,
+        "start_url": site_url("", config),
+        "scope": site_url("", config),
+        "display": pwa.display,
+        "theme_color": pwa.theme_color,
+        "background_color": pwa.background_color,
+        "icons": icons,
+    }))
+}
+
+/// URLs of every page and asset produced by the build, sorted
+///
+/// Computed from the sources rather than by listing the output directory, so
+/// stale files that are about to be pruned are never precached. Pages left out
+/// of the build (drafts, future posts) are not in the site model and skipped.
+fn precache_urls(config: &SiteConfig) -> Vec<String> {
+    let built: HashSet<PathBuf> = site::pages().into_iter().map(|page| page.source).collect();
+    let sources = collect_files(&config.build.content, |source| {
+        source.extension().is_none_or(|ext| ext != "typ") || built.contains(source)
+    })
+    .into_iter()
+    .chain(collect_files(&config.build.assets, |_| true));
+    let mut outputs: Vec<PathBuf> = sources
+        .filter_map(|source| output_path(&source, config).ok())
+        .collect();
+    if config.build.rss.enable {
+        let rss = &config.build.rss;
+        match rss.format {
+            FeedFormat::Rss => outputs.push(rss.path.clone()),
+            FeedFormat::Atom => outputs.push(rss.atom_path.clone()),
+            FeedFormat::Both => outputs.extend([rss.path.clone(), rss.atom_path.clone()]),
+        }
+        if rss.json.enable {
+            outputs.push(rss.json.path.clone());
+        }
+    }
+    outputs.extend(blogroll::outputs(config));
+
+    let mut urls: Vec<_> = outputs
+        .iter()
+        .filter_map(|output| output_url(output, &config.build.output))
+        .collect();
+    urls.sort();
+    urls.dedup();
+    urls
+}
+
+/// Public URL of an output file; `index.html` maps to its directory
+fn output_url(output: &Path, output_dir: &Path) -> Option<String> {
+    let relative = output.strip_prefix(output_dir).ok()?;
+    let url = format!("/{}", relative.to_str()?);
+    Some(match url.strip_suffix("index.html") {
+        Some(dir) => dir.to_owned(),
+        None => url,
+    })
+}
+
+/// Service worker script
+///
+/// Precached files are served cache-first; anything else (e.g. extracted SVGs)
+/// is fetched from the network and cached for later. The cache name is derived
+/// from the URL list, so a changed site replaces the old cache on activation.
+fn service_worker(urls: &[String]) -> String {
+    let list = serde_json::to_string_pretty(urls).unwrap_or_else(|_| "[]".into());
+    let digest = format!("{:x}", Sha1::digest(list.as_bytes()));
+    let version = &digest[..16];
+    format!(
+        r#"const CACHE = "tola-{version}";
+const PRECACHE = {list};
+
+self.addEventListener("install", (event) => {{
+  event.waitUntil(caches.open(CACHE).then((cache) => cache.addAll(PRECACHE)));
+  self.skipWaiting();
+}});
+
+self.addEventListener("activate", (event) => {{
+  event.waitUntil(
+    caches.keys().then((keys) =>
+      Promise.all(keys.filter((key) => key !== CACHE).map((key) => caches.delete(key)))
+    )
+  );
+  self.clients.claim();
+}});
+
+self.addEventListener("fetch", (event) => {{
+  if (event.request.method !== "GET") return;
+  event.respondWith(
+    caches.match(event.request).then((cached) =>
+      cached ||
+      fetch(event.request).then((response) => {{
+        if (response.ok && new URL(event.request.url).origin === location.origin) {{
+          const copy = response.clone();
+          caches.open(CACHE).then((cache) => cache.put(event.request, copy));
+        }}
+        return response;
+      }})
+    )
+  );
+}});
+"#
+    )
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_url() {
+        let output = Path::new("/site/public");
+        assert_eq!(
+            output_url(Path::new("/site/public/index.html"), output).as_deref(),
+            Some("/")
+        );
+        assert_eq!(
+            output_url(Path::new("/site/public/posts/a/index.html"), output).as_deref(),
+            Some("/posts/a/")
+        );
+        assert_eq!(
+            output_url(Path::new("/site/public/style.css"), output).as_deref(),
+            Some("/style.css")
+        );
+        assert_eq!(output_url(Path::new("/elsewhere/a.css"), output), None);
+    }
+
+    #[test]
+    fn test_site_url_with_base_path() {
+        let mut config = SiteConfig::default();
+        assert_eq!(site_url(SERVICE_WORKER, &config), "/sw.js");
+        config.build.base_path = PathBuf::from("blog");
+        assert_eq!(site_url(SERVICE_WORKER, &config), "/blog/sw.js");
+        assert_eq!(site_url("", &config), "/blog/");
+    }
+
+    #[test]
+    fn test_manifest() {
+        let mut config = SiteConfig::default();
+        config.base.title = "My Blog".into();
+        config.build.pwa.short_name = Some("Blog".into());
+        config.build.pwa.icons = vec![crate::config::PwaIcon {
+            path: PathBuf::from("icon.png"),
+            sizes: "512x512".into(),
+        }];
+
+        let manifest = manifest(&config).unwrap();
+        assert_eq!(manifest["name"], "My Blog");
+        assert_eq!(manifest["short_name"], "Blog");
+        assert_eq!(manifest["start_url"], "/");
+        assert_eq!(manifest["icons"][0]["src"], "/icon.png");
+        assert_eq!(manifest["icons"][0]["type"], "image/png");
+    }
+
+    #[test]
+    fn test_service_worker_version_follows_urls() {
+        let a = service_worker(&["/".into()]);
+        let b = service_worker(&["/".into(), "/style.css".into()]);
+        assert!(a.contains(r#"const PRECACHE = ["#));
+        assert!(b.contains(r#""/style.css""#));
+        assert_ne!(a.lines().next(), b.lines().next());
+    }
+}
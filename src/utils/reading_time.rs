@@ -0,0 +1,106 @@
+//! Word count and reading time (`[build.reading_time]`).
+//!
+//! Counted from the visible text of the page content once it is compiled, so
+//! they land in the page metadata next to the summary and reach the head,
+//! the JSON feed and the API endpoints from there.
+
+use crate::{
+    config::{ReadingTimeConfig, SiteConfig},
+    utils::{meta::PageMeta, site::extract_text},
+};
+
+/// Give `meta` the word count and reading time of `html` if it has none
+pub fn fill_reading_time(meta: &mut PageMeta, html: &[u8], config: &SiteConfig) {
+    let reading_time = &config.build.reading_time;
+    if !is_missing(meta, config) {
+        return;
+    }
+    let html = String::from_utf8_lossy(html);
+    // Same content as the summary: `<main>`, `<article>` or `<body>`, the first one present
+    let content = ["<main", "<article", "<body"]
+        .iter()
+        .find_map(|tag| html.find(tag).map(|start| &html[start..]))
+        .unwrap_or(&html);
+    let (words, cjk) = count(&extract_text(content.as_bytes()), reading_time.cjk);
+    meta.word_count = Some(words + cjk);
+    meta.reading_time = Some(minutes(words, cjk, reading_time));
+}
+
+/// Whether `[build.reading_time]` is enabled and `meta` was not counted yet
+pub fn is_missing(meta: &PageMeta, config: &SiteConfig) -> bool {
+    config.build.reading_time.enable && meta.word_count.is_none()
+}
+
+/// Words and CJK characters in `text`
+///
+/// With `cjk` off, CJK characters are part of the words around them, which
+/// suits text that separates words with spaces.
+fn count(text: &str, cjk: bool) -> (usize, usize) {
+    let mut characters = 0;
+    let words = text
+        .split(|c: char| {
+            let split = cjk && is_cjk(c);
+            characters += usize::from(split);
+            split || c.is_whitespace()
+        })
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+    (words, characters)
+}
+
+/// Minutes to read, rounded up and at least one
+fn minutes(words: usize, cjk: usize, config: &ReadingTimeConfig) -> usize {
+    let minutes = words as f64 / config.words_per_minute.max(1) as f64
+        + cjk as f64 / config.cjk_per_minute.max(1) as f64;
+    (minutes.ceil() as usize).max(1)
+}
+
+/// Han, kana and hangul, which are read character by character
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+            | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+            | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+            | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+            | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+            | '\u{20000}'..='\u{2EBEF}' // CJK Extensions B-F
+    )
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        assert_eq!(count("Hello, world — it's me", true), (4, 0));
+        assert_eq!(count("小鹤双拼 is a 双拼 scheme", true), (3, 6));
+        assert_eq!(count("小鹤双拼 is a 双拼 scheme", false), (5, 0));
+    }
+
+    #[test]
+    fn test_minutes() {
+        let config = ReadingTimeConfig::default();
+        assert_eq!(minutes(0, 0, &config), 1);
+        assert_eq!(minutes(200, 0, &config), 1);
+        assert_eq!(minutes(201, 0, &config), 2);
+        assert_eq!(minutes(200, 500, &config), 2);
+    }
+
+    #[test]
+    fn test_fill_reading_time() {
+        let mut config = SiteConfig::default();
+        config.build.reading_time.enable = true;
+        let html = b"<html><head><title>Skipped title</title></head>\
+            <body><nav>Home</nav><main><p>One two three</p><p>\xE4\xBD\xA0\xE5\xA5\xBD</p></main></body></html>";
+        let mut meta = PageMeta::default();
+        fill_reading_time(&mut meta, html, &config);
+        assert_eq!(meta.word_count, Some(5));
+        assert_eq!(meta.reading_time, Some(1));
+    }
+}
@@ -0,0 +1,313 @@
+//! Redirects from old paths.
+//!
+//! Writes a meta refresh stub at every old path from `[build.redirects]` and
+//! from page metadata, so links to restructured content keep working on any
+//! host, plus redirect rules for hosts that can serve real HTTP redirects.
+
+use crate::{
+    config::{RedirectFile, SiteConfig},
+    log,
+    utils::{
+        prune, report,
+        site::{self, Page},
+    },
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Cursor,
+    path::{Component, Path, PathBuf},
+};
+
+/// Write the redirect stubs and files if `[build.redirects]` is enabled
+pub fn build_redirects(config: &SiteConfig) -> Result<()> {
+    if !config.build.redirects.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let redirects = collect_redirects(&pages, config);
+    let page_outputs: HashSet<&Path> = pages.iter().map(|page| page.output.as_path()).collect();
+
+    let mut written = 0;
+    for (from, to) in &redirects {
+        let Some(stub) = stub_path(from, config) else {
+            report::warning(
+                &config.config_path,
+                format!("invalid redirect path `{from}`"),
+            );
+            continue;
+        };
+        // Never replace a page that is still built
+        if page_outputs.contains(stub.as_path()) {
+            report::warning(
+                &config.config_path,
+                format!(
+                    "redirect `{from}` skipped: a page is built at {}",
+                    stub.display()
+                ),
+            );
+            continue;
+        }
+        prune::write_produced(&stub, &render_stub(to)?)?;
+        written += 1;
+    }
+
+    let rules: Vec<(String, &str)> = redirects
+        .iter()
+        .map(|(from, to)| (site_path(from, config), to.as_str()))
+        .collect();
+    for &file in &config.build.redirects.files {
+        let content = match file {
+            RedirectFile::Redirects => redirects_file(&rules),
+            RedirectFile::Netlify => netlify_toml(&rules)?,
+            RedirectFile::Vercel => vercel_json(&rules)?,
+        };
+        prune::write_produced(
+            &config.build.output.join(file.file_name()),
+            content.as_bytes(),
+        )?;
+    }
+
+    log!("redirects"; "{written} redirect(s) written");
+    Ok(())
+}
+
+/// Old path → new path or URL, from the config, then from page metadata
+///
+/// A page redirect from a path the config already redirects elsewhere is
+/// reported and ignored.
+fn collect_redirects(pages: &[Page], config: &SiteConfig) -> BTreeMap<String, String> {
+    let mut redirects: BTreeMap<String, String> = config
+        .build
+        .redirects
+        .paths
+        .iter()
+        .map(|(from, to)| (from.clone(), target_url(to, config)))
+        .collect();
+
+    for page in pages {
+        let Some(meta) = &page.meta else {
+            continue;
+        };
+        for from in &meta.redirects {
+            match redirects.get(from) {
+                Some(to) if *to != page.url => report::warning(
+                    &page.source,
+                    format!("redirect `{from}` already points to {to}"),
+                ),
+                Some(_) => {}
+                None => {
+                    redirects.insert(from.clone(), page.url.clone());
+                }
+            }
+        }
+    }
+    redirects
+}
+
+/// URL of a redirect target: paths starting with `/` are below `base_path`
+fn target_url(to: &str, config: &SiteConfig) -> String {
+    match to.starts_with('/') {
+        true => site_path(to, config),
+        false => to.to_owned(),
+    }
+}
+
+/// Root-relative path of `path` (relative to `base_path`), keeping a trailing `/`
+fn site_path(path: &str, config: &SiteConfig) -> String {
+    let base_path = config.build.base_path.to_string_lossy();
+    let base_path = base_path.trim_matches('/');
+    let path = path.trim_start_matches('/');
+    match base_path.is_empty() {
+        true => format!("/{path}"),
+        false => format!("/{base_path}/{path}"),
+    }
+}
+
+/// Output file of the stub for old path `from`
+///
+/// Paths without an extension (or ending with `/`) get an `index.html`.
+/// Paths leaving the output directory are rejected.
+fn stub_path(from: &str, config: &SiteConfig) -> Option<PathBuf> {
+    let relative = Path::new(from.trim_start_matches('/'));
+    let is_normal = |component| matches!(component, Component::Normal(_));
+    if !relative.components().all(is_normal) {
+        return None;
+    }
+
+    let dir = config.build.output.join(&config.build.base_path);
+    let is_dir = from.ends_with('/') || relative.extension().is_none();
+    Some(match is_dir {
+        true => dir.join(relative).join("index.html"),
+        false => dir.join(relative),
+    })
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Page sending the browser (and search engines) on to `to`
+fn render_stub(to: &str) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    let refresh = format!("0; url={to}");
+
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::Start(BytesStart::new("title")))?;
+    writer.write_event(Event::Text(BytesText::new("Redirecting…")))?;
+    writer.write_event(Event::End(BytesEnd::new("title")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("link").with_attributes([("rel", "canonical"), ("href", to)]),
+    ))?;
+    writer.write_event(Event::Empty(BytesStart::new("meta").with_attributes([
+        ("http-equiv", "refresh"),
+        ("content", refresh.as_str()),
+    ])))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("name", "robots"), ("content", "noindex")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("a").with_attributes([("href", to)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(to)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+/// `_redirects`: one `from to 301` line per redirect
+fn redirects_file(rules: &[(String, &str)]) -> String {
+    rules
+        .iter()
+        .map(|(from, to)| format!("{from} {to} 301\n"))
+        .collect()
+}
+
+fn netlify_toml(rules: &[(String, &str)]) -> Result<String> {
+    #[derive(Serialize)]
+    struct Netlify<'a> {
+        redirects: Vec<NetlifyRedirect<'a>>,
+    }
+    #[derive(Serialize)]
+    struct NetlifyRedirect<'a> {
+        from: &'a str,
+        to: &'a str,
+        status: u16,
+    }
+
+    let redirects = rules
+        .iter()
+        .map(|(from, to)| NetlifyRedirect {
+            from,
+            to,
+            status: 301,
+        })
+        .collect();
+    Ok(toml::to_string(&Netlify { redirects })?)
+}
+
+fn vercel_json(rules: &[(String, &str)]) -> Result<String> {
+    let redirects: Vec<_> = rules
+        .iter()
+        .map(|(from, to)| serde_json::json!({ "source": from, "destination": to, "permanent": true }))
+        .collect();
+    Ok(serde_json::to_string_pretty(
+        &serde_json::json!({ "redirects": redirects }),
+    )?)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn config() -> SiteConfig {
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/site/public");
+        config.build.base_path = PathBuf::from("blog");
+        config
+            .build
+            .redirects
+            .paths
+            .insert("/old/".into(), "/new/".into());
+        config
+    }
+
+    fn page(url: &str, redirects: &[&str]) -> Page {
+        Page {
+            url: url.into(),
+            meta: Some(PageMeta {
+                redirects: redirects.iter().map(|r| r.to_string()).collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collect_redirects() {
+        let pages = [
+            page("https://example.com/blog/a/", &["/2020/a/", "/old/"]),
+            page("https://example.com/blog/b/", &[]),
+        ];
+        let redirects = collect_redirects(&pages, &config());
+        assert_eq!(redirects.len(), 2);
+        assert_eq!(redirects["/old/"], "/blog/new/");
+        assert_eq!(redirects["/2020/a/"], "https://example.com/blog/a/");
+    }
+
+    #[test]
+    fn test_stub_path() {
+        let config = config();
+        assert_eq!(
+            stub_path("/old/", &config),
+            Some(PathBuf::from("/site/public/blog/old/index.html"))
+        );
+        assert_eq!(
+            stub_path("feed.rss", &config),
+            Some(PathBuf::from("/site/public/blog/feed.rss"))
+        );
+        assert_eq!(stub_path("/../etc/passwd", &config), None);
+    }
+
+    #[test]
+    fn test_render_stub() {
+        let html = String::from_utf8(render_stub("/new/?a=1&b=2").unwrap()).unwrap();
+        assert!(
+            html.contains(r#"<meta http-equiv="refresh" content="0; url=/new/?a=1&amp;b=2"/>"#)
+        );
+        assert!(html.contains(r#"<link rel="canonical" href="/new/?a=1&amp;b=2"/>"#));
+    }
+
+    #[test]
+    fn test_redirect_files() {
+        let rules = [("/blog/old/".to_string(), "/blog/new/")];
+        assert_eq!(redirects_file(&rules), "/blog/old/ /blog/new/ 301\n");
+        assert_eq!(
+            netlify_toml(&rules).unwrap(),
+            "[[redirects]]\nfrom = \"/blog/old/\"\nto = \"/blog/new/\"\nstatus = 301\n"
+        );
+        let vercel: serde_json::Value =
+            serde_json::from_str(&vercel_json(&rules).unwrap()).unwrap();
+        assert_eq!(vercel["redirects"][0]["destination"], "/blog/new/");
+        assert_eq!(vercel["redirects"][0]["permanent"], true);
+    }
+}
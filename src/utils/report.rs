@@ -0,0 +1,164 @@
+//! Build diagnostics aggregation.
+//!
+//! Per-file errors and warnings are recorded while the build keeps going, then
+//! printed as one grouped report once every file has been attempted.
+
+use crate::{
+    log,
+    observer::{self, BuildSummary},
+};
+use anyhow::{Result, bail};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Severity of a recorded diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single diagnostic attached to a source file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub severity: Severity,
+    pub message: String,
+}
+
+static DIAGNOSTICS: Mutex<Vec<Diagnostic>> = Mutex::new(Vec::new());
+
+fn push(path: &Path, severity: Severity, message: String) {
+    let mut diagnostics = DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner());
+    diagnostics.push(Diagnostic {
+        path: path.to_path_buf(),
+        severity,
+        message,
+    });
+}
+
+/// Record a failure for `path`, including the full error chain.
+pub fn error(path: &Path, err: &anyhow::Error) {
+    observer::notify(|o| o.on_error(path, err));
+    push(path, Severity::Error, format!("{err:#}"));
+}
+
+/// Record a warning for `path`.
+pub fn warning(path: &Path, message: impl Into<String>) {
+    let message = message.into();
+    observer::notify(|o| o.on_warning(path, &message));
+    push(path, Severity::Warning, message);
+}
+
+/// Drain all recorded diagnostics.
+pub fn take() -> Vec<Diagnostic> {
+    std::mem::take(&mut *DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Print the grouped report and fail if any errors were recorded.
+///
+/// Observers are notified with the final counts first.
+///
+/// Paths are shown relative to `root` where possible.
+pub fn finish(root: &Path) -> Result<()> {
+    let diagnostics = take();
+    let errors = count(&diagnostics, Severity::Error);
+    let warnings = count(&diagnostics, Severity::Warning);
+    observer::notify(|o| o.on_finish(&BuildSummary { errors, warnings }));
+
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+
+    let groups = group_by_file(&diagnostics);
+
+    log!(true; "report"; "{errors} error(s), {warnings} warning(s) in {} file(s)", groups.len());
+    for (path, items) in &groups {
+        let path = path.strip_prefix(root).unwrap_or(path);
+        let file_errors = items
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        let module = if file_errors > 0 { "error" } else { "warn" };
+        log!(true; module; "{} ({file_errors} error(s), {} warning(s))", path.display(), items.len() - file_errors);
+        for item in items {
+            let label = match item.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            // Lines after the first (source spans, snippets, hints) are indented under it
+            let mut lines = item.message.lines();
+            if let Some(first) = lines.next() {
+                log!(true; module; "  {label}: {first}");
+            }
+            for line in lines {
+                log!(true; module; "    {line}");
+            }
+        }
+    }
+
+    if errors > 0 {
+        bail!("Build finished with {errors} error(s)");
+    }
+    Ok(())
+}
+
+/// Group diagnostics by file, in stable path order
+fn group_by_file(diagnostics: &[Diagnostic]) -> BTreeMap<&Path, Vec<&Diagnostic>> {
+    let mut groups: BTreeMap<&Path, Vec<&Diagnostic>> = BTreeMap::new();
+    for d in diagnostics {
+        groups.entry(d.path.as_path()).or_default().push(d);
+    }
+    groups
+}
+
+fn count(diagnostics: &[Diagnostic], severity: Severity) -> usize {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == severity)
+        .count()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(path: &str, severity: Severity) -> Diagnostic {
+        Diagnostic {
+            path: PathBuf::from(path),
+            severity,
+            message: "msg".into(),
+        }
+    }
+
+    #[test]
+    fn test_group_by_file() {
+        let diagnostics = vec![
+            diag("b.typ", Severity::Error),
+            diag("a.typ", Severity::Warning),
+            diag("b.typ", Severity::Warning),
+        ];
+        let groups = group_by_file(&diagnostics);
+        let keys: Vec<_> = groups.keys().map(|p| p.to_str().unwrap()).collect();
+        assert_eq!(keys, vec!["a.typ", "b.typ"]);
+        assert_eq!(groups[Path::new("b.typ")].len(), 2);
+    }
+
+    #[test]
+    fn test_count() {
+        let diagnostics = vec![
+            diag("a.typ", Severity::Error),
+            diag("a.typ", Severity::Warning),
+            diag("b.typ", Severity::Error),
+        ];
+        assert_eq!(count(&diagnostics, Severity::Error), 2);
+        assert_eq!(count(&diagnostics, Severity::Warning), 1);
+    }
+}
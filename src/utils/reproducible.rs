@@ -0,0 +1,93 @@
+//! Reproducible output (`[build.reproducible]`).
+//!
+//! Two builds of the same commit should write byte-identical files, so the
+//! one clock a build reads is pinned to the source date: `SOURCE_DATE_EPOCH`
+//! if set, else the commit time of the site's `HEAD`. Typst gets it as the
+//! current date (`datetime.today()`, PDF dates; `--creation-timestamp` for the
+//! CLI), and every output file gets it as its modification time once the build
+//! is done.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{build::collect_files, git},
+};
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+/// Environment variable overriding the source date, as specified by reproducible-builds.org
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Source date in seconds since the Unix epoch, if `[build.reproducible]` is enabled
+///
+/// Resolved once per process; `None` if neither source is available.
+pub fn source_date(config: &SiteConfig) -> Option<i64> {
+    static SOURCE_DATE: OnceLock<Option<i64>> = OnceLock::new();
+    if !config.build.reproducible.enable {
+        return None;
+    }
+    *SOURCE_DATE.get_or_init(|| {
+        let from_env = std::env::var(SOURCE_DATE_EPOCH)
+            .ok()
+            .and_then(|epoch| epoch.trim().parse().ok());
+        let date = from_env.or_else(|| git::head_commit_time(config.get_root()).ok());
+        if date.is_none() {
+            log!("warn"; "[build.reproducible] needs {SOURCE_DATE_EPOCH} or a git commit, output timestamps are left as is");
+        }
+        date
+    })
+}
+
+/// `--creation-timestamp <date>` for typst, or empty (filtered out) arguments
+pub fn typst_args(config: &SiteConfig) -> [String; 2] {
+    match source_date(config) {
+        Some(date) => ["--creation-timestamp".into(), date.to_string()],
+        None => Default::default(),
+    }
+}
+
+/// Set the modification time of every output file to the source date
+pub fn build_reproducible(config: &SiteConfig) -> Result<()> {
+    let Some(date) = source_date(config) else {
+        return Ok(());
+    };
+    let time = match u64::try_from(date) {
+        Ok(seconds) => SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+        Err(_) => SystemTime::UNIX_EPOCH,
+    };
+
+    let git_dir = config.build.output.join(".git");
+    let files = collect_files(&config.build.output, |path| !path.starts_with(&git_dir));
+    for file in &files {
+        fs::File::options()
+            .write(true)
+            .open(file)
+            .and_then(|file| file.set_modified(time))
+            .with_context(|| {
+                format!("Failed to set the modification time of {}", file.display())
+            })?;
+    }
+
+    log!("reproducible"; "{} file(s) dated {date}", files.len());
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typst_args_disabled() {
+        let config = SiteConfig::default();
+        assert_eq!(source_date(&config), None);
+        assert_eq!(typst_args(&config), [String::new(), String::new()]);
+    }
+}
@@ -0,0 +1,296 @@
+//! Declarative element rewrites (`[[build.html.rewrite]]`).
+//!
+//! Rules rename, remove and set attributes of matching elements and can wrap
+//! them in another element, whose end tag is written after the element's own.
+
+use crate::config::{RewriteRule, WrapRule};
+use crate::utils::sanitize::VOID_ELEMENTS;
+use crate::utils::xml::XmlWriter;
+use anyhow::Result;
+use gix::glob::wildmatch;
+use quick_xml::{
+    escape::escape,
+    events::{BytesEnd, BytesStart, Event},
+};
+use std::borrow::Cow;
+
+/// Element wrapped by a rule, waiting for its end tag
+#[derive(Debug)]
+struct OpenWrap {
+    tag: Vec<u8>,
+    /// Start tags with the same name opened inside it and not yet closed
+    nesting: usize,
+    /// Wrapper tags, outermost first
+    wrappers: Vec<String>,
+}
+
+/// Elements wrapped by rules, innermost last
+#[derive(Debug, Default)]
+pub struct Wrapped(Vec<OpenWrap>);
+
+impl Wrapped {
+    /// Write the wrappers of `elem` and return it with its attributes rewritten
+    ///
+    /// Every call must be followed by [`Wrapped::close`] at the element's end
+    /// tag, or right after it for void and empty elements.
+    pub fn open<'e>(
+        &mut self,
+        elem: &'e BytesStart<'e>,
+        writer: &mut XmlWriter,
+        rules: &[RewriteRule],
+    ) -> Result<Cow<'e, BytesStart<'e>>> {
+        let name = elem.name().as_ref().to_vec();
+        let Some((elem, wraps)) = apply(elem, rules) else {
+            self.enter(&name);
+            return Ok(Cow::Borrowed(elem));
+        };
+
+        if wraps.is_empty() {
+            self.enter(&name);
+        } else {
+            for wrap in &wraps {
+                writer.write_event(Event::Start(wrapper_start(wrap)))?;
+            }
+            self.0.push(OpenWrap {
+                tag: name,
+                nesting: 0,
+                wrappers: wraps.into_iter().map(|wrap| wrap.tag.clone()).collect(),
+            });
+        }
+        Ok(Cow::Owned(elem))
+    }
+
+    /// Write the end tags of the wrappers closing with the element `name`
+    pub fn close(&mut self, name: &[u8], writer: &mut XmlWriter) -> Result<()> {
+        let Some(index) = self.0.iter().rposition(|open| open.tag == name) else {
+            return Ok(());
+        };
+        if self.0[index].nesting > 0 {
+            self.0[index].nesting -= 1;
+            return Ok(());
+        }
+        let open = self.0.remove(index);
+        for tag in open.wrappers.iter().rev() {
+            writer.write_event(Event::End(BytesEnd::new(tag.as_str())))?;
+        }
+        Ok(())
+    }
+
+    /// Count a start tag opened inside a wrapped element of the same name
+    fn enter(&mut self, name: &[u8]) {
+        if let Some(open) = self.0.iter_mut().rev().find(|open| open.tag == name) {
+            open.nesting += 1;
+        }
+    }
+}
+
+/// Whether `name` never has an end tag
+pub fn is_void(name: &[u8]) -> bool {
+    VOID_ELEMENTS.iter().any(|void| void.as_bytes() == name)
+}
+
+/// `elem` rewritten by the rules matching it, with their wraps;
+/// `None` when no rule matches
+fn apply<'r>(
+    elem: &BytesStart<'_>,
+    rules: &'r [RewriteRule],
+) -> Option<(BytesStart<'static>, Vec<&'r WrapRule>)> {
+    if rules.is_empty() {
+        return None;
+    }
+    let name = elem.name().as_ref().to_vec();
+    let mut attrs: Vec<(Vec<u8>, Vec<u8>)> = elem
+        .attributes()
+        .flatten()
+        .map(|attr| (attr.key.as_ref().to_vec(), attr.value.into_owned()))
+        .collect();
+
+    let mut matched = false;
+    let mut wraps = Vec::new();
+    for rule in rules {
+        // Later rules see the attributes as earlier ones left them
+        if !rule_matches(rule, &name, &attrs) {
+            continue;
+        }
+        matched = true;
+        for (from, to) in &rule.rename {
+            for (key, _) in attrs.iter_mut().filter(|(key, _)| key == from.as_bytes()) {
+                *key = to.as_bytes().to_vec();
+            }
+        }
+        attrs.retain(|(key, _)| !rule.remove.iter().any(|name| name.as_bytes() == key));
+        for (key, value) in &rule.set {
+            let value = escape(value.as_str()).as_bytes().to_vec();
+            match attrs.iter_mut().find(|(k, _)| k == key.as_bytes()) {
+                Some((_, existing)) => *existing = value,
+                None => attrs.push((key.as_bytes().to_vec(), value)),
+            }
+        }
+        wraps.extend(&rule.wrap);
+    }
+    if !matched {
+        return None;
+    }
+
+    let mut new_elem = BytesStart::new(String::from_utf8_lossy(&name).into_owned());
+    for (key, value) in &attrs {
+        new_elem.push_attribute((key.as_slice(), value.as_slice()));
+    }
+    Some((new_elem, wraps))
+}
+
+/// Whether `rule` matches the element `name` with `attrs`
+fn rule_matches(rule: &RewriteRule, name: &[u8], attrs: &[(Vec<u8>, Vec<u8>)]) -> bool {
+    if rule.tag != "*" && rule.tag.as_bytes() != name {
+        return false;
+    }
+    let Some(attribute) = &rule.attribute else {
+        return true;
+    };
+    let Some((_, value)) = attrs.iter().find(|(key, _)| key == attribute.as_bytes()) else {
+        return false;
+    };
+    rule.value.as_ref().is_none_or(|pattern| {
+        wildmatch(
+            pattern.as_str().into(),
+            value.as_slice().into(),
+            wildmatch::Mode::empty(),
+        )
+    })
+}
+
+/// Start tag of a wrapper element
+fn wrapper_start(wrap: &WrapRule) -> BytesStart<'static> {
+    let mut elem = BytesStart::new(wrap.tag.clone());
+    for (key, value) in &wrap.attributes {
+        elem.push_attribute((key.as_str(), value.as_str()));
+    }
+    elem
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::xml::create_xml_reader;
+    use std::io::Cursor;
+
+    fn rules(toml: &str) -> Vec<RewriteRule> {
+        #[derive(serde::Deserialize)]
+        struct Rules {
+            rewrite: Vec<RewriteRule>,
+        }
+        toml::from_str::<Rules>(toml).unwrap().rewrite
+    }
+
+    /// Run `html` through the rules the way `process_html` does
+    fn rewrite(html: &str, rules: &[RewriteRule]) -> String {
+        let mut reader = create_xml_reader(html.as_bytes());
+        let mut writer = XmlWriter::new(Cursor::new(Vec::new()));
+        let mut wrapped = Wrapped::default();
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(elem) => {
+                    let name = elem.name().as_ref().to_vec();
+                    let new_elem = wrapped.open(&elem, &mut writer, rules).unwrap();
+                    writer
+                        .write_event(Event::Start(new_elem.into_owned()))
+                        .unwrap();
+                    if is_void(&name) {
+                        wrapped.close(&name, &mut writer).unwrap();
+                    }
+                }
+                Event::Empty(elem) => {
+                    let new_elem = wrapped.open(&elem, &mut writer, rules).unwrap();
+                    writer
+                        .write_event(Event::Empty(new_elem.into_owned()))
+                        .unwrap();
+                    wrapped.close(elem.name().as_ref(), &mut writer).unwrap();
+                }
+                Event::End(elem) => {
+                    writer.write_event(Event::End(elem.to_owned())).unwrap();
+                    wrapped.close(elem.name().as_ref(), &mut writer).unwrap();
+                }
+                Event::Eof => break,
+                event => writer.write_event(event).unwrap(),
+            }
+        }
+        String::from_utf8(writer.into_inner().into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_attributes() {
+        let rules = rules(
+            r#"
+            [[rewrite]]
+            tag = "img"
+            rename = { "data-src" = "src" }
+            remove = ["style"]
+            set = { loading = "lazy", alt = "a \"b\"" }
+            "#,
+        );
+        assert_eq!(
+            rewrite(
+                r#"<p><img data-src="a.png" style="x" alt="old"></p>"#,
+                &rules
+            ),
+            r#"<p><img src="a.png" alt="a &quot;b&quot;" loading="lazy"></p>"#
+        );
+    }
+
+    #[test]
+    fn test_attribute_value_glob() {
+        let rules = rules(
+            r#"
+            [[rewrite]]
+            tag = "*"
+            attribute = "href"
+            value = "*.pdf"
+            set = { download = "" }
+            "#,
+        );
+        assert_eq!(
+            rewrite(r#"<a href="a.pdf">a</a><a href="b.html">b</a>"#, &rules),
+            r#"<a href="a.pdf" download="">a</a><a href="b.html">b</a>"#
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let rules = rules(
+            r#"
+            [[rewrite]]
+            tag = "div"
+            attribute = "class"
+            value = "outer"
+            wrap = { tag = "section" }
+            [[rewrite]]
+            tag = "img"
+            wrap = { tag = "figure", attributes = { class = "image" } }
+            "#,
+        );
+        assert_eq!(
+            rewrite(
+                r#"<div class="outer"><div><img src="a.png"></div></div><img src="b.png"/>"#,
+                &rules
+            ),
+            concat!(
+                r#"<section><div class="outer"><div><figure class="image"><img src="a.png"></figure></div></div></section>"#,
+                r#"<figure class="image"><img src="b.png"/></figure>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_no_match_borrows() {
+        let rules = rules("[[rewrite]]\ntag = \"table\"\n");
+        let elem = BytesStart::new("p");
+        let mut writer = XmlWriter::new(Cursor::new(Vec::new()));
+        let mut wrapped = Wrapped::default();
+        let new_elem = wrapped.open(&elem, &mut writer, &rules).unwrap();
+        assert!(matches!(new_elem, Cow::Borrowed(_)));
+    }
+}
@@ -1,32 +1,45 @@
-//! RSS feed generation.
+//! RSS and Atom feed generation.
 //!
-//! Parses post metadata and generates RSS/Atom feeds.
+//! Generates the feeds from page metadata in the site model; `[build.rss]
+//! format` selects RSS 2.0, Atom 1.0, or both, and `[build.rss.json]` adds a
+//! JSON Feed 1.1.
+//!
+//! Every feed written during a build (site, section, author and taxonomy
+//! feeds) is recorded in a registry, from which `[build.rss.opml]` lists them
+//! in one OPML file.
 
 use crate::{
-    config::SiteConfig,
-    exec, log,
-    utils::{build::collect_files, slug::content_paths},
+    config::{FeedFormat, FeedSort, SiteConfig},
+    log,
+    utils::{
+        opml, prune,
+        site::{self, Page, page_url, section},
+    },
 };
-use anyhow::{Context, Ok, Result, anyhow, bail};
-use rayon::prelude::*;
+use anyhow::{Ok, Result, anyhow, bail};
+use atom_syndication::{Content, Entry, Feed, FixedDateTime, Generator, Link, Person, Text};
+use chrono::NaiveDate;
 use regex::Regex;
 use rss::{ChannelBuilder, GuidBuilder, ItemBuilder, validation::Validate};
-use serde::{Deserialize, Serialize};
-use std::{fs, path::Path, sync::LazyLock};
-
-// ============================================================================
-// Constants
-// ============================================================================
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, MutexGuard},
+};
 
-/// Tag name for querying typst metadata
-const META_TAG_NAME: &str = "<tola-meta>";
+/// Namespace of the RSS `content:encoded` element
+const CONTENT_NAMESPACE: &str = "http://purl.org/rss/1.0/modules/content/";
 
 // ============================================================================
 // Date/Time Types
 // ============================================================================
 
 /// UTC datetime without timezone complexity
-#[derive(Debug, Clone, Copy)]
+///
+/// Fields are ordered from the year down, so the derived order is chronological.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DateTimeUtc {
     pub year: u16,
     pub month: u8,
@@ -163,6 +176,13 @@ impl DateTimeUtc {
         )
     }
 
+    /// Convert for the Atom generator, which uses RFC 3339 dates
+    pub fn to_fixed(self) -> Option<FixedDateTime> {
+        let date = NaiveDate::from_ymd_opt(self.year.into(), self.month.into(), self.day.into())?;
+        let time = date.and_hms_opt(self.hour.into(), self.minute.into(), self.second.into())?;
+        Some(time.and_utc().fixed_offset())
+    }
+
     #[inline]
     fn weekday_index(&self) -> usize {
         let (y, m) = if self.month < 3 {
@@ -210,7 +230,8 @@ fn parse_u16(bytes: &[u8]) -> Option<u16> {
 // RSS Feed Types
 // ============================================================================
 
-/// RSS feed builder
+/// Feed builder, rendering RSS and/or Atom
+#[derive(Clone)]
 pub struct RssFeed {
     title: String,
     description: String,
@@ -219,20 +240,57 @@ pub struct RssFeed {
     posts: Vec<PostMeta>,
 }
 
-/// Metadata extracted from a post
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Metadata of a post, in the form needed for an RSS item or Atom entry
+#[derive(Debug, Clone, Default)]
 struct PostMeta {
     title: Option<String>,
     summary: Option<String>,
     date: Option<String>,
-    #[allow(dead_code)]
     update: Option<String>,
-    #[serde(default)]
     link: Option<String>,
+    /// RSS form of the author, `email (Name)`
     author: Option<String>,
+    /// Author as written in the page metadata
+    author_name: Option<String>,
+    /// Rendered body, with `[build.rss] full_content`
+    content: Option<String>,
+    /// Words in the body, with `[build.reading_time]`
+    word_count: Option<usize>,
+    /// Minutes to read the body, with `[build.reading_time]`
+    reading_time: Option<usize>,
 }
 
 impl PostMeta {
+    /// Take a page from the site model; pages without metadata are skipped
+    fn from_page(page: Page, config: &SiteConfig) -> Option<Self> {
+        let content = config
+            .build
+            .rss
+            .full_content
+            .then(|| page_content(&page.output, config))
+            .flatten();
+        let meta = page.meta?;
+        Some(Self {
+            title: meta.title,
+            summary: meta.summary,
+            date: meta.date,
+            update: meta.update,
+            link: Some(page.url),
+            author: normalize_rss_author(meta.author.as_ref(), config),
+            author_name: meta.author,
+            content,
+            word_count: meta.word_count,
+            reading_time: meta.reading_time,
+        })
+    }
+
+    /// Whether the post has what every feed format requires
+    fn is_complete(&self) -> bool {
+        self.title.is_some()
+            && self.link.is_some()
+            && self.date.as_deref().and_then(DateTimeUtc::parse).is_some()
+    }
+
     /// Convert to RSS item, returns None if required fields are missing
     fn into_rss_item(self) -> Option<rss::Item> {
         let title = self.title?;
@@ -245,124 +303,228 @@ impl PostMeta {
                 .link(self.link)
                 .guid(GuidBuilder::default().permalink(true).value(link).build())
                 .description(self.summary)
+                .content(self.content)
                 .pub_date(pub_date)
                 .author(self.author)
                 .build(),
         )
     }
+
+    /// Convert to Atom entry, returns None if required fields are missing
+    fn into_atom_entry(self) -> Option<Entry> {
+        let title = self.title?;
+        let link = self.link?;
+        let published = DateTimeUtc::parse(self.date.as_deref()?)?.to_fixed()?;
+        let updated = self
+            .update
+            .as_deref()
+            .and_then(DateTimeUtc::parse)
+            .and_then(DateTimeUtc::to_fixed)
+            .unwrap_or(published);
+
+        let mut entry = Entry::default();
+        entry.set_title(title);
+        entry.set_id(link.clone());
+        entry.set_links(vec![alternate_link(link)]);
+        entry.set_published(published);
+        entry.set_updated(updated);
+        entry.set_summary(self.summary.map(Text::html));
+        if let Some(html) = self.content {
+            let mut content = Content::default();
+            content.set_content_type("html".to_string());
+            content.set_value(html);
+            entry.set_content(content);
+        }
+        if let Some(name) = self.author_name {
+            let mut author = Person::default();
+            author.set_name(name);
+            entry.set_authors(vec![author]);
+        }
+        Some(entry)
+    }
+
+    /// Convert to JSON Feed item, returns None if required fields are missing
+    fn into_json_item(self) -> Option<serde_json::Value> {
+        let title = self.title?;
+        let link = self.link?;
+        let published = DateTimeUtc::parse(self.date.as_deref()?)?.to_fixed()?;
+        let modified = self
+            .update
+            .as_deref()
+            .and_then(DateTimeUtc::parse)
+            .and_then(DateTimeUtc::to_fixed);
+
+        let mut item = json!({
+            "id": link,
+            "url": link,
+            "title": title,
+            "content_html": self.content.or(self.summary).unwrap_or_default(),
+            "date_published": published.to_rfc3339(),
+        });
+        if let Some(modified) = modified {
+            item["date_modified"] = modified.to_rfc3339().into();
+        }
+        if let Some(name) = self.author_name {
+            item["authors"] = json!([{ "name": name }]);
+        }
+        // Extensions start with an underscore in JSON Feed
+        if let (Some(words), Some(minutes)) = (self.word_count, self.reading_time) {
+            item["_tola"] = json!({ "word_count": words, "reading_time": minutes });
+        }
+        Some(item)
+    }
+}
+
+fn alternate_link(href: String) -> Link {
+    let mut link = Link::default();
+    link.set_href(href);
+    link.set_rel("alternate");
+    link
 }
 
 // ============================================================================
-// Typst Element Parsing
+// Public API
 // ============================================================================
 
-/// Represents parsed Typst content elements
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(tag = "func", rename_all = "lowercase")]
-enum TypstElement {
-    Space,
-    Linebreak,
-    Text {
-        text: String,
-    },
-    Strike {
-        text: String,
-    },
-    Link {
-        dest: String,
-        body: Box<TypstElement>,
-    },
-    Sequence {
-        children: Vec<TypstElement>,
-    },
-    #[serde(other)]
-    Unknown,
-}
-
-impl TypstElement {
-    /// Convert Typst element to HTML string
-    fn to_html(&self, base_url: &str) -> String {
-        match self {
-            Self::Space => " ".into(),
-            Self::Linebreak => "<br/>".into(),
-            Self::Text { text } => html_escape(text),
-            Self::Strike { text } => format!("<s>{}</s>", html_escape(text)),
-            Self::Link { dest, body } => {
-                let href = normalize_link(dest, base_url);
-                format!("<a href=\"{}\">{}</a>", href, body.to_html(base_url))
-            }
-            Self::Sequence { children } => {
-                let mut result = String::new();
-                for child in children {
-                    result.push_str(&child.to_html(base_url));
-                }
-                result
-            }
-            Self::Unknown => String::new(),
+pub fn build_rss(config: &SiteConfig) -> Result<()> {
+    // Feeds are registered again as they are written
+    feeds_lock().clear();
+    if config.build.rss.enable {
+        RssFeed::build(config)?.write(config)?;
+        if config.build.rss.sections.enable {
+            build_section_feeds(config)?;
         }
     }
+    Ok(())
 }
 
-/// Escape HTML special characters
-#[inline]
-fn html_escape(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '<' => result.push_str("&lt;"),
-            '>' => result.push_str("&gt;"),
-            '&' => result.push_str("&amp;"),
-            '"' => result.push_str("&quot;"),
-            _ => result.push(c),
+/// Write `<section>/feed.xml` (and/or `atom.xml`) for the `[build.rss.sections]`
+fn build_section_feeds(config: &SiteConfig) -> Result<()> {
+    for (name, pages) in section_pages(site::pages(), config) {
+        let title = format!("{} — {name}", config.base.title);
+        let feed = RssFeed::from_pages(title, pages, config);
+        let dir = config.build.output.join(&name);
+        for (path, xml) in feed.render(&dir.join("feed.xml"), &dir.join("atom.xml"), config)? {
+            prune::write_produced(&path, xml.as_bytes())?;
         }
+        log!(true; "rss"; "feed of section `{name}` written");
     }
-    result
+    Ok(())
 }
 
-/// Normalize relative links to absolute URLs
-#[inline]
-fn normalize_link(dest: &str, base_url: &str) -> String {
-    if dest.starts_with(['.', '/']) {
-        let path = dest.trim_start_matches(['.', '/']);
-        format!("{}/{}", base_url.trim_end_matches('/'), path)
-    } else {
-        dest.to_string()
+/// Pages of each section listed in `[build.rss.sections] names`, or of all
+fn section_pages(pages: Vec<Page>, config: &SiteConfig) -> BTreeMap<String, Vec<Page>> {
+    let names = &config.build.rss.sections.names;
+    let mut sections: BTreeMap<String, Vec<Page>> = BTreeMap::new();
+    for page in pages {
+        if let Some(section) = section(&page.source, &config.build.content)
+            && (names.is_empty() || names.contains(&section))
+        {
+            sections.entry(section).or_default().push(page);
+        }
     }
+    sections
 }
 
 // ============================================================================
-// Public API
+// Feed Registry
 // ============================================================================
 
-pub fn build_rss(config: &'static SiteConfig) -> Result<()> {
-    if config.build.rss.enable {
-        RssFeed::build(config)?.write(config)?;
-    }
-    Ok(())
+/// Format of a written feed, in order of preference for subscribing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeedKind {
+    Rss,
+    Atom,
+    Json,
 }
 
-/// Generate GUID URL for a content file.
-///
-/// Converts a `.typ` content path to its corresponding public URL.
-/// Example: `content/posts/hello.typ` → `https://example.com/posts/hello/index.html`
-pub fn get_guid_from_content_path(
-    content_path: &Path,
-    config: &'static SiteConfig,
-) -> Result<String> {
-    let base_url = config.base.url.as_deref().unwrap_or_default();
-    let paths = content_paths(content_path, config)?;
+/// A feed written during the current build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenFeed {
+    pub title: String,
+    pub path: PathBuf,
+    pub kind: FeedKind,
+}
 
-    // Strip output dir prefix to get relative path for URL
-    let html_relative = paths
-        .html
-        .strip_prefix(&config.build.output)
-        .unwrap_or(&paths.html);
+/// Feeds written since the last [`build_rss`], in order of writing
+static FEEDS: Mutex<Vec<WrittenFeed>> = Mutex::new(Vec::new());
 
-    // URL-encode path components but preserve slashes
-    let encoded = urlencoding::encode(html_relative.to_str().unwrap_or_default());
-    let encoded = encoded.replace("%2F", "/");
+fn feeds_lock() -> MutexGuard<'static, Vec<WrittenFeed>> {
+    FEEDS.lock().unwrap_or_else(|e| e.into_inner())
+}
 
-    Ok(format!("{}/{}", base_url.trim_end_matches('/'), encoded))
+fn register_feed(title: &str, path: &Path, kind: FeedKind) {
+    feeds_lock().push(WrittenFeed {
+        title: title.to_owned(),
+        path: path.to_owned(),
+        kind,
+    });
+}
+
+/// Snapshot of the feeds written during the current build
+pub fn feeds() -> Vec<WrittenFeed> {
+    feeds_lock().clone()
+}
+
+/// Write `[build.rss.opml] path`, listing every feed written during the build
+///
+/// Runs once all feeds are written. A channel available in several formats
+/// (such as `feed.xml` and `atom.xml` in the same directory) is listed once,
+/// preferring RSS, then Atom, then JSON Feed.
+pub fn build_feeds_opml(config: &SiteConfig) -> Result<()> {
+    let rss = &config.build.rss;
+    if !rss.enable || !rss.opml.enable {
+        return Ok(());
+    }
+
+    let channels = channels(feeds());
+    if channels.is_empty() {
+        return Ok(());
+    }
+    let urls: Vec<_> = channels
+        .iter()
+        .map(|feed| {
+            let dir = feed.path.parent().unwrap_or(config.build.output.as_path());
+            let html_url = page_url(&dir.join("index.html"), config);
+            let html_url = html_url.trim_end_matches("index.html").to_owned();
+            (page_url(&feed.path, config), html_url)
+        })
+        .collect();
+    let outlines = channels
+        .iter()
+        .zip(&urls)
+        .map(|(feed, (xml_url, html_url))| opml::feed_outline(&feed.title, xml_url, html_url));
+
+    let title = match config.base.title.as_str() {
+        "" => "Feeds".to_owned(),
+        site => format!("{site} — Feeds"),
+    };
+    let xml = opml::render(&title, outlines)?;
+    prune::write_produced(&rss.opml.path, &xml)?;
+
+    log!(true; "rss"; "{} feed(s) listed in opml", channels.len());
+    Ok(())
+}
+
+/// One feed per channel (same title in the same directory), in the preferred format
+fn channels(feeds: Vec<WrittenFeed>) -> Vec<WrittenFeed> {
+    let mut channels: BTreeMap<(PathBuf, String), WrittenFeed> = BTreeMap::new();
+    for feed in feeds {
+        let dir = feed
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        channels
+            .entry((dir, feed.title.clone()))
+            .and_modify(|kept| {
+                if feed.kind < kept.kind {
+                    *kept = feed.clone();
+                }
+            })
+            .or_insert(feed);
+    }
+    channels.into_values().collect()
 }
 
 // ============================================================================
@@ -371,36 +533,60 @@ pub fn get_guid_from_content_path(
 
 impl RssFeed {
     /// Build RSS feed by collecting and parsing all posts
-    pub fn build(config: &'static SiteConfig) -> Result<Self> {
+    pub fn build(config: &SiteConfig) -> Result<Self> {
         log!(true; "rss"; "generating rss feed started");
 
-        let posts_paths = collect_files(&config.build.content, |path| {
-            path.extension().is_some_and(|ext| ext == "typ")
-        });
+        // Metadata was queried once per page while content was built
+        Ok(Self::from_pages(
+            config.base.title.clone(),
+            site::pages(),
+            config,
+        ))
+    }
 
-        let posts: Vec<PostMeta> = posts_paths
-            .par_iter()
-            .map(|path| query_post_meta(path, config))
-            .collect::<Result<_>>()?;
+    /// Feed titled `title` over the given pages, such as the posts of one author
+    ///
+    /// Posts are ordered by `[build.rss] sort` and cut to `limit`.
+    pub fn from_pages(title: String, pages: Vec<Page>, config: &SiteConfig) -> Self {
+        let mut posts: Vec<_> = pages
+            .into_iter()
+            .filter_map(|page| PostMeta::from_page(page, config))
+            .filter(PostMeta::is_complete)
+            .collect();
+        let date = |date: &Option<String>| date.as_deref().and_then(DateTimeUtc::parse);
+        let key = |post: &PostMeta| match config.build.rss.sort {
+            FeedSort::Date => date(&post.date),
+            FeedSort::Update => date(&post.update).or_else(|| date(&post.date)),
+        };
+        posts.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| a.link.cmp(&b.link)));
+        if let Some(limit) = config.build.rss.limit {
+            posts.truncate(limit);
+        }
 
-        Ok(Self {
-            title: config.base.title.clone(),
+        Self {
+            title,
             description: config.base.description.clone(),
             base_url: config.base.url.clone().unwrap_or_default(),
             language: config.base.language.clone(),
             posts,
-        })
+        }
     }
 
     /// Generate RSS XML string
-    fn into_xml(self) -> Result<String> {
+    pub fn into_xml(self) -> Result<String> {
         let items: Vec<_> = self
             .posts
             .into_iter()
             .filter_map(PostMeta::into_rss_item)
             .collect();
 
+        let has_content = items.iter().any(|item| item.content.is_some());
+        let namespaces = match has_content {
+            true => BTreeMap::from([("content".to_string(), CONTENT_NAMESPACE.to_string())]),
+            false => BTreeMap::new(),
+        };
         let channel = ChannelBuilder::default()
+            .namespaces(namespaces)
             .title(self.title)
             .link(self.base_url)
             .description(self.description)
@@ -415,80 +601,135 @@ impl RssFeed {
         Ok(channel.to_string())
     }
 
-    /// Write RSS feed to file
-    pub fn write(self, config: &'static SiteConfig) -> Result<()> {
-        let xml = self.into_xml()?;
-        let rss_path = &config.build.rss.path;
+    /// Generate Atom XML string; `id` is the URL the feed is published at
+    pub fn into_atom(self, id: &str) -> Result<String> {
+        let entries: Vec<_> = self
+            .posts
+            .into_iter()
+            .filter_map(PostMeta::into_atom_entry)
+            .collect();
+        // An empty feed has nothing newer than the epoch to report
+        let updated = entries
+            .iter()
+            .map(|entry| *entry.updated())
+            .max()
+            .unwrap_or_default();
+
+        let mut self_link = Link::default();
+        self_link.set_href(id);
+        self_link.set_rel("self");
+        let mut generator = Generator::default();
+        generator.set_value("tola-ssg");
+
+        let mut feed = Feed::default();
+        feed.set_title(self.title);
+        feed.set_id(id);
+        feed.set_updated(updated);
+        feed.set_subtitle(Text::plain(self.description));
+        feed.set_lang(self.language);
+        feed.set_links(vec![self_link, alternate_link(self.base_url)]);
+        feed.set_generator(generator);
+        feed.set_entries(entries);
+        Ok(feed.to_string())
+    }
 
-        if let Some(parent) = rss_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Generate JSON Feed 1.1 string; `feed_url` is the URL the feed is published at
+    pub fn into_json_feed(self, feed_url: &str) -> Result<String> {
+        let items: Vec<_> = self
+            .posts
+            .into_iter()
+            .filter_map(PostMeta::into_json_item)
+            .collect();
+        let feed = json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": self.title,
+            "home_page_url": self.base_url,
+            "feed_url": feed_url,
+            "description": self.description,
+            "language": self.language,
+            "items": items,
+        });
+        Ok(serde_json::to_string_pretty(&feed)?)
+    }
+
+    /// Render the feeds selected by `[build.rss] format`, as `(path, xml)` pairs
+    ///
+    /// The feeds are recorded in the registry, as callers write them all.
+    pub fn render(
+        self,
+        rss_path: &Path,
+        atom_path: &Path,
+        config: &SiteConfig,
+    ) -> Result<Vec<(PathBuf, String)>> {
+        let format = config.build.rss.format;
+        if matches!(format, FeedFormat::Rss | FeedFormat::Both) {
+            register_feed(&self.title, rss_path, FeedKind::Rss);
+        }
+        if matches!(format, FeedFormat::Atom | FeedFormat::Both) {
+            register_feed(&self.title, atom_path, FeedKind::Atom);
+        }
+
+        let atom = |feed: Self| -> Result<(PathBuf, String)> {
+            let xml = feed.into_atom(&page_url(atom_path, config))?;
+            Ok((atom_path.to_owned(), xml))
+        };
+        Ok(match format {
+            FeedFormat::Rss => vec![(rss_path.to_owned(), self.into_xml()?)],
+            FeedFormat::Atom => vec![atom(self)?],
+            FeedFormat::Both => vec![(rss_path.to_owned(), self.clone().into_xml()?), atom(self)?],
+        })
+    }
+
+    /// Write the site feeds to `[build.rss]` `path`, `atom_path` and `json.path`
+    pub fn write(self, config: &SiteConfig) -> Result<()> {
+        let rss = &config.build.rss;
+        let mut feeds = Vec::new();
+        if rss.json.enable {
+            register_feed(&self.title, &rss.json.path, FeedKind::Json);
+            let json = self
+                .clone()
+                .into_json_feed(&page_url(&rss.json.path, config))?;
+            feeds.push((rss.json.path.clone(), json));
         }
-        fs::write(rss_path, xml)?;
+        feeds.extend(self.render(&rss.path, &rss.atom_path, config)?);
 
-        log!(true; "rss"; "rss feed written successfully");
+        for (path, xml) in feeds {
+            prune::write_produced(&path, xml.as_bytes())?;
+        }
+
+        log!(true; "rss"; "feed written successfully");
         Ok(())
     }
 }
 
 // ============================================================================
-// Metadata Extraction
+// Full Content
 // ============================================================================
 
-/// Query metadata from a Typst post file
-fn query_post_meta(post_path: &Path, config: &'static SiteConfig) -> Result<PostMeta> {
-    let root = config.get_root();
-    let guid = get_guid_from_content_path(post_path, config)?;
-
-    let output = exec!(
-        &config.build.typst.command;
-        "query", "--features", "html", "--format", "json",
-        "--font-path", root, "--root", root,
-        post_path,
-        META_TAG_NAME, "--field", "value", "--one"
-    )
-    .with_context(|| {
-        format!(
-            "Failed to query metadata for post: {}\nEnsure tag name \"{}\" is correct",
-            post_path.display(),
-            META_TAG_NAME
-        )
-    })?;
-
-    let json_str = std::str::from_utf8(&output.stdout)?;
-    parse_post_meta(guid, json_str, config)
-}
-
-/// Parse post metadata from JSON string  
-fn parse_post_meta(guid: String, json_str: &str, config: &'static SiteConfig) -> Result<PostMeta> {
-    let json: serde_json::Value = serde_json::from_str(json_str)
-        .with_context(|| format!("Failed to parse post metadata JSON:\n{json_str}"))?;
+/// Inner HTML of the `<body>` of a built page, with root-relative links made
+/// absolute so they resolve in feed readers
+fn page_content(output: &Path, config: &SiteConfig) -> Option<String> {
+    static RE_ROOT_LINK: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"(?-u:\b)(href|src)="/([^/"]|")"#).unwrap());
 
-    let get_string = |key: &str| json.get(key).and_then(|v| v.as_str()).map(String::from);
+    let html = fs::read_to_string(output).ok()?;
+    let start = html.find("<body")?;
+    let start = start + html[start..].find('>')? + 1;
+    let end = html.rfind("</body>")?;
+    let body = html.get(start..end)?.trim();
 
-    // Parse summary from Typst element
     let base_url = config.base.url.as_deref().unwrap_or_default();
-    let summary = get_string("summary")
-        .and_then(|s| parse_typst_element(&s).ok())
-        .map(|elem| elem.to_html(base_url));
-
-    // Process author field
-    let author = get_string("author");
-    let author = normalize_rss_author(author.as_ref(), config);
-
-    Ok(PostMeta {
-        title: get_string("title"),
-        summary,
-        date: get_string("date"),
-        update: get_string("update"),
-        link: Some(guid),
-        author,
-    })
+    let base_url = base_url.trim_end_matches('/');
+    Some(
+        RE_ROOT_LINK
+            .replace_all(body, format!(r#"${{1}}="{base_url}/${{2}}"#))
+            .into_owned(),
+    )
 }
 
-/// Parse Typst element from JSON string
-fn parse_typst_element(content: &str) -> Result<TypstElement> {
-    serde_json::from_str(content).map_err(Into::into)
-}
+// ============================================================================
+// Author Normalization
+// ============================================================================
 
 /// Normalize author field to RSS format: "email@example.com (Name)"
 ///
@@ -496,9 +737,9 @@ fn parse_typst_element(content: &str) -> Result<TypstElement> {
 /// 1. Post meta author if already in valid format
 /// 2. Site config author if in valid format  
 /// 3. Combine site config email and author
-fn normalize_rss_author(author: Option<&String>, config: &'static SiteConfig) -> Option<String> {
+fn normalize_rss_author(author: Option<&String>, config: &SiteConfig) -> Option<String> {
     static RE_VALID_AUTHOR: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\s*\([^)]+\)$").unwrap()
+        Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}[ \t]*\([^)]+\)$").unwrap()
     });
 
     let author = author?;
@@ -518,54 +759,6 @@ fn normalize_rss_author(author: Option<&String>, config: &'static SiteConfig) ->
     Some(format!("{} ({})", config.base.email, site_author))
 }
 
-#[test]
-fn test_parse_element_from_typst_sequence() {
-    let json_str = r#"
-    {
-        "func": "sequence",
-        "children": [
-            { "func": "space" },
-            { "func": "text", "text": "小鹤双拼是一个简洁, 流畅, 自由的双拼输入法方案" },
-            { "func": "space" },
-            { "func": "linebreak" },
-            { "func": "space" },
-            { "func": "link", "dest": "https://example.com", "body": { "func": "text", "text": "小鹤双拼" } },
-            { "func": "text", "text": "适合想提高打字速度, 但又不想投入巨量精力进行记忆, 追求高性价比的同学" },
-            { "func": "space" },
-            { "func": "unknown_func" }
-        ]
-    }
-    "#;
-
-    let result = parse_typst_element(json_str).unwrap();
-    assert_eq!(
-        result,
-        TypstElement::Sequence {
-            children: vec![
-                TypstElement::Space,
-                TypstElement::Text {
-                    text: "小鹤双拼是一个简洁, 流畅, 自由的双拼输入法方案".to_string()
-                },
-                TypstElement::Space,
-                TypstElement::Linebreak,
-                TypstElement::Space,
-                TypstElement::Link {
-                    dest: "https://example.com".to_string(),
-                    body: Box::new(TypstElement::Text {
-                        text: "小鹤双拼".to_string()
-                    }),
-                },
-                TypstElement::Text {
-                    text: "适合想提高打字速度, 但又不想投入巨量精力进行记忆, 追求高性价比的同学"
-                        .to_string()
-                },
-                TypstElement::Space,
-                TypstElement::Unknown,
-            ]
-        }
-    );
-}
-
 #[test]
 fn test_datetime_utc_new() {
     let dt = DateTimeUtc::new(2024, 6, 15, 14, 30, 45);
@@ -721,71 +914,212 @@ fn test_datetime_utc_all_months() {
 }
 
 #[test]
-fn test_typst_element_text() {
-    let json = r#"{ "func": "text", "text": "Hello World" }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-    assert!(matches!(elem, TypstElement::Text { text } if text == "Hello World"));
+fn test_normalize_rss_author() {
+    let config = SiteConfig::default();
+    let author = "alice@example.com (Alice)".to_string();
+    assert_eq!(
+        normalize_rss_author(Some(&author), &config),
+        Some(author.clone())
+    );
+    assert_eq!(normalize_rss_author(None, &config), None);
+}
+
+#[test]
+fn test_datetime_utc_to_fixed() {
+    let dt = DateTimeUtc::new(2024, 6, 15, 14, 30, 45)
+        .to_fixed()
+        .unwrap();
+    assert_eq!(dt.to_rfc3339(), "2024-06-15T14:30:45+00:00");
 }
 
 #[test]
-fn test_typst_element_space() {
-    let json = r#"{ "func": "space" }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-    assert!(matches!(elem, TypstElement::Space));
+fn test_into_atom() {
+    let feed = RssFeed {
+        title: "Blog".into(),
+        description: "Notes".into(),
+        base_url: "https://example.com".into(),
+        language: "en".into(),
+        posts: vec![
+            PostMeta {
+                title: Some("Hello".into()),
+                date: Some("2024-01-01".into()),
+                update: Some("2024-03-01".into()),
+                link: Some("https://example.com/hello/".into()),
+                author_name: Some("Alice".into()),
+                ..Default::default()
+            },
+            // No date, so no entry
+            PostMeta {
+                title: Some("About".into()),
+                link: Some("https://example.com/about/".into()),
+                ..Default::default()
+            },
+        ],
+    };
+    let xml = feed.into_atom("https://example.com/atom.xml").unwrap();
+
+    assert!(xml.contains("<id>https://example.com/atom.xml</id>"));
+    assert!(xml.contains("<updated>2024-03-01T00:00:00+00:00</updated>"));
+    assert!(xml.contains("<published>2024-01-01T00:00:00+00:00</published>"));
+    assert!(xml.contains("<author><name>Alice</name></author>"));
+    assert_eq!(xml.matches("<entry>").count(), 1);
 }
 
 #[test]
-fn test_typst_element_linebreak() {
-    let json = r#"{ "func": "linebreak" }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-    assert!(matches!(elem, TypstElement::Linebreak));
+fn test_into_json_feed() {
+    let feed = RssFeed {
+        title: "Blog".into(),
+        description: "Notes".into(),
+        base_url: "https://example.com".into(),
+        language: "en".into(),
+        posts: vec![PostMeta {
+            title: Some("Hello".into()),
+            summary: Some("<p>Hi</p>".into()),
+            date: Some("2024-01-01".into()),
+            link: Some("https://example.com/hello/".into()),
+            author_name: Some("Alice".into()),
+            word_count: Some(420),
+            reading_time: Some(3),
+            ..Default::default()
+        }],
+    };
+    let json = feed
+        .into_json_feed("https://example.com/feed.json")
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(json["version"], "https://jsonfeed.org/version/1.1");
+    assert_eq!(json["feed_url"], "https://example.com/feed.json");
+    let item = &json["items"][0];
+    assert_eq!(item["id"], "https://example.com/hello/");
+    assert_eq!(item["content_html"], "<p>Hi</p>");
+    assert_eq!(item["date_published"], "2024-01-01T00:00:00+00:00");
+    assert_eq!(item["authors"][0]["name"], "Alice");
+    assert!(item.get("date_modified").is_none());
+    assert_eq!(item["_tola"]["word_count"], 420);
+    assert_eq!(item["_tola"]["reading_time"], 3);
 }
 
 #[test]
-fn test_typst_element_strike() {
-    let json = r#"{ "func": "strike", "text": "strikethrough" }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-    assert!(matches!(elem, TypstElement::Strike { text } if text == "strikethrough"));
+fn test_from_pages_sort_and_limit() {
+    use crate::utils::meta::PageMeta;
+
+    let page = |slug: &str, date: Option<&str>, update: Option<&str>| Page {
+        url: format!("https://example.com/{slug}/"),
+        meta: Some(PageMeta {
+            title: Some(slug.into()),
+            date: date.map(Into::into),
+            update: update.map(Into::into),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let pages = vec![
+        page("old", Some("2023-01-01"), Some("2024-06-01")),
+        page("new", Some("2024-05-01"), None),
+        page("undated", None, None),
+        page("mid", Some("2024-01-01"), None),
+    ];
+    let titles = |feed: RssFeed| -> Vec<String> {
+        feed.posts
+            .into_iter()
+            .filter_map(|post| post.title)
+            .collect()
+    };
+
+    let mut config = SiteConfig::default();
+    config.build.rss.limit = Some(2);
+    let feed = RssFeed::from_pages("Blog".into(), pages.clone(), &config);
+    assert_eq!(titles(feed), ["new", "mid"]);
+
+    config.build.rss.limit = None;
+    config.build.rss.sort = FeedSort::Update;
+    let feed = RssFeed::from_pages("Blog".into(), pages, &config);
+    assert_eq!(titles(feed), ["old", "new", "mid"]);
 }
 
 #[test]
-fn test_typst_element_link() {
-    let json = r#"{ "func": "link", "dest": "https://example.com", "body": { "func": "text", "text": "link text" } }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
+fn test_full_content() {
+    let dir = std::env::temp_dir().join("tola_rss_content_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("index.html");
+    fs::write(
+        &output,
+        r#"<html><head></head><body class="post"><p><a href="/about/">About</a> <img src="//cdn.example.com/a.png"> <a href="/">Home</a></p></body></html>"#,
+    )
+    .unwrap();
+    let mut config = SiteConfig::default();
+    config.base.url = Some("https://example.com/".into());
 
-    if let TypstElement::Link { dest, body } = elem {
-        assert_eq!(dest, "https://example.com");
-        assert!(matches!(*body, TypstElement::Text { text } if text == "link text"));
-    } else {
-        panic!("Expected Link element");
-    }
+    let content = page_content(&output, &config).unwrap();
+    assert_eq!(
+        content,
+        r#"<p><a href="https://example.com/about/">About</a> <img src="//cdn.example.com/a.png"> <a href="https://example.com/">Home</a></p>"#
+    );
+
+    let feed = RssFeed {
+        title: "Blog".into(),
+        description: "Notes".into(),
+        base_url: "https://example.com".into(),
+        language: "en".into(),
+        posts: vec![PostMeta {
+            title: Some("Hello".into()),
+            date: Some("2024-01-01".into()),
+            link: Some("https://example.com/hello/".into()),
+            content: Some(content),
+            ..Default::default()
+        }],
+    };
+    let xml = feed.into_xml().unwrap();
+    assert!(xml.contains(r#"xmlns:content="http://purl.org/rss/1.0/modules/content/""#));
+    assert!(xml.contains("<content:encoded><![CDATA[<p><a href=\"https://example.com/about/\">"));
+    fs::remove_dir_all(&dir).unwrap();
 }
 
 #[test]
-fn test_typst_element_unknown_ignored() {
-    let json = r#"{ "func": "custom_unknown_func" }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-    assert!(matches!(elem, TypstElement::Unknown));
+fn test_section_pages() {
+    let page = |source: &str| Page {
+        source: PathBuf::from("/site/content").join(source),
+        ..Default::default()
+    };
+    let pages = vec![
+        page("posts/a.typ"),
+        page("notes/b.typ"),
+        page("posts/c.typ"),
+        page("about.typ"),
+    ];
+    let mut config = SiteConfig::default();
+    config.build.content = PathBuf::from("/site/content");
+
+    let sections = section_pages(pages.clone(), &config);
+    assert_eq!(sections.keys().collect::<Vec<_>>(), ["notes", "posts"]);
+    assert_eq!(sections["posts"].len(), 2);
+
+    config.build.rss.sections.names = vec!["notes".into()];
+    let sections = section_pages(pages, &config);
+    assert_eq!(sections.keys().collect::<Vec<_>>(), ["notes"]);
 }
 
 #[test]
-fn test_typst_element_sequence() {
-    let json = r#"{
-        "func": "sequence",
-        "children": [
-            { "func": "text", "text": "Hello" },
-            { "func": "space" },
-            { "func": "text", "text": "World" }
+fn test_channels_prefer_rss() {
+    let feed = |title: &str, path: &str, kind| WrittenFeed {
+        title: title.into(),
+        path: PathBuf::from(path),
+        kind,
+    };
+    let channels = channels(vec![
+        feed("Blog", "/public/feed.json", FeedKind::Json),
+        feed("Blog", "/public/atom.xml", FeedKind::Atom),
+        feed("Blog", "/public/feed.xml", FeedKind::Rss),
+        feed("Blog — rust", "/public/tags/rust/atom.xml", FeedKind::Atom),
+    ]);
+    let paths: Vec<_> = channels.iter().map(|feed| feed.path.as_path()).collect();
+    assert_eq!(
+        paths,
+        [
+            Path::new("/public/feed.xml"),
+            Path::new("/public/tags/rust/atom.xml")
         ]
-    }"#;
-    let elem: TypstElement = serde_json::from_str(json).unwrap();
-
-    if let TypstElement::Sequence { children } = elem {
-        assert_eq!(children.len(), 3);
-        assert!(matches!(&children[0], TypstElement::Text { text } if text == "Hello"));
-        assert!(matches!(&children[1], TypstElement::Space));
-        assert!(matches!(&children[2], TypstElement::Text { text } if text == "World"));
-    } else {
-        panic!("Expected Sequence element");
-    }
+    );
 }
@@ -0,0 +1,196 @@
+//! Allowlist-based HTML sanitizer (`[build.sanitize]`).
+//!
+//! Cleans HTML fragments from semi-trusted sources, such as raw `[build.head]`
+//! elements, before they are inserted into pages. Elements and attributes not on
+//! the allowlist are dropped, as are event handlers and `javascript:` URLs.
+
+use crate::config::SanitizeConfig;
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+};
+use std::borrow::Cow;
+
+use super::xml::create_xml_reader;
+
+/// Elements removed together with everything inside them
+const DROP_WITH_CONTENT: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "template", "noscript", "svg", "math",
+];
+
+/// Elements without a closing tag in HTML
+pub const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Attributes holding URLs, checked for unsafe schemes
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "poster", "cite"];
+
+/// Sanitize an HTML fragment according to `config`.
+pub fn sanitize_fragment(html: &str, config: &SanitizeConfig) -> String {
+    let mut reader = create_xml_reader(html.as_bytes());
+    let mut out = String::with_capacity(html.len());
+    // Name and nesting depth of the element whose content is being dropped
+    let mut dropping: Option<(String, usize)> = None;
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(event) => event,
+            // Malformed rest of input is dropped rather than passed through
+            Err(_) => break,
+        };
+
+        if let Some((name, depth)) = &mut dropping {
+            match &event {
+                Event::Start(e) if tag_name(e) == *name => *depth += 1,
+                Event::End(e) if lowercase(e.name().as_ref()) == *name => {
+                    *depth -= 1;
+                    if *depth == 0 {
+                        dropping = None;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(e) => {
+                let name = tag_name(&e);
+                if DROP_WITH_CONTENT.contains(&name.as_str()) {
+                    dropping = Some((name, 1));
+                } else if is_allowed_tag(&name, config) {
+                    write_start(&mut out, &name, &e, config);
+                }
+            }
+            Event::Empty(e) => {
+                let name = tag_name(&e);
+                if is_allowed_tag(&name, config) && !DROP_WITH_CONTENT.contains(&name.as_str()) {
+                    write_start(&mut out, &name, &e, config);
+                    if !VOID_ELEMENTS.contains(&name.as_str()) {
+                        out.push_str(&format!("</{name}>"));
+                    }
+                }
+            }
+            Event::End(e) => {
+                let name = lowercase(e.name().as_ref());
+                if is_allowed_tag(&name, config) && !VOID_ELEMENTS.contains(&name.as_str()) {
+                    out.push_str(&format!("</{name}>"));
+                }
+            }
+            Event::Text(text) => out.push_str(&String::from_utf8_lossy(&text)),
+            Event::GeneralRef(entity) => {
+                out.push('&');
+                out.push_str(&String::from_utf8_lossy(&entity));
+                out.push(';');
+            }
+            Event::CData(data) => out.push_str(&escape(String::from_utf8_lossy(&data))),
+            // Comments, doctypes and processing instructions are dropped
+            _ => {}
+        }
+    }
+    out
+}
+
+fn lowercase(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).to_ascii_lowercase()
+}
+
+fn tag_name(elem: &BytesStart<'_>) -> String {
+    lowercase(elem.name().as_ref())
+}
+
+fn is_allowed_tag(name: &str, config: &SanitizeConfig) -> bool {
+    config.tags.iter().any(|tag| tag.eq_ignore_ascii_case(name))
+}
+
+/// Write an opening tag with only allowed, safe attributes
+fn write_start(out: &mut String, name: &str, elem: &BytesStart<'_>, config: &SanitizeConfig) {
+    out.push('<');
+    out.push_str(name);
+    for attr in elem.html_attributes().flatten() {
+        let key = lowercase(attr.key.as_ref());
+        let raw = String::from_utf8_lossy(&attr.value);
+        let value = attr
+            .unescape_value()
+            .map_or(raw.clone(), |v| Cow::Owned(v.into_owned()));
+        if !is_allowed_attribute(&key, &value, config) {
+            continue;
+        }
+        out.push_str(&format!(" {key}=\"{}\"", escape(value.as_ref())));
+    }
+    out.push('>');
+}
+
+fn is_allowed_attribute(key: &str, value: &str, config: &SanitizeConfig) -> bool {
+    if key.starts_with("on")
+        || !config
+            .attributes
+            .iter()
+            .any(|a| a.eq_ignore_ascii_case(key))
+    {
+        return false;
+    }
+    !URL_ATTRIBUTES.contains(&key) || is_safe_url(value)
+}
+
+/// Reject script-capable URL schemes, ignoring case and embedded whitespace
+fn is_safe_url(url: &str) -> bool {
+    let normalized: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    !["javascript:", "vbscript:", "data:text/html"]
+        .iter()
+        .any(|scheme| normalized.starts_with(scheme))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(html: &str) -> String {
+        sanitize_fragment(html, &SanitizeConfig::default())
+    }
+
+    #[test]
+    fn test_keeps_allowed_elements() {
+        assert_eq!(
+            sanitize(r#"<meta name="darkreader-lock">"#),
+            r#"<meta name="darkreader-lock">"#
+        );
+        assert_eq!(
+            sanitize(r#"<p class="note">Hi <em>there</em> &amp; bye</p>"#),
+            r#"<p class="note">Hi <em>there</em> &amp; bye</p>"#
+        );
+    }
+
+    #[test]
+    fn test_drops_scripts_and_handlers() {
+        assert_eq!(
+            sanitize(r#"<script>alert(1)</script><b>ok</b>"#),
+            "<b>ok</b>"
+        );
+        assert_eq!(
+            sanitize(r#"<img src="a.png" onerror="alert(1)">"#),
+            r#"<img src="a.png">"#
+        );
+        assert_eq!(
+            sanitize(r#"<a href=" JavaScript:alert(1)">x</a>"#),
+            "<a>x</a>"
+        );
+    }
+
+    #[test]
+    fn test_unwraps_unknown_elements() {
+        assert_eq!(sanitize("<blink>text</blink>"), "text");
+        assert_eq!(sanitize("<div/>"), "<div></div>");
+    }
+}
@@ -0,0 +1,282 @@
+//! Client-side search index.
+//!
+//! Indexes the title, summary and visible text recorded in the site model for
+//! every page, either as a plain list of documents or as a serialized
+//! [elasticlunr](http://elasticlunr.com) index.
+
+use crate::{
+    config::{SearchConfig, SearchField, SearchFormat, SiteConfig},
+    log,
+    utils::{
+        prune,
+        site::{self, Page, extract_text},
+    },
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// Version of elasticlunr.js whose index layout is written
+const ELASTICLUNR_VERSION: &str = "0.9.5";
+
+/// Write the search index if `[build.search]` is enabled
+pub fn build_search(config: &SiteConfig) -> Result<()> {
+    let search = &config.build.search;
+    if !search.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let stop_words: HashSet<String> = search.stop_words.iter().map(|w| w.to_lowercase()).collect();
+    let docs: Vec<_> = pages
+        .iter()
+        .map(|page| SearchDoc::new(page, &search.fields, &stop_words))
+        .collect();
+
+    let json = match search.format {
+        SearchFormat::Json => serde_json::to_string(&docs)?,
+        SearchFormat::Elasticlunr => {
+            serde_json::to_string(&elasticlunr_index(&docs, search, &stop_words))?
+        }
+    };
+    prune::write_produced(&search.path, json.as_bytes())?;
+
+    log!("search"; "{} page(s) indexed", docs.len());
+    Ok(())
+}
+
+/// Indexed fields of a page; unselected fields are left out
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct SearchDoc {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    /// Words of the page text, without stop words
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+impl SearchDoc {
+    fn new(page: &Page, fields: &[SearchField], stop_words: &HashSet<String>) -> Self {
+        let meta = page.meta.as_ref();
+        let wants = |field| fields.contains(&field);
+        Self {
+            url: page.url.clone(),
+            title: meta
+                .and_then(|meta| meta.title.clone())
+                .filter(|_| wants(SearchField::Title)),
+            // Summaries are stored as HTML
+            summary: meta
+                .and_then(|meta| meta.summary.as_deref())
+                .map(|summary| extract_text(summary.as_bytes()))
+                .filter(|_| wants(SearchField::Summary)),
+            body: wants(SearchField::Body).then(|| tokenize(&page.text, stop_words).join(" ")),
+        }
+    }
+
+    /// Text of `field`, if it is indexed
+    fn field(&self, field: SearchField) -> Option<&str> {
+        match field {
+            SearchField::Title => self.title.as_deref(),
+            SearchField::Summary => self.summary.as_deref(),
+            SearchField::Body => self.body.as_deref(),
+        }
+    }
+}
+
+/// Lowercase words of `text`, without stop words
+fn tokenize(text: &str, stop_words: &HashSet<String>) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !stop_words.contains(word))
+        .collect()
+}
+
+// ============================================================================
+// Elasticlunr
+// ============================================================================
+
+/// Index as serialized by `elasticlunr.Index.prototype.toJSON`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ElasticlunrIndex<'a> {
+    version: &'static str,
+    fields: &'a [SearchField],
+    #[serde(rename = "ref")]
+    reference: &'static str,
+    document_store: DocumentStore<'a>,
+    index: BTreeMap<SearchField, InvertedIndex>,
+    pipeline: [&'static str; 2],
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentStore<'a> {
+    /// Documents returned with search results, keyed by URL
+    docs: BTreeMap<&'a str, &'a SearchDoc>,
+    /// Number of words per field, keyed by URL
+    doc_info: BTreeMap<&'a str, BTreeMap<SearchField, usize>>,
+    length: usize,
+    save: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct InvertedIndex {
+    root: TrieNode,
+}
+
+/// Node of the per-field word trie; children are keyed by the next character
+#[derive(Debug, Default, Serialize)]
+struct TrieNode {
+    /// Term frequency per document whose words end here
+    docs: BTreeMap<String, TermFrequency>,
+    /// Number of such documents
+    df: usize,
+    #[serde(flatten)]
+    children: BTreeMap<char, TrieNode>,
+}
+
+#[derive(Debug, Serialize)]
+struct TermFrequency {
+    tf: f64,
+}
+
+impl TrieNode {
+    fn insert(&mut self, word: &str, doc: &str, tf: f64) {
+        let node = word
+            .chars()
+            .fold(self, |node, c| node.children.entry(c).or_default());
+        if !node.docs.contains_key(doc) {
+            node.df += 1;
+        }
+        node.docs.insert(doc.to_owned(), TermFrequency { tf });
+    }
+}
+
+/// Build the index the way `elasticlunr.Index.addDoc` does, without stemming
+fn elasticlunr_index<'a>(
+    docs: &'a [SearchDoc],
+    search: &'a SearchConfig,
+    stop_words: &HashSet<String>,
+) -> ElasticlunrIndex<'a> {
+    let no_stop_words = HashSet::new();
+    let mut index: BTreeMap<SearchField, InvertedIndex> = BTreeMap::new();
+    let mut doc_info = BTreeMap::new();
+
+    for doc in docs {
+        let mut info = BTreeMap::new();
+        for &field in &search.fields {
+            // Bodies are already free of stop words; titles and summaries are not
+            let stop_words = match field {
+                SearchField::Body => &no_stop_words,
+                _ => stop_words,
+            };
+            let words = tokenize(doc.field(field).unwrap_or_default(), stop_words);
+            info.insert(field, words.len());
+
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for word in &words {
+                *counts.entry(word).or_default() += 1;
+            }
+            let root = &mut index.entry(field).or_default().root;
+            for (word, count) in counts {
+                root.insert(word, &doc.url, (count as f64).sqrt());
+            }
+        }
+        doc_info.insert(doc.url.as_str(), info);
+    }
+
+    ElasticlunrIndex {
+        version: ELASTICLUNR_VERSION,
+        fields: &search.fields,
+        reference: "url",
+        document_store: DocumentStore {
+            docs: docs.iter().map(|doc| (doc.url.as_str(), doc)).collect(),
+            doc_info,
+            length: docs.len(),
+            save: true,
+        },
+        index,
+        // Words were not stemmed, so queries must not be either
+        pipeline: ["trimmer", "stopWordFilter"],
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn page(url: &str, title: &str, text: &str) -> Page {
+        Page {
+            url: url.into(),
+            text: text.into(),
+            meta: Some(PageMeta {
+                title: Some(title.into()),
+                summary: Some("<p>A <em>short</em> intro</p>".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let stop_words = HashSet::from(["the".to_string()]);
+        assert_eq!(
+            tokenize("The quick-brown Fox, the end.", &stop_words),
+            ["quick", "brown", "fox", "end"]
+        );
+    }
+
+    #[test]
+    fn test_search_doc_fields() {
+        let page = page("/a/", "Hello", "Hello, World");
+        let doc = SearchDoc::new(
+            &page,
+            &[SearchField::Summary, SearchField::Body],
+            &HashSet::new(),
+        );
+        assert_eq!(doc.title, None);
+        assert_eq!(doc.summary.as_deref(), Some("A short intro"));
+        assert_eq!(doc.body.as_deref(), Some("hello world"));
+    }
+
+    #[test]
+    fn test_elasticlunr_index() {
+        let search = SearchConfig {
+            fields: vec![SearchField::Title, SearchField::Body],
+            ..Default::default()
+        };
+        let docs = [
+            SearchDoc::new(
+                &page("/a/", "Rust", "rust rust typst"),
+                &search.fields,
+                &HashSet::new(),
+            ),
+            SearchDoc::new(
+                &page("/b/", "Typst", "typst"),
+                &search.fields,
+                &HashSet::new(),
+            ),
+        ];
+        let json =
+            serde_json::to_value(elasticlunr_index(&docs, &search, &HashSet::new())).unwrap();
+
+        assert_eq!(json["ref"], "url");
+        assert_eq!(json["fields"], serde_json::json!(["title", "body"]));
+        assert_eq!(json["documentStore"]["docInfo"]["/a/"]["body"], 3);
+        let typst = &json["index"]["body"]["root"]["t"]["y"]["p"]["s"]["t"];
+        assert_eq!(typst["df"], 2);
+        assert_eq!(typst["docs"]["/b/"]["tf"], 1.0);
+        let rust = &json["index"]["body"]["root"]["r"]["u"]["s"]["t"];
+        assert_eq!(rust["docs"]["/a/"]["tf"], 2f64.sqrt());
+    }
+}
@@ -0,0 +1,357 @@
+//! Content sections (`[build.sections]`).
+//!
+//! A content directory with a `_section.toml` is a section. Its settings
+//! cascade to every page below it, nested sections overriding their parents,
+//! and tola writes a listing of its posts to the section's `index.html`.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        build::{collect_files, process_generated_page},
+        meta::PageMeta,
+        pagination::{Pager, paginate_by, write_nav},
+        prune,
+        site::{self, Page},
+        slug::slugify_path,
+    },
+};
+use anyhow::{Context, Result};
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use serde::Deserialize;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// File declaring a section in its content directory
+pub const SECTION_FILE: &str = "_section.toml";
+
+/// Settings of a `_section.toml`, or of all sections a page is in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Section {
+    /// Heading of the listing; not inherited by nested sections
+    pub title: Option<String>,
+    /// Template of the pages below, passed as `sys.inputs.tola-template`
+    pub template: Option<String>,
+    /// Order of the listing
+    pub sort_by: Option<SortBy>,
+    /// Posts per listing page
+    pub page_size: Option<usize>,
+    /// Terms of the pages that declare none for a taxonomy
+    #[serde(default)]
+    pub taxonomies: BTreeMap<String, Vec<String>>,
+}
+
+/// Order of a section listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Newest first
+    #[default]
+    Date,
+    /// Alphabetical
+    Title,
+}
+
+impl Section {
+    fn read(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid section {}", path.display()))
+    }
+
+    /// `self` overridden by the settings of the nested section `child`
+    fn cascade(mut self, child: Self) -> Self {
+        self.title = child.title;
+        self.template = child.template.or(self.template);
+        self.sort_by = child.sort_by.or(self.sort_by);
+        self.page_size = child.page_size.or(self.page_size);
+        self.taxonomies.extend(child.taxonomies);
+        self
+    }
+}
+
+/// Whether `path` is a `_section.toml`
+pub fn is_section_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == SECTION_FILE)
+}
+
+/// Settings of the sections containing `dir`, from the content directory down
+pub fn resolve(dir: &Path, config: &SiteConfig) -> Result<Section> {
+    let content = &config.build.content;
+    let mut dirs: Vec<&Path> = dir
+        .ancestors()
+        .take_while(|dir| dir.starts_with(content))
+        .collect();
+    dirs.reverse();
+
+    let mut section = Section::default();
+    for dir in dirs {
+        let file = dir.join(SECTION_FILE);
+        // Titles belong to their own directory, so one without a file has none
+        let own = match file.exists() {
+            true => Section::read(&file)?,
+            false => Section::default(),
+        };
+        section = section.cascade(own);
+    }
+    Ok(section)
+}
+
+/// Settings of the sections `source` is in; empty without `[build.sections]`
+pub fn page_section(source: &Path, config: &SiteConfig) -> Result<Section> {
+    match (config.build.sections.enable, source.parent()) {
+        (true, Some(dir)) => resolve(dir, config),
+        _ => Ok(Section::default()),
+    }
+}
+
+/// Give `meta` the section's terms of the taxonomies it declares none for
+pub fn apply(meta: &mut PageMeta, section: &Section, config: &SiteConfig) {
+    for name in &config.build.taxonomies.names {
+        if let Some(terms) = section.taxonomies.get(name) {
+            meta.taxonomies
+                .entry(name.clone())
+                .or_insert_with(|| terms.clone());
+        }
+    }
+}
+
+/// `sys.inputs` of a page in `section`
+pub fn page_inputs(section: &Section) -> Vec<(&'static str, String)> {
+    section
+        .template
+        .clone()
+        .map(|template| ("tola-template", template))
+        .into_iter()
+        .collect()
+}
+
+// ============================================================================
+// Listings
+// ============================================================================
+
+/// Write the listing of every section a page does not already occupy
+pub fn build_sections(config: &SiteConfig) -> Result<()> {
+    if !config.build.sections.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let mut dirs: Vec<PathBuf> = collect_files(&config.build.content, is_section_file)
+        .into_iter()
+        .filter_map(|file| file.parent().map(Path::to_path_buf))
+        .collect();
+    dirs.sort();
+
+    let mut written = 0;
+    for dir in &dirs {
+        let relative = dir.strip_prefix(&config.build.content)?;
+        let index = config
+            .build
+            .output
+            .join(&config.build.base_path)
+            .join(slugify_path(relative.join("index.html"), config));
+        if pages.iter().any(|page| page.output == index) {
+            continue;
+        }
+
+        let section = resolve(dir, config)?;
+        let posts = section_posts(&pages, dir, section.sort_by.unwrap_or_default());
+        let title = match &section.title {
+            Some(title) => title.clone(),
+            None => relative.to_string_lossy().into_owned(),
+        };
+        for (pager, posts) in paginate_by(&posts, &index, section.page_size, config) {
+            let html = render_page(&title, posts, &pager)?;
+            let html = process_generated_page(&pager.output, &html, config)?;
+            prune::write_produced(&pager.output, &html)?;
+        }
+        written += 1;
+    }
+
+    log!("sections"; "{written} section listing(s) written");
+    Ok(())
+}
+
+/// Posts (pages with a `date`) below `dir`, in `sort_by` order
+fn section_posts(pages: &[Page], dir: &Path, sort_by: SortBy) -> Vec<Page> {
+    let mut posts: Vec<Page> = pages
+        .iter()
+        .filter(|page| page.source.starts_with(dir))
+        .filter(|page| page.meta.as_ref().is_some_and(|meta| meta.date.is_some()))
+        .cloned()
+        .collect();
+    match sort_by {
+        SortBy::Date => posts.sort_by_key(|post| Reverse(post.date())),
+        SortBy::Title => posts.sort_by(|a, b| post_title(a).cmp(post_title(b))),
+    }
+    posts
+}
+
+fn post_title(page: &Page) -> &str {
+    page.meta
+        .as_ref()
+        .and_then(|meta| meta.title.as_deref())
+        .unwrap_or(&page.url)
+}
+
+/// Page listing the posts of a section, before head injection and minification
+fn render_page(title: &str, posts: &[Page], pager: &Pager) -> Result<Vec<u8>> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    write_text_element(&mut writer, "h1", title)?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-section-posts")]),
+    ))?;
+    for post in posts {
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("a").with_attributes([("href", post.url.as_str())]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(post_title(post))))?;
+        writer.write_event(Event::End(BytesEnd::new("a")))?;
+        let date = post.date_text();
+        writer.write_event(Event::Text(BytesText::new(" ")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("time").with_attributes([("datetime", date)]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(date)))?;
+        writer.write_event(Event::End(BytesEnd::new("time")))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+    write_nav(&mut writer, pager)?;
+
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+fn write_text_element(writer: &mut XmlWriter, tag: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(source: &str, title: &str, date: &str) -> Page {
+        Page {
+            source: source.into(),
+            url: format!("/{title}/"),
+            meta: Some(PageMeta {
+                title: Some(title.into()),
+                date: Some(date.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve_cascades() {
+        let root = std::env::temp_dir().join("tola_sections_test");
+        let _ = fs::remove_dir_all(&root);
+        let content = root.join("content");
+        fs::create_dir_all(content.join("notes/rust/async")).unwrap();
+        fs::write(
+            content.join(SECTION_FILE),
+            "template = \"page.typ\"\n[taxonomies]\ncategories = [\"misc\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            content.join("notes").join(SECTION_FILE),
+            "title = \"Notes\"\nsort_by = \"title\"\npage_size = 5\n[taxonomies]\ncategories = [\"notes\"]\ntags = [\"til\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            content.join("notes/rust").join(SECTION_FILE),
+            "template = \"rust.typ\"\n",
+        )
+        .unwrap();
+
+        let mut config = SiteConfig::default();
+        config.build.content = content.clone();
+        let section = resolve(&content.join("notes/rust/async"), &config).unwrap();
+        assert_eq!(section.title, None);
+        assert_eq!(section.template.as_deref(), Some("rust.typ"));
+        assert_eq!(section.sort_by, Some(SortBy::Title));
+        assert_eq!(section.page_size, Some(5));
+        assert_eq!(section.taxonomies["categories"], ["notes"]);
+
+        let section = resolve(&content.join("notes"), &config).unwrap();
+        assert_eq!(section.title.as_deref(), Some("Notes"));
+        assert_eq!(section.template.as_deref(), Some("page.typ"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_apply_keeps_declared_terms() {
+        let config = SiteConfig::default();
+        let section = Section {
+            taxonomies: [
+                ("tags".into(), vec!["til".into()]),
+                ("categories".into(), vec!["notes".into()]),
+                ("series".into(), vec!["x".into()]),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let mut meta = PageMeta {
+            taxonomies: [("tags".into(), vec!["rust".into()])].into(),
+            ..Default::default()
+        };
+        apply(&mut meta, &section, &config);
+        assert_eq!(meta.taxonomies["tags"], ["rust"]);
+        assert_eq!(meta.taxonomies["categories"], ["notes"]);
+        // Only `[build.taxonomies] names` are taxonomies
+        assert!(!meta.taxonomies.contains_key("series"));
+    }
+
+    #[test]
+    fn test_section_posts() {
+        let pages = [
+            post("content/notes/b.typ", "b", "2024-02-01"),
+            post("content/notes/deep/a.typ", "a", "2024-01-01"),
+            post("content/posts/c.typ", "c", "2024-03-01"),
+        ];
+        let dir = Path::new("content/notes");
+        let titles = |posts: Vec<Page>| {
+            posts
+                .iter()
+                .map(|p| post_title(p).to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles(section_posts(&pages, dir, SortBy::Date)), ["b", "a"]);
+        assert_eq!(
+            titles(section_posts(&pages, dir, SortBy::Title)),
+            ["a", "b"]
+        );
+    }
+}
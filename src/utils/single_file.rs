@@ -0,0 +1,318 @@
+//! Single-file HTML export (`tola build --single-file <page>`).
+//!
+//! Inlines a built page's stylesheets, scripts, images and fonts so the result
+//! is one self-contained HTML file, e.g. for emailing or archiving an article.
+//! Only files from the output directory are inlined; external URLs are kept.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        site::{attribute_span, tag_attribute},
+        slug::content_paths,
+    },
+};
+use anyhow::{Context, Result, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+/// Export a built page as one self-contained HTML file in the site root
+///
+/// `page` is either a source file (`content/posts/hello.typ`) or a path in the
+/// output directory (`posts/hello`, `posts/hello/index.html`).
+pub fn export(page: &Path, config: &SiteConfig) -> Result<PathBuf> {
+    let html_path = resolve_page(page, config)?;
+    let html = fs::read_to_string(&html_path)
+        .with_context(|| format!("Failed to read {}", html_path.display()))?;
+    let page_dir = html_path.parent().unwrap_or(&config.build.output);
+    let inlined = inline_html(&html, page_dir, &config.build.output);
+
+    let target = config
+        .get_root()
+        .join(format!("{}.html", export_name(&html_path, config)));
+    fs::write(&target, inlined).with_context(|| format!("Failed to write {}", target.display()))?;
+    log!("single-file"; "written to {}", target.display());
+    Ok(target)
+}
+
+/// Output HTML file of `page`
+fn resolve_page(page: &Path, config: &SiteConfig) -> Result<PathBuf> {
+    if page.extension().is_some_and(|ext| ext == "typ") {
+        let source = [
+            page.to_path_buf(),
+            config.get_root().join(page),
+            config.build.content.join(page),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+        .with_context(|| format!("Source file not found: {}", page.display()))?;
+        return Ok(content_paths(&source.canonicalize()?, config)?.html);
+    }
+
+    let relative = page.strip_prefix("/").unwrap_or(page);
+    let output = config.build.output.join(relative);
+    if output.is_file() {
+        return Ok(output);
+    }
+    if output.join("index.html").is_file() {
+        return Ok(output.join("index.html"));
+    }
+    bail!("No built page found for `{}`", page.display())
+}
+
+/// File name for the export: the page's directory name, or `index` for the root
+fn export_name(html_path: &Path, config: &SiteConfig) -> String {
+    let stem = html_path.file_stem().unwrap_or_default();
+    let name = match stem == "index" {
+        true => html_path
+            .parent()
+            .filter(|dir| *dir != config.build.output)
+            .and_then(Path::file_name),
+        false => Some(stem),
+    };
+    name.map_or_else(
+        || "index".into(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+// ============================================================================
+// Inlining
+// ============================================================================
+
+/// Inline local resources referenced by `html`
+///
+/// Works on raw tags rather than a parsed document, since the page may be minified.
+fn inline_html(html: &str, page_dir: &Path, output: &Path) -> String {
+    let mut out = String::with_capacity(html.len() * 2);
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start..start + end + 1];
+        rest = &rest[start + end + 1..];
+        let inner = tag.trim_start_matches('<').trim_end_matches('>');
+        let name = inner
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match name.as_str() {
+            "link" if is_stylesheet(inner) => {
+                let css = tag_attribute(inner, "href")
+                    .and_then(|href| resolve(href, page_dir, output))
+                    .and_then(|path| Some((fs::read_to_string(&path).ok()?, path)));
+                match css {
+                    Some((css, path)) => {
+                        let css_dir = path.parent().unwrap_or(output);
+                        out.push_str("<style>");
+                        out.push_str(&inline_css(&css, css_dir, output));
+                        out.push_str("</style>");
+                    }
+                    None => out.push_str(tag),
+                }
+            }
+            "script" | "style" => {
+                // Element content is raw text, copied up to the closing tag
+                let close = format!("</{name}");
+                let body_end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                let (body, after) = rest.split_at(body_end);
+                rest = after;
+
+                let script = (name == "script")
+                    .then(|| tag_attribute(inner, "src"))
+                    .flatten()
+                    .and_then(|src| resolve(src, page_dir, output))
+                    .and_then(|path| fs::read_to_string(path).ok());
+                match (name.as_str(), script) {
+                    ("script", Some(script)) => {
+                        out.push_str(&format!("<{}>", remove_attribute(inner, "src")));
+                        out.push_str(&script);
+                    }
+                    ("style", _) => {
+                        out.push_str(tag);
+                        out.push_str(&inline_css(body, page_dir, output));
+                    }
+                    _ => {
+                        out.push_str(tag);
+                        out.push_str(body);
+                    }
+                }
+            }
+            "img" | "source" | "video" | "audio" | "input" => {
+                let data = tag_attribute(inner, "src")
+                    .and_then(|src| resolve(src, page_dir, output))
+                    .and_then(|path| data_uri(&path));
+                match data {
+                    Some(data) => {
+                        out.push_str(&format!("<{}>", replace_attribute(inner, "src", &data)))
+                    }
+                    None => out.push_str(tag),
+                }
+            }
+            _ => out.push_str(tag),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replace `url(...)` references in CSS with data URIs
+fn inline_css(css: &str, css_dir: &Path, output: &Path) -> String {
+    static RE_URL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#"url\([ \t]*(['"]?)([^'")]+)(['"]?)[ \t]*\)"#).unwrap());
+
+    RE_URL
+        .replace_all(css, |caps: &regex::Captures| {
+            resolve(&caps[2], css_dir, output)
+                .and_then(|path| data_uri(&path))
+                .map_or_else(|| caps[0].to_owned(), |data| format!("url(\"{data}\")"))
+        })
+        .into_owned()
+}
+
+fn is_stylesheet(tag: &str) -> bool {
+    tag_attribute(tag, "rel").is_some_and(|rel| {
+        rel.split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+    })
+}
+
+/// Local file a URL points to; `None` for external, data and missing files
+fn resolve(url: &str, base_dir: &Path, output: &Path) -> Option<PathBuf> {
+    let url = url.trim();
+    let is_external = url.starts_with("//") || url.contains(':');
+    if is_external || url.is_empty() {
+        return None;
+    }
+    let url = url.split(['?', '#']).next()?;
+    let url = urlencoding::decode(url).unwrap_or(Cow::Borrowed(url));
+    let path = match url.strip_prefix('/') {
+        Some(absolute) => output.join(absolute),
+        None => base_dir.join(url.as_ref()),
+    };
+    path.is_file().then_some(path)
+}
+
+/// `data:` URI with the file's contents
+fn data_uri(path: &Path) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime_type(path),
+        STANDARD.encode(data)
+    ))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    match ext.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `tag` with the value of attribute `name` replaced by `value` (quoted)
+fn replace_attribute(tag: &str, name: &str, value: &str) -> String {
+    match attribute_span(tag, name) {
+        Some(span) => format!("{}\"{value}\"{}", &tag[..span.start], &tag[span.end..]),
+        None => tag.to_owned(),
+    }
+}
+
+/// `tag` without attribute `name`
+fn remove_attribute(tag: &str, name: &str) -> String {
+    match attribute_span(tag, name) {
+        Some(span) => {
+            let before = &tag[..span.start];
+            let start = before.rfind(name).unwrap_or(span.start);
+            format!("{}{}", tag[..start].trim_end(), &tag[span.end..])
+        }
+        None => tag.to_owned(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_html() {
+        let output = std::env::temp_dir().join("tola_single_file_test");
+        fs::create_dir_all(output.join("posts/a")).unwrap();
+        fs::write(output.join("style.css"), "body{background:url(bg.png)}").unwrap();
+        fs::write(output.join("bg.png"), [1, 2, 3]).unwrap();
+        fs::write(output.join("app.js"), "let x = 1 < 2;").unwrap();
+        fs::write(output.join("posts/a/svg-0.svg"), "<svg/>").unwrap();
+
+        let html = concat!(
+            r#"<html><head><link rel=stylesheet href="/style.css">"#,
+            r#"<script defer src=/app.js></script></head>"#,
+            r#"<body><img src=svg-0.svg alt=x><img src="https://cdn.example/a.png">"#,
+            r#"<script>if (a<b) {}</script></body></html>"#
+        );
+        let inlined = inline_html(html, &output.join("posts/a"), &output);
+        let _ = fs::remove_dir_all(&output);
+
+        assert_eq!(
+            inlined,
+            concat!(
+                r#"<html><head><style>body{background:url("data:image/png;base64,AQID")}</style>"#,
+                r#"<script defer>let x = 1 < 2;</script></head>"#,
+                r#"<body><img src="data:image/svg+xml;base64,PHN2Zy8+" alt=x><img src="https://cdn.example/a.png">"#,
+                r#"<script>if (a<b) {}</script></body></html>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_resolve_skips_external() {
+        let dir = Path::new("/nonexistent");
+        assert_eq!(resolve("https://example.com/a.css", dir, dir), None);
+        assert_eq!(resolve("data:image/png;base64,AA", dir, dir), None);
+        assert_eq!(resolve("//cdn.example/a.css", dir, dir), None);
+    }
+
+    #[test]
+    fn test_export_name() {
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/site/public");
+        let name = |path: &str| export_name(Path::new(path), &config);
+        assert_eq!(name("/site/public/posts/hello/index.html"), "hello");
+        assert_eq!(name("/site/public/index.html"), "index");
+        assert_eq!(name("/site/public/about.html"), "about");
+    }
+}
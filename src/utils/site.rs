@@ -0,0 +1,631 @@
+//! Shared site model.
+//!
+//! The content pass compiles every page once and records its output path, URL,
+//! metadata and plain text here. Site-wide generators such as the RSS feed read
+//! this model after the pass instead of compiling or querying pages again.
+
+use crate::{
+    config::SiteConfig,
+    utils::{
+        backlinks,
+        meta::{META_TAG_NAME, PageMeta, parse_meta},
+        report,
+        rss::DateTimeUtc,
+    },
+};
+use anyhow::anyhow;
+use chrono::Utc;
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// A compiled page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Page {
+    /// Source `.typ` file
+    pub source: PathBuf,
+    /// Output HTML file
+    pub output: PathBuf,
+    /// Absolute public URL
+    pub url: String,
+    /// Page metadata; `None` if it is missing or invalid
+    pub meta: Option<PageMeta>,
+    /// Visible text of the page body
+    pub text: String,
+    /// Outbound links; only collected for `[build.webmention]`
+    pub links: Vec<String>,
+    /// Site paths of the internal pages linked; only collected for `[build.backlinks]`
+    pub internal_links: Vec<String>,
+}
+
+impl Page {
+    /// Parsed `date`; `None` if the page has none or it is not a valid date
+    pub fn date(&self) -> Option<DateTimeUtc> {
+        DateTimeUtc::parse(self.date_text())
+    }
+
+    /// `date` as written in the metadata, or `""`
+    pub fn date_text(&self) -> &str {
+        self.meta
+            .as_ref()
+            .and_then(|meta| meta.date.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// Pages keyed by source path, so iteration order is stable
+static PAGES: Mutex<BTreeMap<PathBuf, Page>> = Mutex::new(BTreeMap::new());
+
+fn pages_lock() -> MutexGuard<'static, BTreeMap<PathBuf, Page>> {
+    PAGES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether any enabled feature reads page metadata
+pub fn needs_metadata(config: &SiteConfig) -> bool {
+    let build = &config.build;
+    build.rss.enable
+        || build.comments.enable
+        || build.webmention.enable
+        || build.text.llms
+        || build.api.enable
+        || build.authors.enable
+        || build.nav.enable
+        || build.taxonomies.enable
+        || build.search.enable
+        || build.pagination.enable
+        || build.redirects.enable
+        || build.sitemap.enable
+        || build.sections.enable
+        || build.reading_time.enable
+        || build.backlinks.enable
+        || build.archive.enable
+}
+
+/// Parse the metadata JSON a page was compiled with.
+///
+/// Missing or invalid metadata yields `None`, so the page still builds, and is
+/// reported if [`needs_metadata`]. Pages without metadata are fine when it is
+/// only needed to filter drafts and future posts.
+pub fn page_meta(source: &Path, json: Option<&str>, config: &SiteConfig) -> Option<PageMeta> {
+    let meta = match json {
+        Some(json) => parse_meta(json, config),
+        None => Err(anyhow!("No {META_TAG_NAME} metadata in page")),
+    };
+    meta.map_err(|e| {
+        if needs_metadata(config) {
+            report::error(source, &e);
+        }
+    })
+    .ok()
+}
+
+/// Whether a page is left out of the build: a draft, or dated in the future,
+/// unless `[build] drafts`/`future` (or `--drafts`/`--future`) include it.
+pub fn is_excluded(meta: Option<&PageMeta>, config: &SiteConfig) -> bool {
+    let Some(meta) = meta else { return false };
+    if meta.draft && !config.build.drafts {
+        return true;
+    }
+    let date = meta.date.as_deref().and_then(DateTimeUtc::parse);
+    !config.build.future && date.and_then(DateTimeUtc::to_fixed) > Some(Utc::now().fixed_offset())
+}
+
+/// Record a page after it was compiled (or found up to date).
+pub fn record_page(
+    source: &Path,
+    output: &Path,
+    html: &[u8],
+    meta: Option<PageMeta>,
+    config: &SiteConfig,
+) {
+    let links = match config.build.webmention.enable {
+        true => extract_links(html, config.base.url.as_deref().unwrap_or_default()),
+        false => Vec::new(),
+    };
+    let internal_links = match config.build.backlinks.enable {
+        true => backlinks::internal_links(html, output, config),
+        false => Vec::new(),
+    };
+    let page = Page {
+        source: source.to_path_buf(),
+        output: output.to_path_buf(),
+        url: page_url(output, config),
+        meta,
+        text: extract_text(html),
+        links,
+        internal_links,
+    };
+    pages_lock().insert(page.source.clone(), page);
+}
+
+/// Forget a page whose source was removed
+pub fn remove_page(source: &Path) {
+    pages_lock().remove(source);
+}
+
+/// Drop all pages before a full build
+pub fn clear() {
+    pages_lock().clear();
+}
+
+/// Snapshot of all recorded pages, ordered by source path
+pub fn pages() -> Vec<Page> {
+    pages_lock().values().cloned().collect()
+}
+
+/// Public URL of an output HTML file.
+///
+/// Example: `public/posts/hello/index.html` → `https://example.com/posts/hello/index.html`
+pub fn page_url(output: &Path, config: &SiteConfig) -> String {
+    let base_url = config.base.url.as_deref().unwrap_or_default();
+
+    // Strip output dir prefix to get relative path for URL
+    let html_relative = output.strip_prefix(&config.build.output).unwrap_or(output);
+
+    // URL-encode path components but preserve slashes
+    let encoded = urlencoding::encode(html_relative.to_str().unwrap_or_default());
+    let encoded = encoded.replace("%2F", "/");
+
+    format!("{}/{}", base_url.trim_end_matches('/'), encoded)
+}
+
+/// First directory of `source` below the content directory
+pub fn section(source: &Path, content: &Path) -> Option<String> {
+    let relative = source.strip_prefix(content).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    // A file directly in the content directory belongs to no section
+    components.next()?;
+    match first {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Text Extraction
+// ============================================================================
+
+/// Elements whose content is not visible text
+///
+/// `title` is listed on its own since minified pages omit the `<head>` tag.
+const HIDDEN_ELEMENTS: &[&str] = &["head", "script", "style", "svg", "template", "title"];
+
+/// Elements that start a new paragraph in plain text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Visible text of an HTML document
+///
+/// Whitespace is collapsed within lines; `<br>` breaks a line and block
+/// elements are separated by a blank line.
+pub fn extract_text(html: &[u8]) -> String {
+    let html = String::from_utf8_lossy(html);
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut hidden: Option<&str> = None;
+    let mut rest = html.as_ref();
+
+    while let Some(start) = rest.find('<') {
+        if hidden.is_none() {
+            push_text(&mut text, &rest[..start]);
+        }
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match hidden {
+            Some(element) if tag.starts_with('/') && name == element => hidden = None,
+            Some(_) => {}
+            None if !tag.starts_with('/') && !tag.ends_with('/') => {
+                hidden = HIDDEN_ELEMENTS.iter().copied().find(|e| *e == name);
+            }
+            None => {}
+        }
+        // Tag boundaries separate words
+        text.push_str(match name.as_str() {
+            "br" => "\n",
+            name if BLOCK_ELEMENTS.contains(&name) => "\n\n",
+            _ => " ",
+        });
+    }
+    if hidden.is_none() {
+        push_text(&mut text, rest);
+    }
+
+    collapse_whitespace(&text)
+}
+
+/// Collapse spaces within lines and runs of blank lines into one
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.split('\n') {
+        let words: Vec<_> = line.split_whitespace().collect();
+        if words.is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank { "\n\n" } else { "\n" });
+        }
+        out.push_str(&words.join(" "));
+        blank = false;
+    }
+    out
+}
+
+/// Append `raw` with common entities decoded
+fn push_text(text: &mut String, raw: &str) {
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" | "#39" => '\'',
+                "nbsp" => ' ',
+                entity => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#')?.parse().ok())
+                    .and_then(char::from_u32)?,
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                text.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+}
+
+// ============================================================================
+// Summaries
+// ============================================================================
+
+/// Give `meta` a summary derived from the page content if it has none
+pub fn fill_summary(meta: &mut PageMeta, html: &[u8], config: &SiteConfig) {
+    let summary = &config.build.summary;
+    if !summary.enable || meta.summary.is_some() {
+        return;
+    }
+    meta.summary = auto_summary(html, summary.length)
+        .map(|text| quick_xml::escape::escape(text.as_str()).into_owned());
+}
+
+/// First paragraph of the page content as text, cut at a word boundary after
+/// `length` characters
+///
+/// The content is `<main>`, `<article>` or `<body>`, the first one present;
+/// without a paragraph, its text is used.
+fn auto_summary(html: &[u8], length: usize) -> Option<String> {
+    let html = String::from_utf8_lossy(html);
+    let content = ["<main", "<article", "<body"]
+        .iter()
+        .find_map(|tag| html.find(tag).map(|start| &html[start..]))
+        .unwrap_or(&html);
+    let paragraph = content
+        .match_indices("<p")
+        .find(|(start, _)| {
+            content[start + 2..].starts_with(|c: char| c == '>' || c.is_whitespace())
+        })
+        .map(|(start, _)| {
+            let rest = &content[start..];
+            &rest[..rest.find("</p>").unwrap_or(rest.len())]
+        });
+    let text = extract_text(paragraph.unwrap_or(content).as_bytes()).replace('\n', " ");
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= length {
+        return Some(text.to_owned());
+    }
+
+    let cut = text
+        .char_indices()
+        .nth(length)
+        .map_or(text.len(), |(i, _)| i);
+    let truncated = match text[..cut].rfind(' ') {
+        Some(space) if !text[cut..].starts_with(' ') => &text[..space],
+        _ => &text[..cut],
+    };
+    Some(format!("{}…", truncated.trim_end()))
+}
+
+// ============================================================================
+// Link Extraction
+// ============================================================================
+
+/// Absolute `http(s)` links of `<a>` elements that leave the site, deduplicated
+pub fn extract_links(html: &[u8], base_url: &str) -> Vec<String> {
+    let base_url = base_url.trim_end_matches('/');
+    let mut links = Vec::new();
+    for href in anchor_hrefs(html) {
+        let is_external = href.starts_with("http://") || href.starts_with("https://");
+        let is_own = !base_url.is_empty() && href.starts_with(base_url);
+        if is_external && !is_own && !links.contains(&href) {
+            links.push(href);
+        }
+    }
+    links
+}
+
+/// `href` of every `<a>` element, in document order
+///
+/// Scans raw tags instead of parsing, since minified output need not be valid XML.
+pub fn anchor_hrefs(html: &[u8]) -> Vec<String> {
+    let html = String::from_utf8_lossy(html);
+    let mut hrefs = Vec::new();
+    let mut rest = html.as_ref();
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let is_anchor = tag
+            .strip_prefix(['a', 'A'])
+            .is_some_and(|attrs| attrs.starts_with(char::is_whitespace));
+        let Some(href) = is_anchor.then(|| tag_attribute(tag, "href")).flatten() else {
+            continue;
+        };
+        hrefs.push(href.replace("&amp;", "&"));
+    }
+    hrefs
+}
+
+/// Value of attribute `name` in a raw start tag (quoted or unquoted)
+pub fn tag_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let raw = &tag[attribute_span(tag, name)?];
+    Some(match raw.chars().next()? {
+        quote @ ('"' | '\'') => raw[1..].strip_suffix(quote).unwrap_or(&raw[1..]),
+        _ => raw,
+    })
+}
+
+/// Byte range of the raw value of attribute `name`, including any quotes
+pub fn attribute_span(tag: &str, name: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    while let Some(pos) = tag[offset..].find(name) {
+        let pos = offset + pos;
+        offset = pos + name.len();
+        let preceded_by_space = tag[..pos].ends_with(char::is_whitespace);
+        let after = &tag[offset..];
+        let Some(value) = after
+            .trim_start()
+            .strip_prefix('=')
+            .filter(|_| preceded_by_space)
+        else {
+            continue;
+        };
+        let trimmed = value.trim_start();
+        let start = tag.len() - trimmed.len();
+        let len = match trimmed.chars().next()? {
+            quote @ ('"' | '\'') => trimmed[1..]
+                .find(quote)
+                .map_or(trimmed.len(), |end| end + 2),
+            _ => trimmed.find(char::is_whitespace).unwrap_or(trimmed.len()),
+        };
+        return Some(start..start + len);
+    }
+    None
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_text() {
+        let html = br#"<!DOCTYPE html><html><head><title>T</title><style>p{}</style></head>
+            <body><h1>Hello</h1><p>A &amp; B&#33;<br/>next</p><script>var x = "<p>";</script></body></html>"#;
+        assert_eq!(extract_text(html), "Hello\n\nA & B!\nnext");
+    }
+
+    #[test]
+    fn test_extract_text_minified_head() {
+        let html = b"<!doctype html><html lang=en><title>T</title><meta charset=utf-8><body><p>x";
+        assert_eq!(extract_text(html), "x");
+    }
+
+    #[test]
+    fn test_extract_text_paragraphs() {
+        let html = b"<body><p>one  two</p>\n\n<ul><li>a</li><li>b <em>c</em></li></ul></body>";
+        assert_eq!(extract_text(html), "one two\n\na\n\nb c");
+    }
+
+    #[test]
+    fn test_extract_text_skips_inline_svg() {
+        let html = b"<p>before<svg><text>label</text></svg>after</p>";
+        assert_eq!(extract_text(html), "before after");
+    }
+
+    #[test]
+    fn test_auto_summary() {
+        let html = b"<html><head><title>T</title></head><body><nav><p>Menu</p></nav>\
+            <main><h1>Title</h1><p class=\"lead\">The <em>first</em> paragraph.</p>\
+            <p>Second</p></main></body></html>";
+        assert_eq!(
+            auto_summary(html, 200).as_deref(),
+            Some("The first paragraph.")
+        );
+        assert_eq!(auto_summary(html, 12).as_deref(), Some("The first…"));
+        assert_eq!(auto_summary(html, 9).as_deref(), Some("The first…"));
+        assert_eq!(
+            auto_summary(b"<body><pre>no paragraph</pre></body>", 200).as_deref(),
+            Some("no paragraph")
+        );
+        assert_eq!(auto_summary(b"<body></body>", 200), None);
+
+        let mut config = SiteConfig::default();
+        let mut meta = PageMeta::default();
+        fill_summary(&mut meta, b"<body><p>a &lt; b</p></body>", &config);
+        assert_eq!(meta.summary.as_deref(), Some("a &lt; b"));
+
+        let mut meta = PageMeta {
+            summary: Some("<p>Own</p>".into()),
+            ..Default::default()
+        };
+        fill_summary(&mut meta, html, &config);
+        assert_eq!(meta.summary.as_deref(), Some("<p>Own</p>"));
+
+        config.build.summary.enable = false;
+        let mut meta = PageMeta::default();
+        fill_summary(&mut meta, html, &config);
+        assert_eq!(meta.summary, None);
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let html = br#"<p><a href="https://other.site/post?a=1&amp;b=2">x</a>
+            <a class=x href=https://third.site/>y</a>
+            <a href="https://example.com/posts/a/">own</a>
+            <a href="/relative/">rel</a><a href='https://other.site/post?a=1&b=2'>dup</a>
+            <link href="https://cdn.site/style.css"><abbr title="x">z</abbr></p>"#;
+        assert_eq!(
+            extract_links(html, "https://example.com/"),
+            ["https://other.site/post?a=1&b=2", "https://third.site/"]
+        );
+    }
+
+    #[test]
+    fn test_tag_attribute() {
+        let tag = r#"img data-src="x" src='a b.png' alt=hi"#;
+        assert_eq!(tag_attribute(tag, "src"), Some("a b.png"));
+        assert_eq!(tag_attribute(tag, "alt"), Some("hi"));
+        assert_eq!(tag_attribute(tag, "title"), None);
+        assert_eq!(&tag[attribute_span(tag, "src").unwrap()], "'a b.png'");
+    }
+
+    #[test]
+    fn test_page_url() {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com/".into());
+        config.build.output = PathBuf::from("/site/public");
+
+        let url = page_url(
+            Path::new("/site/public/posts/hello world/index.html"),
+            &config,
+        );
+        assert_eq!(url, "https://example.com/posts/hello%20world/index.html");
+    }
+
+    #[test]
+    fn test_page_date() {
+        let page = |date: &str| Page {
+            meta: Some(PageMeta {
+                date: Some(date.into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(page("2024-10-01").date() > page("2024-09-30T23:59:59Z").date());
+        assert_eq!(page("2024-10-01").date_text(), "2024-10-01");
+        // Unpadded and invalid dates are no dates, rather than sorting as text
+        assert_eq!(page("2024-5-3").date(), None);
+        assert_eq!(page("2024-02-30").date(), None);
+        assert_eq!(Page::default().date(), None);
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let mut config = SiteConfig::default();
+        let meta = |draft, date: &str| PageMeta {
+            draft,
+            date: Some(date.into()),
+            ..Default::default()
+        };
+        let draft = meta(true, "2024-01-01");
+        let future = meta(false, "9999-01-01");
+
+        assert!(!is_excluded(None, &config));
+        assert!(!is_excluded(Some(&meta(false, "2024-01-01")), &config));
+        assert!(is_excluded(Some(&draft), &config));
+        assert!(is_excluded(Some(&future), &config));
+
+        config.build.drafts = true;
+        config.build.future = true;
+        assert!(!is_excluded(Some(&draft), &config));
+        assert!(!is_excluded(Some(&future), &config));
+    }
+
+    #[test]
+    fn test_page_meta() {
+        // No feature reads metadata, yet drafts are still filtered by default
+        let config = SiteConfig::default();
+        let source = Path::new("content/draft.typ");
+        let meta = page_meta(source, Some(r#"{ "draft": true }"#), &config);
+        assert!(is_excluded(meta.as_ref(), &config));
+        assert_eq!(page_meta(source, None, &config), None);
+    }
+
+    #[test]
+    fn test_section() {
+        let content = Path::new("/site/content");
+        assert_eq!(
+            section(Path::new("/site/content/posts/2024/a.typ"), content).as_deref(),
+            Some("posts")
+        );
+        assert_eq!(section(Path::new("/site/content/a.typ"), content), None);
+        assert_eq!(section(Path::new("/elsewhere/a.typ"), content), None);
+    }
+}
@@ -0,0 +1,294 @@
+//! XML sitemap generation.
+//!
+//! Lists every page of the site model with its last modification date and
+//! the `changefreq`/`priority` of the first matching `[build.sitemap]` rule.
+//! Sitemaps are limited to 50,000 URLs, so larger sites get numbered sitemaps
+//! and a sitemap index at `[build.sitemap] path`.
+
+use crate::{
+    config::{SiteConfig, SitemapRule},
+    log,
+    utils::{
+        prune,
+        rss::DateTimeUtc,
+        site::{self, Page},
+    },
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use gix::glob::wildmatch;
+use quick_xml::escape::escape;
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Most URLs a single sitemap may list
+const MAX_URLS: usize = 50_000;
+
+const XMLNS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+/// A `<url>` entry
+#[derive(Debug)]
+struct SitemapUrl<'a> {
+    loc: String,
+    lastmod: Option<String>,
+    rule: Option<&'a SitemapRule>,
+}
+
+/// Write the sitemap if `[build.sitemap]` is enabled
+pub fn build_sitemap(config: &SiteConfig) -> Result<()> {
+    if !config.build.sitemap.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let urls = sitemap_urls(&pages, config);
+    let files = write_sitemaps(&urls, &config.build.sitemap.path, config, MAX_URLS)?;
+
+    log!("sitemap"; "{} URL(s) in {} file(s)", urls.len(), files.len());
+    Ok(())
+}
+
+/// One entry per page, in URL order
+fn sitemap_urls<'a>(pages: &[Page], config: &'a SiteConfig) -> Vec<SitemapUrl<'a>> {
+    let mut urls: Vec<_> = pages
+        .iter()
+        .map(|page| {
+            let relative = page
+                .output
+                .strip_prefix(&config.build.output)
+                .unwrap_or(&page.output)
+                .to_string_lossy()
+                .replace('\\', "/");
+            SitemapUrl {
+                loc: pretty_url(&page.url).to_owned(),
+                lastmod: lastmod(page),
+                rule: config
+                    .build
+                    .sitemap
+                    .rules
+                    .iter()
+                    .find(|rule| matches_pattern(&relative, &rule.pattern)),
+            }
+        })
+        .collect();
+    urls.sort_by(|a, b| a.loc.cmp(&b.loc));
+    urls
+}
+
+/// `https://example.com/posts/a/index.html` → `https://example.com/posts/a/`
+fn pretty_url(url: &str) -> &str {
+    url.strip_suffix("index.html").unwrap_or(url)
+}
+
+/// `update`, `date` or the source modification date, as a W3C datetime
+fn lastmod(page: &Page) -> Option<String> {
+    let meta = page.meta.as_ref();
+    let declared = meta
+        .and_then(|meta| meta.update.as_deref())
+        .into_iter()
+        .chain(meta.and_then(|meta| meta.date.as_deref()))
+        .find(|date| DateTimeUtc::parse(date).is_some());
+    if let Some(date) = declared {
+        return Some(date.to_owned());
+    }
+    let modified = fs::metadata(&page.source).ok()?.modified().ok()?;
+    Some(
+        DateTime::<Utc>::from(modified)
+            .format("%Y-%m-%d")
+            .to_string(),
+    )
+}
+
+/// Whether `relative` matches a glob (`*` stays within a directory, `**` crosses them)
+fn matches_pattern(relative: &str, pattern: &str) -> bool {
+    wildmatch(
+        pattern.into(),
+        relative.into(),
+        wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+    )
+}
+
+/// Write `urls` to `path`, or to numbered sitemaps and an index at `path`
+/// when there are more than `max_urls`; returns the written files
+fn write_sitemaps(
+    urls: &[SitemapUrl],
+    path: &Path,
+    config: &SiteConfig,
+    max_urls: usize,
+) -> Result<Vec<PathBuf>> {
+    if urls.len() <= max_urls {
+        prune::write_produced(path, render_urlset(urls).as_bytes())?;
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let base_url = config.base.url.as_deref().unwrap_or_default();
+    let mut files = Vec::new();
+    let mut locs = Vec::new();
+    for (i, chunk) in urls.chunks(max_urls).enumerate() {
+        let file = path.with_file_name(format!("{stem}-{}.xml", i + 1));
+        prune::write_produced(&file, render_urlset(chunk).as_bytes())?;
+        let relative = file.strip_prefix(&config.build.output).unwrap_or(&file);
+        locs.push(format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            relative.to_string_lossy().replace('\\', "/")
+        ));
+        files.push(file);
+    }
+    prune::write_produced(path, render_index(&locs).as_bytes())?;
+    files.push(path.to_path_buf());
+    Ok(files)
+}
+
+fn render_urlset(urls: &[SitemapUrl]) -> String {
+    let mut xml =
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"{XMLNS}\">\n");
+    for url in urls {
+        let _ = write!(xml, "  <url><loc>{}</loc>", escape(url.loc.as_str()));
+        if let Some(lastmod) = &url.lastmod {
+            let _ = write!(xml, "<lastmod>{}</lastmod>", escape(lastmod.as_str()));
+        }
+        if let Some(changefreq) = url.rule.and_then(|rule| rule.changefreq) {
+            let _ = write!(xml, "<changefreq>{}</changefreq>", changefreq.as_str());
+        }
+        if let Some(priority) = url.rule.and_then(|rule| rule.priority) {
+            let _ = write!(xml, "<priority>{priority:.1}</priority>");
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn render_index(locs: &[String]) -> String {
+    let mut xml =
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"{XMLNS}\">\n");
+    for loc in locs {
+        let _ = writeln!(
+            xml,
+            "  <sitemap><loc>{}</loc></sitemap>",
+            escape(loc.as_str())
+        );
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::ChangeFreq, utils::meta::PageMeta};
+
+    fn page(output: &str, date: Option<&str>, update: Option<&str>) -> Page {
+        Page {
+            source: PathBuf::from("/nonexistent/content.typ"),
+            output: PathBuf::from("/site/public").join(output),
+            url: format!("https://example.com/{output}"),
+            meta: Some(PageMeta {
+                date: date.map(Into::into),
+                update: update.map(Into::into),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn config() -> SiteConfig {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com".into());
+        config.build.output = PathBuf::from("/site/public");
+        config.build.sitemap.rules = vec![
+            SitemapRule {
+                pattern: "posts/**".into(),
+                changefreq: Some(ChangeFreq::Monthly),
+                priority: Some(0.8),
+            },
+            SitemapRule {
+                pattern: "*".into(),
+                changefreq: Some(ChangeFreq::Daily),
+                priority: None,
+            },
+        ];
+        config
+    }
+
+    #[test]
+    fn test_sitemap_urls() {
+        let config = config();
+        let pages = [
+            page("posts/b/index.html", Some("2024-01-01"), Some("2024-02-01")),
+            page("index.html", None, None),
+            page("posts/a/index.html", Some("2024-03-01"), Some("soon")),
+        ];
+        let urls = sitemap_urls(&pages, &config);
+        let locs: Vec<_> = urls.iter().map(|url| url.loc.as_str()).collect();
+        assert_eq!(
+            locs,
+            [
+                "https://example.com/",
+                "https://example.com/posts/a/",
+                "https://example.com/posts/b/"
+            ]
+        );
+        // No valid date and no source file
+        assert_eq!(urls[0].lastmod, None);
+        assert_eq!(urls[1].lastmod.as_deref(), Some("2024-03-01"));
+        assert_eq!(urls[2].lastmod.as_deref(), Some("2024-02-01"));
+        assert_eq!(urls[0].rule.unwrap().pattern, "*");
+        assert_eq!(urls[1].rule.unwrap().pattern, "posts/**");
+
+        let xml = render_urlset(&urls);
+        assert!(xml.contains(
+            "<url><loc>https://example.com/posts/a/</loc><lastmod>2024-03-01</lastmod>\
+             <changefreq>monthly</changefreq><priority>0.8</priority></url>"
+        ));
+        assert!(
+            xml.contains(
+                "<url><loc>https://example.com/</loc><changefreq>daily</changefreq></url>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_sitemaps_index() {
+        let dir = std::env::temp_dir().join("tola_sitemap_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut config = config();
+        config.build.output = dir.clone();
+        let urls: Vec<_> = (0..5)
+            .map(|i| SitemapUrl {
+                loc: format!("https://example.com/{i}/"),
+                lastmod: None,
+                rule: None,
+            })
+            .collect();
+
+        let path = dir.join("sitemap.xml");
+        let files = write_sitemaps(&urls, &path, &config, 2).unwrap();
+        assert_eq!(
+            files,
+            [
+                dir.join("sitemap-1.xml"),
+                dir.join("sitemap-2.xml"),
+                dir.join("sitemap-3.xml"),
+                path.clone()
+            ]
+        );
+        let index = fs::read_to_string(&path).unwrap();
+        assert!(index.contains("<sitemapindex"));
+        assert!(index.contains("<loc>https://example.com/sitemap-3.xml</loc>"));
+        let last = fs::read_to_string(dir.join("sitemap-3.xml")).unwrap();
+        assert!(last.contains("https://example.com/4/"));
+
+        assert_eq!(write_sitemaps(&urls, &path, &config, 5).unwrap(), [path]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
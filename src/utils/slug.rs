@@ -2,34 +2,72 @@
 //!
 //! Converts paths and fragments to URL-safe formats.
 
-use crate::config::{SiteConfig, SlugMode};
-use anyhow::{Result, anyhow};
-use std::path::{Path, PathBuf};
+use crate::config::{SiteConfig, SlugCase, SlugMode};
+use crate::utils::theme;
+use anyhow::{Result, bail};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+use unicode_normalization::UnicodeNormalization;
 
 /// Characters forbidden in file paths and fragments
 const FORBIDDEN_CHARS: &[char] = &[
     '<', '>', ':', '|', '?', '*', '#', '\\', '(', ')', '[', ']', '\t', '\r', '\n',
 ];
 
+/// Device names Windows reserves in every directory, with or without extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest file name most file systems accept, in bytes
+const MAX_COMPONENT_LEN: usize = 255;
+
+/// Longest output path accepted, relative to the output directory.
+///
+/// Leaves room for the output directory itself under Windows' 260-character `MAX_PATH`.
+const MAX_RELATIVE_PATH_LEN: usize = 200;
+
 // ============================================================================
 // Slugification
 // ============================================================================
 
 /// Convert fragment text to URL-safe format based on config
-pub fn slugify_fragment(text: &str, config: &'static SiteConfig) -> String {
+pub fn slugify_fragment(text: &str, config: &SiteConfig) -> String {
+    let text = normalize(text, config);
     match config.build.slug.fragment {
-        SlugMode::Safe => sanitize_text(text),
+        SlugMode::Safe => sanitize_text(&text),
         SlugMode::On => slug::slugify(text),
-        SlugMode::No => text.to_owned(),
+        SlugMode::No => text,
     }
 }
 
 /// Convert path to URL-safe format based on config
-pub fn slugify_path(path: impl AsRef<Path>, config: &'static SiteConfig) -> PathBuf {
+pub fn slugify_path(path: impl AsRef<Path>, config: &SiteConfig) -> PathBuf {
+    let path = map_normal_components(path.as_ref(), |text| normalize(text, config));
     match config.build.slug.path {
-        SlugMode::Safe => sanitize_path(path.as_ref()),
-        SlugMode::On => slug::slugify(path.as_ref().to_string_lossy()).into(),
-        SlugMode::No => path.as_ref().to_path_buf(),
+        SlugMode::Safe => sanitize_path(&path),
+        // Slugifying needs text; non-UTF-8 paths fall back to sanitizing
+        SlugMode::On => match path.to_str() {
+            Some(path) => windows_safe_path(Path::new(&slug::slugify(path))),
+            None => sanitize_path(&path),
+        },
+        SlugMode::No => path,
+    }
+}
+
+/// Apply NFC normalization and the configured case policy.
+///
+/// Text typed in decomposed form (common on macOS) then matches generated
+/// anchors and directories.
+fn normalize(text: &str, config: &SiteConfig) -> String {
+    let text: String = text.nfc().collect();
+    match config.build.slug.case {
+        SlugCase::Preserve => text,
+        SlugCase::Lower => text.to_lowercase(),
     }
 }
 
@@ -42,20 +80,107 @@ fn sanitize_text(text: &str) -> String {
         .collect()
 }
 
-/// Sanitize each component of a path
+/// Sanitize each component of a path, keeping it creatable on Windows
+///
+/// Components that are not valid UTF-8 are kept byte-for-byte.
 fn sanitize_path(path: &Path) -> PathBuf {
+    map_normal_components(path, |text| windows_safe(&sanitize_text(text)))
+}
+
+/// Apply [`windows_safe`] to each component of a path
+fn windows_safe_path(path: &Path) -> PathBuf {
+    map_normal_components(path, windows_safe)
+}
+
+/// Map the UTF-8 file and directory names of a path, keeping roots and prefixes
+fn map_normal_components(path: &Path, f: impl Fn(&str) -> String) -> PathBuf {
     path.components()
-        .map(|c| sanitize_text(&c.as_os_str().to_string_lossy()))
+        .map(|c| match (c, c.as_os_str().to_str()) {
+            (Component::Normal(_), Some(text)) => OsString::from(f(text)),
+            _ => c.as_os_str().to_owned(),
+        })
         .collect()
 }
 
+// ============================================================================
+// Windows Compatibility
+// ============================================================================
+
+/// Whether `name` is a reserved device name such as `con` or `lpt1.txt`
+fn is_windows_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Adjust a file name so Windows can create it.
+///
+/// Trailing dots and spaces are dropped, and reserved device names get a `_`
+/// appended to their stem (`con.html` → `con_.html`).
+fn windows_safe(name: &str) -> String {
+    let name = name.trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        return "_".to_owned();
+    }
+    if !is_windows_reserved(name) {
+        return name.to_owned();
+    }
+    match name.split_once('.') {
+        Some((stem, ext)) => format!("{stem}_.{ext}"),
+        None => format!("{name}_"),
+    }
+}
+
+/// Check that an output path, relative to the output directory, can be
+/// created on every platform.
+///
+/// Slugified paths are already adjusted; this catches paths kept as-is
+/// (`[build.slug] path = "no"`) and paths that are too long.
+pub fn check_output_path(relative: &Path) -> Result<()> {
+    for component in relative.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        if name.len() > MAX_COMPONENT_LEN {
+            bail!(
+                "Output file name is longer than {MAX_COMPONENT_LEN} bytes: {}",
+                name.to_string_lossy()
+            );
+        }
+        let Some(name) = name.to_str() else { continue };
+        if is_windows_reserved(name) {
+            bail!(
+                "Output path `{}` uses `{name}`, a reserved file name on Windows; rename the source file",
+                relative.display()
+            );
+        }
+        if name.ends_with(['.', ' ']) {
+            bail!(
+                "Output path `{}` has a name ending in a dot or space, which Windows cannot create",
+                relative.display()
+            );
+        }
+    }
+
+    let len = relative.as_os_str().len();
+    if len > MAX_RELATIVE_PATH_LEN {
+        bail!(
+            "Output path is {len} characters long, over the limit of {MAX_RELATIVE_PATH_LEN} \
+             (Windows paths are limited to 260): {}",
+            relative.display()
+        );
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Content Path Utilities
 // ============================================================================
 
 /// Computed paths for a content file.
 pub struct ContentPaths {
-    /// Relative path without `.typ` extension.
+    /// Relative path without `.typ` extension, for display.
     /// Example: `content/posts/hello.typ` → `"posts/hello"`
     pub relative: String,
 
@@ -78,30 +203,87 @@ pub struct ContentPaths {
 /// |--------|----------|------|
 /// | `content/posts/hello.typ` | `posts/hello` | `public/posts/hello/index.html` |
 /// | `content/index.typ` | `index` | `public/index.html` |
-pub fn content_paths(content_path: &Path, config: &'static SiteConfig) -> Result<ContentPaths> {
+/// | `content/posts/index.typ` | `posts/index` | `public/posts/index.html` |
+/// | `content/404.typ` | `404` | `public/404.html` |
+pub fn content_paths(content_path: &Path, config: &SiteConfig) -> Result<ContentPaths> {
     let content_dir = &config.build.content;
     let output_dir = config.build.output.join(&config.build.base_path);
 
     // Strip content dir and .typ extension: "content/posts/hello.typ" → "posts/hello"
-    let relative = content_path
-        .strip_prefix(content_dir)?
-        .to_str()
-        .ok_or_else(|| anyhow!("Invalid path encoding"))?
-        .strip_suffix(".typ")
-        .ok_or_else(|| anyhow!("Not a .typ file: {}", content_path.display()))?
-        .to_owned();
+    let relative = content_path.strip_prefix(content_dir)?;
+    if relative.extension().is_none_or(|ext| ext != "typ") {
+        bail!("Not a .typ file: {}", content_path.display());
+    }
+    let relative = relative.with_extension("");
 
     // Special case: index.typ → public/index.html (not public/index/index.html)
-    let is_index = content_path.file_name().is_some_and(|p| p == "index.typ");
+    let is_index = relative == Path::new("index");
+    // A nested index.typ is the index of its directory: posts/index.typ → public/posts/index.html
+    let index_dir = match relative.file_name() {
+        Some(name) if name == "index" && !is_index => relative.parent(),
+        _ => None,
+    };
 
+    // Special case: 404.typ → public/404.html, the not found page hosts look for
+    let is_not_found = relative == Path::new("404");
+
+    // Only the part below the output directory is slugified and checked
     let html = if is_index {
         config.build.output.join("index.html")
+    } else if is_not_found {
+        config.build.output.join("404.html")
     } else {
-        output_dir.join(&relative).join("index.html")
+        let page = index_dir.unwrap_or(&relative).join("index.html");
+        let page = slugify_path(page, config);
+        check_output_path(&page)?;
+        output_dir.join(page)
     };
-    let html = slugify_path(html, config);
 
-    Ok(ContentPaths { relative, html })
+    Ok(ContentPaths {
+        relative: relative.to_string_lossy().into_owned(),
+        html,
+    })
+}
+
+/// Output path of any source file under the content or assets directory.
+///
+/// `.typ` pages map through [`content_paths`]; other files, theme assets
+/// included, are copied as-is.
+pub fn output_path(source: &Path, config: &SiteConfig) -> Result<PathBuf> {
+    if source.extension().is_some_and(|ext| ext == "typ") {
+        return Ok(content_paths(source, config)?.html);
+    }
+    let relative = match source.strip_prefix(&config.build.content) {
+        Ok(relative) => relative,
+        Err(_) => theme::asset_relative(source, config)?,
+    };
+    Ok(config
+        .build
+        .output
+        .join(&config.build.base_path)
+        .join(relative))
+}
+
+/// Output paths claimed by more than one source, with the sources in input order.
+///
+/// Paths differing only in case count as the same output, since they collide on
+/// case-insensitive file systems (macOS, Windows).
+pub fn find_collisions(
+    outputs: impl IntoIterator<Item = (PathBuf, PathBuf)>,
+) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let mut claimed: BTreeMap<String, (PathBuf, Vec<PathBuf>)> = BTreeMap::new();
+    for (source, output) in outputs {
+        let key = output.to_string_lossy().to_lowercase();
+        claimed
+            .entry(key)
+            .or_insert_with(|| (output, Vec::new()))
+            .1
+            .push(source);
+    }
+    claimed
+        .into_values()
+        .filter(|(_, sources)| sources.len() > 1)
+        .collect()
 }
 
 #[cfg(test)]
@@ -219,4 +401,163 @@ mod tests {
         let result = sanitize_text(input);
         assert_eq!(result, "My_Article_2024_-_Part_1");
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sanitize_path_keeps_non_utf8_components() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9 menu");
+        let path = Path::new("posts").join(name).join("a b");
+        let result = sanitize_path(&path);
+        assert_eq!(result, Path::new("posts").join(name).join("a_b"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_content_paths_not_found_page() {
+        let mut config = SiteConfig::default();
+        config.build.content = PathBuf::from("/site/content");
+        config.build.output = PathBuf::from("/site/public");
+        config.build.base_path = PathBuf::from("blog");
+
+        let paths = content_paths(Path::new("/site/content/404.typ"), &config).unwrap();
+        assert_eq!(paths.html, PathBuf::from("/site/public/404.html"));
+        let paths = content_paths(Path::new("/site/content/posts/404.typ"), &config).unwrap();
+        assert_eq!(
+            paths.html,
+            PathBuf::from("/site/public/blog/posts/404/index.html")
+        );
+    }
+
+    #[test]
+    fn test_content_paths_nested_index() {
+        let mut config = SiteConfig::default();
+        config.build.content = PathBuf::from("/site/content");
+        config.build.output = PathBuf::from("/site/public");
+        config.build.base_path = PathBuf::from("blog");
+
+        let paths = content_paths(Path::new("/site/content/index.typ"), &config).unwrap();
+        assert_eq!(paths.html, PathBuf::from("/site/public/index.html"));
+        let paths = content_paths(Path::new("/site/content/posts/index.typ"), &config).unwrap();
+        assert_eq!(paths.relative, "posts/index");
+        assert_eq!(
+            paths.html,
+            PathBuf::from("/site/public/blog/posts/index.html")
+        );
+    }
+
+    #[test]
+    fn test_content_paths_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut config = SiteConfig::default();
+        config.build.content = PathBuf::from("/site/content");
+        config.build.output = PathBuf::from("/site/public");
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9.typ");
+
+        let paths = content_paths(&config.build.content.join(name), &config).unwrap();
+        let dir = std::ffi::OsStr::from_bytes(b"caf\xe9");
+        assert_eq!(
+            paths.html,
+            Path::new("/site/public").join(dir).join("index.html")
+        );
+        assert_eq!(paths.relative, "caf\u{FFFD}");
+    }
+
+    #[test]
+    fn test_windows_safe() {
+        assert_eq!(windows_safe("hello"), "hello");
+        assert_eq!(windows_safe("con"), "con_");
+        assert_eq!(windows_safe("Lpt1.html"), "Lpt1_.html");
+        assert_eq!(windows_safe("console"), "console");
+        assert_eq!(windows_safe("notes. "), "notes");
+        assert_eq!(windows_safe("..."), "_");
+    }
+
+    #[test]
+    fn test_sanitize_path_windows_reserved() {
+        let path = Path::new("/site/public/aux/nul.txt/index.html");
+        let result = sanitize_path(path);
+        assert_eq!(
+            result,
+            PathBuf::from("/site/public/aux_/nul_.txt/index.html")
+        );
+    }
+
+    #[test]
+    fn test_check_output_path() {
+        assert!(check_output_path(Path::new("posts/hello/index.html")).is_ok());
+        assert!(check_output_path(Path::new("posts/con/index.html")).is_err());
+        assert!(check_output_path(Path::new("posts/hello./index.html")).is_err());
+        assert!(check_output_path(&Path::new("posts").join("a".repeat(256))).is_err());
+
+        let long = PathBuf::from_iter(std::iter::repeat_n("abcdefghij", 21));
+        assert!(check_output_path(&long).is_err());
+    }
+
+    #[test]
+    fn test_slugify_normalizes_to_nfc() {
+        let config = SiteConfig::default();
+        let decomposed = "cafe\u{301}";
+        assert_eq!(slugify_fragment(decomposed, &config), "cafe");
+        assert_eq!(
+            slugify_path(Path::new("posts").join(decomposed), &config),
+            Path::new("posts").join("caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn test_slugify_case_policy() {
+        let mut config = SiteConfig::default();
+        config.build.slug.fragment = SlugMode::No;
+        assert_eq!(slugify_fragment("Intro", &config), "Intro");
+        assert_eq!(
+            slugify_path("Posts/Hello", &config),
+            PathBuf::from("Posts/Hello")
+        );
+
+        config.build.slug.case = SlugCase::Lower;
+        assert_eq!(slugify_fragment("Intro", &config), "intro");
+        assert_eq!(
+            slugify_path("Posts/Hello", &config),
+            PathBuf::from("posts/hello")
+        );
+    }
+
+    #[test]
+    fn test_find_collisions() {
+        let outputs = [
+            ("content/Foo.typ", "public/Foo/index.html"),
+            ("content/bar.typ", "public/bar/index.html"),
+            ("content/foo.typ", "public/foo/index.html"),
+        ]
+        .map(|(s, o)| (PathBuf::from(s), PathBuf::from(o)));
+
+        let collisions = find_collisions(outputs);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, PathBuf::from("public/Foo/index.html"));
+        assert_eq!(
+            collisions[0].1,
+            vec![
+                PathBuf::from("content/Foo.typ"),
+                PathBuf::from("content/foo.typ")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_path() {
+        let mut config = SiteConfig::default();
+        config.build.content = PathBuf::from("/site/content");
+        config.build.assets = PathBuf::from("/site/assets");
+        config.build.output = PathBuf::from("/site/public");
+
+        let page = output_path(Path::new("/site/content/posts/a.typ"), &config).unwrap();
+        assert_eq!(page, PathBuf::from("/site/public/posts/a/index.html"));
+        let image = output_path(Path::new("/site/content/posts/a.png"), &config).unwrap();
+        assert_eq!(image, PathBuf::from("/site/public/posts/a.png"));
+        let style = output_path(Path::new("/site/assets/main.css"), &config).unwrap();
+        assert_eq!(style, PathBuf::from("/site/public/main.css"));
+    }
 }
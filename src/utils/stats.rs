@@ -0,0 +1,114 @@
+//! Build timing report (`build --stats`, `[build.stats]`).
+//!
+//! Stages and pages record how long they took while the build runs; once it
+//! ends, the report lists the time of each stage and the slowest pages.
+//! Content and assets are processed at the same time and SVG time is summed
+//! over every page, so stage times do not add up to the total.
+
+use crate::{config::SiteConfig, log, utils::log::format_duration};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// Timings recorded by the current build
+struct Timings {
+    /// Stages in the order they were first recorded
+    stages: Vec<(&'static str, Duration)>,
+    pages: Vec<(PathBuf, Duration)>,
+}
+
+static TIMINGS: Mutex<Timings> = Mutex::new(Timings {
+    stages: Vec::new(),
+    pages: Vec::new(),
+});
+
+fn timings() -> MutexGuard<'static, Timings> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Forget the timings of the previous build
+pub fn clear() {
+    let mut timings = timings();
+    timings.stages.clear();
+    timings.pages.clear();
+}
+
+/// Add `duration` to the time of `stage`
+pub fn stage(stage: &'static str, duration: Duration) {
+    let mut timings = timings();
+    match timings.stages.iter_mut().find(|(name, _)| *name == stage) {
+        Some((_, total)) => *total += duration,
+        None => timings.stages.push((stage, duration)),
+    }
+}
+
+/// Run `f`, adding the time it took to `stage`
+pub fn time<R>(stage_name: &'static str, f: impl FnOnce() -> R) -> R {
+    let started = Instant::now();
+    let result = f();
+    stage(stage_name, started.elapsed());
+    result
+}
+
+/// Record how long the page compiled from `path` took
+pub fn page(path: &Path, duration: Duration) {
+    timings().pages.push((path.to_path_buf(), duration));
+}
+
+/// Print the timing report if `[build.stats]` is enabled
+///
+/// Page paths are shown relative to the content directory.
+pub fn report(config: &SiteConfig, total: Duration) {
+    let stats = &config.build.stats;
+    if !stats.enable {
+        return;
+    }
+
+    let timings = timings();
+    log!(true; "stats"; "{:<10} {:>9}", "stage", "time");
+    for (name, duration) in &timings.stages {
+        log!(true; "stats"; "{name:<10} {:>9}", format_duration(*duration));
+    }
+    log!(true; "stats"; "{:<10} {:>9}", "total", format_duration(total));
+
+    let slowest = slowest(&timings.pages, stats.slowest);
+    if slowest.is_empty() {
+        return;
+    }
+    log!(true; "stats"; "slowest {} of {} page(s):", slowest.len(), timings.pages.len());
+    for (path, duration) in slowest {
+        let path = path.strip_prefix(&config.build.content).unwrap_or(path);
+        log!(true; "stats"; "{:>9}  {}", format_duration(*duration), path.display());
+    }
+}
+
+/// The `n` slowest pages, slowest first
+fn slowest(pages: &[(PathBuf, Duration)], n: usize) -> Vec<&(PathBuf, Duration)> {
+    let mut sorted: Vec<_> = pages.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted.truncate(n);
+    sorted
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slowest() {
+        let page = |path: &str, ms| (PathBuf::from(path), Duration::from_millis(ms));
+        let pages = vec![page("a.typ", 10), page("b.typ", 30), page("c.typ", 20)];
+        let paths: Vec<_> = slowest(&pages, 2)
+            .iter()
+            .map(|(path, _)| path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["b.typ", "c.typ"]);
+        assert!(slowest(&pages, 0).is_empty());
+    }
+}
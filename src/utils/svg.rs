@@ -3,25 +3,48 @@
 //! This module handles:
 //! - Extracting SVG elements from Typst-generated HTML
 //! - Optimizing SVGs using usvg
-//! - Compressing to AVIF using various backends (builtin, magick, ffmpeg)
+//! - Compressing to AVIF on the image queue using various backends (builtin: rendered with resvg
+//!   and encoded with ravif; magick; ffmpeg),
+//!   with WebP/PNG fallbacks in a `<picture>` per `[build.typst.svg.formats]`
+//! - Naming the files by content hash, so identical SVGs are written once
+//!   (per page, or per site with `[build.typst.svg] dir`) and files that
+//!   already exist are not encoded again
+//! - Recoloring SVGs for dark color schemes (`[build.typst.svg.dark]`)
+//! - Labelling SVGs for screen readers, from an `aria-label`, `alt` or
+//!   `data-alt` attribute, a `<title>` or `<desc>` child, or
+//!   `[build.typst.svg] alt`
 
 use anyhow::{Context, Result};
+use quick_xml::escape::resolve_predefined_entity;
 use quick_xml::events::attributes::Attribute;
-use quick_xml::events::{BytesStart, Event};
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::{Reader, Writer};
-use rayon::prelude::*;
+use regex::bytes::Regex;
+use resvg::tiny_skia::{Pixmap, Transform};
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
 use std::fmt::Write as FmtWrite;
 use std::fs;
 use std::io::{Cursor, Write};
 use std::path::Path;
-
-use crate::config::{ExtractSvgType, SiteConfig};
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
+
+use crate::config::{ExtractSvgType, SiteConfig, SvgAvifConfig, SvgDarkMode};
+use crate::utils::log::duration_suffix;
+use crate::utils::meta::PageMeta;
+use crate::utils::rewrite::Wrapped;
+use crate::utils::toc::TocEntry;
+use crate::utils::{image_queue, progress, prune, stats};
 use crate::{exec_with_stdin, log};
 
 // ============================================================================
 // Constants
 // ============================================================================
 
+/// Hex digits of the content hash kept in file names
+const HASH_LEN: usize = 16;
+
 /// Padding adjustments for SVG viewBox (typst HTML output quirks)
 const SVG_PADDING_TOP: f32 = 5.0;
 const SVG_PADDING_BOTTOM: f32 = 4.0;
@@ -36,21 +59,35 @@ pub struct Svg {
     pub data: Vec<u8>,
     /// Dimensions (width, height) in pixels
     pub size: (f32, f32),
-    /// Sequential index for naming
+    /// Position in the page, for logging
     pub index: usize,
+    /// Hash of the content, for naming
+    pub hash: String,
+    /// Text alternative, if the SVG carries one
+    pub label: Option<String>,
+    /// Variant for dark color schemes, with `[build.typst.svg.dark] mode = "paired"`
+    pub dark: Option<Box<Svg>>,
 }
 
 impl Svg {
     /// Create new SVG with the given data, size, and index
-    #[inline]
-    pub const fn new(data: Vec<u8>, size: (f32, f32), index: usize) -> Self {
-        Self { data, size, index }
+    pub fn new(data: Vec<u8>, size: (f32, f32), index: usize) -> Self {
+        let digest = format!("{:x}", Sha1::digest(&data));
+        Self {
+            data,
+            size,
+            index,
+            hash: digest[..HASH_LEN].to_owned(),
+            label: None,
+            dark: None,
+        }
     }
 
     /// Determine the output format based on config and file size
     #[inline]
     fn output_format(&self, config: &SiteConfig) -> OutputFormat {
-        if matches!(config.build.typst.svg.extract_type, ExtractSvgType::JustSvg)
+        if config.build.skip_images
+            || matches!(config.build.typst.svg.extract_type, ExtractSvgType::JustSvg)
             || self.data.len() < config.get_inline_max_size()
         {
             OutputFormat::Svg
@@ -59,14 +96,44 @@ impl Svg {
         }
     }
 
-    /// Generate output filename (e.g., "svg-0.svg" or "svg-0.avif")
-    #[inline]
+    /// Generate output filename (e.g., "svg-<hash>.svg" or "svg-<hash>.avif")
+    ///
+    /// Raster names also hash the encoder and scale, which change the output.
     pub fn filename(&self, config: &SiteConfig) -> String {
         match self.output_format(config) {
-            OutputFormat::Svg => format!("svg-{}.svg", self.index),
-            OutputFormat::Avif => format!("svg-{}.avif", self.index),
+            OutputFormat::Svg => format!("svg-{}.svg", self.hash),
+            OutputFormat::Avif => format!("{}.avif", self.raster_stem(config)),
         }
     }
+
+    /// Filenames of the `[build.typst.svg.formats]` fallbacks of an AVIF,
+    /// from the most to the least preferred
+    pub fn fallback_filenames(&self, config: &SiteConfig) -> Vec<String> {
+        if self.output_format(config) == OutputFormat::Svg {
+            return Vec::new();
+        }
+        let formats = &config.build.typst.svg.formats;
+        let stem = self.raster_stem(config);
+        [(formats.webp, "webp"), (formats.png, "png")]
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, ext)| format!("{stem}.{ext}"))
+            .collect()
+    }
+
+    fn raster_stem(&self, config: &SiteConfig) -> String {
+        let svg = &config.build.typst.svg;
+        let params = format!(
+            "{}{:?}{}{}{}",
+            self.hash,
+            svg.extract_type,
+            config.get_scale(),
+            svg.avif.quality,
+            svg.avif.speed
+        );
+        let digest = format!("{:x}", Sha1::digest(params.as_bytes()));
+        format!("svg-{}", &digest[..HASH_LEN])
+    }
 }
 
 /// Output format for extracted SVGs
@@ -78,22 +145,37 @@ enum OutputFormat {
 
 /// Processing context for HTML transformation
 pub struct HtmlContext<'a> {
-    pub config: &'static SiteConfig,
+    pub config: &'a SiteConfig,
     pub html_path: &'a Path,
+    /// Source `.typ` file; `None` for pages generated by tola
+    pub source: Option<&'a Path>,
+    /// Metadata of the page, if queried
+    pub meta: Option<&'a PageMeta>,
     pub svg_count: usize,
     pub extract_svg: bool,
+    /// Table of contents of the page, with `[build.toc]`
+    pub toc: Vec<TocEntry>,
+    /// Id of the open heading, whose `[build.anchors]` link goes before its end
+    pub anchor: Option<String>,
+    /// Elements wrapped by `[build.html]` rules, awaiting their end tags
+    pub wrapped: Wrapped,
 }
 
 impl<'a> HtmlContext<'a> {
-    pub fn new(config: &'static SiteConfig, html_path: &'a Path) -> Self {
+    pub fn new(config: &'a SiteConfig, html_path: &'a Path, meta: Option<&'a PageMeta>) -> Self {
         Self {
             config,
             html_path,
+            source: None,
+            meta,
             svg_count: 0,
             extract_svg: !matches!(
                 config.build.typst.svg.extract_type,
                 ExtractSvgType::Embedded
             ),
+            toc: Vec::new(),
+            anchor: None,
+            wrapped: Wrapped::default(),
         }
     }
 }
@@ -115,11 +197,24 @@ pub fn extract_svg_element(
     // Capture complete SVG content
     let raw_svg = capture_svg_content(reader, &attrs)?;
 
+    // usvg drops titles and unknown attributes, so look for a label first
+    let label = attr_label(elem).or_else(|| child_label(&raw_svg));
+
     // Optimize with usvg
     let (optimized_data, size) = optimize_svg(&raw_svg, ctx.config)?;
 
     // Create SVG and write placeholder
-    let svg = Svg::new(optimized_data, size, ctx.svg_count);
+    let dark = &ctx.config.build.typst.svg.dark;
+    let dark = (dark.mode == SvgDarkMode::Paired).then(|| {
+        Box::new(Svg::new(
+            recolor(&optimized_data, &dark.color),
+            size,
+            ctx.svg_count,
+        ))
+    });
+    let mut svg = Svg::new(optimized_data, size, ctx.svg_count);
+    svg.label = label;
+    svg.dark = dark;
     ctx.svg_count += 1;
 
     write_img_placeholder(writer, &svg, ctx)?;
@@ -152,8 +247,16 @@ fn capture_svg_content(reader: &mut Reader<&[u8]>, attrs: &[Attribute<'_>]) -> R
     svg_writer.write_event(Event::Start(
         BytesStart::new("svg").with_attributes(attrs.iter().cloned()),
     ))?;
+    copy_svg_content(reader, &mut svg_writer)?;
+
+    Ok(svg_writer.into_inner().into_inner())
+}
 
-    // Capture nested content
+/// Copy the content of an open `<svg>` up to and including its end tag
+fn copy_svg_content(
+    reader: &mut Reader<&[u8]>,
+    svg_writer: &mut Writer<Cursor<Vec<u8>>>,
+) -> Result<()> {
     let mut depth = 1u32;
     loop {
         let event = reader.read_event()?;
@@ -171,8 +274,7 @@ fn capture_svg_content(reader: &mut Reader<&[u8]>, attrs: &[Attribute<'_>]) -> R
         }
         svg_writer.write_event(event)?;
     }
-
-    Ok(svg_writer.into_inner().into_inner())
+    Ok(())
 }
 
 /// Optimize SVG using usvg, returning optimized bytes and dimensions
@@ -201,11 +303,7 @@ fn write_img_placeholder(
     svg: &Svg,
     ctx: &HtmlContext<'_>,
 ) -> Result<()> {
-    let filename = svg.filename(ctx.config);
-    let output_dir = ctx.html_path.parent().context("Invalid html path")?;
-
-    // Build src attribute
-    let src = build_src_path(output_dir, &filename, &ctx.config.build.output);
+    let output_dir = artifact_dir(ctx.html_path, ctx.config)?;
 
     // Build style attribute with scaled dimensions
     let scale = ctx.config.get_scale();
@@ -213,15 +311,136 @@ fn write_img_placeholder(
     let mut style = String::with_capacity(40);
     let _ = write!(style, "width:{w}px;height:{h}px;");
 
-    // Write img element
+    // Write img element; an empty `alt` marks it as decorative
+    let alt = svg
+        .label
+        .as_deref()
+        .unwrap_or(&ctx.config.build.typst.svg.alt);
+    let srcs = |svg: &Svg| -> Vec<String> {
+        [svg.filename(ctx.config)]
+            .into_iter()
+            .chain(svg.fallback_filenames(ctx.config))
+            .map(|filename| build_src_path(output_dir, &filename, &ctx.config.build.output))
+            .collect()
+    };
+    let light = srcs(svg);
+    let dark = svg.dark.as_deref().map(srcs).unwrap_or_default();
+
+    // Browsers pick the first source they support (and whose media matches), else the img
+    let (img_src, sources) = light.split_last().context("SVG without a file")?;
+    let picture = !sources.is_empty() || !dark.is_empty();
+    if picture {
+        writer.write_event(Event::Start(BytesStart::new("picture")))?;
+        for source in &dark {
+            write_picture_source(writer, source, Some("(prefers-color-scheme: dark)"))?;
+        }
+        for source in sources {
+            write_picture_source(writer, source, None)?;
+        }
+    }
+
     let mut img = BytesStart::new("img");
-    img.push_attribute(("src", src.as_str()));
+    img.push_attribute(("src", img_src.as_str()));
+    img.push_attribute(("alt", alt));
     img.push_attribute(("style", style.as_str()));
+    if ctx.config.build.images.lazy.enable {
+        img.push_attribute(("loading", "lazy"));
+        img.push_attribute(("decoding", "async"));
+    }
     writer.write_event(Event::Start(img))?;
 
+    if picture {
+        writer.write_event(Event::End(BytesEnd::new("picture")))?;
+    }
     Ok(())
 }
 
+/// `<source srcset="..." type="image/...">` of a `<picture>`, for `media` if given
+fn write_picture_source(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    src: &str,
+    media: Option<&str>,
+) -> Result<()> {
+    let mime = match Path::new(src).extension().and_then(|ext| ext.to_str()) {
+        Some("avif") => "image/avif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        _ => "image/png",
+    };
+    let mut source = BytesStart::new("source");
+    if let Some(media) = media {
+        source.push_attribute(("media", media));
+    }
+    source.push_attribute(("srcset", src));
+    source.push_attribute(("type", mime));
+    writer.write_event(Event::Start(source))?;
+    Ok(())
+}
+
+/// Write the start of an SVG kept in the page, labelled for screen readers
+///
+/// Adds `role="img"`, an `aria-label` and a leading `<title>` unless the SVG
+/// already has them; SVGs without any label are left as they are. With
+/// `[build.typst.svg.dark]`, the whole SVG is written, with black fills and
+/// strokes following the text color of the page.
+pub fn write_embedded_svg(
+    elem: &BytesStart<'_>,
+    reader: &mut Reader<&[u8]>,
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    config: &SiteConfig,
+) -> Result<()> {
+    let label = attr_label(elem).or_else(|| {
+        let alt = &config.build.typst.svg.alt;
+        (!alt.is_empty()).then(|| alt.clone())
+    });
+    let follow_text_color = config.build.typst.svg.dark.mode != SvgDarkMode::None;
+    if label.is_none() && !follow_text_color {
+        writer.write_event(Event::Start(elem.to_owned()))?;
+        return Ok(());
+    }
+
+    let mut svg_writer = Writer::new(Cursor::new(Vec::new()));
+    let mut svg = elem.to_owned();
+    let has = |name: &[u8]| elem.try_get_attribute(name).ok().flatten().is_some();
+    if let Some(label) = &label {
+        if !has(b"role") {
+            svg.push_attribute(("role", "img"));
+        }
+        if !has(b"aria-label") {
+            svg.push_attribute(("aria-label", label.as_str()));
+        }
+    }
+    svg_writer.write_event(Event::Start(svg))?;
+    if let Some(label) = &label
+        && !starts_with_title(reader.clone())
+    {
+        svg_writer.write_event(Event::Start(BytesStart::new("title")))?;
+        svg_writer.write_event(Event::Text(BytesText::new(label)))?;
+        svg_writer.write_event(Event::End(BytesEnd::new("title")))?;
+    }
+
+    let mut content = svg_writer.into_inner().into_inner();
+    if follow_text_color {
+        // The rest of the SVG is recolored here instead of passing through
+        // Append after the start tag rather than over it
+        let mut cursor = Cursor::new(content);
+        cursor.set_position(cursor.get_ref().len() as u64);
+        let mut rest = Writer::new(cursor);
+        copy_svg_content(reader, &mut rest)?;
+        content = recolor(&rest.into_inner().into_inner(), "currentColor");
+    }
+    writer.get_mut().write_all(&content)?;
+    Ok(())
+}
+
+/// Directory the SVG files of the page at `html_path` are written to
+fn artifact_dir<'a>(html_path: &'a Path, config: &'a SiteConfig) -> Result<&'a Path> {
+    match &config.build.typst.svg.dir {
+        Some(dir) => Ok(dir),
+        None => html_path.parent().context("Invalid html path"),
+    }
+}
+
 /// Build the src path for an SVG file
 fn build_src_path(output_dir: &Path, filename: &str, output_root: &Path) -> String {
     let full_path = output_dir.join(filename);
@@ -237,6 +456,81 @@ fn build_src_path(output_dir: &Path, filename: &str, output_root: &Path) -> Stri
     }
 }
 
+// ============================================================================
+// SVG Labels
+// ============================================================================
+
+/// Label given on the `<svg>` element itself, such as through typst's `html.elem`
+fn attr_label(elem: &BytesStart<'_>) -> Option<String> {
+    [b"aria-label".as_slice(), b"alt", b"data-alt"]
+        .into_iter()
+        .filter_map(|name| elem.try_get_attribute(name).ok().flatten())
+        .filter_map(|attr| attr.unescape_value().ok())
+        .map(|value| value.trim().to_owned())
+        .find(|value| !value.is_empty())
+}
+
+/// Text of the first `<title>` of an SVG, or else of its first `<desc>`
+fn child_label(svg: &[u8]) -> Option<String> {
+    let text_of = |tag: &[u8]| {
+        let mut reader = Reader::from_reader(svg);
+        loop {
+            match reader.read_event().ok()? {
+                Event::Start(e) if e.local_name().as_ref() == tag => {
+                    let mut text = String::new();
+                    loop {
+                        match reader.read_event().ok()? {
+                            Event::Text(t) => text.push_str(&String::from_utf8_lossy(&t)),
+                            Event::GeneralRef(entity) => {
+                                if let Ok(Some(c)) = entity.resolve_char_ref() {
+                                    text.push(c);
+                                } else if let Some(resolved) =
+                                    resolve_predefined_entity(&String::from_utf8_lossy(&entity))
+                                {
+                                    text.push_str(resolved);
+                                }
+                            }
+                            Event::End(_) | Event::Eof => break,
+                            _ => {}
+                        }
+                    }
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    return (!text.is_empty()).then_some(text);
+                }
+                Event::Eof => return None,
+                _ => {}
+            }
+        }
+    };
+    text_of(b"title").or_else(|| text_of(b"desc"))
+}
+
+/// Whether the next element of `reader` (inside an `<svg>`) is a `<title>`
+fn starts_with_title(mut reader: Reader<&[u8]>) -> bool {
+    loop {
+        match reader.read_event() {
+            Ok(Event::Text(text)) if text.iter().all(u8::is_ascii_whitespace) => {}
+            Ok(Event::Comment(_)) => {}
+            Ok(Event::Start(e)) => return e.local_name().as_ref() == b"title",
+            _ => return false,
+        }
+    }
+}
+
+// ============================================================================
+// Dark Color Schemes
+// ============================================================================
+
+/// Replace black fills and strokes (typst's default text color) with `color`
+fn recolor(svg: &[u8], color: &str) -> Vec<u8> {
+    static RE_BLACK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"(?-u)((?:fill|stroke)(?:="|:[ ]*))(?:#000000|#000|black)\b"#).unwrap()
+    });
+    RE_BLACK
+        .replace_all(svg, format!("${{1}}{color}").as_bytes())
+        .into_owned()
+}
+
 // ============================================================================
 // SVG Attribute Adjustments
 // ============================================================================
@@ -313,65 +607,129 @@ fn extract_attr<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
 // SVG Compression
 // ============================================================================
 
-/// Compress multiple SVGs in parallel
-pub fn compress_svgs_parallel(
-    svgs: &[Svg],
-    html_path: &Path,
-    config: &'static SiteConfig,
-) -> Result<()> {
-    let output_dir = html_path.parent().context("Invalid html path")?;
+/// Settings the encoder threads need, copied out of the config
+#[derive(Clone)]
+struct EncodeSettings {
+    extract_type: ExtractSvgType,
+    scale: f32,
+    avif: SvgAvifConfig,
+}
+
+/// Queue the SVGs of a page for compression on the [`image_queue`]
+///
+/// Each file is written once, and skipped if it already exists: its name is
+/// derived from its content, and only given to it once it is fully written.
+pub fn queue_svgs(svgs: Vec<Svg>, html_path: &Path, config: &SiteConfig) -> Result<()> {
+    let output_dir = artifact_dir(html_path, config)?;
+    fs::create_dir_all(output_dir)?;
     let relative_path = html_path
         .strip_prefix(&config.build.output)
         .map(|p| p.to_string_lossy())
         .unwrap_or_default();
-    let log_prefix = relative_path.trim_end_matches("index.html");
-    let scale = config.get_scale();
-
-    svgs.par_iter().try_for_each(|svg| {
-        let output_path = output_dir.join(svg.filename(config));
-        log!("svg"; "in {log_prefix}: compressing svg-{}", svg.index);
+    let log_prefix: Arc<str> = relative_path.trim_end_matches("index.html").into();
+    let settings = EncodeSettings {
+        extract_type: config.build.typst.svg.extract_type.clone(),
+        scale: config.get_scale(),
+        avif: config.build.typst.svg.avif.clone(),
+    };
 
-        compress_svg(svg, &output_path, scale, config)?;
+    let mut seen = HashSet::new();
+    let with_dark = svgs.into_iter().flat_map(|mut svg| {
+        let dark = svg.dark.take().map(|dark| *dark);
+        [svg].into_iter().chain(dark)
+    });
+    for svg in with_dark {
+        let filenames = [svg.filename(config)]
+            .into_iter()
+            .chain(svg.fallback_filenames(config));
+        let outputs: Vec<_> = filenames
+            .map(|filename| output_dir.join(filename))
+            .filter(|output_path| seen.insert(output_path.clone()))
+            .collect();
+        let svg = Arc::new(svg);
+        for output_path in outputs {
+            prune::produced(&output_path);
+            if output_path.exists() {
+                continue;
+            }
+            progress::grow("svg", 1);
+            let (svg, settings, log_prefix) = (svg.clone(), settings.clone(), log_prefix.clone());
+            image_queue::submit(config, move || {
+                log!("svg"; "in {log_prefix}: compressing svg-{}", svg.index);
+                let started = Instant::now();
+
+                // Encoded into a temporary file, so a failed or interrupted encode
+                // never leaves a truncated file under the name later builds reuse
+                let name = output_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                let partial = output_path.with_file_name(format!(".tmp-{name}"));
+                // Images encode concurrently, so this sums to more than wall time
+                let result = stats::time("svg", || compress_svg(&svg, &partial, &settings))
+                    .and_then(|()| fs::rename(&partial, &output_path).map_err(Into::into));
+                if result.is_err() {
+                    let _ = fs::remove_file(&partial);
+                }
+                result.with_context(|| format!("Failed to write {}", output_path.display()))?;
 
-        log!("svg"; "in {log_prefix}: done svg-{}", svg.index);
-        Ok(())
-    })
+                log!("svg"; "in {log_prefix}: done svg-{}{}", svg.index, duration_suffix(started));
+                progress::advance("svg", 1);
+                Ok(())
+            });
+        }
+    }
+    Ok(())
 }
 
-/// Compress a single SVG based on configuration
-fn compress_svg(svg: &Svg, output_path: &Path, scale: f32, config: &SiteConfig) -> Result<()> {
-    // Small SVGs or JustSvg mode: write as-is
-    if svg.output_format(config) == OutputFormat::Svg {
+/// Write a single SVG file, or encode it to the format of `output_path`
+fn compress_svg(svg: &Svg, output_path: &Path, settings: &EncodeSettings) -> Result<()> {
+    // Small SVGs, JustSvg mode or `--skip-images`: write as-is
+    let ext = output_path.extension().and_then(|ext| ext.to_str());
+    if ext == Some("svg") {
         return fs::write(output_path, &svg.data).map_err(Into::into);
     }
 
-    // Compress to AVIF using configured backend
-    match &config.build.typst.svg.extract_type {
+    // Compress to AVIF (or a fallback format) using configured backend
+    let scale = settings.scale;
+    match &settings.extract_type {
         ExtractSvgType::Embedded | ExtractSvgType::JustSvg => {
             // Already handled above
             Ok(())
         }
         ExtractSvgType::Magick => compress_magick(output_path, &svg.data, scale),
-        ExtractSvgType::Ffmpeg => compress_ffmpeg(output_path, &svg.data),
-        ExtractSvgType::Builtin => compress_builtin(output_path, &svg.data, svg.size, scale),
+        ExtractSvgType::Ffmpeg if ext == Some("avif") => compress_ffmpeg(output_path, &svg.data),
+        ExtractSvgType::Ffmpeg => compress_ffmpeg_image(output_path, &svg.data),
+        ExtractSvgType::Builtin if ext == Some("avif") => {
+            compress_builtin(output_path, &svg.data, scale, &settings.avif)
+        }
+        ExtractSvgType::Builtin if ext == Some("png") => {
+            let png = rasterize(&svg.data, scale)?.encode_png()?;
+            fs::write(output_path, png).map_err(Into::into)
+        }
+        ExtractSvgType::Builtin => anyhow::bail!(
+            "Cannot write {} with the builtin encoder",
+            output_path.display()
+        ),
     }
 }
 
 /// Compress using ImageMagick
 fn compress_magick(output: &Path, data: &[u8], scale: f32) -> Result<()> {
     let density = (scale * 96.0).to_string();
-    let mut stdin = exec_with_stdin!(
+    exec_with_stdin!(
         ["magick"];
-        "-background", "none", "-density", density, "-", output
+        "-background", "none", "-density", density, "-", output;
+        input = data
     )?;
-    stdin.write_all(data)?;
     Ok(())
 }
 
 /// Compress using FFmpeg
 fn compress_ffmpeg(output: &Path, data: &[u8]) -> Result<()> {
-    let mut stdin = exec_with_stdin!(
+    exec_with_stdin!(
         ["ffmpeg"];
+        "-loglevel", "error",
         "-f", "svg_pipe",
         "-frame_size", "1000000000",
         "-i", "pipe:",
@@ -386,34 +744,70 @@ fn compress_ffmpeg(output: &Path, data: &[u8]) -> Result<()> {
         "-still-picture", "1",
         "-strict", "experimental",
         "-c:v", "libaom-av1",
-        "-y", output
+        "-y", output;
+        input = data
     )?;
-    stdin.write_all(data)?;
     Ok(())
 }
 
-/// Compress using built-in ravif encoder
-fn compress_builtin(output: &Path, data: &[u8], size: (f32, f32), scale: f32) -> Result<()> {
-    let (width, height) = ((size.0 * scale) as usize, (size.1 * scale) as usize);
-    let pixel_count = width * height;
-
-    // Pre-allocate with exact capacity
-    let mut pixmap = Vec::with_capacity(pixel_count);
-
-    // Use chunks_exact for better optimization
-    for c in data.chunks_exact(4) {
-        pixmap.push(ravif::RGBA8::new(c[0], c[1], c[2], c[3]));
-    }
+/// Convert to a single-frame image (WebP or PNG, by extension) using FFmpeg
+fn compress_ffmpeg_image(output: &Path, data: &[u8]) -> Result<()> {
+    exec_with_stdin!(
+        ["ffmpeg"];
+        "-loglevel", "error",
+        "-f", "svg_pipe",
+        "-frame_size", "1000000000",
+        "-i", "pipe:",
+        "-frames:v", "1",
+        "-y", output;
+        input = data
+    )?;
+    Ok(())
+}
 
+/// Compress using built-in ravif encoder, with `[build.typst.svg.avif]` settings
+fn compress_builtin(output: &Path, data: &[u8], scale: f32, avif: &SvgAvifConfig) -> Result<()> {
+    let pixmap = rasterize(data, scale)?;
+
+    // tiny-skia stores premultiplied alpha, ravif expects straight alpha
+    let pixels: Vec<_> = pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let c = pixel.demultiply();
+            ravif::RGBA8::new(c.red(), c.green(), c.blue(), c.alpha())
+        })
+        .collect();
+
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
     let encoded = ravif::Encoder::new()
-        .with_quality(90.0)
-        .with_speed(4)
-        .encode_rgba(ravif::Img::new(&pixmap, width, height))?;
+        .with_quality(avif.quality)
+        .with_speed(avif.speed)
+        .encode_rgba(ravif::Img::new(&pixels[..], width, height))?;
 
     fs::write(output, encoded.avif_file)?;
     Ok(())
 }
 
+/// Render an (optimized) SVG to pixels, `scale` times its size in px
+fn rasterize(data: &[u8], scale: f32) -> Result<Pixmap> {
+    let tree =
+        usvg::Tree::from_data(data, &usvg::Options::default()).context("Failed to parse SVG")?;
+    let size = tree.size();
+    let (width, height) = (
+        (size.width() * scale).ceil() as u32,
+        (size.height() * scale).ceil() as u32,
+    );
+    let mut pixmap = Pixmap::new(width, height)
+        .with_context(|| format!("Cannot rasterize an SVG of {width}x{height} px"))?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap)
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -590,13 +984,79 @@ mod tests {
     fn test_svg_filename() {
         let config = Box::leak(Box::new(SiteConfig::default()));
 
-        // Small SVG gets .svg extension
+        // Small SVG gets .svg extension, named by content
         let small = Svg::new(vec![0; 10], (10.0, 10.0), 5);
-        assert_eq!(small.filename(config), "svg-5.svg");
+        assert_eq!(small.filename(config), "svg-9694c4ebd673a5e2.svg");
+        let same = Svg::new(vec![0; 10], (10.0, 10.0), 0);
+        assert_eq!(same.filename(config), small.filename(config));
 
         // Large SVG gets .avif extension
         let large = Svg::new(vec![0; 100_000], (100.0, 100.0), 3);
-        assert_eq!(large.filename(config), "svg-3.avif");
+        let avif = large.filename(config);
+        assert!(avif.starts_with("svg-") && avif.ends_with(".avif"));
+        assert_eq!(avif.len(), "svg-.avif".len() + HASH_LEN);
+
+        // Encoding with another backend gives another file
+        let mut ffmpeg = SiteConfig::default();
+        ffmpeg.build.typst.svg.extract_type = ExtractSvgType::Ffmpeg;
+        assert_ne!(large.filename(&ffmpeg), avif);
+    }
+
+    #[test]
+    fn test_img_placeholder_with_fallbacks() {
+        let render = |config: &SiteConfig| {
+            let svg = Svg::new(vec![0; 100_000], (100.0, 50.0), 0);
+            let ctx = HtmlContext::new(config, Path::new("/public/a/index.html"), None);
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            write_img_placeholder(&mut writer, &svg, &ctx).unwrap();
+            let stem = svg.raster_stem(config);
+            let html = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+            html.replace(&stem, "svg-h")
+        };
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/public");
+        config.build.typst.svg.extract_type = ExtractSvgType::Magick;
+        assert!(render(&config).starts_with(r#"<img src="/a/svg-h.avif""#));
+
+        config.build.typst.svg.formats.webp = true;
+        config.build.typst.svg.formats.png = true;
+        let html = render(&config);
+        assert!(html.starts_with(concat!(
+            r#"<picture><source srcset="/a/svg-h.avif" type="image/avif">"#,
+            r#"<source srcset="/a/svg-h.webp" type="image/webp">"#,
+            r#"<img src="/a/svg-h.png""#
+        )));
+        assert!(html.ends_with("</picture>"));
+    }
+
+    // ------------------------------------------------------------------------
+    // Rasterization Tests
+    // ------------------------------------------------------------------------
+
+    #[test]
+    fn test_rasterize() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+            <rect width="2" height="2" fill="red"/></svg>"#;
+        let pixmap = rasterize(svg, 2.0).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (8, 4));
+
+        let red = pixmap.pixel(1, 1).unwrap().demultiply();
+        assert_eq!((red.red(), red.green(), red.alpha()), (255, 0, 255));
+        assert_eq!(pixmap.pixel(6, 1).unwrap().alpha(), 0);
+    }
+
+    #[test]
+    fn test_compress_builtin() {
+        let dir = std::env::temp_dir().join("tola_svg_builtin_test");
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("svg.avif");
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+            <circle cx="8" cy="8" r="6" fill="black"/></svg>"#;
+
+        compress_builtin(&output, svg, 1.0, &SvgAvifConfig::default()).unwrap();
+        let avif = fs::read(&output).unwrap();
+        assert_eq!(&avif[4..12], b"ftypavif");
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     // ------------------------------------------------------------------------
@@ -624,7 +1084,127 @@ mod tests {
         // Embedded mode: don't extract
         config.build.typst.svg.extract_type = ExtractSvgType::Embedded;
         let config = Box::leak(Box::new(config));
-        let ctx = HtmlContext::new(config, Path::new("/test.html"));
+        let ctx = HtmlContext::new(config, Path::new("/test.html"), None);
         assert!(!ctx.extract_svg);
     }
+
+    // ------------------------------------------------------------------------
+    // Label Tests
+    // ------------------------------------------------------------------------
+
+    fn start_of(svg: &str) -> BytesStart<'static> {
+        let mut reader = Reader::from_str(svg);
+        match reader.read_event().unwrap() {
+            Event::Start(e) => e.into_owned(),
+            event => panic!("unexpected {event:?}"),
+        }
+    }
+
+    #[test]
+    fn test_attr_label() {
+        assert_eq!(
+            attr_label(&start_of(r#"<svg data-alt=" E = mc^2 ">"#)).as_deref(),
+            Some("E = mc^2")
+        );
+        assert_eq!(
+            attr_label(&start_of(r#"<svg aria-label="" alt="x &lt; y">"#)).as_deref(),
+            Some("x < y")
+        );
+        assert_eq!(attr_label(&start_of(r#"<svg class="typst-frame">"#)), None);
+    }
+
+    #[test]
+    fn test_child_label() {
+        let svg = b"<svg><desc>A sum</desc><title>\n Sum  of a &amp; b </title></svg>";
+        assert_eq!(child_label(svg).as_deref(), Some("Sum of a & b"));
+        assert_eq!(
+            child_label(b"<svg><desc>A sum</desc></svg>").as_deref(),
+            Some("A sum")
+        );
+        assert_eq!(child_label(b"<svg><g><path/></g></svg>"), None);
+    }
+
+    #[test]
+    fn test_write_embedded_svg() {
+        let render = |html: &str, config: &SiteConfig| {
+            let mut reader = Reader::from_str(html);
+            let Event::Start(elem) = reader.read_event().unwrap() else {
+                unreachable!()
+            };
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            write_embedded_svg(&elem, &mut reader, &mut writer, config).unwrap();
+            String::from_utf8(writer.into_inner().into_inner()).unwrap()
+        };
+        let mut config = SiteConfig::default();
+
+        assert_eq!(
+            render(r#"<svg data-alt="x^2"><path/></svg>"#, &config),
+            r#"<svg data-alt="x^2" role="img" aria-label="x^2"><title>x^2</title>"#
+        );
+        // An existing title is kept
+        assert_eq!(
+            render(r#"<svg aria-label="x"> <title>x</title></svg>"#, &config),
+            r#"<svg aria-label="x" role="img">"#
+        );
+        // Unlabelled SVGs are left alone unless there is a fallback
+        assert_eq!(render("<svg><path/></svg>", &config), "<svg>");
+        config.build.typst.svg.alt = "Formula".into();
+        assert_eq!(
+            render("<svg><path/></svg>", &config),
+            r#"<svg role="img" aria-label="Formula"><title>Formula</title>"#
+        );
+    }
+
+    // ------------------------------------------------------------------------
+    // Dark Color Scheme Tests
+    // ------------------------------------------------------------------------
+
+    #[test]
+    fn test_recolor() {
+        let svg = br##"<g fill="#000000" stroke="#ff0000"><path style="stroke: black" fill="#0000ff"/></g>"##;
+        assert_eq!(
+            String::from_utf8(recolor(svg, "currentColor")).unwrap(),
+            r##"<g fill="currentColor" stroke="#ff0000"><path style="stroke: currentColor" fill="#0000ff"/></g>"##
+        );
+    }
+
+    #[test]
+    fn test_embedded_svg_follows_text_color() {
+        let mut config = SiteConfig::default();
+        config.build.typst.svg.dark.mode = SvgDarkMode::CurrentColor;
+        let html = r##"<svg><path fill="#000000"/></svg><p>after</p>"##;
+        let mut reader = Reader::from_str(html);
+        let Event::Start(elem) = reader.read_event().unwrap() else {
+            unreachable!()
+        };
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_embedded_svg(&elem, &mut reader, &mut writer, &config).unwrap();
+
+        let svg = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+        assert_eq!(svg, r#"<svg><path fill="currentColor"/></svg>"#);
+        // The reader continues after the SVG
+        assert!(
+            matches!(reader.read_event().unwrap(), Event::Start(e) if e.name().as_ref() == b"p")
+        );
+    }
+
+    #[test]
+    fn test_img_placeholder_with_dark_variant() {
+        let mut config = SiteConfig::default();
+        config.build.output = PathBuf::from("/public");
+        config.build.typst.svg.extract_type = ExtractSvgType::JustSvg;
+        let mut svg = Svg::new(br##"<svg fill="#000000"/>"##.to_vec(), (10.0, 10.0), 0);
+        let dark = Svg::new(recolor(&svg.data, "#ffffff"), (10.0, 10.0), 0);
+        let (light_name, dark_name) = (svg.filename(&config), dark.filename(&config));
+        svg.dark = Some(Box::new(dark));
+
+        let ctx = HtmlContext::new(&config, Path::new("/public/a/index.html"), None);
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_img_placeholder(&mut writer, &svg, &ctx).unwrap();
+        let html = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+        assert!(html.starts_with(&format!(
+            r#"<picture><source media="(prefers-color-scheme: dark)" srcset="/a/{dark_name}" type="image/svg+xml"><img src="/a/{light_name}""#
+        )));
+        assert!(html.ends_with("</picture>"));
+    }
 }
@@ -0,0 +1,223 @@
+//! Taxonomy pages (tags, categories, ...).
+//!
+//! Groups the posts recorded in the site model by the terms of every
+//! `[build.taxonomies]` name, and renders a page (plus RSS feed) per term and
+//! an index of all terms per taxonomy.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        build::process_generated_page,
+        pagination::{Pager, paginate, write_nav},
+        prune,
+        rss::RssFeed,
+        site::{self, Page},
+        slug::slugify_path,
+    },
+};
+use anyhow::Result;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+use std::{cmp::Reverse, collections::BTreeMap, io::Cursor};
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+/// Generate the pages (and feeds) of every taxonomy
+pub fn build_taxonomies(config: &SiteConfig) -> Result<()> {
+    if !config.build.taxonomies.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    for name in &config.build.taxonomies.names {
+        let dir = config.build.output.join(name);
+        let posts_by_term = group_by_term(&pages, name);
+
+        let mut terms = Vec::with_capacity(posts_by_term.len());
+        for (term, posts) in &posts_by_term {
+            let slug = slugify_path(term.replace('/', "-"), config);
+            let term_dir = dir.join(&slug);
+
+            for (pager, posts) in paginate(posts, &term_dir.join("index.html"), config) {
+                let html = render_term_page(term, posts, &pager)?;
+                let html = process_generated_page(&pager.output, &html, config)?;
+                prune::write_produced(&pager.output, &html)?;
+            }
+
+            if config.build.rss.enable {
+                let title = format!("{} — {term}", config.base.title);
+                let feed = RssFeed::from_pages(title, posts.clone(), config);
+                let (rss, atom) = (term_dir.join("feed.xml"), term_dir.join("atom.xml"));
+                for (path, xml) in feed.render(&rss, &atom, config)? {
+                    prune::write_produced(&path, xml.as_bytes())?;
+                }
+            }
+            terms.push((
+                term.as_str(),
+                slug.to_string_lossy().into_owned(),
+                posts.len(),
+            ));
+        }
+
+        let page = dir.join("index.html");
+        let html = render_index_page(name, &terms)?;
+        prune::write_produced(&page, &process_generated_page(&page, &html, config)?)?;
+
+        log!("taxonomies"; "{} term(s) in {name}", terms.len());
+    }
+    Ok(())
+}
+
+/// Posts (pages with a `date`) keyed by their terms of taxonomy `name`, newest first
+fn group_by_term(pages: &[Page], name: &str) -> BTreeMap<String, Vec<Page>> {
+    let mut terms: BTreeMap<String, Vec<Page>> = BTreeMap::new();
+    for page in pages {
+        let Some(meta) = page.meta.as_ref().filter(|meta| meta.date.is_some()) else {
+            continue;
+        };
+        for term in meta.taxonomies.get(name).into_iter().flatten() {
+            terms.entry(term.clone()).or_default().push(page.clone());
+        }
+    }
+    for posts in terms.values_mut() {
+        posts.sort_by_key(|post| Reverse(post.date()));
+    }
+    terms
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// Page listing the posts of one term, before head injection and minification
+fn render_term_page(term: &str, posts: &[Page], pager: &Pager) -> Result<Vec<u8>> {
+    let mut writer = start_page()?;
+    write_text_element(&mut writer, "h1", term)?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-taxonomy-posts")]),
+    ))?;
+    for post in posts {
+        let title = post
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.title.as_deref())
+            .unwrap_or(&post.url);
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        write_link(&mut writer, &post.url, title)?;
+        let date = post.date_text();
+        writer.write_event(Event::Text(BytesText::new(" ")))?;
+        writer.write_event(Event::Start(
+            BytesStart::new("time").with_attributes([("datetime", date)]),
+        ))?;
+        writer.write_event(Event::Text(BytesText::new(date)))?;
+        writer.write_event(Event::End(BytesEnd::new("time")))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+    write_nav(&mut writer, pager)?;
+
+    end_page(writer)
+}
+
+/// Page listing every term of a taxonomy as `(term, slug, post count)`
+fn render_index_page(name: &str, terms: &[(&str, String, usize)]) -> Result<Vec<u8>> {
+    let mut writer = start_page()?;
+    write_text_element(&mut writer, "h1", name)?;
+
+    writer.write_event(Event::Start(
+        BytesStart::new("ul").with_attributes([("class", "tola-taxonomy-terms")]),
+    ))?;
+    for (term, slug, count) in terms {
+        writer.write_event(Event::Start(BytesStart::new("li")))?;
+        write_link(&mut writer, &format!("{slug}/"), term)?;
+        writer.write_event(Event::Text(BytesText::new(&format!(" ({count})"))))?;
+        writer.write_event(Event::End(BytesEnd::new("li")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("ul")))?;
+
+    end_page(writer)
+}
+
+fn start_page() -> Result<XmlWriter> {
+    let mut writer = Writer::new(Cursor::new(Vec::new()));
+    writer.write_event(Event::DocType(BytesText::from_escaped("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("html")))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Empty(
+        BytesStart::new("meta").with_attributes([("charset", "utf-8")]),
+    ))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    Ok(writer)
+}
+
+fn end_page(mut writer: XmlWriter) -> Result<Vec<u8>> {
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("html")))?;
+    Ok(writer.into_inner().into_inner())
+}
+
+fn write_link(writer: &mut XmlWriter, href: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(
+        BytesStart::new("a").with_attributes([("href", href)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
+    Ok(())
+}
+
+fn write_text_element(writer: &mut XmlWriter, tag: &str, text: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(tag)))?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn post(url: &str, tags: &[&str], date: Option<&str>) -> Page {
+        Page {
+            url: url.into(),
+            meta: Some(PageMeta {
+                title: Some(url.into()),
+                date: date.map(Into::into),
+                taxonomies: [("tags".into(), tags.iter().map(|t| t.to_string()).collect())].into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_by_term() {
+        let pages = [
+            post("/a/", &["rust"], Some("2024-01-01")),
+            post("/b/", &["rust", "typst"], Some("2024-02-01")),
+            post("/about/", &["rust"], None),
+        ];
+        let terms = group_by_term(&pages, "tags");
+        assert_eq!(terms.len(), 2);
+        let urls: Vec<_> = terms["rust"].iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(urls, ["/b/", "/a/"]);
+        assert!(group_by_term(&pages, "categories").is_empty());
+    }
+
+    #[test]
+    fn test_render_index_page() {
+        let terms = [("C & C++", "c-c".to_string(), 2)];
+        let html = String::from_utf8(render_index_page("tags", &terms).unwrap()).unwrap();
+        assert!(html.contains("<h1>tags</h1>"));
+        assert!(html.contains(r#"<li><a href="c-c/">C &amp; C++</a> (2)</li>"#));
+    }
+}
@@ -0,0 +1,158 @@
+//! Plain-text output variants.
+//!
+//! Writes the visible text recorded in the site model as `index.txt` next to
+//! each page, and as `llms.txt`/`llms-full.txt` (see <https://llmstxt.org>) at
+//! the site root.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{
+        prune,
+        site::{self, Page, extract_text},
+    },
+};
+use anyhow::{Context, Result};
+use std::{fmt::Write as _, fs, path::Path};
+
+/// Generate the enabled `[build.text]` outputs from the recorded pages
+pub fn build_text(config: &SiteConfig) -> Result<()> {
+    let text = &config.build.text;
+    if !text.pages && !text.llms {
+        return Ok(());
+    }
+    let pages = site::pages();
+
+    if text.pages {
+        for page in &pages {
+            write_if_changed(&page.output.with_extension("txt"), &page.text)?;
+        }
+    }
+
+    if text.llms {
+        let root = config.build.output.join(&config.build.base_path);
+        write_if_changed(&root.join("llms.txt"), &llms_index(&pages, config))?;
+        write_if_changed(&root.join("llms-full.txt"), &llms_full(&pages, config))?;
+    }
+
+    log!("text"; "plain text written for {} page(s)", pages.len());
+    Ok(())
+}
+
+/// Write `content` unless the file already holds it, keeping mtimes stable
+fn write_if_changed(path: &Path, content: &str) -> Result<()> {
+    prune::produced(path);
+    if fs::read(path).is_ok_and(|old| old == content.as_bytes()) {
+        return Ok(());
+    }
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Page title from metadata, falling back to its URL
+fn page_title(page: &Page) -> &str {
+    page.meta
+        .as_ref()
+        .and_then(|meta| meta.title.as_deref())
+        .unwrap_or(&page.url)
+}
+
+/// Site heading shared by both llms files
+fn llms_header(config: &SiteConfig) -> String {
+    let mut out = format!("# {}\n", config.base.title);
+    if !config.base.description.is_empty() {
+        let _ = write!(out, "\n> {}\n", config.base.description);
+    }
+    out
+}
+
+/// `llms.txt`: one link per page, with its summary
+fn llms_index(pages: &[Page], config: &SiteConfig) -> String {
+    let mut out = llms_header(config);
+    out.push_str("\n## Pages\n\n");
+    for page in pages {
+        let _ = write!(out, "- [{}]({})", page_title(page), page.url);
+        let summary = page
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.summary.as_deref())
+            .map(|summary| extract_text(summary.as_bytes()).replace('\n', " "))
+            .filter(|summary| !summary.is_empty());
+        if let Some(summary) = summary {
+            let _ = write!(out, ": {summary}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// `llms-full.txt`: the text of every page under its own heading
+fn llms_full(pages: &[Page], config: &SiteConfig) -> String {
+    let mut out = llms_header(config);
+    for page in pages {
+        let _ = write!(
+            out,
+            "\n## {}\n\nURL: {}\n\n{}\n",
+            page_title(page),
+            page.url,
+            page.text
+        );
+    }
+    out
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn pages() -> Vec<Page> {
+        vec![
+            Page {
+                url: "https://example.com/posts/a/index.html".into(),
+                meta: Some(PageMeta {
+                    title: Some("Post A".into()),
+                    summary: Some("<p>About <em>A</em></p>".into()),
+                    ..Default::default()
+                }),
+                text: "Post A\n\nBody".into(),
+                ..Default::default()
+            },
+            Page {
+                url: "https://example.com/about/index.html".into(),
+                text: "About me".into(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn config() -> SiteConfig {
+        let mut config = SiteConfig::default();
+        config.base.title = "My Blog".into();
+        config.base.description = "Notes".into();
+        config
+    }
+
+    #[test]
+    fn test_llms_index() {
+        assert_eq!(
+            llms_index(&pages(), &config()),
+            "# My Blog\n\n> Notes\n\n## Pages\n\n\
+             - [Post A](https://example.com/posts/a/index.html): About A\n\
+             - [https://example.com/about/index.html](https://example.com/about/index.html)\n"
+        );
+    }
+
+    #[test]
+    fn test_llms_full() {
+        assert_eq!(
+            llms_full(&pages(), &config()),
+            "# My Blog\n\n> Notes\n\
+             \n## Post A\n\nURL: https://example.com/posts/a/index.html\n\nPost A\n\nBody\n\
+             \n## https://example.com/about/index.html\n\nURL: https://example.com/about/index.html\n\nAbout me\n"
+        );
+    }
+}
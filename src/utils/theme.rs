@@ -0,0 +1,126 @@
+//! Theme file layering (`[theme]`).
+//!
+//! Templates and assets are looked up in the site first and in the theme
+//! second, so a site file overrides the theme file of the same relative path.
+//! Typst pages import templates through the `templates` map of the
+//! `tola-site` input, which points every name at the file that wins.
+
+use crate::{config::SiteConfig, utils::build::collect_files};
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// `templates/` of the theme in use
+pub fn templates_dir(config: &SiteConfig) -> Option<PathBuf> {
+    config.theme_path().map(|theme| theme.join("templates"))
+}
+
+/// `assets/` of the theme in use
+pub fn assets_dir(config: &SiteConfig) -> Option<PathBuf> {
+    config.theme_path().map(|theme| theme.join("assets"))
+}
+
+/// Theme assets the site does not override, in walk order
+pub fn assets(config: &SiteConfig) -> Vec<PathBuf> {
+    let Some(dir) = assets_dir(config) else {
+        return Vec::new();
+    };
+    collect_files(&dir, |path| !is_overridden(path, &dir, config))
+}
+
+/// Whether the site has an asset at the path of `theme_asset` in `theme_assets`
+pub fn is_overridden(theme_asset: &Path, theme_assets: &Path, config: &SiteConfig) -> bool {
+    theme_asset
+        .strip_prefix(theme_assets)
+        .is_ok_and(|relative| config.build.assets.join(relative).exists())
+}
+
+/// Path of a site or theme asset relative to its assets directory
+pub fn asset_relative<'a>(asset: &'a Path, config: &SiteConfig) -> Result<&'a Path> {
+    asset
+        .strip_prefix(&config.build.assets)
+        .ok()
+        .or_else(|| asset.strip_prefix(assets_dir(config)?).ok())
+        .with_context(|| format!("{} is not an asset", asset.display()))
+}
+
+/// Import path of every template, site templates first:
+/// `page.typ` → `/templates/page.typ` or `/themes/<name>/templates/page.typ`
+pub fn templates(config: &SiteConfig) -> BTreeMap<String, String> {
+    let root = config.get_root();
+    let dirs = [Some(config.build.templates.clone()), templates_dir(config)];
+    let mut templates = BTreeMap::new();
+    for dir in dirs.iter().flatten() {
+        for file in collect_files(dir, |_| true) {
+            let (Ok(name), Ok(import)) = (file.strip_prefix(dir), file.strip_prefix(root)) else {
+                continue;
+            };
+            templates
+                .entry(slash_path(name))
+                .or_insert_with(|| format!("/{}", slash_path(import)));
+        }
+    }
+    templates
+}
+
+/// `a\b` → `a/b`, as typst import paths use forward slashes
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_theme_layering() {
+        let root = std::env::temp_dir().join("tola_theme_test");
+        let _ = fs::remove_dir_all(&root);
+        let theme = root.join("themes/paper");
+        for dir in [
+            "templates",
+            "assets/styles",
+            "themes/paper/templates/partials",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::create_dir_all(theme.join("assets/styles")).unwrap();
+        fs::write(root.join("templates/page.typ"), "").unwrap();
+        fs::write(theme.join("templates/page.typ"), "").unwrap();
+        fs::write(theme.join("templates/partials/nav.typ"), "").unwrap();
+        fs::write(root.join("assets/styles/main.css"), "").unwrap();
+        fs::write(theme.join("assets/styles/main.css"), "").unwrap();
+        fs::write(theme.join("assets/styles/code.css"), "").unwrap();
+
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        config.build.templates = root.join("templates");
+        config.build.assets = root.join("assets");
+        config.theme.name = Some("paper".into());
+        config.theme.dir = root.join("themes");
+
+        let templates = templates(&config);
+        assert_eq!(templates["page.typ"], "/templates/page.typ");
+        assert_eq!(
+            templates["partials/nav.typ"],
+            "/themes/paper/templates/partials/nav.typ"
+        );
+
+        assert_eq!(assets(&config), vec![theme.join("assets/styles/code.css")]);
+        assert_eq!(
+            asset_relative(&theme.join("assets/styles/code.css"), &config).unwrap(),
+            Path::new("styles/code.css")
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+//! Tables of contents.
+//!
+//! Headings are read from the compiled page before it is processed, so a
+//! `<tola-toc>` marker above them can be replaced in the same pass. Their ids
+//! are slugified the way `process_html` rewrites them, so the links match.
+
+use crate::{
+    config::SiteConfig,
+    utils::{prune, site::extract_text, slug::slugify_fragment, xml::create_xml_reader},
+};
+use anyhow::Result;
+use quick_xml::{escape::escape, events::Event, name::QName};
+use serde::Serialize;
+use std::{fmt::Write, path::Path};
+
+/// Element replaced by the table of contents
+pub const MARKER: &[u8] = b"tola-toc";
+
+/// A heading and the headings nested under it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TocEntry>,
+}
+
+/// Headings of `html` with an id, within the configured levels, nested
+pub fn collect(html: &[u8], config: &SiteConfig) -> Vec<TocEntry> {
+    let toc = &config.build.toc;
+    let mut reader = create_xml_reader(html);
+    let mut headings = Vec::new();
+    loop {
+        let elem = match reader.read_event() {
+            Ok(Event::Start(elem)) => elem,
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        let Some(level) = heading_level(elem.name().as_ref()) else {
+            continue;
+        };
+        let id = elem
+            .try_get_attribute("id")
+            .ok()
+            .flatten()
+            .map(|attr| String::from_utf8_lossy(&attr.value).into_owned());
+        let name = elem.name().as_ref().to_vec();
+        let Ok(span) = reader.read_to_end(QName(&name)) else {
+            break;
+        };
+        let Some(id) = id.filter(|_| (toc.min_level..=toc.max_level).contains(&level)) else {
+            continue;
+        };
+        let inner = &html[span.start as usize..span.end as usize];
+        headings.push(TocEntry {
+            level,
+            id: slugify_fragment(&id, config),
+            title: extract_text(inner).replace('\n', " "),
+            children: Vec::new(),
+        });
+    }
+    nest(headings)
+}
+
+fn heading_level(name: &[u8]) -> Option<u8> {
+    match name {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+/// Put each heading under the closest preceding heading of a higher level
+fn nest(headings: Vec<TocEntry>) -> Vec<TocEntry> {
+    // Path of open entries, from the top level down
+    let mut stack: Vec<TocEntry> = Vec::new();
+    let mut roots = Vec::new();
+    for heading in headings {
+        while stack.last().is_some_and(|open| open.level >= heading.level) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(heading);
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+fn close(stack: &mut Vec<TocEntry>, roots: &mut Vec<TocEntry>) {
+    let Some(entry) = stack.pop() else { return };
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry),
+    }
+}
+
+/// `<nav class="toc">` with nested lists of links
+pub fn render(entries: &[TocEntry]) -> String {
+    let mut html = String::from(r#"<nav class="toc">"#);
+    render_list(entries, &mut html);
+    html.push_str("</nav>");
+    html
+}
+
+fn render_list(entries: &[TocEntry], html: &mut String) {
+    if entries.is_empty() {
+        return;
+    }
+    html.push_str("<ul>");
+    for entry in entries {
+        let _ = write!(
+            html,
+            r##"<li><a href="#{}">{}</a>"##,
+            escape(entry.id.as_str()),
+            escape(entry.title.as_str())
+        );
+        render_list(&entry.children, html);
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+}
+
+/// Write the entries next to the page, `index.html` → `index.toc.json`
+pub fn write_json(entries: &[TocEntry], html_path: &Path) -> Result<()> {
+    let path = html_path.with_extension("toc.json");
+    let json = serde_json::to_string(entries)?;
+    prune::write_produced(&path, json.as_bytes())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SiteConfig {
+        let mut config = SiteConfig::default();
+        config.build.toc.enable = true;
+        config.build.toc.max_level = 3;
+        config
+    }
+
+    #[test]
+    fn test_collect() {
+        let html = br#"<html><body><h1>Page</h1><tola-toc></tola-toc>
+<h2 id="intro">Intro &amp; <em>goals</em></h2>
+<h3 id="scope">Scope</h3>
+<h4 id="deep">Too deep</h4>
+<h3>No label</h3>
+<h2 id="usage">Usage</h2>
+</body></html>"#;
+        let entries = collect(html, &config());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Intro & goals");
+        assert_eq!(entries[0].level, 2);
+        assert_eq!(entries[0].children.len(), 1);
+        assert_eq!(entries[0].children[0].id, "scope");
+        assert!(entries[1].children.is_empty());
+
+        assert_eq!(
+            render(&entries),
+            r##"<nav class="toc"><ul><li><a href="#intro">Intro &amp; goals</a><ul><li><a href="#scope">Scope</a></li></ul></li><li><a href="#usage">Usage</a></li></ul></nav>"##
+        );
+        let json = serde_json::to_string(&entries).unwrap();
+        assert!(
+            json.starts_with(r#"[{"level":2,"id":"intro","title":"Intro & goals","children":[{"#)
+        );
+    }
+
+    #[test]
+    fn test_nest_skipped_levels() {
+        let entry = |level, id: &str| TocEntry {
+            level,
+            id: id.into(),
+            title: id.into(),
+            children: Vec::new(),
+        };
+        let entries = nest(vec![
+            entry(3, "a"),
+            entry(2, "b"),
+            entry(4, "c"),
+            entry(3, "d"),
+        ]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "a");
+        let ids: Vec<_> = entries[1].children.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["c", "d"]);
+    }
+}
@@ -0,0 +1,493 @@
+//! Typst compiled into tola (`[build.typst] backend = "library"`).
+//!
+//! [`compile_to_html`] compiles a page in process, as `typst compile --features
+//! html --format html` would: [`TolaWorld`] reads files below the site root
+//! (typst's `--root`), packages from typst's package directories (downloading
+//! them like the CLI does) and fonts from the site root, the system and the
+//! fonts embedded in typst.
+//!
+//! The fonts and files are kept by an [`Environment`] shared by every page for
+//! as long as tola runs, so templates are read and parsed once per build. A
+//! file is read again once its modification time or size changes, and parsed
+//! again (incrementally) only if its content did, so rebuilding a single post
+//! while serving reuses everything else typst memoized.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{exec::collect_warnings, meta, report, reproducible},
+};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, MutexGuard, OnceLock},
+    time::SystemTime,
+};
+use typst::{
+    Feature, Library, World, WorldExt,
+    diag::{FileError, FileResult, PackageError, Severity, SourceDiagnostic, Warned},
+    foundations::{Bytes, Datetime, Dict, IntoValue},
+    html::HtmlDocument,
+    syntax::{FileId, Source, Span, VirtualPath, package::PackageSpec},
+    text::{Font, FontBook},
+    utils::LazyHash,
+};
+use typst_kit::{
+    download::{DownloadState, Downloader, Progress},
+    fonts::{FontSlot, Fonts},
+    package::PackageStorage,
+};
+
+/// Id of the source including the page (see [`meta::compile_source`])
+static MAIN_ID: LazyLock<FileId> = LazyLock::new(|| FileId::new_fake(VirtualPath::new("<page>")));
+
+/// Builds a memoized result may go unused for before it is dropped
+const EVICT_AFTER_BUILDS: usize = 10;
+
+/// Compile the page `path` to HTML, passing the site data and `inputs` as
+/// `sys.inputs`; warnings are reported for the page
+pub fn compile_to_html(
+    path: &Path,
+    inputs: &[(&str, String)],
+    config: &SiteConfig,
+) -> Result<Vec<u8>> {
+    let world = TolaWorld::new(path, inputs, config)?;
+    let Warned { output, warnings } = typst::compile::<HtmlDocument>(&world);
+
+    // Rendered like the CLI prints them, so the same noise is filtered out
+    let warnings = render_diagnostics(&world, &warnings);
+    for warning in collect_warnings(warnings.as_bytes()) {
+        report::warning(path, warning);
+    }
+    match output.and_then(|document| typst_html::html(&document)) {
+        Ok(html) => Ok(html.into_bytes()),
+        Err(errors) => bail!(
+            "Failed to compile with typst\n{}",
+            render_diagnostics(&world, &errors)
+        ),
+    }
+}
+
+/// Drop what typst memoized and no build used lately, once a build is done
+pub fn evict_cache() {
+    typst::comemo::evict(EVICT_AFTER_BUILDS);
+}
+
+// ============================================================================
+// World
+// ============================================================================
+
+/// Everything typst reads while compiling one page
+pub struct TolaWorld {
+    /// Source including the page
+    main: Source,
+    library: LazyHash<Library>,
+    environment: Arc<Environment>,
+    /// Files as this compile first read them, so it sees one version of each
+    files: Mutex<HashMap<FileId, FileResult<Bytes>>>,
+    sources: Mutex<HashMap<FileId, FileResult<Source>>>,
+    /// Current time, fixed to the source date of reproducible builds
+    now: OnceLock<DateTime<Utc>>,
+}
+
+impl TolaWorld {
+    /// World compiling the page `path` with the site data and `inputs`
+    pub fn new(path: &Path, inputs: &[(&str, String)], config: &SiteConfig) -> Result<Self> {
+        let main = Source::new(*MAIN_ID, meta::compile_source(path, config)?);
+
+        let site_input = config.typst_site_input();
+        let inputs: Dict = site_input
+            .split_once('=')
+            .into_iter()
+            .chain(inputs.iter().map(|(key, value)| (*key, value.as_str())))
+            .map(|(key, value)| (key.into(), value.into_value()))
+            .collect();
+        let library = Library::builder()
+            .with_inputs(inputs)
+            .with_features([Feature::Html].into_iter().collect())
+            .build();
+
+        let now = OnceLock::new();
+        if let Some(date) =
+            reproducible::source_date(config).and_then(|date| DateTime::from_timestamp(date, 0))
+        {
+            let _ = now.set(date);
+        }
+
+        Ok(Self {
+            main,
+            library: LazyHash::new(library),
+            environment: Environment::shared(config.get_root()),
+            files: Mutex::default(),
+            sources: Mutex::default(),
+            now,
+        })
+    }
+}
+
+impl World for TolaWorld {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.environment.book
+    }
+
+    fn main(&self) -> FileId {
+        self.main.id()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+        lock(&self.sources)
+            .entry(id)
+            .or_insert_with(|| self.environment.source(id))
+            .clone()
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        lock(&self.files)
+            .entry(id)
+            .or_insert_with(|| self.environment.file(id))
+            .clone()
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.environment.fonts.get(index)?.get()
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        let now = self.now.get_or_init(Utc::now);
+        // The given UTC offset in hours, or the local time zone
+        let now = match offset {
+            None => now.with_timezone(&Local).fixed_offset(),
+            Some(hours) => {
+                let seconds = i32::try_from(hours).ok()?.checked_mul(3600)?;
+                now.with_timezone(&FixedOffset::east_opt(seconds)?)
+            }
+        };
+        Datetime::from_ymd(
+            now.year(),
+            now.month().try_into().ok()?,
+            now.day().try_into().ok()?,
+        )
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ============================================================================
+// Environment
+// ============================================================================
+
+/// Fonts and files shared by the compiles of a site
+struct Environment {
+    /// Site root, relative to which absolute paths are resolved
+    root: PathBuf,
+    /// Fonts of the site root, the system and the fonts embedded in typst
+    book: LazyHash<FontBook>,
+    fonts: Vec<FontSlot>,
+    files: Mutex<HashMap<FileId, Arc<Mutex<FileSlot>>>>,
+}
+
+impl Environment {
+    /// Environment of the pages of `root`, created (and fonts searched) on first use
+    fn shared(root: &Path) -> Arc<Self> {
+        static SHARED: Mutex<Option<Arc<Environment>>> = Mutex::new(None);
+        let mut shared = lock(&SHARED);
+        if let Some(environment) = shared.as_ref().filter(|env| env.root == root) {
+            return Arc::clone(environment);
+        }
+        let found = Fonts::searcher()
+            .include_system_fonts(true)
+            .search_with([root]);
+        let environment = Arc::new(Self {
+            root: root.to_path_buf(),
+            book: LazyHash::new(found.book),
+            fonts: found.fonts,
+            files: Mutex::default(),
+        });
+        *shared = Some(Arc::clone(&environment));
+        environment
+    }
+
+    /// Slot of the file `id`, read again if the file changed
+    ///
+    /// Compiles reading other files go on while it is read.
+    fn slot(&self, id: FileId) -> Arc<Mutex<FileSlot>> {
+        let slot = Arc::clone(lock(&self.files).entry(id).or_default());
+        lock(&slot).refresh(self.path(id));
+        slot
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        lock(&self.slot(id)).content.clone()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        lock(&self.slot(id)).source(id)
+    }
+
+    /// Path of the file `id`, below the site root or its package
+    fn path(&self, id: FileId) -> FileResult<PathBuf> {
+        let root = match id.package() {
+            Some(spec) => &package_dir(spec)?,
+            None => &self.root,
+        };
+        id.vpath().resolve(root).ok_or(FileError::AccessDenied)
+    }
+}
+
+/// Modification time and size of a file
+type Stamp = (SystemTime, u64);
+
+/// A file as last read, and the source typst parsed from it
+struct FileSlot {
+    /// Stamp of the file when it was read; `None` until read, or if it could not be
+    stamp: Option<Stamp>,
+    content: FileResult<Bytes>,
+    /// Hash of `content`, so a file saved without changes is not parsed again
+    fingerprint: u128,
+    /// Source parsed from the content of the fingerprint it is paired with
+    source: Option<(u128, FileResult<Source>)>,
+}
+
+impl Default for FileSlot {
+    fn default() -> Self {
+        Self {
+            stamp: None,
+            content: Err(FileError::Other(None)),
+            fingerprint: 0,
+            source: None,
+        }
+    }
+}
+
+impl FileSlot {
+    /// Read the file at `path` again unless its stamp is unchanged
+    fn refresh(&mut self, path: FileResult<PathBuf>) {
+        let path = match path {
+            Ok(path) => path,
+            Err(err) => {
+                self.update(None, Err(err));
+                return;
+            }
+        };
+        let stamp = fs::metadata(&path)
+            .and_then(|meta| Ok((meta.modified()?, meta.len())))
+            .ok();
+        if stamp.is_some() && stamp == self.stamp {
+            return;
+        }
+        let content = match path.is_dir() {
+            true => Err(FileError::IsDirectory),
+            false => fs::read(&path)
+                .map(Bytes::new)
+                .map_err(|err| FileError::from_io(err, &path)),
+        };
+        self.update(stamp, content);
+    }
+
+    fn update(&mut self, stamp: Option<Stamp>, content: FileResult<Bytes>) {
+        self.stamp = stamp;
+        let fingerprint = typst::utils::hash128(&content);
+        if fingerprint != self.fingerprint {
+            self.fingerprint = fingerprint;
+            self.content = content;
+        }
+    }
+
+    /// Source of the current content, reparsing only what changed since the last one
+    fn source(&mut self, id: FileId) -> FileResult<Source> {
+        if let Some((fingerprint, source)) = &self.source
+            && *fingerprint == self.fingerprint
+        {
+            return source.clone();
+        }
+        let previous = self.source.take().and_then(|(_, source)| source.ok());
+        let source = self.content.clone().and_then(|bytes| {
+            // A UTF-8 byte order mark is not part of the text
+            let text = std::str::from_utf8(bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&bytes))?;
+            Ok(match previous {
+                Some(mut source) => {
+                    source.replace(text);
+                    source
+                }
+                None => Source::new(id, text.into()),
+            })
+        });
+        self.source = Some((self.fingerprint, source.clone()));
+        source
+    }
+}
+
+// ============================================================================
+// Packages
+// ============================================================================
+
+/// Directory of the package `spec`, downloaded into typst's cache if needed
+fn package_dir(spec: &PackageSpec) -> Result<PathBuf, PackageError> {
+    static PACKAGES: LazyLock<PackageStorage> = LazyLock::new(|| {
+        let downloader = Downloader::new(concat!("tola/", env!("CARGO_PKG_VERSION")));
+        PackageStorage::new(None, None, downloader)
+    });
+    PACKAGES.prepare_package(spec, &mut LogDownload(spec))
+}
+
+/// Logs the packages downloaded while compiling
+struct LogDownload<'a>(&'a PackageSpec);
+
+impl Progress for LogDownload<'_> {
+    fn print_start(&mut self) {
+        log!("typst"; "downloading {}", self.0);
+    }
+
+    fn print_progress(&mut self, _: &DownloadState) {}
+
+    fn print_finish(&mut self, _: &DownloadState) {}
+}
+
+// ============================================================================
+// Diagnostics
+// ============================================================================
+
+/// Diagnostics as the CLI prints them: the message, where it occurred with the
+/// source line, the call trace and the hints
+fn render_diagnostics(world: &TolaWorld, diagnostics: &[SourceDiagnostic]) -> String {
+    let mut text = String::new();
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(text, "{severity}: {}", diagnostic.message);
+        text.push_str(&location(world, diagnostic.span).unwrap_or_default());
+        // The include of the page by its wrapper source is no news to the user
+        let trace = diagnostic
+            .trace
+            .iter()
+            .filter(|point| point.span.id() != Some(*MAIN_ID));
+        for point in trace {
+            let _ = writeln!(text, "  = help: {}", point.v);
+            text.push_str(&location(world, point.span).unwrap_or_default());
+        }
+        for hint in &diagnostic.hints {
+            let _ = writeln!(text, "  = hint: {hint}");
+        }
+        text.push('\n');
+    }
+    text.trim_end().to_owned()
+}
+
+/// `file:line:column` of a span, followed by its source line with the span marked
+fn location(world: &TolaWorld, span: Span) -> Option<String> {
+    let id = span.id()?;
+    let source = world.source(id).ok()?;
+    let range = world.range(span)?;
+    let line = source.byte_to_line(range.start)?;
+    let column = source.byte_to_column(range.start)?;
+    let file = match id.package() {
+        Some(spec) => format!("{spec}{}", id.vpath().as_rooted_path().display()),
+        None => id.vpath().as_rootless_path().display().to_string(),
+    };
+    let line_range = source.line_to_range(line)?;
+    let text = source.text()[line_range.clone()].trim_end();
+    let end = range.end.clamp(range.start, line_range.start + text.len());
+    let width = source.text()[range.start..end].chars().count().max(1);
+    Some(format!(
+        "  ┌─ {file}:{}:{}\n  │ {text}\n  │ {}{}\n",
+        line + 1,
+        column + 1,
+        " ".repeat(column),
+        "^".repeat(width)
+    ))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Site root with `content/page.typ` holding `page`
+    fn site(name: &str, page: &str) -> (PathBuf, SiteConfig) {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("content")).unwrap();
+        fs::write(root.join("content/page.typ"), page).unwrap();
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        (root, config)
+    }
+
+    #[test]
+    fn test_compile_to_html() {
+        let (root, config) = site(
+            "tola_typst_compile_test",
+            "#metadata((title: \"Hi\")) <tola-meta>\n= Hi #sys.inputs.at(\"section\")\n",
+        );
+        let page = root.join("content/page.typ");
+        let html = compile_to_html(&page, &[("section", "posts".into())], &config).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("Hi posts</h2>"));
+        assert_eq!(
+            meta::embedded_meta(html.as_bytes()).unwrap(),
+            r#"{"title":"Hi"}"#
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_slot_refresh() {
+        let dir = std::env::temp_dir().join("tola_typst_slot_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.typ");
+        let id = FileId::new(None, VirtualPath::new("a.typ"));
+
+        fs::write(&path, "= One").unwrap();
+        let mut slot = FileSlot::default();
+        slot.refresh(Ok(path.clone()));
+        assert_eq!(slot.source(id).unwrap().text(), "= One");
+        let fingerprint = slot.fingerprint;
+
+        // Saved again without changes: the content is kept
+        fs::write(&path, "= One").unwrap();
+        slot.refresh(Ok(path.clone()));
+        assert_eq!(slot.fingerprint, fingerprint);
+
+        fs::write(&path, "= Two!").unwrap();
+        slot.refresh(Ok(path.clone()));
+        assert_eq!(slot.source(id).unwrap().text(), "= Two!");
+        assert_eq!(&*slot.content.clone().unwrap(), b"= Two!");
+
+        fs::remove_file(&path).unwrap();
+        slot.refresh(Ok(path.clone()));
+        assert!(slot.source(id).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compile_to_html_error() {
+        let (root, config) = site("tola_typst_error_test", "= Hi\n#unknown(1)\n");
+        let page = root.join("content/page.typ");
+        let error = compile_to_html(&page, &[], &config)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("error: unknown variable: unknown"));
+        assert!(error.contains("┌─ content/page.typ:2:2\n  │ #unknown(1)\n  │  ^^^^^^^"));
+        assert!(!error.contains("<page>"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -2,15 +2,21 @@
 //!
 //! Handles content and asset changes triggered by file watcher.
 
-use super::build::{process_asset, process_content};
+use super::{
+    build::{process_asset, process_content},
+    image_queue, nav, pool, prune, site,
+    slug::{content_paths, output_path},
+    typst,
+};
 use crate::{config::SiteConfig, exec, log};
 use anyhow::{Result, anyhow, bail};
 use rayon::prelude::*;
 use std::{
+    collections::BTreeSet,
     env, fs,
     path::{Path, PathBuf},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 /// Type of file change detected
@@ -20,20 +26,86 @@ pub enum ChangeType {
     Content,
     /// Asset file changed
     Asset,
-    /// Template, utils, or config changed - requires full rebuild
+    /// Template, utils, or config changed - rebuilds dependent pages, or everything
     FullRebuild,
     /// Unknown file type
     Unknown,
 }
 
-/// Process changed content files (.typ)
-pub fn process_watched_content(files: &[&PathBuf], config: &'static SiteConfig) -> Result<()> {
-    files.par_iter().for_each(|path| {
-        let path = normalize_path(path, config);
-        if let Err(e) = process_content(&path, config, true, false) {
-            log!("watch"; "{e}");
+/// A batch is processed at the latest this many windows after its first event
+const MAX_BATCH_WINDOWS: u32 = 10;
+
+/// Changed paths collected until no event arrived for a whole window
+///
+/// Editors writing a file several times, or a `git checkout` touching many
+/// files, end up as one deduplicated batch. A batch is due at the latest
+/// [`MAX_BATCH_WINDOWS`] windows after its first event, so a steady stream of
+/// changes still gets built.
+#[derive(Debug)]
+pub struct EventBatch {
+    window: Duration,
+    paths: BTreeSet<PathBuf>,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl EventBatch {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            paths: BTreeSet::new(),
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Add the paths of an event received at `now`
+    pub fn add(&mut self, paths: impl IntoIterator<Item = PathBuf>, now: Instant) {
+        self.paths.extend(paths);
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    /// When the pending paths are due, if there are any
+    pub fn deadline(&self) -> Option<Instant> {
+        let quiet = self.last? + self.window;
+        let longest = self.first? + self.window * MAX_BATCH_WINDOWS;
+        Some(quiet.min(longest))
+    }
+
+    /// Take the pending paths if they are due at `now`
+    pub fn take_due(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        if self.deadline()? > now {
+            return None;
         }
+        self.first = None;
+        self.last = None;
+        Some(std::mem::take(&mut self.paths).into_iter().collect())
+    }
+}
+
+/// Process changed content files (.typ)
+///
+/// With `force_rebuild`, pages are compiled even if their source is unchanged,
+/// e.g. because a template they import changed.
+pub fn process_watched_content(
+    files: &[&PathBuf],
+    config: &SiteConfig,
+    force_rebuild: bool,
+) -> Result<()> {
+    pool::io(config).install(|| {
+        files.par_iter().for_each(|path| {
+            let path = normalize_path(path, config);
+            if let Err(e) = process_content(&path, config, true, force_rebuild) {
+                log!("watch"; "{e}");
+            }
+        })
     });
+    image_queue::wait()?;
+    typst::evict_cache();
+
+    // Neighbouring posts may link to a changed title
+    nav::build_post_nav(config)?;
 
     // Rebuild tailwind CSS if enabled
     if config.build.tailwind.enable {
@@ -46,20 +118,51 @@ pub fn process_watched_content(files: &[&PathBuf], config: &'static SiteConfig)
 /// Process changed asset files
 pub fn process_watched_assets(
     files: &[&PathBuf],
-    config: &'static SiteConfig,
+    config: &SiteConfig,
     should_wait_until_stable: bool,
 ) -> Result<()> {
-    files
-        .par_iter()
-        .filter(|path| path.exists())
-        .try_for_each(|path| {
-            let path = normalize_path(path, config);
-            process_asset(&path, config, should_wait_until_stable, true)
-        })
+    pool::io(config).install(|| {
+        files
+            .par_iter()
+            .filter(|path| path.exists())
+            .try_for_each(|path| {
+                let path = normalize_path(path, config);
+                process_asset(&path, config, should_wait_until_stable, true)
+            })
+    })
+}
+
+/// Remove the output of deleted content and asset files
+///
+/// Deleted pages are also dropped from the site, so neighbouring posts no
+/// longer link to them.
+pub fn process_removed_files(files: &[&PathBuf], config: &SiteConfig) -> Result<()> {
+    let output = &config.build.output;
+    let mut removed_pages = false;
+    for path in files {
+        let path = normalize_path(path, config);
+        let is_page = path.extension().is_some_and(|ext| ext == "typ");
+        if is_page && path.starts_with(&config.build.content) {
+            let html = content_paths(&path, config)?.html;
+            site::remove_page(&path);
+            prune::remove_page_output(&html, output)?;
+            removed_pages = true;
+        } else if !is_page && let Ok(target) = output_path(&path, config) {
+            prune::remove_output(&target, output)?;
+        } else {
+            continue;
+        }
+        log!("watch"; "removed output of {}", path.display());
+    }
+
+    if removed_pages {
+        nav::build_post_nav(config)?;
+    }
+    Ok(())
 }
 
 /// Process all watched file changes
-pub fn process_watched_files(files: &[PathBuf], config: &'static SiteConfig) -> Result<()> {
+pub fn process_watched_files(files: &[PathBuf], config: &SiteConfig) -> Result<()> {
     let content_files: Vec<_> = files
         .iter()
         .filter(|p| p.exists() && p.extension().is_some_and(|ext| ext == "typ"))
@@ -73,8 +176,13 @@ pub fn process_watched_files(files: &[PathBuf], config: &'static SiteConfig) ->
         })
         .collect();
 
+    let removed_files: Vec<_> = files.iter().filter(|p| !p.exists()).collect();
+
+    if !removed_files.is_empty() {
+        process_removed_files(&removed_files, config)?;
+    }
     if !content_files.is_empty() {
-        process_watched_content(&content_files, config)?;
+        process_watched_content(&content_files, config, false)?;
     }
     if !asset_files.is_empty() {
         process_watched_assets(&asset_files, config, true)?;
@@ -99,7 +207,7 @@ fn normalize_path(path: &Path, _config: &SiteConfig) -> PathBuf {
 }
 
 /// Rebuild tailwind CSS
-fn rebuild_tailwind(config: &'static SiteConfig) -> Result<()> {
+fn rebuild_tailwind(config: &SiteConfig) -> Result<()> {
     let input = config
         .build
         .tailwind
@@ -107,10 +215,7 @@ fn rebuild_tailwind(config: &'static SiteConfig) -> Result<()> {
         .as_ref()
         .ok_or_else(|| anyhow!("Tailwind input path not configured"))?;
 
-    let relative_path = input
-        .strip_prefix(&config.build.assets)?
-        .to_str()
-        .ok_or_else(|| anyhow!("Invalid tailwind input path"))?;
+    let relative_path = input.strip_prefix(&config.build.assets)?;
 
     // Config paths are already absolute
     let output = config.build.output.join(relative_path);
@@ -142,3 +247,35 @@ pub fn wait_until_stable(path: &Path, max_retries: usize) -> Result<()> {
 
     bail!("File did not stabilize after {max_retries} retries")
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_batch() {
+        let window = Duration::from_millis(200);
+        let start = Instant::now();
+        let mut batch = EventBatch::new(window);
+        assert_eq!(batch.deadline(), None);
+
+        let a = PathBuf::from("/site/content/a.typ");
+        let b = PathBuf::from("/site/content/b.typ");
+        batch.add([a.clone()], start);
+        batch.add([b.clone(), a.clone()], start + window / 2);
+        assert_eq!(batch.deadline(), Some(start + window * 3 / 2));
+        assert_eq!(batch.take_due(start + window), None);
+        assert_eq!(batch.take_due(start + window * 2), Some(vec![a.clone(), b]));
+        assert_eq!(batch.deadline(), None);
+
+        // Events that never pause are flushed after the longest wait
+        for i in 0..20 {
+            batch.add([a.clone()], start + window / 2 * i);
+        }
+        assert_eq!(batch.deadline(), Some(start + window * MAX_BATCH_WINDOWS));
+    }
+}
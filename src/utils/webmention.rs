@@ -0,0 +1,75 @@
+//! Outbound link list for sending webmentions.
+//!
+//! Writes `links.json`, mapping each post URL to the external links it contains,
+//! from the links recorded in the site model.
+
+use crate::{
+    config::SiteConfig,
+    log,
+    utils::{prune, site},
+};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Write the outbound link list if `[build.webmention]` is enabled
+pub fn build_links(config: &SiteConfig) -> Result<()> {
+    if !config.build.webmention.enable {
+        return Ok(());
+    }
+
+    let pages = site::pages();
+    let links = outbound_links(&pages);
+    let json = serde_json::to_string_pretty(&links)?;
+    prune::write_produced(&config.build.webmention.links, json.as_bytes())?;
+
+    log!("webmention"; "{} post(s) with outbound links", links.len());
+    Ok(())
+}
+
+/// Post URL → outbound links, for posts that link anywhere
+fn outbound_links(pages: &[site::Page]) -> BTreeMap<&str, &[String]> {
+    pages
+        .iter()
+        .filter(|page| page.meta.as_ref().is_some_and(|meta| meta.date.is_some()))
+        .filter(|page| !page.links.is_empty())
+        .map(|page| (page.url.as_str(), page.links.as_slice()))
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::meta::PageMeta;
+
+    fn page(url: &str, date: Option<&str>, links: &[&str]) -> site::Page {
+        site::Page {
+            url: url.into(),
+            meta: Some(PageMeta {
+                date: date.map(Into::into),
+                ..Default::default()
+            }),
+            links: links.iter().map(|&link| link.into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_outbound_links_only_posts_with_links() {
+        let pages = [
+            page(
+                "https://example.com/a/",
+                Some("2024-01-01"),
+                &["https://x.org/"],
+            ),
+            page("https://example.com/b/", Some("2024-01-02"), &[]),
+            page("https://example.com/about/", None, &["https://y.org/"]),
+        ];
+        let links = outbound_links(&pages);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links["https://example.com/a/"], ["https://x.org/"]);
+    }
+}
@@ -1,6 +1,6 @@
 //! XML/HTML processing utilities.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use quick_xml::{
     Reader, Writer,
     events::{BytesEnd, BytesStart, BytesText, Event},
@@ -11,7 +11,11 @@ use std::io::{Cursor, Write};
 use std::str;
 
 use crate::config::SiteConfig;
-use crate::utils::slug::{slugify_fragment, slugify_path};
+use crate::utils::inject;
+use crate::utils::meta::PageMeta;
+use crate::utils::sanitize::sanitize_fragment;
+use crate::utils::site::extract_text;
+use crate::utils::slug::{content_paths, slugify_fragment, slugify_path};
 
 // ============================================================================
 // Type Aliases
@@ -97,41 +101,140 @@ pub fn write_html_with_lang(
     Ok(())
 }
 
-/// Write heading element with slugified `id` attribute.
+/// Write heading element with slugified `id` attribute, returning the id.
 pub fn write_heading_with_slugified_id(
     elem: &BytesStart<'_>,
     writer: &mut XmlWriter,
-    config: &'static SiteConfig,
-) -> Result<()> {
+    config: &SiteConfig,
+) -> Result<Option<String>> {
+    let mut id = None;
     let new_elem = rebuild_elem(elem, |key, value| {
         if key == b"id" {
-            let v = str::from_utf8(value.as_ref()).unwrap_or_default();
-            slugify_fragment(v, config).into_bytes().into()
+            let v = String::from_utf8_lossy(value.as_ref());
+            let slug = slugify_fragment(&v, config);
+            id = Some(slug.clone());
+            slug.into_bytes().into()
         } else {
             value.into_owned().into()
         }
     });
     writer.write_event(Event::Start(new_elem))?;
+    Ok(id)
+}
+
+/// Write the `[build.anchors]` link to the heading with `id`.
+pub fn write_heading_anchor(writer: &mut XmlWriter, id: &str, config: &SiteConfig) -> Result<()> {
+    let href = format!("#{id}");
+    let mut elem = BytesStart::new("a");
+    elem.push_attribute(("class", "anchor"));
+    elem.push_attribute(("href", href.as_str()));
+    writer.write_event(Event::Start(elem))?;
+    writer.write_event(Event::Text(BytesText::new(&config.build.anchors.symbol)))?;
+    writer.write_event(Event::End(BytesEnd::new("a")))?;
     Ok(())
 }
 
 /// Write element with processed `href` and `src` attributes.
+///
+/// Links to `.typ` files are resolved against `source`, the page being built.
+/// External `<a>` links also get the `[build.links]` attributes.
 pub fn write_element_with_processed_links(
     elem: &BytesStart<'_>,
     writer: &mut XmlWriter,
-    config: &'static SiteConfig,
+    config: &SiteConfig,
+    source: Option<&Path>,
 ) -> Result<()> {
-    let new_elem = rebuild_elem_try(elem, |key, value| {
+    let mut new_elem = rebuild_elem_try(elem, |key, value| {
         if matches!(key, b"href" | b"src") {
-            process_link_value(&value, config)
+            match process_source_link(&value, source, config)? {
+                Some(url) => Ok(url.into_bytes().into()),
+                None => process_link_value(&value, config),
+            }
         } else {
             Ok(value.into_owned().into())
         }
     })?;
+    if config.build.links.enable && elem.name().as_ref() == b"a" {
+        new_elem = with_external_link_attributes(new_elem, config);
+    }
     writer.write_event(Event::Start(new_elem))?;
     Ok(())
 }
 
+/// Add `rel`, `target` and `class` to an `<a>` element linking to another site.
+///
+/// Existing tokens of `rel` and `class` are kept, and an explicit `target` wins.
+fn with_external_link_attributes(
+    elem: BytesStart<'static>,
+    config: &SiteConfig,
+) -> BytesStart<'static> {
+    let links = &config.build.links;
+    let href = elem.try_get_attribute("href").ok().flatten();
+    let host = href.and_then(|href| external_host(&String::from_utf8_lossy(&href.value)));
+    let Some(host) = host else {
+        return elem;
+    };
+    let own_host = config.base.url.as_deref().and_then(external_host);
+    let is_exempt = own_host
+        .iter()
+        .chain(&links.allowlist)
+        .map(|domain| domain.trim_start_matches('.').to_ascii_lowercase())
+        .any(|domain| host == domain || host.ends_with(&format!(".{domain}")));
+    if is_exempt {
+        return elem;
+    }
+
+    let mut new_elem = BytesStart::new("a");
+    let (mut rel, mut class) = (Vec::new(), Vec::new());
+    let mut has_target = false;
+    for attr in elem.attributes().flatten() {
+        let tokens = || {
+            String::from_utf8_lossy(&attr.value)
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        match attr.key.as_ref() {
+            b"rel" => rel.extend(tokens()),
+            b"class" => class.extend(tokens()),
+            key => {
+                has_target |= key == b"target";
+                new_elem.push_attribute(attr);
+            }
+        }
+    }
+    push_token(&mut rel, "noopener");
+    push_token(&mut rel, "noreferrer");
+    if let Some(extra) = &links.class {
+        push_token(&mut class, extra);
+    }
+
+    new_elem.push_attribute(("rel".as_bytes(), rel.join(" ").as_bytes()));
+    if !class.is_empty() {
+        new_elem.push_attribute(("class".as_bytes(), class.join(" ").as_bytes()));
+    }
+    if links.new_tab && !has_target {
+        new_elem.push_attribute(("target", "_blank"));
+    }
+    new_elem
+}
+
+fn push_token(tokens: &mut Vec<String>, token: &str) {
+    if !tokens.iter().any(|t| t == token) {
+        tokens.push(token.to_owned());
+    }
+}
+
+/// Lowercase host of an `http(s)://` URL, without user info or port
+fn external_host(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
 // ============================================================================
 // Link Processing
 // ============================================================================
@@ -146,8 +249,12 @@ pub fn write_element_with_processed_links(
 /// | `#` | Fragment | `process_fragment_link` |
 /// | `../` or `../../` | Relative | `process_relative_or_external_link` |
 /// | `https://` | External | kept unchanged |
-pub fn process_link_value(value: &[u8], config: &'static SiteConfig) -> Result<Cow<'static, [u8]>> {
-    let value_str = str::from_utf8(value)?;
+///
+/// Values that are not valid UTF-8 are kept unchanged.
+pub fn process_link_value(value: &[u8], config: &SiteConfig) -> Result<Cow<'static, [u8]>> {
+    let Ok(value_str) = str::from_utf8(value) else {
+        return Ok(Cow::Owned(value.to_vec()));
+    };
     let processed = match value_str.bytes().next() {
         Some(b'/') => process_absolute_link(value_str, config)?,
         Some(b'#') => process_fragment_link(value_str, config)?,
@@ -157,6 +264,63 @@ pub fn process_link_value(value: &[u8], config: &'static SiteConfig) -> Result<C
     Ok(Cow::Owned(processed.into_bytes()))
 }
 
+/// Resolve a link to a `.typ` source file to the URL of the page built from it.
+///
+/// `/content/posts/a.typ` is relative to the site root (as in typst), and
+/// `./a.typ` or `a.typ` to the directory of `source`. Returns `None` for other
+/// links, and an error if the target page does not exist.
+///
+/// # Examples
+///
+/// | Input (in `content/posts/b.typ`) | Output |
+/// |----------------------------------|--------|
+/// | `./a.typ` | `/posts/a/` |
+/// | `/content/about.typ#contact` | `/about/#contact` |
+pub fn process_source_link(
+    value: &[u8],
+    source: Option<&Path>,
+    config: &SiteConfig,
+) -> Result<Option<String>> {
+    let Ok(value) = str::from_utf8(value) else {
+        return Ok(None);
+    };
+    let (path, fragment) = value.split_once('#').unwrap_or((value, ""));
+    if !path.ends_with(".typ") || is_external_link(path) || path.starts_with("//") {
+        return Ok(None);
+    }
+
+    let target = match path.strip_prefix('/') {
+        Some(path) => config.get_root().join(path),
+        None => {
+            let dir = source
+                .and_then(Path::parent)
+                .with_context(|| format!("Relative link `{value}` outside a content page"))?;
+            dir.join(path)
+        }
+    };
+    let target = target
+        .canonicalize()
+        .with_context(|| format!("Linked page not found: `{value}`"))?;
+    let html = content_paths(&target, config)
+        .with_context(|| format!("Linked file is not a content page: `{value}`"))?
+        .html;
+
+    // `public/posts/a/index.html` → `/posts/a/`
+    let dir = html
+        .parent()
+        .and_then(|dir| dir.strip_prefix(&config.build.output).ok())
+        .unwrap_or(Path::new(""));
+    let mut url = format!("/{}", dir.display());
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+    if !fragment.is_empty() {
+        url.push('#');
+        url.push_str(&slugify_fragment(fragment, config));
+    }
+    Ok(Some(url))
+}
+
 /// Process absolute links (starting with `/` or `//`).
 ///
 /// # Examples
@@ -166,7 +330,7 @@ pub fn process_link_value(value: &[u8], config: &'static SiteConfig) -> Result<C
 /// | `/about` | `/about` |
 /// | `/about#team` | `/about#team` (fragment slugified) |
 /// | `//example.com` | `//example.com` (protocol-relative) |
-pub fn process_absolute_link(value: &str, config: &'static SiteConfig) -> Result<String> {
+pub fn process_absolute_link(value: &str, config: &SiteConfig) -> Result<String> {
     let base_path = &config.build.base_path;
 
     if is_asset_link(value, config) {
@@ -187,7 +351,7 @@ pub fn process_absolute_link(value: &str, config: &'static SiteConfig) -> Result
 }
 
 /// Process fragment links (starting with `#`).
-pub fn process_fragment_link(value: &str, config: &'static SiteConfig) -> Result<String> {
+pub fn process_fragment_link(value: &str, config: &SiteConfig) -> Result<String> {
     Ok(format!("#{}", slugify_fragment(&value[1..], config)))
 }
 
@@ -217,21 +381,58 @@ pub fn process_relative_or_external_link(value: &str) -> Result<String> {
 // ============================================================================
 
 /// Write `<head>` section content before closing tag.
-pub fn write_head_content(writer: &mut XmlWriter, config: &'static SiteConfig) -> Result<()> {
+///
+/// `title`, `description` and `head_extra` of the page metadata take
+/// precedence over the site-wide values; without a `description`, the text of
+/// the page `summary` is used.
+pub fn write_head_content(
+    writer: &mut XmlWriter,
+    config: &SiteConfig,
+    meta: Option<&PageMeta>,
+) -> Result<()> {
     let head = &config.build.head;
     let base_path = &config.build.base_path;
 
-    if !config.base.title.is_empty() {
-        write_text_element(writer, "title", &config.base.title)?;
+    let title = meta
+        .and_then(|meta| meta.title.as_deref())
+        .unwrap_or(&config.base.title);
+    if !title.is_empty() {
+        write_text_element(writer, "title", title)?;
+    }
+    let summary = meta
+        .and_then(|meta| meta.summary.as_deref())
+        .map(|summary| extract_text(summary.as_bytes()).replace('\n', " "));
+    let description = meta
+        .and_then(|meta| meta.description.as_deref())
+        .or(summary.as_deref().filter(|summary| !summary.is_empty()))
+        .unwrap_or(&config.base.description);
+    if !description.is_empty() {
+        write_empty_elem(
+            writer,
+            "meta",
+            &[("name", "description"), ("content", description)],
+        )?;
+        write_empty_elem(
+            writer,
+            "meta",
+            &[("property", "og:description"), ("content", description)],
+        )?;
     }
-    if !config.base.description.is_empty() {
+
+    if config.build.reading_time.enable
+        && let Some(meta) = meta
+        && let (Some(words), Some(minutes)) = (meta.word_count, meta.reading_time)
+    {
+        let (words, minutes) = (words.to_string(), minutes.to_string());
         write_empty_elem(
             writer,
             "meta",
-            &[
-                ("name", "description"),
-                ("content", &config.base.description),
-            ],
+            &[("name", "tola:word-count"), ("content", &words)],
+        )?;
+        write_empty_elem(
+            writer,
+            "meta",
+            &[("name", "tola:reading-time"), ("content", &minutes)],
         )?;
     }
 
@@ -266,11 +467,22 @@ pub fn write_head_content(writer: &mut XmlWriter, config: &'static SiteConfig) -
         write_script(writer, &src, script.is_defer(), script.is_async())?;
     }
 
-    // Raw HTML elements (trusted input)
-    for raw in &head.elements {
-        writer.get_mut().write_all(raw.as_bytes())?;
+    // Raw HTML elements, trusted unless `[build.sanitize]` is enabled
+    let sanitize = &config.build.sanitize;
+    let page_elements = meta
+        .map(|meta| meta.head_extra.as_slice())
+        .unwrap_or_default();
+    for raw in head.elements.iter().chain(page_elements) {
+        if sanitize.enable {
+            let clean = sanitize_fragment(raw, sanitize);
+            writer.get_mut().write_all(clean.as_bytes())?;
+        } else {
+            writer.get_mut().write_all(raw.as_bytes())?;
+        }
     }
 
+    inject::write_head_end(writer, config)?;
+
     writer.write_event(Event::End(BytesEnd::new("head")))?;
     Ok(())
 }
@@ -362,7 +574,7 @@ pub fn compute_asset_href(asset_path: &Path, base_path: &Path) -> Result<String>
 }
 
 /// Compute stylesheet href from input path
-pub fn compute_stylesheet_href(input: &Path, config: &'static SiteConfig) -> Result<String> {
+pub fn compute_stylesheet_href(input: &Path, config: &SiteConfig) -> Result<String> {
     let base_path = &config.build.base_path;
     // Config assets path is already absolute
     let assets = &config.build.assets;
@@ -382,7 +594,7 @@ fn get_asset_top_levels(assets_dir: &Path) -> &'static HashSet<OsString> {
 }
 
 /// Check if a path is an asset link
-pub fn is_asset_link(path: &str, config: &'static SiteConfig) -> bool {
+pub fn is_asset_link(path: &str, config: &SiteConfig) -> bool {
     let asset_top_levels = get_asset_top_levels(&config.build.assets);
 
     // Extract first path component after the leading slash
@@ -521,6 +733,69 @@ mod tests {
         assert!(output.contains("href=\"/styles/main.css\""));
     }
 
+    #[test]
+    fn test_write_head_content_page_overrides() {
+        let mut config = SiteConfig::default();
+        config.base.title = "Site".into();
+        config.base.description = "Site description".into();
+        config.build.head.elements = vec![r#"<meta name="site">"#.into()];
+        let head = |meta: Option<&PageMeta>| {
+            let mut writer = Writer::new(Cursor::new(Vec::new()));
+            write_head_content(&mut writer, &config, meta).unwrap();
+            String::from_utf8(writer.into_inner().into_inner()).unwrap()
+        };
+
+        let output = head(None);
+        assert!(output.contains("<title>Site</title>"));
+        assert!(output.contains(r#"content="Site description""#));
+
+        let meta = PageMeta {
+            title: Some("Post".into()),
+            description: Some("Post description".into()),
+            head_extra: vec![r#"<link rel="canonical" href="https://a.example/post/">"#.into()],
+            ..Default::default()
+        };
+        let output = head(Some(&meta));
+        assert!(output.contains("<title>Post</title>"));
+        assert!(output.contains(r#"content="Post description""#));
+        assert!(!output.contains("Site"));
+        let site = output.find(r#"<meta name="site">"#).unwrap();
+        let page = output.find(r#"<link rel="canonical""#).unwrap();
+        assert!(site < page);
+
+        let meta = PageMeta {
+            summary: Some("<p>The <em>summary</em></p>".into()),
+            ..Default::default()
+        };
+        let output = head(Some(&meta));
+        assert!(output.contains(r#"<meta name="description" content="The summary"/>"#));
+        assert!(output.contains(r#"<meta property="og:description" content="The summary"/>"#));
+    }
+
+    #[test]
+    fn test_write_heading_anchor() {
+        let mut config = SiteConfig::default();
+        config.build.anchors.symbol = "¶".into();
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        let elem = BytesStart::from_content(r#"h2 id="Getting Started""#, 2);
+        let id = write_heading_with_slugified_id(&elem, &mut writer, &config).unwrap();
+        write_heading_anchor(&mut writer, id.as_deref().unwrap(), &config).unwrap();
+        let output = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+        let slug = slugify_fragment("Getting Started", &config);
+        assert_eq!(id.as_deref(), Some(slug.as_str()));
+        assert_eq!(
+            output,
+            format!(r##"<h2 id="{slug}"><a class="anchor" href="#{slug}">¶</a>"##)
+        );
+
+        let elem = BytesStart::new("h3");
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        assert_eq!(
+            write_heading_with_slugified_id(&elem, &mut writer, &config).unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_write_script_basic() {
         let mut writer = Writer::new(Cursor::new(Vec::new()));
@@ -616,5 +891,88 @@ mod tests {
         let value = Cow::Borrowed(b"../images/logo.png".as_slice());
         let result = process_link_value(&value, config).unwrap();
         assert_eq!(String::from_utf8_lossy(&result), "../../images/logo.png");
+
+        // Non-UTF-8 link is kept unchanged
+        let value = Cow::Borrowed(b"/caf\xe9".as_slice());
+        let result = process_link_value(&value, config).unwrap();
+        assert_eq!(result.as_ref(), b"/caf\xe9");
+    }
+
+    fn render_link(html: &str, config: &SiteConfig) -> String {
+        let mut reader = create_xml_reader(html.as_bytes());
+        let Ok(Event::Start(elem)) = reader.read_event() else {
+            panic!("expected a start tag");
+        };
+        let mut writer = Writer::new(Cursor::new(Vec::new()));
+        write_element_with_processed_links(&elem, &mut writer, config, None).unwrap();
+        String::from_utf8(writer.into_inner().into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_external_link_attributes() {
+        let mut config = SiteConfig::default();
+        config.base.url = Some("https://example.com".into());
+        config.build.links.enable = true;
+        config.build.links.new_tab = true;
+        config.build.links.class = Some("external".into());
+        config.build.links.allowlist = vec!["github.com".into()];
+
+        assert_eq!(
+            render_link(
+                r#"<a href="https://other.org/x" rel="me" class="btn">"#,
+                &config
+            ),
+            r#"<a href="https://other.org/x" rel="me noopener noreferrer" class="btn external" target="_blank">"#
+        );
+        assert_eq!(
+            render_link(r#"<a href="http://Other.org" target="_self">"#, &config),
+            r#"<a href="http://Other.org" target="_self" rel="noopener noreferrer" class="external">"#
+        );
+        // Own site, allowlisted domains and non-http links are left alone
+        for html in [
+            r#"<a href="https://example.com/about/">"#,
+            r#"<a href="https://gist.github.com/x">"#,
+            r#"<a href="mailto:me@other.org">"#,
+        ] {
+            assert_eq!(render_link(html, &config), html);
+        }
+    }
+
+    #[test]
+    fn test_external_host() {
+        assert_eq!(
+            external_host("https://user@Sub.Example.com:8080/path?q#f").as_deref(),
+            Some("sub.example.com")
+        );
+        assert_eq!(external_host("//example.com"), None);
+        assert_eq!(external_host("https:///path"), None);
+    }
+
+    #[test]
+    fn test_process_source_link() {
+        let root = std::env::temp_dir().join("tola_source_link_test");
+        fs::create_dir_all(root.join("content/posts")).unwrap();
+        fs::write(root.join("content/posts/a.typ"), "").unwrap();
+        fs::write(root.join("content/about.typ"), "").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let mut config = SiteConfig::default();
+        config.set_root(&root);
+        config.build.content = root.join("content");
+        config.build.output = root.join("public");
+        let source = root.join("content/posts/b.typ");
+        let resolve = |link: &str| process_source_link(link.as_bytes(), Some(&source), &config);
+
+        assert_eq!(resolve("./a.typ").unwrap().as_deref(), Some("/posts/a/"));
+        assert_eq!(
+            resolve("/content/about.typ#Contact Me").unwrap().as_deref(),
+            Some("/about/#contact-me")
+        );
+        assert_eq!(resolve("../about.typ").unwrap().as_deref(), Some("/about/"));
+        assert_eq!(resolve("/about").unwrap(), None);
+        assert_eq!(resolve("https://example.com/x.typ").unwrap(), None);
+        assert!(resolve("./missing.typ").is_err());
+
+        let _ = fs::remove_dir_all(&root);
     }
 }
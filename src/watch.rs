@@ -4,37 +4,85 @@
 //! and triggers rebuilds accordingly.
 
 use crate::{
-    config::SiteConfig,
-    log,
-    utils::watch::{ChangeType, process_watched_files},
+    config::{SiteConfig, WatchStrategy},
+    log, serve, trace,
+    utils::{
+        self, cache, deps, report, sections,
+        watch::{ChangeType, EventBatch, process_watched_content, process_watched_files},
+    },
 };
 use anyhow::{Context, Result};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
 use std::{
-    collections::HashMap,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
     },
     time::{Duration, Instant},
 };
 
-/// Debounce duration in milliseconds to prevent duplicate events
-const DEBOUNCE_MS: u64 = 300;
+/// How long the watcher waits for events while nothing is pending
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Cooldown duration after full rebuild to prevent loops
 const FULL_REBUILD_COOLDOWN_MS: u64 = 1000;
 
 /// Start blocking file watcher for content and asset changes
+///
+/// When the config file changes it is loaded again and the site fully rebuilt;
+/// `restart_server` is called with the new config if `[serve]` or the output
+/// directory changed. An invalid config is reported and the previous one kept.
 pub fn watch_for_changes_blocking(
-    config: &'static SiteConfig,
+    config: &SiteConfig,
     server_ready: Arc<AtomicBool>,
+    restart_server: impl Fn(&SiteConfig),
 ) -> Result<()> {
-    if !config.serve.watch {
-        return Ok(());
+    let mut config = config.clone();
+    while config.serve.watch {
+        let Some((mut new_config, paths)) = watch_until_reload(&config, &server_ready)? else {
+            break;
+        };
+        serve::apply_port_fallback(&mut new_config);
+        let changed = config.changed_sections(&new_config);
+        if changed.is_empty() {
+            log!("watch"; "config saved without changes");
+            // Other files changed in the same batch still need their rebuild
+            if !paths.is_empty() {
+                let started = Instant::now();
+                handle_event(&paths, &config);
+                serve::record_rebuild(started.elapsed());
+            }
+            continue;
+        }
+
+        log!("watch"; "config changed in [{}], triggering full rebuild...", changed.join("], ["));
+        let restart = changed.contains(&"serve") || new_config.build.output != config.build.output;
+        if new_config.serve.port != config.serve.port
+            && let Err(err) = serve::pick_port(&mut new_config)
+        {
+            log!("serve"; "{err}");
+        }
+        config = new_config;
+        utils::log::configure(&config.log);
+        let started = Instant::now();
+        full_rebuild(&config);
+        serve::record_rebuild(started.elapsed());
+        if restart {
+            restart_server(&config);
+        }
     }
+    Ok(())
+}
 
+/// Watch for changes until the config file changes, returning the reloaded
+/// config with the other paths changed in the same batch, or `None` once the
+/// server stopped
+fn watch_until_reload(
+    config: &SiteConfig,
+    server_ready: &AtomicBool,
+) -> Result<Option<(SiteConfig, Vec<PathBuf>)>> {
     let (tx, rx) = std::sync::mpsc::channel();
     let mut watcher = notify::recommended_watcher(tx).context("Failed to create file watcher")?;
 
@@ -54,20 +102,28 @@ pub fn watch_for_changes_blocking(
     if config.config_path.exists() {
         watch_file(&mut watcher, "config", &config.config_path)?;
     }
+    for include in config.include.iter().filter(|include| include.exists()) {
+        watch_file(&mut watcher, "config include", include)?;
+    }
+
+    // Watch extra paths from `[serve.watch_paths]`
+    for path in config.serve.watch_paths.keys() {
+        match path.is_dir() {
+            true => watch_directory(&mut watcher, "watch path", path)?,
+            false if path.exists() => watch_file(&mut watcher, "watch path", path)?,
+            false => log!("watch"; "skipping missing watch path: {}", path.display()),
+        }
+    }
 
-    let debounce_duration = Duration::from_millis(DEBOUNCE_MS);
     let rebuild_cooldown = Duration::from_millis(FULL_REBUILD_COOLDOWN_MS);
-    let mut pending_paths: HashMap<String, std::path::PathBuf> = HashMap::new();
-    let mut last_event_time: Option<Instant> = None;
+    let mut batch = EventBatch::new(Duration::from_millis(config.serve.debounce));
     let mut last_full_rebuild: Option<Instant> = None;
 
     loop {
-        // Use timeout to allow debounce batching
-        let timeout = if pending_paths.is_empty() {
-            Duration::from_secs(60) // Long timeout when idle
-        } else {
-            debounce_duration
-        };
+        // Wake up when the batch is due, or rarely while idle
+        let timeout = batch.deadline().map_or(IDLE_TIMEOUT, |deadline| {
+            deadline.saturating_duration_since(Instant::now())
+        });
 
         match rx.recv_timeout(timeout) {
             Ok(res) => {
@@ -78,6 +134,7 @@ pub fn watch_for_changes_blocking(
                 match res {
                     Err(e) => log!("watch"; "error: {e:?}"),
                     Ok(event) if should_process_event(&event) => {
+                        trace!("watch"; "event: {event:?}");
                         let now = Instant::now();
 
                         // Skip all events during full rebuild cooldown
@@ -86,36 +143,40 @@ pub fn watch_for_changes_blocking(
                         {
                             continue;
                         }
-
-                        // Collect paths for batched processing
-                        for path in event.paths {
-                            let path_str = path.to_string_lossy().to_string();
-                            pending_paths.insert(path_str, path);
-                        }
-                        last_event_time = Some(now);
+                        batch.add(event.paths, now);
                     }
                     _ => {}
                 }
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                // Process pending paths after debounce timeout
-                if !pending_paths.is_empty()
-                    && let Some(last_time) = last_event_time
-                    && Instant::now().duration_since(last_time) >= debounce_duration
-                {
-                    let paths: Vec<_> = pending_paths.drain().map(|(_, p)| p).collect();
-                    let did_full_rebuild = handle_event(&paths, config);
-                    if did_full_rebuild {
-                        last_full_rebuild = Some(Instant::now());
-                    }
-                    last_event_time = None;
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        // A steady stream of events never times out, so the batch is checked after each one
+        let Some(mut paths) = batch.take_due(Instant::now()) else {
+            continue;
+        };
+        if paths.iter().any(|p| is_config_file(p, config)) {
+            paths.retain(|p| !is_config_file(p, config));
+            match config.reload() {
+                Ok(new_config) => return Ok(Some((new_config, paths))),
+                Err(err) => {
+                    log!("watch"; "config not reloaded, keeping the previous one: {err:#}")
                 }
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if paths.is_empty() {
+            continue;
+        }
+        let started = Instant::now();
+        let did_full_rebuild = handle_event(&paths, config);
+        serve::record_rebuild(started.elapsed());
+        if did_full_rebuild {
+            last_full_rebuild = Some(Instant::now());
         }
     }
 
-    Ok(())
+    Ok(None)
 }
 
 /// Watch a directory and log the action
@@ -138,7 +199,10 @@ fn watch_file(watcher: &mut impl Watcher, name: &str, path: &Path) -> Result<()>
 
 /// Determine if an event should trigger a rebuild
 fn should_process_event(event: &Event) -> bool {
-    matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+    matches!(
+        event.kind,
+        EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
+    )
 }
 
 /// Classify file change type based on path
@@ -150,6 +214,8 @@ fn classify_change(path: &Path, config: &SiteConfig) -> ChangeType {
     if path == config.config_path
         || path.starts_with(&config.build.templates)
         || path.starts_with(&config.build.utils)
+        || watch_path(&path, config).is_some()
+        || (config.build.sections.enable && sections::is_section_file(&path))
     {
         ChangeType::FullRebuild
     } else if path.starts_with(&config.build.content) {
@@ -161,31 +227,101 @@ fn classify_change(path: &Path, config: &SiteConfig) -> ChangeType {
     }
 }
 
-/// Handle file change events, returns true if full rebuild was performed
-fn handle_event(paths: &[std::path::PathBuf], config: &'static SiteConfig) -> bool {
-    // Classify all paths and find which triggered full rebuild
-    let rebuild_trigger = paths
+/// Entry of `[serve.watch_paths]` containing `path`, the innermost if nested
+fn watch_path<'a>(path: &Path, config: &'a SiteConfig) -> Option<(&'a Path, WatchStrategy)> {
+    config
+        .serve
+        .watch_paths
         .iter()
-        .find(|p| matches!(classify_change(p, config), ChangeType::FullRebuild));
+        .filter(|(watched, _)| path.starts_with(watched))
+        .max_by_key(|(watched, _)| watched.components().count())
+        .map(|(watched, strategy)| (watched.as_path(), *strategy))
+}
 
-    if let Some(trigger_path) = rebuild_trigger {
+/// Handle file change events, returns true if full rebuild was performed
+fn handle_event(paths: &[PathBuf], config: &SiteConfig) -> bool {
+    // Templates and utils only affect the pages importing them
+    let (rebuild_triggers, changed): (Vec<_>, Vec<_>) = paths
+        .iter()
+        .cloned()
+        .partition(|p| classify_change(p, config) == ChangeType::FullRebuild);
+    let mut dependents = Vec::new();
+    if let Some(trigger_path) = rebuild_triggers.first() {
         let reason = get_rebuild_reason(trigger_path, config);
-        log!("watch"; "{reason} changed, triggering full rebuild...");
-        if let Err(err) = crate::build::build_site(config, true) {
-            log!("watch"; "full rebuild failed: {err}");
+        match template_dependents(&rebuild_triggers, config) {
+            Some(pages) => {
+                log!("watch"; "{reason} changed, rebuilding {} dependent page(s)...", pages.len());
+                dependents = pages;
+            }
+            None => {
+                log!("watch"; "{reason} changed, triggering full rebuild...");
+                full_rebuild(config);
+                return true;
+            }
         }
-        return true;
     }
 
     // Process incremental changes
-    if let Err(err) =
-        process_watched_files(paths, config).context("Failed to process changed files")
-    {
+    let result = process_watched_files(&changed, config).and_then(|_| {
+        let dependents: Vec<_> = dependents
+            .iter()
+            .filter(|page| !changed.contains(page))
+            .collect();
+        match dependents.is_empty() {
+            true => Ok(()),
+            false => process_watched_content(&dependents, config, true),
+        }
+    });
+    if let Err(err) = result.context("Failed to process changed files") {
+        log!("watch"; "{err}");
+    }
+    if let Err(err) = cache::save_all() {
+        log!("watch"; "failed to save build cache: {err}");
+    }
+    if let Err(err) = report::finish(config.get_root()) {
         log!("watch"; "{err}");
     }
+    serve::notify_reload();
     false
 }
 
+/// Rebuild the whole site and reload the browsers
+fn full_rebuild(config: &SiteConfig) {
+    let result = crate::build::build_site(config, true);
+    // Drained even after a failed build, or its diagnostics end up in the next report
+    let report_result = report::finish(config.get_root());
+    if let Err(err) = result.and(report_result) {
+        log!("watch"; "full rebuild failed: {err}");
+    }
+    serve::notify_reload();
+}
+
+/// Whether `path` is the config file or one of its includes
+fn is_config_file(path: &Path, config: &SiteConfig) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path == config.config_path || config.include.contains(&path)
+}
+
+/// Content pages importing the changed templates, utils or `incremental` watch paths
+///
+/// `None` if a `full` watch path changed, or if no page imports them by a
+/// literal path: they may still be reached through a computed one, so
+/// everything is rebuilt.
+fn template_dependents(triggers: &[PathBuf], config: &SiteConfig) -> Option<Vec<PathBuf>> {
+    let triggers: Vec<PathBuf> = triggers
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+        .collect();
+    let needs_full = |path: &PathBuf| {
+        watch_path(path, config).is_some_and(|(_, strategy)| strategy == WatchStrategy::Full)
+    };
+    if triggers.iter().any(needs_full) {
+        return None;
+    }
+    let pages = deps::dependents(&triggers, config);
+    (!pages.is_empty()).then_some(pages)
+}
+
 /// Get a human-readable reason for the rebuild trigger
 fn get_rebuild_reason(path: &Path, config: &SiteConfig) -> String {
     let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
@@ -209,6 +345,11 @@ fn get_rebuild_reason(path: &Path, config: &SiteConfig) -> String {
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
         format!("utils ({file_name})")
+    } else if sections::is_section_file(&path) {
+        let dir = path.parent().unwrap_or(&path);
+        format!("section ({})", dir.display())
+    } else if let Some((watched, _)) = watch_path(&path, config) {
+        format!("watch path ({})", watched.display())
     } else {
         "unknown".to_string()
     }